@@ -37,6 +37,26 @@ Press `y` and `ENTER` to create a `ata2.toml` file.
 
 Next, request an API key via <https://beta.openai.com/account/api-keys> and update the key in the example configuration file.
 
+On Windows and macOS, you can keep the key out of the file: `ata2 --save-key` asks for it and stores it in Credential Manager or the Keychain, and ata² uses it whenever `api_key` isn't set. With `--config`, it's stored for that profile.
+
+A team can share a configuration: `ata2 --config https://intranet/ata2/team.toml` downloads it and keeps a copy in the cache, which is downloaded again only when its ETag says it changed, and used as it is when the server can't be reached. Whatever the local file of the same name, `team.toml` next to `ata2.toml`, sets goes over it, so each of you can keep your own `api_key` there. What it sets of `api_key`, `api_base`, `auth`, `provider`, `rotation`, `integrations` and `tools`, where requests go and what tools may run, is left out with a warning unless the local file trusts it with that, like `trust_remote = ["api_base", "tools"]`.
+
+A local server that takes no key, like Ollama's or llama.cpp's, needs none in the configuration either: set `auth = "none"` and point `api_base` (`https://api.openai.com/v1` unless `OPENAI_API_BASE` says otherwise) at it.
+
+```toml
+auth = "none"
+api_base = "http://localhost:11434/v1"
+model = "llama3.2"
+```
+
+Any setting of `ata2.toml` can also be set by an environment variable, which wins over the file: `ATA2_` and the setting's path in capitals, joined by `_`, like `ATA2_MODEL=gpt-4o`, `ATA2_UI_WRAP=100` or `ATA2_UI_THEME_ERROR="bold red"`. `1`, `true`, `yes` and `on` are true, and `0`, `false`, `no`, `off` and nothing are false. A list is written as JSON or separated by commas, and a table as JSON. The names of settings under `[ui]` work without `UI_` too, as they did before.
+
+To see where a setting comes from, `ata2 config explain ui.wrap` prints what it's set to by the default, the config file, the environment and the rest, and which of them applies. `ata2 --dump-config` prints every setting, with where the one that applies comes from.
+
+Only the configuration is kept in `~/.config/ata2`. What ata² writes as it's used goes where the XDG base directories say: the history and the offline queue in `~/.local/state/ata2`, the database of `storage = "sqlite"` in `~/.local/share/ata2` and cached responses in `~/.cache/ata2`, each unless `history_file`, `database_file` or the `dir` of `[cache]` says otherwise. Those that older versions kept next to `ata2.toml` are moved there the first time ata² runs.
+
+On Windows, the history, the database and saved conversations are kept under `%APPDATA%\Ask the Terminal Anything (ATA) Project Authors\ata2\data`, next to the `config` folder that `ata2.toml` is in, and `Ctrl-X Ctrl-E` opens Notepad unless `VISUAL` or `EDITOR` is set.
+
 For more information, see:
 
 ```sh
@@ -77,13 +97,538 @@ You may also:
 $ cargo install --path .
 ```
 
+### Shell completion and man page
+
+`ata2 completions <shell>` prints a script that completes ata²'s options in bash, zsh, fish, elvish or PowerShell, along with the profiles for `--config` and the conversations saved in `ui.save_dir` for `--load`. The script asks `ata2` what to complete as you type, so load it anew when your shell starts, rather than saving it:
+
+```sh
+echo 'source <(ata2 completions bash)' >> ~/.bashrc
+echo 'source <(ata2 completions zsh)' >> ~/.zshrc
+echo 'ata2 completions fish | source' >> ~/.config/fish/config.fish
+```
+
+`ata2 manpage > ata2.1` writes the man page.
+
+### Commands
+
+Lines starting with `/` that name a known command are handled by ata² instead of being sent to the model. `/help` lists them all. Tab completes command names and their arguments.
+
+- `/continue` continues the last response, if it was cut off at `max_tokens`, see below.
+- `/regen [k]` asks for the last response again k times at once (3 without `k`), with the parameters of its prompt, prints them numbered and asks which one the conversation goes on from. Enter keeps the one there was. It needs `api = "chat"`.
+- `/diff` switches between showing code a response revises as a diff and in full, see below.
+- `/load <file>` replaces the conversation with one saved to a file, like `--load`.
+- `/links` lists the links in the last response, and `/links <n>` opens the nth of them in your browser (with `xdg-open`, or `open` on macOS).
+- `/models` lists the models the API offers, like `ata2 models`.
+- `/model gpt-4o` sends the prompts after to `gpt-4o`, but those that say otherwise with `@m=`, and Tab completes the model from those `/models` or `ata2 models` listed last. `/model` prints the model prompts are sent to.
+- `/search <words>` finds the saved conversations that have all of the words, with an excerpt and when each was saved, and `/resume <n>` continues the nth of them. `ata2 search <words>` does the same from the shell, and `ata2 search <words> --resume <n>` starts with the nth loaded. Without `n`, `/resume` continues the newest saved conversation.
+- `/tag rust debugging` tags the conversation, saving it first if it isn't yet, and `/tag -debugging` removes a tag. `ata2 sessions list` lists the saved conversations with their tags, and `ata2 sessions list --tag rust` only those tagged `rust`.
+- `/append 2024-05-01-borrow-checker` adds a saved conversation to the end of this one, for a new question that depends on an old discussion, or a detailed summary of it if both wouldn't fit in the model's context with room for the response. `ata2 sessions merge a b -o c` saves conversations `a` and `b`, one after the other, as `c`, with the system message of `a`. With file storage, the tags are kept in `tags.json` in `save_dir`, so the conversations themselves don't change.
+- `/show [n]` prints the last n exchanges of the conversation again through your pager (`ui.pager`, by default `$PAGER`), for when the terminal's scrollback is gone. Each message is numbered, and marked if it's pinned.
+- `/pin 3` pins message 3, or `/pin` the last prompt, and `/pin -3` unpins it. When a conversation gets too long for the model's context, with room for `max_tokens` of response, its oldest messages are left out of the request, but for the system message and those that are pinned, such as the one with the requirements that everything after depends on. The conversation keeps them all, and pins are saved with it.
+- `/tmux capture [pane] [lines]` adds the scrollback of a tmux pane (by default, ata²'s own) to the conversation.
+- `/upload <file>` uploads a file for the next prompt, with `api = "responses"`, see below.
+- `/tmux send [pane]` pastes the last code block of the conversation into a tmux pane (by default, the last active one).
+- `/trust <tool>` stops ata² asking before a tool is used, for the rest of the session, and `/trust` lists how each tool is used, see below.
+- `/attach <file> [lines a-b]` sends a text file, or only some of its lines, after the next prompt, and `/attach-tree <dir>` a directory, see below. `/attachments` lists what's attached. `/attachments clear` takes it all off.
+
+The tmux integration is configured in `ata2.toml`:
+
+```toml
+[integrations.tmux]
+capture_lines = 200
+send_pane = "{last}"
+send_enter = false
+```
+
+Whether a tool is used without asking is up to `[tools.policy]`. Each of `tmux_send`, `code_interpreter` and `file_search` is `allow`ed, which is the default for them, `deny`ed, or `always-ask`ed about, with the code to be pasted, or before a prompt is sent that the model could use it for. Rules give what a regular expression matches all of a mode of its own: each command of the code of `/tmux send`, split at lines, `;`, `&`, `|`, brackets and backticks, or the prompt for the tools of the Responses API, which run at OpenAI. Code is only allowed if all of its commands are, and of the rules that match, one that denies goes over one that asks, which goes over one that allows, in whatever order they're in. Asked while a response streams in, the answer is the next line you type, which Enter sends, and stopping the response says no. Without a terminal to ask on, a tool that's to be asked about isn't used.
+
+```toml
+[tools.policy]
+tmux_send = "always-ask"
+rules = [
+  { tool = "tmux_send", pattern = "git (status|log|diff)\\b.*", mode = "allow" },
+  { tool = "tmux_send", pattern = ".*\\brm\\s+-rf\\b.*", mode = "deny" },
+]
+```
+
+`[tools]` limits them too, for a tool that runs away not to hang ata² or flood the conversation. A tool that runs longer than its timeout is given up on, and the response with it. Of what a tool outputs, and of the scrollback of `/tmux capture`, only `max_output_bytes` is kept, half from its start and half from its end. With a `workdir`, `/upload` only uploads files from it, and `/tmux send` only pastes into a pane whose current directory is in it.
+
+```toml
+[tools]
+max_output_bytes = 4000  # 0 for all of it
+workdir = "/home/me/src/project"
+
+[tools.timeouts]  # seconds, 0 for as long as it takes
+tmux = 10
+code_interpreter = 300
+file_search = 60
+custom = 60
+```
+
+Tools of your own are defined in `[tools.custom]`, each a command, with no plugin to build. With chat completions, the model is told of each, by its name, `description` and the JSON schema of its `parameters`. When it calls one, its `command` is run with `sh -c`, each `{name}` in it replaced by the parameter of that name, quoted for the shell. It runs in `workdir`, if there's one, as `mode` says, which is `always-ask` unless it's set, and as the rules of `[tools.policy]` say, matched against the command. What it outputs goes back to the model, shown in the response as the tools of the Responses API are, and the model answers on from there, for up to 8 rounds of calls in a row. The calls and what came of them are kept in the conversation, so the prompts after are sent them too.
+
+```toml
+[tools.custom.pods]
+description = "List the pods of a Kubernetes namespace"
+parameters = '{"type": "object", "properties": {"namespace": {"type": "string"}}, "required": ["namespace"]}'
+command = "kubectl get pods -n {namespace}"
+mode = "allow"
+```
+
+`ata2 tools import` makes tools of the operations of an OpenAPI spec, in YAML or JSON, for an API to be the model's to use without writing anything for it. Each is a request, with `method` and `path` in place of `command`, to an API of `[tools.apis]`. Each `{name}` in its path is replaced by the parameter of that name, the other parameters go in its query, and `body` is the JSON it sends. It prints them, with the API, for your `ata2.toml`. `--filter` imports only the operations of paths that match, where `*` is anything. The API is named after the spec's title, unless `--api` names it. Its `headers`, one for each way the spec says it's authenticated, are sent with each request, and are yours to fill in. Imported tools are `always-ask`ed about, with the method and URL of the request, until they're allowed.
+
+```sh
+ata2 tools import openapi.yaml --filter '/v1/users*' --api users >> ~/.config/ata2/ata2.toml
+```
+
+```toml
+[tools.apis.users]
+base_url = "https://internal.example.com/api"
+headers = { Authorization = "Bearer …" }
+
+[tools.custom.getUser]
+description = "Get a user"
+api = "users"
+method = "GET"
+path = "/v1/users/{id}"
+parameters = '{"properties":{"id":{"type":"string"}},"required":["id"],"type":"object"}'
+```
+
+`ata2 share <conversation>` writes a saved conversation to `<name>.html`, a single page with its own styles and the code highlighted, which you can mail or put anywhere. `-o` writes it elsewhere, and `-o -` to stdout. `--format ipynb` writes a Jupyter notebook instead, the prose in Markdown cells and each code block in a code cell of its language, and `--format org` an Org document whose code blocks are source blocks Babel can run. Code blocks without a language, or in `text`, stay prose in both. `ata2 share <conversation> --gist` uploads it, in any of the formats, as a secret gist instead and prints the gist's URL. That takes a GitHub token with the `gist` scope, from `GITHUB_TOKEN` or `ata2.toml`:
+
+```toml
+[integrations.github]
+token = "github_pat_…"
+```
+
+### Parameters of a single prompt
+
+A prompt can start with parameters for itself alone: `@t=0.1 @m=gpt-4o explain this stack trace` sends `explain this stack trace` to `gpt-4o` at temperature 0.1, and the next prompt goes out as configured again. Each is `@name=value`, with the name of a setting or its short name: `m` (`model`), `t` (`temperature`), `p` (`top_p`), `mt` (`max_tokens`), `pp` (`presence_penalty`) and `fp` (`frequency_penalty`). They end at the first word that isn't one. A parameter ata² doesn't know or a value that isn't valid stops the prompt from being sent. `/continue` continues a response with the parameters of its prompt, and a queued prompt keeps them for `ata2 flush`.
+
+For a whole session, `--model` (`-m`), `--temperature` (`-t`) and `--max-tokens` set them over the configuration and the environment, as in `echo "prove this" | ata2 -m o3-mini`.
+
+### Workflows
+
+For something done again and again, a workflow file says how its conversations start, and `ata2 new --from release-notes.toml` starts one as it says:
+
+```toml
+name = "Release notes"
+model = "gpt-4o-mini"
+system = "You write release notes from the changelog and the commits since the last tag."
+# Files, or globs of them, attached as by /attach.
+attach = ["CHANGELOG.md", "src/**/*.rs"]
+# Commands whose output is attached.
+commands = ["git log --oneline $(git describe --tags --abbrev=0)..HEAD"]
+# Of [tools.custom] and the tools of the Responses API, the only ones the model is given.
+tools = ["pods"]
+```
+
+Everything is optional. `--model` and `--system` still take precedence, and the conversation goes on as any other. A glob that matches nothing, a command that fails or a tool that isn't configured stops it from starting.
+
+### Reports from cron
+
+With a `prompt` too, `ata2 run daily-logs.toml --output report.md` sends it once, with what the workflow attaches, and writes the response, for cron to make a daily summary of logs or a digest of news:
+
+```toml
+name = "Daily logs"
+system = "You read server logs for a team that has no time to."
+commands = ["journalctl --since yesterday --priority warning --no-pager"]
+prompt = "Summarize what went wrong yesterday, the most serious first."
+```
+
+```sh
+0 7 * * * cd ~/reports && ata2 run daily-logs.toml --output "logs-$(date +\%F).md"
+```
+
+It asks nothing, so a tool that `[tools.policy]` would ask about is refused. A request that fails for the rate limit, an error of the server or the connection is sent again, up to `--retries` times (3 by default), after 2, 4, 8… seconds, and it gives up after `--timeout` seconds in all (600 by default). The report is written once it's complete, so a run that fails doesn't leave half of one, and the exit code says why: 1 for the workflow, the configuration, what's attached or the output, 3 for requests that still failed and 4 for the timeout. Without `--output`, the response goes to stdout, and everything else to stderr.
+
+### Routing prompts between models
+
+With `enabled = true` under `[router]`, each prompt goes to a cheap model or a strong one, by what it's like, for a mix of quick questions and hard ones to cost less than it would all at the price of the strong model. A prompt goes to `strong` when it has one of the `markers` in it, like "think hard", has files attached, has code in it, or is longer than `max_cheap_tokens`, and to `cheap` otherwise. Above each response is which model it's from and why, as in `→ gpt-4o-mini (12 tokens)`. A prompt that starts with `@m=` goes to that model instead.
+
+```toml
+[router]
+enabled = true
+cheap = "gpt-4o-mini"
+strong = "gpt-4o"
+max_cheap_tokens = 400
+code = true
+attachments = true
+markers = ["think hard", "think carefully", "step by step"]
+```
+
+### Drafts from a fast model
+
+With `enabled = true` under `[draft]`, an experiment, a fast model (`model`, by default `gpt-4o-mini`) answers each prompt at once, while the configured model, which may take a while to think, answers it in the background. When its answer is in, it's printed under the draft, and `r` replaces the draft with it, `a` adds it after the draft, and Enter keeps the draft, for the conversation to go on from. With stdin not a terminal, it replaces the draft. Both answers are paid for. It's not for `api = "responses"`.
+
+```toml
+model = "o3"
+
+[draft]
+enabled = true
+model = "gpt-4o-mini"
+```
+
+### Responses API
+
+With `api = "responses"` in `ata2.toml`, conversations go through OpenAI's Responses API instead of chat completions. OpenAI keeps the conversation, so after the first exchange only the new prompt is sent. The ID of the last response is saved with the conversation, in `threads.json` in `save_dir` or in the database, so a resumed conversation continues where it left off. A conversation from chat completions is sent whole the first time.
+
+The model can use OpenAI's built-in tools:
+
+```toml
+api = "responses"
+
+[responses]
+tools = ["file_search", "code_interpreter"]
+vector_store_ids = ["vs_…"]  # the vector stores file_search searches
+```
+
+`/upload <file>` uploads a file for the next prompt. The model reads a PDF itself, and with `code_interpreter` any file is put where the code it runs can read it.
+
+When a tool runs in the middle of a response, it's shown where it ran, set apart by a bar: which tool, how long it took, the code it ran or what it searched for, and what came of it, up to `tools.max_output_bytes` of it, its start and its end. It's kept in the conversation as a tool message, which `ata2 share` folds away under its first line, and isn't sent with chat completions.
+
+### Evaluating prompts
+
+`ata2 eval suite.toml` sends each prompt of a suite to each of its models (by default, the configured one), checks the responses, and prints which passed, how long each took and, per model, what it all cost. It fails if any response didn't pass, so it can run in CI. `--repeat n` runs each case n times, for models that don't always answer alike.
+
+```toml
+models = ["gpt-4o-mini", "gpt-4o"]
+repeat = 3
+system = "You are terse."  # optional, and a case can have its own
+
+[[case]]
+name = "capital"
+prompt = "What's the capital of France? Answer in one word."
+matches = ["(?i)^paris\\.?$"]  # regular expressions the response has to match
+not_matches = ["London"]        # and mustn't
+
+[[case]]
+name = "json"
+prompt = 'Reply with {"answer": …}, the answer being 6 × 7.'
+json = { "/answer" = 42 }       # JSON pointers and their values; "" for the whole response
+```
+
+The response to a case with `json` has to be JSON, all of it or its first code block. The other parameters, like `temperature`, come from the configuration.
+
+### Comparing models
+
+`ata2 bench --models gpt-4o-mini,gpt-4o --prompt "…"` streams the response to the prompt from each model three times, or `--runs n`, and prints a table of the medians: how long the first token took, how many tokens a second followed it, and how long the whole response took, from your network and with your prompt. Without `--models`, it times the configured model.
+
+```
+Model        First token  Tokens/s   Total  Tokens
+gpt-4o-mini       0.41 s      82.3  2.37 s     163
+gpt-4o            0.58 s      61.0  3.21 s     159
+```
+
+### Sweeping parameters
+
+`ata2 sweep --param temperature=0:1:0.2 --prompt-file p.txt` sends the prompt in `p.txt` (`-` for stdin) once for each value of the parameter, and writes the responses to stdout, or to a file with `-o`. A parameter's values are a range, `start:end:step`, or a list, `a,b,c`. Give `--param` more than once to try every combination. `temperature`, `top_p`, `presence_penalty`, `frequency_penalty`, `max_tokens` and `model` can be swept. The others come from the configuration.
+
+By default, each response is a line of JSON with its parameters, how many tokens it was and how many seconds it took. `--format markdown` writes a section a response instead, headed by its parameters, to read them side by side.
+
+```sh
+ata2 sweep --param temperature=0,0.7,1.4 --param model=gpt-4o-mini,gpt-4o \
+    --prompt-file p.txt --format markdown -o sweep.md
+```
+
+`ata2 sweep` and `ata2 eval` send up to 4 requests at once (`concurrency`, or `ATA2_CONCURRENCY`), and show how many are done on a line of their own while they wait. The results still come in order. A model with a lower rate limit than the others can be held to fewer with `per_model`:
+
+```toml
+[scheduler]
+concurrency = 8
+per_model = { "o1" = 2 }
+```
+
+### Piping prompts
+
+When stdin isn't a terminal, ata² reads all of it and sends it as one prompt. Escape sequences, like the colors of a log, other control characters and bytes that aren't UTF-8 are stripped from it first, and from what you paste, with a warning saying how much was, so they don't garble the terminal or end up in saved conversations. With `--delimiter`, the text between lines that are just the delimiter is sent as a prompt of its own instead, each after the response to the one before, in one conversation:
+
+```sh
+printf 'Name a prime.\n===\nAnd the next one?\n' | ata2 --delimiter ===
+```
+
+`stdin_wrapper` frames what's piped in the same way each time, for the model to tell it from your question. `{input}` is replaced by the prompt, and `{filename}` by the name of the file that stdin is, as in `ata2 < notes.txt` on Linux, or `stdin`:
+
+```toml
+stdin_wrapper = "Here is {filename}:\n```\n{input}\n```"
+```
+
+With `--stdin-format jsonl`, each line is a message, like `{"role": "user", "content": "Name a prime."}`. The user's messages are sent as prompts, and those of `system` and `assistant` are added to the conversation as they are, where they come. A line that isn't a message stops anything from being sent.
+
+With `--stdin-format conversation`, stdin is a whole conversation, in the JSON that ata² saves conversations in, ending with a prompt. ata² sends it, prints the response to stderr as it streams in, and writes the conversation with the response added to stdout, for the next step of a script to read:
+
+```sh
+ata2 --stdin-format conversation < draft.json | jq '.[-1].content'
+```
+
+A script can set up the whole request on the command line, without a config file of its own: `--system` starts the conversation with that system message, in place of any it has, and `--template` sends each prompt piped in inside a prompt of `[templates]`, where it says `{text}` or after it:
+
+```sh
+ata2 --system "You are a strict JSON generator" --template extract_fields < input.txt
+```
+
+### Translating documents
+
+`ata2 translate --to ja < README.md > README.ja.md` translates a Markdown document, in parts of whole paragraphs small enough for a response each. Code blocks are copied as they are rather than sent, and the model is told to keep the Markdown of each part as it is, and to leave inline code and links alone. Each part is sent on its own, with the model and parameters of the configuration and none of a conversation.
+
+### Summarizing
+
+`ata2 summarize notes.md` prints a summary of a few sentences, and `--length detailed` one of the points of each part with the facts and figures that matter. It takes a file, read as `/attach` reads it, the URL of a web page, or `-` for stdin. What doesn't fit in one request is cut into chunks of whole paragraphs that are summarized at once, as `[scheduler]` allows, and their summaries are merged in groups until one is left.
+
+### Grammars for local models
+
+With a local server that constrains what a model generates, like llama.cpp's, `--grammar answer.gbnf` has every response follow that grammar, as a script that reads them needs. A `.json` file is taken for a JSON schema, and the grammar is derived from it: objects and their properties, arrays, strings, numbers, booleans, `null`, `enum`, `const` and `anyOf`. The schema is also sent as the `response_format`, for Ollama, which doesn't take grammars.
+
+```sh
+ata2 --grammar invoice.schema.json --template extract_fields < invoice.txt
+```
+
+### How sure the model was
+
+`--logprobs` asks for how likely each token of a response was, and shows the response again below it, shaded by that: plain for tokens the model was at least 90% sure of, then a darker background the less sure it was. `--logprobs 5` also shows, after each token it was unsure of, up to five alternatives it weighed, in brackets, for finding where a prompt leaves the model guessing. Without colors, the likelihood is bracketed too. It needs an API that gives logprobs, like OpenAI's chat completions.
+
+### Models run by ata² itself
+
+Built with `cargo install ata --features local`, ata² runs a GGUF model itself with llama.cpp, without a server, when `api = "local"`. `local-cuda`, `local-metal` and `local-vulkan` build it for a GPU instead. The model is loaded the first time it's asked something. Conversations, prompts and whatever else asks a model something, like `ata2 translate`, `ata2 bench`, `ata2 eval`, `ata2 sweep`, `/regen` and ghost text, go to it, in its own chat template, and `--grammar` holds for it too. `--fim` doesn't, as the model only chats. Building it needs CMake and a C++ compiler.
+
+```toml
+api = "local"
+model = "qwen2.5-7b-instruct"
+
+[provider.local]
+model = "/home/me/models/qwen2.5-7b-instruct-q4_k_m.gguf"
+# Tokens of prompt and response together, 0 for as many as the model was trained on.
+context_size = 8192
+# Layers offloaded to the GPU, for a build with one.
+gpu_layers = 99
+# 0 for as many as llama.cpp picks.
+threads = 0
+```
+
+### Filling in the middle
+
+`ata2 --fim` completes the text on stdin where it says `<CURSOR>`, and prints only what goes there, for an editor to insert. What's before the marker is sent as the prompt and what's after it as the suffix, to `fim_model` (by default `gpt-3.5-turbo-instruct`), which has to be a completions model rather than a chat one. Without a marker, the configuration's `suffix` comes after the text, if there is one.
+
+```sh
+printf 'def add(a, b):\n<CURSOR>\n\nprint(add(1, 2))\n' | ata2 --fim
+```
+
+### Editors
+
+An editor's plugin can keep `ata2 lsp` running and send it requests, instead of starting ata² for each one. They're JSON-RPC on stdin and stdout, each message after a `Content-Length` header as with a language server, so a client of those, like Neovim's `vim.lsp.rpc.start`, can talk to it, and each is answered as soon as it's done, whatever the order they came in:
+
+| Method | Params | Result |
+|---|---|---|
+| `complete` | `{"prefix": …, "suffix": …}` | What goes between them, from `fim_model`, as with `--fim` |
+| `edit-selection` | `{"text": …, "instruction": …, "language": "rust"}` | The text as the instruction has it |
+| `chat` | `{"prompt": …, "conversation": …}` | The response, going on from the prompts before it with the same `conversation` |
+
+Each result is `{"text": …}`, and `suffix`, `language` and `conversation` are optional. The configuration is read once, as ata² starts, and `--config` and `--model` go for every request.
+
+### A daemon for quick prompts
+
+Started anew for each prompt, as an editor's command might start it, ata² reads its configuration and connects to the API every time. `ata2 daemon` does that once and keeps the connection open, warming it up before the server closes it for being idle, and `ata2 ask "what does EINTR mean?"` sends a prompt to it and prints the response, starting in a few milliseconds. Without a daemon, `ata2 ask` sends the prompt itself. The daemon listens on a Unix socket in `$XDG_RUNTIME_DIR/ata2`, named after the profile, like `default.sock`, and answers with its own configuration, but with the `--model` of `ata2 ask`.
+
+With systemd, it can be started by the first `ata2 ask` instead, with a socket unit:
+
+```ini
+# ~/.config/systemd/user/ata2.socket
+[Socket]
+ListenStream=%t/ata2/default.sock
+SocketMode=0600
+
+[Install]
+WantedBy=sockets.target
+```
+
+```ini
+# ~/.config/systemd/user/ata2.service
+[Service]
+ExecStart=%h/.cargo/bin/ata2 daemon
+```
+
+and `systemctl --user enable --now ata2.socket`.
+
+### Caching responses
+
+With `enabled = true` under `[cache]`, `--fim`, `ata2 sweep`, `ata2 eval` and `ata2 summarize` keep each response on disk under a hash of its request: the model, the messages and every parameter. Sending the same request again, as a build script does each time it runs, gets the same response at once and at no cost, without a request to the API. Responses are used for 24 hours (`ttl_hours`, 0 for as long as they're there), and kept in `~/.cache/ata2` (`dir`). `ata2 --no-cache` sends requests anyway and caches the new responses instead. `ata2 eval` doesn't use the cache for a case that it runs more than once, and neither the conversations of the REPL nor `ata2 bench` ever do.
+
+```toml
+[cache]
+enabled = true
+ttl_hours = 168
+```
+
+### Queueing prompts offline
+
+On a train or a flaky connection, `ata2 --queue` queues a prompt that can't be sent because there's no connection, along with the conversation before it, instead of failing. `ata2 flush` sends the queued prompts later, oldest first: it prints each prompt and its response, and saves each conversation to `save_dir`, where `--load` continues it. It stops at the first prompt that still can't be sent, and a prompt the API fails on stays queued too. Each profile has its own queue, `queue-{profile}` next to its history file, or in the database with `storage = "sqlite"`.
+
+### Rotating API keys
+
+A team that shares rate limits across several API keys can list them under `[rotation]`, after `api_key`. With `strategy = "failover"`, the default, every request goes out with the same key until it's rate limited, and from then on with the next one. With `strategy = "round-robin"` each request takes the next key in turn. Either way, a chat or responses request that's rate limited is sent again with the next key, as long as there's one it wasn't sent with. `--fim`, `ata2 eval`, `ata2 sweep` and `ata2 bench` pick a key when they start.
+
+With `storage = "sqlite"`, the usage ledger records which key each exchange was made with, by its last four characters. `monthly_limit` then passes over a key once it has spent that many USD this calendar month (UTC), and `ata2 keys` lists the keys with what each spent.
+
+```toml
+[rotation]
+keys = ["sk-…", "sk-…"]
+strategy = "round-robin"
+monthly_limit = 50.0
+```
+
+### Models
+
+`ata2 models` lists the models the API offers your key, the configured one marked with `*`, with how many tokens of context each takes and what it can do, where either is known. The list is kept as `models.json` in the cache's `dir`, whether or not the cache is enabled: offline, `ata2 models` shows it as it was, and shell completion offers its models for `ata2 bench --models` and `ata2 sweep --param model=`.
+
+### When it doesn't work
+
+`ata2 doctor` checks what ata² needs and prints what passed (✓), what may get in the way (!) and what failed (✗): whether the configuration is there and valid, whether `api_base` answers, through the proxy of `HTTPS_PROXY` and the like if there is one, with what went wrong with TLS or the proxy if it doesn't, whether the API takes the key, as it lists its models, whether the configured model is one of them, whether the directories of the history, the conversations and the cache can be written to, and whether the terminal shows truecolor and sets the clipboard with OSC 52, for `/copy`. With `api = "local"`, it checks the model file instead of the API. It exits with an error if anything failed.
+
+### Reply language
+
+`reply_language = "German"` tells the model to reply in German, whatever language it's asked in, and `reply_language = "auto"` in the language each prompt is written in, or that of the locale (`LANG`) for a prompt too short or too full of code to tell. The instruction goes with the system message of each request, and isn't saved with the conversation.
+
+### Watching a file
+
+`ata2 watch --file build.log --prompt "Summarize new errors"` keeps an eye on a file and, whenever something is added to it, sends that with the prompt and prints the answer under the file's name and the time. What the file holds when it starts isn't sent, writes in quick succession are sent together, and of a large addition only the last 32 KiB go. A file that's truncated or replaced, as a build writing its log anew does, is read from the start. Each addition is asked about on its own, with the model and parameters of the configuration, and a request that fails is reported and skipped. Ctrl-C stops it.
+
+### Watching the clipboard
+
+`ata2 clipwatch --template translate-to-en` sends whatever text you copy with a prompt of `[templates]`, and puts the answer in the clipboard in its place, ready to paste. `{text}` in the prompt is replaced by what was copied, which otherwise comes after it. As it sends what you copy to the API, it only runs with `enabled = true` under `[clipwatch]`. Text of more than `max_chars` characters, or that `exclude` matches, is left alone, so that a password manager's passwords or a whole log aren't sent. The clipboard is looked at every `interval_ms` milliseconds, with `wl-paste` and `wl-copy`, `xclip` or `xsel` on Linux, `pbpaste` and `pbcopy` on macOS, and PowerShell on Windows.
+
+```toml
+[templates]
+translate-to-en = "Translate this to English. Answer with the translation only.\n\n{text}"
+
+[clipwatch]
+enabled = true
+max_chars = 4000
+exclude = '^\S{16,}$'  # a single long word, like a password or a token
+```
+
+### Full-screen interface
+
+`ata2 --tui` opens a full-screen interface instead: the conversation on top, a prompt box below, the conversations you saved with F2 in a sidebar and a status bar at the bottom. Enter sends the prompt and Alt-Enter starts a new line. Tab moves between the prompt and the sidebar, and Enter in the sidebar loads the selected conversation. Scroll with PageUp/PageDown or the mouse wheel. Dragging over the conversation copies the lines it covers to the clipboard. Ctrl-C cancels a response while it streams in, and quits otherwise.
+
 ### Keybindings
+
+`/help` and `ata2 --print-shortcuts` list the keys as your configuration binds them, along with the slash commands. By default:
+
 ```text
+Keyboard shortcuts:
+Ctrl-D              (With `ui.multiline_insertions`) Send the message you're
+                    typing.
+Enter               (With `ui.multiline_insertions`) Start a new line.
+F2                  Save the conversation (not including the message you're
+                    typing) to a file in `ui.save_dir`.
+Ctrl-G              Stop the response that is streaming in, keeping what
+                    arrived so far.
+Alt-W               Copy the last response to the clipboard (with OSC 52).
+Ctrl-X Ctrl-E       Edit the message you're typing in $VISUAL or $EDITOR.
+Ctrl-O              Print the rest of the response that is streaming in at
+                    once, ignoring `ui.stream_pacing`.
+Ctrl-R              Fuzzy-search the prompt history and the conversations
+                    saved in `ui.save_dir`. Picking a conversation offers to
+                    resume it.
+Tab                 (With `ui.ghost_text`) Accept the suggested rest of the
+                    prompt, shown dimmed after the cursor.
+Ctrl-C              Exit (when pressed twice in a row, with `ui.double_ctrlc`,
+                    the second time within `ui.double_ctrlc_window_ms`).
+
 EOF
-cat ./ata²/src/help/keybindings.txt
+cat ./ata²/src/help/rustyline.txt
 cat << 'EOF'
 ```
 
+ata²'s own bindings are set in `ata2.toml`, with chords like `ctrl-d`, `alt-enter`, `f2` or, for a sequence, `ctrl-x ctrl-e`. An empty chord unbinds the action.
+
+```toml
+[ui.keybindings]
+accept_line = "ctrl-d"
+newline = "enter"
+save_conversation = "f2"
+cancel_generation = "ctrl-g"
+copy_response = "alt-w"
+open_editor = "ctrl-x ctrl-e"
+```
+
+F2 saves conversations as `{date}-{title}.json` in the current directory, unless told otherwise. With `autosave`, the conversation is also saved after every response and on exit, to one file per session, and `ata2 --resume-last` picks up the newest one where it left off.
+
+```toml
+[ui]
+save_dir = "/home/me/Documents/ata2"
+save_filename = "{date}-{title}.json"
+autosave = true
+```
+
+Each profile keeps its own prompt history, `history-{profile}` in `~/.local/state/ata2` (`$XDG_STATE_HOME`) unless `history_file` says otherwise. It holds the last 1000 prompts (`history_max_entries`), each with the time it was sent, and a prompt sent again replaces its earlier copy unless `history_dedup = false`. With a long history, `history_load_max` has the REPL load only that many of the newest prompts, reading the file from its end, while the rest stay in the file as new ones are added to it. Several ata² can run at once, as in panes of tmux side by side: each saves the history with what the others added to it since it started, and they take turns saving conversations and the history, through lock files in `~/.local/state/ata2`, for none to write over another's.
+
+With `storage = "sqlite"`, saved conversations, the history of every profile and a ledger of each exchange's tokens and cost go into one database, `ata2.db` in `~/.local/share/ata2` (`$XDG_DATA_HOME`, `database_file`), instead of files. `ata2 search` and `/search` then find the prompts of the history too, and rank what they find by how well it matches. Conversations saved as files before are still listed and can still be loaded, and a profile's history file is read until the history is first saved to the database.
+
+```toml
+[ui]
+storage = "sqlite"
+```
+
+Nothing is deleted on its own. `ata2 gc` deletes the saved conversations older than `retention_days` and all but the newest `max_sessions`, drops prompts older than `retention_days` from the history, deletes cached responses older than `ttl_hours`, and removes temporary files a crash left behind. `retention_days` and `max_sessions` are 0, keep everything, by default. `ata2 gc --dry-run` lists what would go without deleting it. Like the rest of the configuration, the settings are per profile, so `ata2 --config work gc` cleans up after `work`.
+
+```toml
+[ui]
+retention_days = 90
+max_sessions = 500
+```
+
+A newline in something you paste doesn't send it. When a paste is 2000 characters or more (`large_paste_size`), ata² asks whether to send it in a code fence, as an attachment after the rest of your message, or as is. Set `large_paste` to `fence`, `attach` or `inline` to stop it from asking; the full-screen interface never asks and fences.
+
+Rather than paste a file, attach it: `/attach src/main.rs lines 40-120`, or `ata2 --attach src/main.rs:40-120` for the first prompt. Each file goes after the next prompt in a code fence, under its path and the lines it's from, each line with its number, for the model to say where something is. All of them together can't be more than `attachment_max_tokens`, 8000 unless set otherwise, or 0 for no limit.
+
+Of a PDF or a Word document (`.docx`), the text is attached, each page after a marker like `[Page 3]` so that answers can say which page they mean. A `.docx` is broken into pages where Word last broke it when it was saved, and where a page break was put in.
+
+Of a recording, like `/attach meeting.mp3`, the transcript is attached, from the transcription endpoint with `transcription_model` (default `whisper-1`), to ask for a summary of a meeting, say. A recording over 25 MB, more than the API takes, is split into parts with `ffmpeg`, which has to be installed for it.
+
+A CSV or TSV file is summed up rather than attached whole, which would fill the context window: its header, the type of each column, how many rows it has and `attachment_sample_rows` of them (default 20), taken evenly from the file. With `attachment_sample_rows = 0`, or lines like `/attach data.csv lines 1-50`, it's attached as it is.
+
+For a question about a whole project, `/attach-tree src/ --max-files 50 --glob '*.rs'` attaches the tree of the files under `src/` that the glob matches, and their contents, the most recently changed first and then the smallest, as many as `--max-files` (50 by default) and the tokens left allow. Hidden files and directories are left out. `*` in the glob matches within a directory, `**` across them.
+
+The lines above your input and above each response are `prompt_format` and `response_format`. `{model}`, `{profile}`, `{time}`, `{turn}` and `{cost}` are filled in, and tags like `<dim>…</dim>`, `<cyan>…</cyan>` or `<#ffaf00>…</#ffaf00>` style what they enclose:
+
+```toml
+[ui]
+prompt_format = "Prompt <dim>#{turn} · {model} · {cost}</dim>"
+response_format = "<green>Response</green> <dim>{time}</dim>"
+```
+
+With `status_line = true`, a dim line above the prompt shows the model, the profile, how full the context is and what the session has cost. OpenAI caches the start of prompts it has seen recently, which a conversation's next prompt always repeats, and bills it for less; ata² sends each conversation's requests with the same `prompt_cache_key` so they go where it's cached, counts tokens and cost as OpenAI reports them, and adds how much of the session's prompts was cached to the status line. Other servers, which may not know `prompt_cache_key` or the option that has a stream report its usage, aren't sent either, and what's sent to them is counted with tiktoken.
+
+With `show_meta = true`, a dim line below each response shows the model as the API names it, why it stopped (`stop`, `length`, …), its prompt and completion tokens, how long it took to the first token and in all, and the `system_fingerprint`, for telling apart responses that came from different backends.
+
+Colors are set in `[ui.theme]`. A style is any of `bold`, `dim`, `italic`, `underline` and `reverse`, a color, and `on` followed by a background color. Colors are `black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan` and `white`, each also as `bright-red` and so on, numbers 0–255 of the 256-color palette, and `#rrggbb` on terminals with true color. An empty style leaves the text as is. The defaults are:
+
+```toml
+[ui.theme]
+prompt = "bold"       # prompt_format
+response = "bold"     # response_format
+error = "bold red"    # errors, like those of the API
+code = "cyan"         # code in your input
+user = ""             # your input
+```
+
+Nothing is styled when the output isn't a terminal, or when `NO_COLOR` is set. `--color=never` turns styles off regardless, and `--color=always` keeps them even in a pipe, as in `ata2 --color=always | less -R`.
+
+For a screen reader or braille display, set `screen_reader = true` under `[ui]` or run `ata2 --a11y`. Output is then plain text, one line at a time: no styles, spinner, progress, typewriter pacing, suggestions or token counts, and the `Prompt:` and `Response:` lines (`prompt_format` and `response_format`) tell whose turn it is. `--tui` falls back to the line-based interface.
+
+Responses are wrapped at word boundaries to the width of the terminal, following it as it is resized. The lines a list item wraps onto are indented to line up with its text, and code blocks are left as they are so they can be copied. Set `wrap` to a number of columns to wrap narrower, or to `"off"` to leave wrapping to the terminal.
+
+Links in responses are clickable on terminals that support hyperlinks (OSC 8), such as those based on VTE, kitty, WezTerm, iTerm2, foot, Windows Terminal and Konsole. Elsewhere they're printed as they are. Set `hyperlinks = false` to always print them plainly, or `FORCE_HYPERLINK=1` for a terminal ata² doesn't recognize.
+
+When a response revises code from earlier in the conversation, the block is printed as a colored unified diff against the earlier version instead of in full. A block counts as a revision of the most similar earlier one in the same language, if they have at least half their lines in common. What's kept in the conversation, copied or saved is the whole block all the same. Set `diff_code = false` to print code in full, or switch for the session with `/diff`.
+
+A response cut off at `max_tokens` says so, and `/continue` asks the model for the rest. What it answers is added to the same response, leaving out what it repeats of the end. With `continue_truncated = "auto"` that happens right away, up to five times for one response.
+
+While you type, code blocks and `inline code` in your input are colored (`highlight_input`), and an estimate of how many tokens your input is shows after it (`input_tokens`). Sending more than 10000 (`confirm_above_tokens`, 0 to never ask) asks you to confirm first, in case that paste was bigger than you thought. With `forecast = true`, each request is estimated before it's sent, with the conversation before it, the system message and what's attached, as in `This request ≈ 6.2k prompt tokens (~$0.03)`, priced for the model it goes to. A request estimated to cost more USD than `confirm_above_cost` (0, the default, to never ask) asks you to confirm first.
+
+ata²'s messages are in the language of your locale (`LANG`, or `LC_MESSAGES` or `LC_ALL`) if there is a translation to it, and in English otherwise. See [`ata²/locales`](ata²/locales/README.md) for how to add one.
+
 # License
 
    Copyright 2023 Fredrick R. Brennan &lt;copypaste@kittens.ph&gt;, Rik Huijzer &lt;rikhuijzer@pm.me&gt;, &amp; ATA Project Authors
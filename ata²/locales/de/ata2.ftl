@@ -0,0 +1,641 @@
+# Deutsche Übersetzung der Meldungen von ata².
+
+## Fragen
+
+answer-yes = j
+yes-no = [j/N]
+
+## Fehlende Konfigurationsdatei
+
+config-missing =
+    Die Datei `{ $filename }` wurde nicht gefunden. Lege { $path } an, um das zu beheben.
+
+    Zum Beispiel mit diesem Inhalt (dem Text zwischen den ```):
+
+    ```
+    { $example }
+    ```
+
+    Ersetze darin `<YOUR SECRET API KEY>` durch deinen API-Schlüssel, den du unter https://beta.openai.com/account/api-keys beantragen kannst.
+
+    `max_tokens` legt fest, wie viele Tokens eine Antwort des Servers höchstens haben darf.
+    Längere Antworten werden abgeschnitten.
+
+    `temperature` legt die „sampling temperature“ fest. Aus der Dokumentation der OpenAI-API: „What sampling temperature to use. Higher values means the model will take more risks. Try 0.9 for more creative applications, and 0 (argmax sampling) for ones with a well-defined answer.“ Laut Stephen Wolfram { "[" }1] funktioniert ein höherer Wert wie 0.8 in der Praxis wahrscheinlich am besten.
+
+
+    { "[" }1]: https://writings.stephenwolfram.com/2023/02/what-is-chatgpt-doing-and-why-does-it-work/
+config-write-example = Soll ich diese Beispieldatei nach { $path } schreiben, damit du sie bearbeiten kannst?
+
+## Der API-Schlüssel im Anmeldeinformationsspeicher
+
+credentials-prompt = API-Schlüssel:
+credentials-no-key = Kein API-Schlüssel angegeben
+credentials-saved = Der API-Schlüssel des Profils { $profile } wurde im Anmeldeinformationsspeicher gespeichert.
+credentials-read-failed = Der API-Schlüssel konnte nicht aus dem Anmeldeinformationsspeicher gelesen werden: { $error }
+credentials-unsupported = Auf diesem System gibt es keinen Anmeldeinformationsspeicher, setze stattdessen api_key in der Konfigurationsdatei oder OPENAI_API_KEY
+
+## Befehle
+
+commands-heading = Befehle:
+command-help = Die verfügbaren Befehle auflisten.
+command-continue = Die letzte Antwort fortsetzen, die bei max_tokens abgeschnitten wurde.
+command-regen = Die letzte Antwort k-mal neu anfordern (Standard: 3) und wählen, welche bleibt.
+command-diff = Von einer Antwort überarbeiteten Code vollständig zeigen, oder als Diff zur früheren Fassung.
+command-load = Das Gespräch durch eines aus einer Datei ersetzen, wie --load.
+command-links = Die Links der letzten Antwort auflisten, oder Link n der Liste öffnen.
+command-model = Das Modell ausgeben, an das Prompts gehen, oder sie ab jetzt an ein anderes schicken.
+command-models = Die Modelle auflisten, die die API anbietet, wie ata2 models.
+command-search = Die gespeicherten Gespräche finden, die alle Wörter enthalten.
+command-resume = Gespräch n der letzten /search fortsetzen, oder das neueste gespeicherte Gespräch.
+command-show = Die letzten n Wechsel des Gesprächs erneut ausgeben (Standard: 1).
+command-append = Ein gespeichertes Gespräch an dieses anhängen, oder eine Zusammenfassung davon, wenn nicht beide passen.
+command-tag = Das Gespräch mit Tags versehen, nachdem es gespeichert wird, falls es das noch nicht ist. -tag entfernt einen Tag, ohne Angabe werden die Tags angezeigt.
+command-pin = Nachricht n, wie /show sie nummeriert, oder den letzten Prompt immer senden, wenn ältere weggelassen werden, damit das Gespräch passt. -n hebt das auf.
+command-trust = Für den Rest der Sitzung nicht mehr fragen, bevor das Werkzeug benutzt wird, oder anzeigen, wie jedes Werkzeug benutzt wird.
+command-tmux = Den Verlauf eines tmux-Panes zum Gespräch hinzufügen, oder den letzten Codeblock des Gesprächs in ein Pane einfügen.
+command-attach = Eine Textdatei, oder nur die Zeilen a bis b, mit dem nächsten Prompt senden.
+command-attach-tree = Den Dateibaum eines Verzeichnisses mit dem nächsten Prompt senden, und so viele der neuesten und kleinsten Dateien, wie passen.
+command-attachments = Die für den nächsten Prompt angehängten Dateien auflisten, oder entfernen.
+command-upload = Eine Datei für den nächsten Prompt hochladen, mit api = "responses".
+command-unknown = Unbekannter Befehl: { $line }
+command-usage = Aufruf: /{ $name } { $usage }
+
+shortcuts-heading = Tastenkürzel:
+key-accept-line = Die Nachricht senden, die du gerade schreibst.
+key-newline = Eine neue Zeile beginnen.
+key-save-conversation = Das Gespräch (ohne die Nachricht, die du gerade schreibst) in eine Datei in `ui.save_dir` speichern.
+key-cancel-generation = Die Antwort, die gerade eintrifft, anhalten und behalten, was bisher angekommen ist.
+key-copy-response = Die letzte Antwort in die Zwischenablage kopieren (mit OSC 52).
+key-open-editor = Die Nachricht, die du gerade schreibst, in $VISUAL oder $EDITOR bearbeiten.
+key-skip-pacing = Den Rest der Antwort, die gerade eintrifft, auf einmal ausgeben, ohne `ui.stream_pacing`.
+key-search = Den Prompt-Verlauf und die in `ui.save_dir` gespeicherten Gespräche unscharf durchsuchen. Ein gewähltes Gespräch lässt sich fortsetzen.
+key-accept-suggestion = Den vorgeschlagenen, blass hinter dem Cursor angezeigten Rest des Prompts übernehmen.
+key-exit = Beenden.
+key-exit-twice = Beenden, wenn zweimal hintereinander gedrückt.
+
+links-no-response = Noch keine Antwort
+links-none = Die letzte Antwort enthält keine Links.
+links-opened = { $url } geöffnet
+links-no-such = Kein Link { $n }, die letzte Antwort hat { $count }
+conversation-empty = Das Gespräch ist leer
+tags = Tags: { $tags }
+tags-none = Keine Tags
+tags-unsaved = Das Gespräch ist noch nicht gespeichert und hat daher keine Tags
+sessions-no-such = Kein gespeichertes Gespräch { $name }
+pinned = Nachricht { $n } angeheftet
+unpinned = Nachricht { $n } nicht mehr angeheftet
+pin-no-such = Keine Nachricht { $n }, das Gespräch hat { $count }
+show-pinned = #{ $n } · angeheftet
+context-trimmed = { $count ->
+        [one] Die älteste Nachricht weggelassen
+       *[other] Die { $count } ältesten Nachrichten weggelassen
+    }, damit das Gespräch passt, { $tokens } Tokens werden gesendet
+sessions-merged = { $count } Gespräche in { $path } zusammengeführt
+appended = { $count ->
+        [one] Eine Nachricht
+       *[other] { $count } Nachrichten
+    } von { $name } angehängt
+append-summarizing = Mit { $name } hätte das Gespräch { $tokens } Tokens, mehr als passen, daher wird es zusammengefasst…
+appended-summary = Eine Zusammenfassung von { $name } angehängt
+tmux-no-pane = Nicht in tmux, und kein Pane zum Erfassen angegeben
+tmux-no-code-block = Kein Codeblock im Gespräch gefunden
+tmux-captured = { $lines ->
+        [one] 1 Zeile
+       *[other] { $lines } Zeilen
+    } aus dem tmux-Pane { $pane } zum Gespräch hinzugefügt.
+tmux-pasted = Codeblock in das tmux-Pane { $pane } eingefügt.
+continue-nothing = Die letzte Antwort wurde nicht abgeschnitten
+continue-offer = Bei max_tokens abgeschnitten. /continue setzt sie fort.
+
+run-failed = { $program } konnte nicht gestartet werden: { $error }
+program-failed = { $program } ist fehlgeschlagen: { $error }
+
+## Prompts senden
+
+# Die Buchstaben in Klammern sind die Antworten und bleiben f, a und i.
+paste-choose = Du hast { $lines ->
+        [one] eine Zeile
+       *[other] { $lines } Zeilen
+    } eingefügt ({ $chars ->
+        [one] ein Zeichen
+       *[other] { $chars } Zeichen
+    }). (f) als Codeblock, (a) als Anhang oder (i) direkt senden? [f]
+confirm-tokens = Dieser Prompt hat etwa { $tokens } Tokens. Senden?
+not-sent = Nicht gesendet. Mit Pfeil nach oben holst du ihn zurück.
+press-ctrl-c-again = Zum Beenden erneut Strg-C drücken.
+press-ctrl-c-again-within = Zum Beenden innerhalb von { $seconds } s erneut Strg-C drücken.
+input-stripped = { $bytes ->
+        [one] Ein Byte
+       *[other] { $bytes } Bytes
+    } an Escape-Sequenzen, Steuerzeichen und ungültigem UTF-8 aus der Eingabe entfernt.
+empty-prompt = Leerer Prompt, Abbruch.
+jsonl-invalid = Zeile { $line } der Standardeingabe ist keine Nachricht: { $error }
+stdin-conversation-invalid = Die Standardeingabe ist kein Gespräch: { $error }
+stdin-conversation-no-prompt = Das Gespräch auf der Standardeingabe endet nicht mit einem Prompt, auf den zu antworten wäre
+jsonl-role = Zeile { $line } der Standardeingabe: Nur Nachrichten von system, user und assistant können gelesen werden
+override-unknown = @{ $name }: Diesen Parameter gibt es nicht, nur { $params }
+override-not-valid = @{ $name }={ $value }: Kein gültiger Wert
+override-no-prompt = Nach den Parametern ist nichts zu senden
+regen-nothing = Es gibt keine Antwort, die neu angefordert werden könnte.
+regen-responses = /regen braucht api = "chat", da Antworten von der letzten aus fortgesetzt werden.
+regen-candidate = Antwort { $n }:
+regen-failed = Antwort { $n } fehlgeschlagen: { $error }
+request-failed = Die Anfrage ist fehlgeschlagen: { $error }
+regen-choose = Welche soll bleiben? [1–{ $count }, Enter behält die bisherige]
+regen-kept = Antwort { $n } behalten.
+diff-on = Überarbeiteter Code wird als Diff gezeigt.
+diff-off = Überarbeiteter Code wird vollständig gezeigt.
+
+## Fehler der API
+
+api-error-key = Der API-Schlüssel { $key } wurde abgelehnt. Prüfe api_key in ata2.toml, oder speichere einen anderen mit ata2 --save-key.
+api-error-model = Dein API-Schlüssel hat keinen Zugriff auf { $model }, oder es gibt kein solches Modell. Führe ata2 models aus, um die zu sehen, die er nutzen kann.
+api-error-quota = Dein OpenAI-Konto hat kein Guthaben mehr oder sein Budget überschritten. Siehe https://platform.openai.com/settings/organization/billing
+api-error-rate-limit = Ratenlimit erreicht. Warte einen Moment und versuch es noch einmal, oder verteile die Anfragen unter [rotation] auf mehr Schlüssel.
+api-error-context = Das Gespräch ist zu lang für { $model }. Beginne ein neues, oder wechsle zu einem Modell mit größerem Kontextfenster.
+api-error-server = Die Server von OpenAI sind fehlgeschlagen ({ $status }). Versuch es gleich noch einmal.
+
+## Gespräche
+
+conversation-resumed = { $path } fortgesetzt.
+conversation-resume-failed = { $path } konnte nicht fortgesetzt werden: { $error }
+conversation-busy = Das Gespräch ist beschäftigt, versuche es noch einmal.
+conversation-loaded = { $path } geladen
+conversation-load-failed = { $path } konnte nicht geladen werden: { $error }
+conversation-saved = Gespräch unter { $path } gespeichert
+conversation-save-failed = Das Gespräch konnte nicht gespeichert werden: { $error }
+conversation-autosave-failed = Das Gespräch konnte nicht automatisch gespeichert werden: { $error }
+
+## ata2 search
+
+search-no-results = Nichts gefunden.
+search-prompt = Prompt des Profils { $profile }
+search-resume-hint = /resume n setzt Gespräch n fort.
+search-no-such = Kein Gespräch { $n }, die letzte Suche hat { $count } gefunden
+search-failed = Die Suche ist fehlgeschlagen: { $error }
+# In der Vollbildsuche von Strg-R.
+search-status = { $matches }/{ $total } · Enter: auswählen · Esc: abbrechen
+# Die Buchstaben in Klammern bleiben, sie sind die Antworten.
+search-confirm-resume = { $path } fortsetzen? (y/n)
+
+## ata2 share
+
+share-written = { $path } geschrieben
+share-no-token = Setze integrations.github.token oder GITHUB_TOKEN auf einen Token mit dem Scope gist, um Gists hochzuladen
+share-gist-failed = Das Gist konnte nicht erstellt werden: { $error }
+share-footer = Geteilt aus ata²
+
+## ata2 eval
+
+eval-pass = OK
+eval-fail = FEHLER
+eval-error = { $error }
+eval-no-match = passt nicht auf { $regex }
+eval-matches = passt auf { $regex }
+eval-not-json = ist kein JSON
+eval-json-missing = hat nichts bei { $pointer }
+eval-json-differs = hat { $actual } bei { $pointer }, nicht { $expected }
+eval-summary = { $model }: { $passed } von { $runs } bestanden, durchschnittlich { $latency } s, { $cost }
+eval-cost-unknown = Kosten unbekannt
+eval-failed = { $count ->
+        [one] Ein Durchlauf
+       *[other] { $count } Durchläufe
+    } fehlgeschlagen
+
+## ata2 bench
+
+bench-run = { $model } #{ $n }: erstes Token nach { $first_token }, { $rate } Tokens/s, insgesamt { $total }, { $tokens } Tokens
+bench-error = { $model }: { $error }
+bench-model = Modell
+bench-first-token = Erstes Token
+bench-rate = Tokens/s
+bench-total = Gesamt
+bench-tokens = Tokens
+
+## ata2 sweep und ata2 eval, die Anfragen gleichzeitig senden
+
+scheduler-progress = { $done } von { $total } fertig, { $running } laufen
+scheduler-zero = scheduler.concurrency und die Grenzen von scheduler.per_model müssen mindestens 1 sein
+
+## ata2 sweep
+
+sweep-bad-param = { $param }: weder name=start:ende:schritt noch name=a,b,c
+sweep-unknown-param = { $name } kann nicht variiert werden, nur { $params }
+sweep-not-number = { $name }: { $value } ist kein gültiger Wert
+sweep-bad-range = { $param }: der Bereich muss start:ende:schritt sein, mit positivem Schritt und Ende nicht vor dem Start
+sweep-stats = { $seconds } s · { $tokens ->
+        [one] ein Token
+       *[other] { $tokens } Tokens
+    }
+sweep-error = Fehlgeschlagen: { $error }
+sweep-written = { $count ->
+        [one] Eine Antwort
+       *[other] { $count } Antworten
+    } in { $path } geschrieben
+
+## [tools.policy], /trust
+
+policy-ask-tmux = { $enter ->
+        [true] Das in tmux-Pane { $pane } einfügen und ausführen?
+       *[other] Das in tmux-Pane { $pane } einfügen?
+    }
+policy-ask-tool = Das Modell für diesen Prompt { $tool } benutzen lassen?
+policy-cannot-ask = { $tool } wird nicht benutzt, da es kein Terminal gibt, um zu fragen
+policy-not-allowed = { $tool } ist durch tools.policy nicht erlaubt
+policy-unknown-tool = Unbekanntes Werkzeug { $tool }, nur { $tools }
+trusted = { $tool } wird für den Rest der Sitzung vertraut, sofern tools.policy es nicht verbietet
+
+## api = "responses"
+
+upload-chat-api = Dateien können nur mit api = "responses" hochgeladen werden
+upload-no-such = Keine solche Datei: { $path }
+upload-not-pdf = Ohne das Werkzeug code_interpreter können nur PDFs hochgeladen werden
+upload-done = { $path } als { $id } hochgeladen, für den nächsten Prompt
+tool-truncated = … { $count ->
+        [one] ein Byte
+       *[other] { $count } Bytes
+    } ausgelassen …
+tool-timed-out = { $tool } nach { $secs } s aufgegeben, wie tools.timeouts es vorgibt
+tool-outside-workdir = { $path } liegt außerhalb von { $workdir }, worauf tools.workdir Werkzeuge beschränkt
+
+## [tools.custom]
+
+policy-ask-command = { $tool } `{ $command }` ausführen lassen?
+tool-unknown = Es gibt kein Werkzeug { $tool }
+tool-bad-arguments = Die Argumente von { $tool } sind kein JSON-Objekt: { $error }
+tool-declined = { $tool } durfte nicht ausgeführt werden
+tool-failed = (beendet mit { $status })
+tool-http-status = (HTTP { $status })
+tool-too-many-rounds = Modell nach { $count ->
+        [one] einer Runde
+       *[other] { $count } Runden
+    } von Werkzeugaufrufen in Folge angehalten
+
+## ata2 tools import
+
+openapi-imported = { $count ->
+        [one] Ein Werkzeug
+       *[other] { $count } Werkzeuge
+    } von { $api } importiert. Für die Authentifizierung die headers von [tools.apis.{ $api }] ausfüllen.
+openapi-none = Keine der Operationen der Spezifikation passt
+openapi-no-base-url = Die Spezifikation sagt nicht, wo { $api } ist, also base_url von [tools.apis.{ $api }] setzen
+
+## ata2 new --from
+
+workflow-started = { $name } gestartet.
+workflow-unknown-tool = Der Arbeitsablauf nennt { $tool }, das weder in tools.custom steht noch code_interpreter oder file_search ist
+
+## ata2 run
+
+report-no-prompt = { $file } hat keinen prompt zum Senden
+report-retrying = Erneuter Versuch in { $seconds } s ({ $attempt } von { $retries }): { $error }
+report-timed-out = Nach { $seconds } s aufgegeben
+report-written = { $file } geschrieben.
+
+## ata2 lsp
+
+lsp-no-method = Die Anfrage hat keine Methode
+lsp-unknown-method = Es gibt keine Methode { $method }
+
+## ata2 daemon
+
+daemon-listening = Lauscht auf { $socket }.
+daemon-running = Auf { $socket } lauscht schon ein Daemon
+daemon-unix-only = ata2 daemon braucht Unix-Sockets, die dieses System nicht hat
+
+## ata2 --fim
+
+fim-markers = Die Eingabe enthält mehr als ein { $marker }
+
+## ata2 gc
+
+gc-deleted = { $path } gelöscht
+gc-would-delete = Würde { $path } löschen
+gc-history = { $count ->
+        [one] Einen Prompt
+       *[other] { $count } Prompts
+    } aus dem Verlauf in { $path } entfernt
+gc-would-drop = Würde { $count ->
+        [one] einen Prompt
+       *[other] { $count } Prompts
+    } aus dem Verlauf in { $path } entfernen
+gc-nothing = Nichts zu löschen.
+
+## ata2 keys
+
+keys-none = Kein API-Schlüssel ist eingerichtet
+keys-spent = { $key }: ${ $spent } diesen Monat
+keys-spent-limit = { $key }: ${ $spent } von ${ $limit } diesen Monat
+keys-untracked = { $key } (mit storage = "sqlite" wird festgehalten, was er ausgibt)
+keys-spent-all = Jeder API-Schlüssel hat diesen Monat rotation.monthly_limit ausgegeben
+keys-rate-limited = API-Schlüssel { $key } ist rate-limitiert
+keys-ledger-read-failed = Das Nutzungsprotokoll konnte nicht gelesen werden: { $error }
+keys-ledger-write-failed = Das Nutzungsprotokoll konnte nicht ergänzt werden: { $error }
+
+## ata2 models
+
+models-context = { $tokens } Token
+models-capability = { $capability ->
+        [chat] Chat
+        [tools] Werkzeuge
+        [vision] Bilder ein
+        [audio] Audio
+        [reasoning] Reasoning
+        [completions] Completions
+        [embeddings] Embeddings
+        [images] Bilder aus
+        [transcription] Transkription
+        [speech] Sprache
+        [moderation] Moderation
+       *[other] { $capability }
+    }
+models-unexpected = Die Liste der Modelle der API ist nicht wie erwartet
+models-cache-failed = Die Liste der Modelle konnte nicht in { $path } abgelegt werden: { $error }
+models-offline = Die API war nicht erreichbar ({ $error }), also sind das die Modelle, die sie letztes Mal angeboten hat
+model-switched = Prompts gehen ab jetzt an { $model }
+
+## ata2 watch
+
+watch-started = { $file } wird beobachtet, Strg-C zum Beenden.
+watch-answer = { $file } um { $time }:
+
+## ata2 translate
+
+translate-part = Teil { $part } von { $count } wird übersetzt…
+translate-cut-off = Die Übersetzung von Teil { $part } wurde bei max_tokens abgeschnitten.
+
+## ata2 summarize
+
+summarize-empty = In { $input } gibt es nichts zusammenzufassen.
+summarize-fetch-failed = { $url } konnte nicht heruntergeladen werden: { $error }
+
+## --grammar
+
+grammar-unreadable = Die Grammatik { $path } konnte nicht gelesen werden: { $error }
+grammar-no-root = { $path } hat keine Regel root, mit der eine Grammatik beginnt.
+grammar-unsupported = Aus einem JSON-Schema mit { $keyword } lässt sich keine Grammatik ableiten.
+
+## api = "local"
+
+local-not-built = api = "local" braucht ein mit dem Feature local gebautes ata²: cargo install ata --features local
+local-loading = { $path } wird geladen …
+local-load-failed = Das Modell { $path } konnte nicht geladen werden: { $error }
+local-no-template = Das Modell hat keine verwendbare Chatvorlage: { $error }
+local-too-long = Die Unterhaltung hat { $tokens } Token, der Kontext des Modells nur { $context }.
+local-no-completions = Ein Modell mit api = "local" kann nur chatten, keinen Prompt in der Mitte ergänzen.
+
+## [router]
+
+router-routed = → { $model } ({ $reason })
+router-marker = „{ $marker }“ steht darin
+router-attachments = Dateien sind angehängt
+router-code = Code steht darin
+router-long = { $tokens } Token
+router-short = { $tokens } Token
+
+## [draft]
+
+draft-waiting = { $model } verbessert die Antwort …
+draft-failed = { $model } konnte die Antwort nicht verbessern: { $error }
+draft-refined = Verbessert von { $model }:
+draft-choose = Den Entwurf damit e[r]setzen, es [a]nhängen, oder mit Enter den Entwurf behalten?
+
+## --logprobs
+
+logprobs-legend = Schattiert nach der Wahrscheinlichkeit jedes Tokens: ohne ab 90 %, dann ab 50 %, ab 20 % und darunter, mit [was das Modell sonst erwog].
+logprobs-legend-plain = Auf Tokens unter 90 % folgt [wie wahrscheinlich sie waren | was das Modell sonst erwog].
+logprobs-none = Die API hat nicht gesagt, wie wahrscheinlich die Tokens waren.
+
+## ui.show_meta
+
+meta = { $model } · { $finish_reason } · { $prompt_tokens } + { $completion_tokens } Tokens · { $first_token } s bis zum ersten Token, { $total } s insgesamt
+
+## ui.forecast
+
+forecast = Diese Anfrage ≈ { $tokens } Prompt-Token (~${ $cost })
+forecast-unpriced = Diese Anfrage ≈ { $tokens } Prompt-Token
+confirm-forecast = { $forecast }. Senden?
+
+## ata2 doctor
+
+doctor-configuration = Konfiguration
+doctor-connection = Verbindung
+doctor-key = API-Schlüssel
+doctor-model = Modell
+doctor-history = Verlauf
+doctor-conversations = Unterhaltungen
+doctor-cache = Cache
+doctor-terminal = Terminal
+doctor-colors = Farben
+doctor-osc52 = Zwischenablage (OSC 52)
+doctor-config-missing = { $path } existiert nicht
+doctor-via-proxy = (über { $proxy })
+doctor-connection-ok = { $url } antwortet ({ $status })
+doctor-key-none = nicht nötig, da auth = "none"
+doctor-key-missing = es gibt keinen
+doctor-key-ok = angenommen, { $count } Modelle werden angeboten
+doctor-model-missing = { $model } ist keines der Modelle, die die API anbietet
+doctor-model-unchecked = { $model } konnte nicht geprüft werden
+doctor-not-a-file = { $path } ist keine Datei
+doctor-not-a-terminal = stdout ist kein Terminal
+doctor-colors-off = aus
+doctor-truecolor = Truecolor
+doctor-no-truecolor = 256 oder weniger, da COLORTERM nicht truecolor ist (TERM={ $term })
+doctor-osc52-yes = { $term } unterstützt sie
+doctor-osc52-no = { $term } unterstützt sie nicht, /copy erreicht die Zwischenablage also nicht
+doctor-osc52-tmux = tmux reicht sie nur mit set-clipboard on weiter
+doctor-osc52-unknown = unbekannt, ob { $term } sie unterstützt
+doctor-failed = { $count ->
+    [one] 1 Prüfung ist fehlgeschlagen
+   *[other] { $count } Prüfungen sind fehlgeschlagen
+}
+
+## /attach, --attach
+
+attach-added = { $name } angehängt, ~{ $tokens } Tokens.
+attach-total = { $tokens } Tokens für den nächsten Prompt angehängt.
+attach-total-of = { $tokens } von { $max } Tokens für den nächsten Prompt angehängt.
+attach-none = Nichts angehängt.
+attach-cleared = Anhänge entfernt.
+attach-unreadable = { $path } konnte nicht gelesen werden: { $error }
+attach-not-text = { $path } ist kein Text.
+attach-bad-lines = Keine Zeilen: { $range }. Etwa 40-120, 40- oder 40.
+attach-no-lines = { $path } hat nur { $count } Zeilen.
+attach-name-lines = { $path }, Zeilen { $first }–{ $last }
+attach-not-dir = { $path } ist kein Verzeichnis.
+attach-name-tree = { $path }, { $included } von { $count ->
+        [one] einer Datei
+       *[other] { $count } Dateien
+    }
+attach-over-budget = { $name } hat ~{ $tokens } Tokens, von attachment_max_tokens sind aber nur noch { $left } übrig.
+attach-no-match = Nichts passt zu { $pattern }.
+attach-command-failed = { $command } ist fehlgeschlagen: { $error }
+extract-page = [Seite { $page }]
+tabular-shape = { $rows } Zeilen, { $columns } Spalten:
+tabular-empty = { $count } leer
+tabular-sample = { $rows } der { $count } Zeilen:
+transcribe-started = { $path } wird transkribiert…
+transcribe-part = Teil { $part } von { $count } wird transkribiert…
+transcribe-no-duration = Die Länge von { $path } ließ sich nicht bestimmen, um die Datei aufzuteilen.
+transcribe-no-ffmpeg = Aufnahmen über 25 MB werden zum Transkribieren mit ffmpeg aufgeteilt, { $program } wurde aber nicht gefunden.
+
+## [templates], --template
+
+template-unknown = Keine Vorlage { $name } in [templates], nur: { $templates }
+
+## ata2 clipwatch
+
+clipwatch-disabled = ata2 clipwatch sendet, was Sie kopieren, an die API; setzen Sie clipwatch.enabled = true, um das zu erlauben
+clipwatch-started = Die Zwischenablage wird beobachtet, Strg-C zum Beenden.
+clipwatch-too-long = Kopiertes nicht bearbeitet, { $chars } Zeichen sind mehr als clipwatch.max_chars.
+clipwatch-excluded = Kopiertes nicht bearbeitet, clipwatch.exclude trifft darauf zu.
+clipwatch-replaced = Kopiertes ({ $chars } Zeichen) durch die Antwort ersetzt.
+clipboard-no-program = Keines von { $programs } gefunden, um die Zwischenablage zu nutzen
+
+## ata2 flush
+
+queue-queued = Keine Verbindung. Der Prompt wartet darauf, dass ata2 flush ihn sendet, { $count ->
+        [one] als einziger
+       *[other] mit { $count } insgesamt
+    }.
+flush-nothing = Nichts in der Warteschlange.
+flush-saved = In { $path } gespeichert
+flush-offline = Immer noch keine Verbindung, { $count ->
+        [one] ein Prompt wartet
+       *[other] { $count } Prompts warten
+    } weiter.
+flush-kept = { $count ->
+        [one] Ein Prompt konnte
+       *[other] { $count } Prompts konnten
+    } nicht gesendet werden und { $count ->
+        [one] wartet
+       *[other] warten
+    } weiter.
+
+## --config https://…
+
+config-remote-failed = Die Konfiguration unter { $url } konnte nicht heruntergeladen werden: { $error }
+config-remote-cached = Die Konfiguration unter { $url } konnte nicht heruntergeladen werden, die Kopie vom letzten Mal wird verwendet: { $error }
+config-remote-invalid = Was unter { $url } liegt, ist keine Konfiguration: { $error }
+config-remote-untrusted = Die geteilte Konfiguration setzt { $keys }, was ausgelassen wird, da trust_remote in Ihrer eigenen es nicht aufführt
+
+## ata2 config explain, --dump-config
+
+config-source-default = Standard
+config-source-url = { $url }
+config-source-file = { $path }
+config-source-env = Umgebungsvariable { $var }
+config-source-credentials = Anmeldeinformationsspeicher
+config-source-profile = Profil { $profile }
+config-source-flag = { $flag }
+config-source-implied = durch { $key }
+config-unset = (nicht gesetzt)
+config-applies = ← gilt
+config-unknown-key = Es gibt keine Einstellung { $key }. `ata2 --dump-config` listet alle auf.
+
+## Vollbildoberfläche
+
+tui-hints = Enter: senden · Alt-Enter: neue Zeile · Tab: Sitzungen · Mausrad: scrollen · Strg-C: beenden
+tui-prompt = Prompt
+tui-prompt-waiting = Prompt (warte auf Antwort…)
+tui-prompt-tokens = Prompt (~{ $tokens })
+tui-sessions = Sitzungen
+tui-conversation = Gespräch
+tui-conversation-scrolled = Gespräch (↑{ $lines })
+tui-you = Du
+tui-assistant = Assistent
+tui-system = System
+tui-tool = Werkzeug
+tui-error = Fehler: { $error }
+tui-cancelled = Abgebrochen.
+tui-wait = Warte erst, bis die Antwort fertig ist.
+tui-copied = { $lines ->
+        [one] Eine Zeile
+       *[other] { $lines } Zeilen
+    } kopiert.
+tui-confirm-tokens = Dieser Prompt hat etwa { $tokens } Tokens. Enter sendet ihn trotzdem.
+tui-confirm-forecast = { $forecast }. Nochmal Enter sendet sie.
+tui-screen-reader = Die Vollbildoberfläche ist mit einem Screenreader nicht nutzbar, die zeilenbasierte wird verwendet.
+
+## Desktop-Benachrichtigungen
+
+notification-summary = Antwort fertig
+notification-failed = Die Desktop-Benachrichtigung konnte nicht angezeigt werden: { $error }
+
+## Fehler in der Konfiguration
+
+config-no-api-key = Der API-Schlüssel fehlt
+config-no-model = Die Modell-ID fehlt
+config-max-tokens = max_tokens muss zwischen 1 und 2048 liegen
+config-temperature = temperature muss zwischen 0.0 und 1.0 liegen
+config-suffix = suffix darf keine leere Zeichenkette sein
+config-top-p = top_p muss zwischen 0.0 und 1.0 liegen
+config-n = n muss zwischen 1 und 10 liegen
+config-stop = stop darf keine leeren Phrasen enthalten
+config-presence-penalty = presence_penalty muss zwischen 0.0 und 1.0 liegen
+config-frequency-penalty = frequency_penalty muss zwischen 0.0 und 1.0 liegen
+config-user-id = user_id darf keine leere Zeichenkette sein
+config-logit-bias = logit_bias für { $token } muss zwischen -2.0 und 2.0 liegen
+config-local-model = api = "local" braucht die GGUF-Datei eines Modells in provider.local.model
+config-monthly-limit = rotation.monthly_limit darf nicht negativ sein
+config-monthly-limit-storage = rotation.monthly_limit braucht storage = "sqlite", um zu wissen, was jeder Schlüssel ausgegeben hat
+config-router = router braucht ein günstiges und ein starkes Modell
+config-draft = draft braucht das Modell, das Antworten entwirft
+config-clipwatch-exclude = clipwatch.exclude ist kein gültiger regulärer Ausdruck: { $error }
+config-clipwatch-interval = clipwatch.interval_ms muss mindestens 1 sein
+config-policy-pattern = Das Muster { $pattern } in tools.policy.rules ist kein gültiger regulärer Ausdruck: { $error }
+config-policy-tool = Unbekanntes Werkzeug { $tool } in tools.policy.rules, nur tmux_send, code_interpreter, file_search und die aus tools.custom
+config-tool-name = Der Name { $name } in tools.custom darf nur bis zu 64 Buchstaben, Ziffern, _ oder - haben
+config-tool-built-in = tools.custom.{ $name } ist der Name eines eingebauten Werkzeugs
+config-tool-no-command = tools.custom.{ $name } hat keinen Befehl
+config-tool-command-and-api = tools.custom.{ $name } hat einen Befehl und eine API, nur eins von beiden geht
+config-tool-api = tools.custom.{ $name }.api { $api } ist keine aus tools.apis
+config-tool-method = tools.custom.{ $name }.method { $method } ist keine HTTP-Methode
+config-tool-parameters = tools.custom.{ $name }.parameters muss ein JSON-Schema-Objekt sein
+config-tool-parameters-json = tools.custom.{ $name }.parameters ist kein gültiges JSON: { $error }
+config-tool-base-url = tools.apis.{ $name }.base_url { $url } ist keine URL: { $error }
+config-responses-tool = Unbekanntes Werkzeug { $tool } für responses, nur file_search und code_interpreter
+config-responses-vector-stores = Das Werkzeug file_search für responses braucht vector_store_ids
+config-tmux-capture-lines = tmux capture_lines muss mindestens 1 sein
+config-tmux-send-pane = tmux send_pane darf keine leere Zeichenkette sein
+config-history-no-parent = Die Verlaufsdatei hat kein übergeordnetes Verzeichnis
+config-history-metadata = Fehler bei den Metadaten der Verlaufsdatei: { $error }
+config-history-read-only = Das Verzeichnis der Verlaufsdatei ist schreibgeschützt
+config-history-max-entries = history_max_entries muss mindestens 1 sein
+config-typewriter-cps = typewriter_cps muss mindestens 1 sein
+config-save-filename = save_filename muss ein Dateiname sein, das Verzeichnis steht in save_dir
+config-confirm-above-cost = ui.confirm_above_cost darf nicht negativ sein
+config-wrap = wrap muss auto, off oder eine Anzahl Spalten sein, nicht `{ $value }`
+config-key = Unbekannte Taste `{ $key }`
+config-key-modifier = Unbekannte Umschalttaste `{ $modifier }` in `{ $key }`
+config-style-no-color = Keine Farbe nach `on` in `{ $style }`
+config-style-color = Unbekannte Farbe `{ $color }` in `{ $style }`
+config-style-word = Unbekannter Stil `{ $word }` in `{ $style }`
+config-placeholder = Unbekannter Platzhalter { $name }, möglich sind { $known }
+config-stream-ignored = stream ist ausgeschaltet. Das wird nicht mehr unterstützt und ignoriert.
+config-env-ignored = { $var }={ $value } wird ignoriert: { $error }
+config-env-not-boolean = weder true noch false
+config-error = Fehler in der Konfiguration: { $error }. Abbruch.
+history-unusable = Fehler in der Konfiguration: { $error }. Der Verlauf wird nicht gespeichert.
+
+## Der Verlauf, der Gesprächsspeicher und der Cache
+
+history-save-failed = Der Verlauf konnte nicht gespeichert werden: { $error }
+history-load-failed = Der Verlauf konnte nicht aus { $path } geladen werden und wird daher nicht gespeichert: { $error }
+history-saved = Verlauf unter { $path } gespeichert. Anzahl der Einträge: { $entries }
+history-moved = { $from } nach { $to } verschoben
+history-move-failed = { $from } konnte nicht nach { $to } verschoben werden: { $error }
+store-open-failed = { $path } konnte nicht geöffnet werden: { $error }
+cache-write-failed = Die Antwort konnte nicht in { $path } zwischengespeichert werden: { $error }
+
+## ata2 completions
+
+completions-none = Keine Vervollständigungen für { $shell }
+
+## Tokens zählen
+
+usage-tokens = { $tokens ->
+        [one] 1 Token
+       *[other] { $tokens } Tokens
+    }
@@ -0,0 +1,642 @@
+# ata²'s own messages. See locales/README.md for how to translate them.
+
+## Questions
+
+# The answer to a yes or no question that means yes, of which the first letter is enough. `y`
+# always works too.
+answer-yes = y
+# After a question, for the answer.
+yes-no = [y/N]
+
+## Missing configuration file
+
+config-missing =
+    Could not find the file `{ $filename }`. To fix this, create { $path }.
+
+    For example, use the following content (the text between the ```):
+
+    ```
+    { $example }
+    ```
+
+    Here, replace `<YOUR SECRET API KEY>` with your API key, which you can request via https://beta.openai.com/account/api-keys.
+
+    The `max_tokens` sets the maximum amount of tokens that the server can answer with.
+    Longer answers will be truncated.
+
+    The `temperature` sets the `sampling temperature`. From the OpenAI API docs: "What sampling temperature to use. Higher values means the model will take more risks. Try 0.9 for more creative applications, and 0 (argmax sampling) for ones with a well-defined answer." According to Stephen Wolfram { "[" }1], setting it to a higher value such as 0.8 will likely work best in practice.
+
+
+    { "[" }1]: https://writings.stephenwolfram.com/2023/02/what-is-chatgpt-doing-and-why-does-it-work/
+config-write-example = Do you want me to write this example file to { $path } for you to edit?
+
+## The API key in the credential store
+
+credentials-prompt = API key:
+credentials-no-key = No API key given
+credentials-saved = Saved the API key of profile { $profile } to the credential store.
+credentials-read-failed = Could not read the API key from the credential store: { $error }
+credentials-unsupported = There's no credential store to use on this system, set api_key in the configuration file or OPENAI_API_KEY instead
+
+## Slash commands
+
+commands-heading = Commands:
+command-help = List the available commands.
+command-continue = Continue the last response, which was cut off at max_tokens.
+command-regen = Ask for the last response again k times (default: 3) and choose which to keep.
+command-diff = Show code that a response revises in full, or as a diff against the earlier version.
+command-load = Replace the conversation with one saved to a file, like --load.
+command-links = List the links in the last response, or open link n of the list.
+command-model = Print the model prompts are sent to, or send them to another from now on.
+command-models = List the models the API offers, like ata2 models.
+command-search = Find the saved conversations that have all of the words.
+command-resume = Continue conversation n of the last /search, or the newest saved conversation.
+command-show = Print the last n exchanges of the conversation again (default: 1).
+command-append = Add a saved conversation to the end of this one, or a summary of it if both wouldn't fit.
+command-tag = Tag the conversation, saving it first if it isn't yet. -tag removes a tag, and without any the tags are shown.
+command-pin = Always send message n, as /show numbers them, or the last prompt, when older ones are left out for the conversation to fit. -n unpins it.
+command-trust = Don't ask before the tool is used for the rest of the session, or list how each tool is used.
+command-tmux = Add a tmux pane's scrollback to the conversation, or paste the last code block of the conversation into a pane.
+command-attach = Send a text file, or only lines a to b of it, with the next prompt.
+command-attach-tree = Send the tree of a directory's files with the next prompt, and as many of the newest and smallest of them as fit.
+command-attachments = List the files attached for the next prompt, or clear them.
+command-upload = Upload a file for the next prompt, with api = "responses".
+command-unknown = Unknown command: { $line }
+command-usage = usage: /{ $name } { $usage }
+
+shortcuts-heading = Keyboard shortcuts:
+key-accept-line = Send the message you're typing.
+key-newline = Start a new line.
+key-save-conversation = Save the conversation (not including the message you're typing) to a file in `ui.save_dir`.
+key-cancel-generation = Stop the response that is streaming in, keeping what arrived so far.
+key-copy-response = Copy the last response to the clipboard (with OSC 52).
+key-open-editor = Edit the message you're typing in $VISUAL or $EDITOR.
+key-skip-pacing = Print the rest of the response that is streaming in at once, ignoring `ui.stream_pacing`.
+key-search = Fuzzy-search the prompt history and the conversations saved in `ui.save_dir`. Picking a conversation offers to resume it.
+key-accept-suggestion = Accept the suggested rest of the prompt, shown dimmed after the cursor.
+key-exit = Exit.
+key-exit-twice = Exit, when pressed twice in a row.
+
+links-no-response = No response yet
+links-none = The last response has no links.
+links-opened = Opened { $url }
+links-no-such = No link { $n }, the last response has { $count }
+conversation-empty = The conversation is empty
+tags = Tags: { $tags }
+tags-none = No tags
+tags-unsaved = The conversation isn't saved yet, so it has no tags
+sessions-no-such = No saved conversation { $name }
+pinned = Pinned message { $n }
+unpinned = Unpinned message { $n }
+pin-no-such = No message { $n }, the conversation has { $count }
+show-pinned = #{ $n } · pinned
+context-trimmed = Left out the { $count ->
+        [one] oldest message
+       *[other] { $count } oldest messages
+    } for the conversation to fit, { $tokens } tokens are sent
+sessions-merged = Merged { $count } conversations into { $path }
+appended = Added { $count ->
+        [one] one message
+       *[other] { $count } messages
+    } of { $name }
+append-summarizing = With { $name }, the conversation would be { $tokens } tokens, more than fit, so it's summarized…
+appended-summary = Added a summary of { $name }
+tmux-no-pane = Not running inside tmux, and no pane to capture given
+tmux-no-code-block = No code block found in the conversation
+tmux-captured = Added { $lines ->
+        [one] 1 line
+       *[other] { $lines } lines
+    } from tmux pane { $pane } to the conversation.
+tmux-pasted = Pasted code block into tmux pane { $pane }.
+continue-nothing = The last response wasn't cut off
+continue-offer = Cut off at max_tokens. /continue continues it.
+
+# Running another program, such as xdg-open or tmux.
+run-failed = Could not run { $program }: { $error }
+program-failed = { $program } failed: { $error }
+
+## Sending prompts
+
+# Keep the letters in parentheses, they're what to answer.
+paste-choose = You pasted { $lines ->
+        [one] one line
+       *[other] { $lines } lines
+    } ({ $chars ->
+        [one] one character
+       *[other] { $chars } characters
+    }). Send them (f)enced, as an (a)ttachment or (i)nline? [f]
+confirm-tokens = This prompt is about { $tokens } tokens. Send it?
+not-sent = Not sent. Press Up to get it back.
+press-ctrl-c-again = Press Ctrl-C again to exit.
+press-ctrl-c-again-within = Press Ctrl-C again within { $seconds } s to exit.
+input-stripped = Stripped { $bytes ->
+        [one] one byte
+       *[other] { $bytes } bytes
+    } of escape sequences, control characters and what isn't UTF-8 from the input.
+empty-prompt = Empty prompt, aborting.
+jsonl-invalid = Line { $line } of stdin is not a message: { $error }
+stdin-conversation-invalid = stdin is not a conversation: { $error }
+stdin-conversation-no-prompt = The conversation on stdin doesn't end with a prompt to respond to
+jsonl-role = Line { $line } of stdin: only the messages of system, user and assistant can be read
+override-unknown = @{ $name }: no such parameter, only { $params }
+override-not-valid = @{ $name }={ $value }: not a valid value
+override-no-prompt = Nothing to send after the parameters
+regen-nothing = There's no response to ask for again.
+regen-responses = /regen needs api = "chat", as responses continue from the last one.
+regen-candidate = Response { $n }:
+regen-failed = Response { $n } failed: { $error }
+request-failed = failed to request: { $error }
+regen-choose = Keep which one? [1–{ $count }, Enter keeps the one there was]
+regen-kept = Kept response { $n }.
+diff-on = Revised code is shown as a diff.
+diff-off = Revised code is shown in full.
+
+## Errors of the API
+
+api-error-key = The API key { $key } was refused. Check api_key in ata2.toml, or save another with ata2 --save-key.
+api-error-model = Your API key lacks access to { $model }, or there's no such model. Run ata2 models to see those it can use.
+api-error-quota = Your OpenAI account is out of credits or over its budget. See https://platform.openai.com/settings/organization/billing
+api-error-rate-limit = Rate limited. Wait a moment and try again, or share the requests between more keys under [rotation].
+api-error-context = The conversation is too long for { $model }. Start a new one, or switch to a model with a larger context window.
+api-error-server = OpenAI's servers failed ({ $status }). Try again in a moment.
+
+## Conversations
+
+conversation-resumed = Resumed { $path }.
+conversation-resume-failed = Could not resume { $path }: { $error }
+conversation-busy = The conversation is busy, try again.
+conversation-loaded = Loaded { $path }
+conversation-load-failed = Could not load { $path }: { $error }
+conversation-saved = Saved conversation to { $path }
+conversation-save-failed = Could not save the conversation: { $error }
+conversation-autosave-failed = Could not autosave the conversation: { $error }
+
+## ata2 search
+
+search-no-results = Nothing found.
+search-prompt = Prompt of profile { $profile }
+search-resume-hint = /resume n continues conversation n.
+search-no-such = No conversation { $n }, the last search found { $count }
+search-failed = Search failed: { $error }
+# In the full-screen search of Ctrl-R.
+search-status = { $matches }/{ $total } · Enter: pick · Esc: cancel
+# Keep the letters in parentheses, they're what to answer.
+search-confirm-resume = Resume { $path }? (y/n)
+
+## ata2 share
+
+share-written = Wrote { $path }
+share-no-token = Set integrations.github.token, or GITHUB_TOKEN, to a token with the gist scope to upload gists
+share-gist-failed = Could not create the gist: { $error }
+share-footer = Shared from ata²
+
+## ata2 eval
+
+eval-pass = PASS
+eval-fail = FAIL
+eval-error = { $error }
+eval-no-match = doesn't match { $regex }
+eval-matches = matches { $regex }
+eval-not-json = isn't JSON
+eval-json-missing = has nothing at { $pointer }
+eval-json-differs = has { $actual } at { $pointer }, not { $expected }
+eval-summary = { $model }: { $passed } of { $runs } passed, { $latency } s on average, { $cost }
+eval-cost-unknown = cost unknown
+eval-failed = { $count ->
+        [one] One run
+       *[other] { $count } runs
+    } failed
+
+## ata2 bench
+
+bench-run = { $model } #{ $n }: first token after { $first_token }, { $rate } tokens/s, { $total } in all, { $tokens } tokens
+bench-error = { $model }: { $error }
+bench-model = Model
+bench-first-token = First token
+bench-rate = Tokens/s
+bench-total = Total
+bench-tokens = Tokens
+
+## ata2 sweep and ata2 eval, sending requests at once
+
+scheduler-progress = { $done } of { $total } done, { $running } running
+scheduler-zero = scheduler.concurrency and the limits of scheduler.per_model must be at least 1
+
+## ata2 sweep
+
+sweep-bad-param = { $param }: not name=start:end:step or name=a,b,c
+sweep-unknown-param = Can't sweep { $name }, only { $params }
+sweep-not-number = { $name }: { $value } isn't a valid value
+sweep-bad-range = { $param }: the range has to be start:end:step, with a positive step and end not before start
+sweep-stats = { $seconds } s · { $tokens ->
+        [one] one token
+       *[other] { $tokens } tokens
+    }
+sweep-error = Failed: { $error }
+sweep-written = Wrote { $count ->
+        [one] one response
+       *[other] { $count } responses
+    } to { $path }
+
+## [tools.policy], /trust
+
+policy-ask-tmux = { $enter ->
+        [true] Paste this into tmux pane { $pane } and run it?
+       *[other] Paste this into tmux pane { $pane }?
+    }
+policy-ask-tool = Let the model use { $tool } for this prompt?
+policy-cannot-ask = Not using { $tool }, as there's no terminal to ask on
+policy-not-allowed = { $tool } isn't allowed by tools.policy
+policy-unknown-tool = Unknown tool { $tool }, only { $tools }
+trusted = Trusting { $tool } for the rest of the session, unless tools.policy denies it
+
+## api = "responses"
+
+upload-chat-api = Files can only be uploaded with api = "responses"
+upload-no-such = No such file: { $path }
+upload-not-pdf = Only PDFs can be uploaded without the code_interpreter tool
+upload-done = Uploaded { $path } as { $id }, for the next prompt
+tool-truncated = … { $count ->
+        [one] one byte
+       *[other] { $count } bytes
+    } left out …
+tool-timed-out = Gave up on { $tool } after { $secs } s, as tools.timeouts says
+tool-outside-workdir = { $path } is outside { $workdir }, which tools.workdir confines tools to
+
+## [tools.custom]
+
+policy-ask-command = Let { $tool } run `{ $command }`?
+tool-unknown = There's no tool { $tool }
+tool-bad-arguments = The arguments of { $tool } aren't a JSON object: { $error }
+tool-declined = { $tool } wasn't allowed to run
+tool-failed = (exited with { $status })
+tool-http-status = (HTTP { $status })
+tool-too-many-rounds = Stopped the model after { $count ->
+        [one] one round
+       *[other] { $count } rounds
+    } of tool calls in a row
+
+## ata2 tools import
+
+openapi-imported = Imported { $count ->
+        [one] one tool
+       *[other] { $count } tools
+    } of { $api }. Fill in the headers of [tools.apis.{ $api }] for its auth.
+openapi-none = None of the operations of the spec match
+openapi-no-base-url = The spec doesn't say where { $api } is, so set base_url of [tools.apis.{ $api }]
+
+## ata2 new --from
+
+workflow-started = Started { $name }.
+workflow-unknown-tool = The workflow names { $tool }, which isn't one of tools.custom, code_interpreter or file_search
+
+## ata2 run
+
+report-no-prompt = { $file } has no prompt to send
+report-retrying = Sending it again in { $seconds } s ({ $attempt } of { $retries }): { $error }
+report-timed-out = Gave up after { $seconds } s
+report-written = Wrote { $file }.
+
+## ata2 lsp
+
+lsp-no-method = The request has no method
+lsp-unknown-method = There's no method { $method }
+
+## ata2 daemon
+
+daemon-listening = Listening on { $socket }.
+daemon-running = A daemon is already listening on { $socket }
+daemon-unix-only = ata2 daemon needs Unix sockets, which this system doesn't have
+
+## ata2 --fim
+
+fim-markers = The input has more than one { $marker }
+
+## ata2 gc
+
+gc-deleted = Deleted { $path }
+gc-would-delete = Would delete { $path }
+gc-history = Dropped { $count ->
+        [one] one prompt
+       *[other] { $count } prompts
+    } from the history in { $path }
+gc-would-drop = Would drop { $count ->
+        [one] one prompt
+       *[other] { $count } prompts
+    } from the history in { $path }
+gc-nothing = Nothing to delete.
+
+## ata2 keys
+
+keys-none = No API key is configured
+keys-spent = { $key }: ${ $spent } this month
+keys-spent-limit = { $key }: ${ $spent } of ${ $limit } this month
+keys-untracked = { $key } (with storage = "sqlite", what it spends is kept track of)
+keys-spent-all = Every API key spent rotation.monthly_limit this month
+keys-rate-limited = API key { $key } is rate limited
+keys-ledger-read-failed = Could not read the usage ledger: { $error }
+keys-ledger-write-failed = Could not add to the usage ledger: { $error }
+
+## ata2 models
+
+models-context = { $tokens } tokens
+models-capability = { $capability ->
+        [chat] chat
+        [tools] tools
+        [vision] images in
+        [audio] audio
+        [reasoning] reasoning
+        [completions] completions
+        [embeddings] embeddings
+        [images] images out
+        [transcription] transcription
+        [speech] speech
+        [moderation] moderation
+       *[other] { $capability }
+    }
+models-unexpected = The API's list of models isn't as expected
+models-cache-failed = Could not keep the list of models in { $path }: { $error }
+models-offline = Could not reach the API ({ $error }), so these are the models it offered last time
+model-switched = Prompts are sent to { $model } from now on
+
+## ata2 watch
+
+watch-started = Watching { $file }, Ctrl-C to stop.
+watch-answer = { $file } at { $time }:
+
+## ata2 translate
+
+translate-part = Translating part { $part } of { $count }…
+translate-cut-off = The translation of part { $part } was cut off at max_tokens.
+
+## ata2 summarize
+
+summarize-empty = There's nothing to summarize in { $input }.
+summarize-fetch-failed = Could not download { $url }: { $error }
+
+## --grammar
+
+grammar-unreadable = Could not read the grammar { $path }: { $error }
+grammar-no-root = { $path } has no root rule, where a grammar starts.
+grammar-unsupported = A grammar can't be derived from a JSON schema with { $keyword }.
+
+## api = "local"
+
+local-not-built = api = "local" needs ata² built with the local feature: cargo install ata --features local
+local-loading = Loading { $path }…
+local-load-failed = Could not load the model { $path }: { $error }
+local-no-template = The model has no chat template that can be used: { $error }
+local-too-long = The conversation is { $tokens } tokens, and the model's context only { $context }.
+local-no-completions = A model of api = "local" only chats, and can't complete a prompt in the middle.
+
+## [router]
+
+router-routed = → { $model } ({ $reason })
+router-marker = it says “{ $marker }”
+router-attachments = files are attached
+router-code = it has code
+router-long = { $tokens } tokens
+router-short = { $tokens } tokens
+
+## [draft]
+
+draft-waiting = { $model } is refining the answer…
+draft-failed = { $model } could not refine the answer: { $error }
+draft-refined = Refined by { $model }:
+draft-choose = [r]eplace the draft with it, [a]ppend it to the draft, or Enter to keep the draft?
+
+## --logprobs
+
+logprobs-legend = Shaded by how likely each token was: plain from 90%, then from 50%, from 20% and below, with [what else the model weighed].
+logprobs-legend-plain = Tokens below 90% are followed by [how likely they were | what else the model weighed].
+logprobs-none = The API didn't say how likely the tokens were.
+
+## ui.show_meta
+
+meta = { $model } · { $finish_reason } · { $prompt_tokens } + { $completion_tokens } tokens · { $first_token } s to the first token, { $total } s in all
+
+## ui.forecast
+
+forecast = This request ≈ { $tokens } prompt tokens (~${ $cost })
+forecast-unpriced = This request ≈ { $tokens } prompt tokens
+confirm-forecast = { $forecast }. Send it?
+
+## ata2 doctor
+
+doctor-configuration = Configuration
+doctor-connection = Connection
+doctor-key = API key
+doctor-model = Model
+doctor-history = History
+doctor-conversations = Conversations
+doctor-cache = Cache
+doctor-terminal = Terminal
+doctor-colors = Colors
+doctor-osc52 = Clipboard (OSC 52)
+doctor-config-missing = { $path } does not exist
+doctor-via-proxy = (through { $proxy })
+doctor-connection-ok = { $url } answers ({ $status })
+doctor-key-none = not needed, as auth = "none"
+doctor-key-missing = there is none
+doctor-key-ok = accepted, { $count } models are offered
+doctor-model-missing = { $model } is not one of the models the API offers
+doctor-model-unchecked = { $model } could not be checked
+doctor-not-a-file = { $path } is not a file
+doctor-not-a-terminal = stdout is not a terminal
+doctor-colors-off = off
+doctor-truecolor = truecolor
+doctor-no-truecolor = 256 or fewer, as COLORTERM is not truecolor (TERM={ $term })
+doctor-osc52-yes = { $term } takes it
+doctor-osc52-no = { $term } does not take it, so /copy will not reach the clipboard
+doctor-osc52-tmux = tmux passes it on only with set-clipboard on
+doctor-osc52-unknown = unknown whether { $term } takes it
+doctor-failed = { $count ->
+    [one] 1 check failed
+   *[other] { $count } checks failed
+}
+
+## /attach, --attach
+
+attach-added = Attached { $name }, ~{ $tokens } tokens.
+attach-total = { $tokens } tokens attached for the next prompt.
+attach-total-of = { $tokens } of { $max } tokens attached for the next prompt.
+attach-none = Nothing is attached.
+attach-cleared = Removed the attachments.
+attach-unreadable = Could not read { $path }: { $error }
+attach-not-text = { $path } isn't text.
+attach-bad-lines = Not lines: { $range }. Like 40-120, 40- or 40.
+attach-no-lines = { $path } has only { $count } lines.
+attach-name-lines = { $path }, lines { $first }–{ $last }
+attach-not-dir = { $path } isn't a directory.
+attach-name-tree = { $path }, { $included } of { $count ->
+        [one] one file
+       *[other] { $count } files
+    }
+attach-over-budget = { $name } is ~{ $tokens } tokens, but only { $left } are left of attachment_max_tokens.
+attach-no-match = Nothing matches { $pattern }.
+attach-command-failed = { $command } failed: { $error }
+extract-page = [Page { $page }]
+tabular-shape = { $rows } rows, { $columns } columns:
+tabular-empty = { $count } empty
+tabular-sample = { $rows } of the { $count } rows:
+transcribe-started = Transcribing { $path }…
+transcribe-part = Transcribing part { $part } of { $count }…
+transcribe-no-duration = Could not tell how long { $path } is, to split it.
+transcribe-no-ffmpeg = Recordings over 25 MB are split with ffmpeg to transcribe them, but { $program } wasn't found.
+
+## [templates], --template
+
+template-unknown = No template { $name } in [templates], only: { $templates }
+
+## ata2 clipwatch
+
+clipwatch-disabled = ata2 clipwatch sends what you copy to the API, set clipwatch.enabled = true to let it
+clipwatch-started = Watching the clipboard, Ctrl-C to stop.
+clipwatch-too-long = Left alone what was copied, { $chars } characters are more than clipwatch.max_chars.
+clipwatch-excluded = Left alone what was copied, clipwatch.exclude matches it.
+clipwatch-replaced = Replaced what was copied, { $chars } characters, with the answer.
+clipboard-no-program = Found none of { $programs } to use the clipboard with
+
+## ata2 flush
+
+queue-queued = No connection. The prompt is queued for ata2 flush to send, { $count ->
+        [one] the only one
+       *[other] with { $count } in all
+    }.
+flush-nothing = Nothing queued.
+flush-saved = Saved to { $path }
+flush-offline = Still no connection, { $count ->
+        [one] one prompt is
+       *[other] { $count } prompts are
+    } still queued.
+flush-kept = { $count ->
+        [one] One prompt
+       *[other] { $count } prompts
+    } couldn't be sent and stay queued.
+
+## --config https://…
+
+config-remote-failed = Could not download the configuration at { $url }: { $error }
+config-remote-cached = Could not download the configuration at { $url }, using the copy from last time: { $error }
+config-remote-invalid = What's at { $url } isn't a configuration: { $error }
+config-remote-untrusted = The shared configuration sets { $keys }, left out as trust_remote in your own doesn't list it
+
+## ata2 config explain, --dump-config
+
+config-source-default = default
+config-source-url = { $url }
+config-source-file = { $path }
+config-source-env = environment variable { $var }
+config-source-credentials = credential store
+config-source-profile = profile { $profile }
+config-source-flag = { $flag }
+config-source-implied = implied by { $key }
+config-unset = (unset)
+config-applies = ← applies
+config-unknown-key = There's no setting { $key }. `ata2 --dump-config` lists them all.
+
+## Full-screen interface
+
+tui-hints = Enter: send · Alt-Enter: newline · Tab: sessions · wheel: scroll · Ctrl-C: quit
+tui-prompt = Prompt
+tui-prompt-waiting = Prompt (waiting for response…)
+tui-prompt-tokens = Prompt (~{ $tokens })
+tui-sessions = Sessions
+tui-conversation = Conversation
+tui-conversation-scrolled = Conversation (↑{ $lines })
+tui-you = You
+tui-assistant = Assistant
+tui-system = System
+tui-tool = Tool
+tui-error = Error: { $error }
+tui-cancelled = Cancelled.
+tui-wait = Wait for the response to finish first.
+tui-copied = Copied { $lines ->
+        [one] one line
+       *[other] { $lines } lines
+    }.
+tui-confirm-tokens = This prompt is about { $tokens } tokens. Enter again sends it.
+tui-confirm-forecast = { $forecast }. Enter again sends it.
+tui-screen-reader = The full-screen interface isn't usable with a screen reader, using the line-based one.
+
+## Desktop notifications
+
+notification-summary = Response complete
+notification-failed = Could not show desktop notification: { $error }
+
+## Errors in the configuration
+
+config-no-api-key = API key is missing
+config-no-model = Model ID is missing
+config-max-tokens = Max tokens must be between 1 and 2048
+config-temperature = Temperature must be between 0.0 and 1.0
+config-suffix = Suffix cannot be an empty string
+config-top-p = Top-p must be between 0.0 and 1.0
+config-n = n must be between 1 and 10
+config-stop = Stop phrases cannot contain empties
+config-presence-penalty = Presence penalty must be between 0.0 and 1.0
+config-frequency-penalty = Frequency penalty must be between 0.0 and 1.0
+config-user-id = User ID cannot be an empty string
+config-logit-bias = logit_bias for { $token } must be between -2.0 and 2.0
+config-local-model = api = "local" needs the GGUF file of a model in provider.local.model
+config-monthly-limit = rotation.monthly_limit cannot be negative
+config-monthly-limit-storage = rotation.monthly_limit needs storage = "sqlite", to know what each key spent
+config-router = router needs a cheap model and a strong one
+config-draft = draft needs the model that drafts answers
+config-clipwatch-exclude = clipwatch.exclude is not a valid regular expression: { $error }
+config-clipwatch-interval = clipwatch.interval_ms must be at least 1
+config-policy-pattern = tools.policy.rules pattern { $pattern } is not a valid regular expression: { $error }
+config-policy-tool = Unknown tool { $tool } in tools.policy.rules, only tmux_send, code_interpreter, file_search and those of tools.custom
+config-tool-name = tools.custom name { $name } must be up to 64 letters, digits, _ or -
+config-tool-built-in = tools.custom.{ $name } is the name of a built-in tool
+config-tool-no-command = tools.custom.{ $name } has no command
+config-tool-command-and-api = tools.custom.{ $name } has a command and an api, only one of them
+config-tool-api = tools.custom.{ $name }.api { $api } is not one of tools.apis
+config-tool-method = tools.custom.{ $name }.method { $method } is not an HTTP method
+config-tool-parameters = tools.custom.{ $name }.parameters must be a JSON schema object
+config-tool-parameters-json = tools.custom.{ $name }.parameters is not valid JSON: { $error }
+config-tool-base-url = tools.apis.{ $name }.base_url { $url } is not a URL: { $error }
+config-responses-tool = Unknown responses tool { $tool }, only file_search and code_interpreter
+config-responses-vector-stores = responses tool file_search needs vector_store_ids
+config-tmux-capture-lines = tmux capture_lines must be at least 1
+config-tmux-send-pane = tmux send_pane cannot be an empty string
+config-history-no-parent = History file has no parent
+config-history-metadata = History file metadata error: { $error }
+config-history-read-only = History file dir is read-only
+config-history-max-entries = history_max_entries must be at least 1
+config-typewriter-cps = typewriter_cps must be at least 1
+config-save-filename = save_filename must be a file name, use save_dir for the directory
+config-confirm-above-cost = ui.confirm_above_cost cannot be negative
+config-wrap = wrap must be auto, off or a number of columns, not `{ $value }`
+config-key = Unknown key `{ $key }`
+config-key-modifier = Unknown modifier `{ $modifier }` in key `{ $key }`
+config-style-no-color = No color after `on` in `{ $style }`
+config-style-color = Unknown color `{ $color }` in `{ $style }`
+config-style-word = Unknown style `{ $word }` in `{ $style }`
+config-placeholder = Unknown placeholder { $name }, use { $known }
+config-stream-ignored = Stream is disabled. This is not supported anymore and will be ignored.
+config-env-ignored = Ignoring { $var }={ $value }: { $error }
+config-env-not-boolean = not true or false
+config-error = Config error!: { $error }. Dying.
+history-unusable = Config error!: { $error }. The history won't be saved.
+
+## The history, the conversation store and the cache
+
+history-save-failed = Could not save history: { $error }
+history-load-failed = Could not load history from { $path }, so it won't be saved: { $error }
+history-saved = Saved history to { $path }. Number of entries: { $entries }
+history-moved = Moved { $from } to { $to }
+history-move-failed = Could not move { $from } to { $to }: { $error }
+store-open-failed = Could not open { $path }: { $error }
+cache-write-failed = Could not cache the response in { $path }: { $error }
+
+## ata2 completions
+
+completions-none = No completions for { $shell }
+
+## Counting tokens
+
+usage-tokens = { $tokens ->
+        [one] 1 token
+       *[other] { $tokens } tokens
+    }
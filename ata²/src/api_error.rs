@@ -0,0 +1,145 @@
+//! Errors of the API, told as what to do about them: a key that's refused, a model the key can't
+//! use, a rate limit or an account out of credits, a conversation too long for the model. Others are
+//! shown as the API words them.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::error::OpenAIError;
+use reqwest::{Response, StatusCode};
+use serde_json::Value;
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::rotation;
+
+/// An unsuccessful response of the API.
+#[derive(Debug)]
+pub struct ApiError {
+    /// `None` for those of async-openai, which doesn't keep it.
+    status: Option<StatusCode>,
+    code: Option<String>,
+    message: String,
+    /// Of the request.
+    model: String,
+    /// The [fingerprint](rotation::fingerprint) of the key the request was sent with.
+    key: Option<String>,
+}
+
+impl ApiError {
+    /// The error in the unsuccessful `response` to a request to `model`.
+    pub async fn of(response: Response, model: &str) -> Self {
+        let status = response.status();
+        let json = response.json::<Value>().await.unwrap_or_default();
+        let error = &json["error"];
+        let ret = Self {
+            status: Some(status),
+            code: error["code"]
+                .as_str()
+                .or_else(|| error["type"].as_str())
+                .map(str::to_string),
+            message: error["message"]
+                .as_str()
+                .map_or_else(|| status.to_string(), str::to_string),
+            model: model.to_string(),
+            key: rotation::last(),
+        };
+        debug!("API error: {status} {json}");
+        ret
+    }
+
+    /// What it means, if we know.
+    fn explanation(&self) -> Option<String> {
+        let status = self.status.map(|status| status.as_u16());
+        let key = self.key.as_deref().unwrap_or("");
+        let model = self.model.as_str();
+        Some(match (status, self.code.as_deref()) {
+            (_, Some("invalid_api_key")) | (Some(401), _) => tr!("api-error-key", key = key),
+            (_, Some("model_not_found")) | (Some(404), None) => {
+                tr!("api-error-model", model = model)
+            }
+            (_, Some("insufficient_quota")) => tr!("api-error-quota"),
+            (_, Some("rate_limit_exceeded")) | (Some(429), _) => tr!("api-error-rate-limit"),
+            (_, Some("context_length_exceeded")) => tr!("api-error-context", model = model),
+            (Some(status), _) if status >= 500 => tr!("api-error-server", status = status),
+            _ => return None,
+        })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.explanation() {
+            Some(explanation) => write!(f, "{explanation}"),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Error for ApiError {}
+
+/// Whether a request that failed with `e` may succeed if it's sent again: the API refused it for
+/// the rate limit or its own error, or there was no response at all.
+pub fn transient(e: &(dyn Error + 'static)) -> bool {
+    let Some(e) = e.downcast_ref::<ApiError>() else {
+        return true;
+    };
+    match (e.status.map(|status| status.as_u16()), e.code.as_deref()) {
+        (_, Some("insufficient_quota")) => false,
+        (_, Some("rate_limit_exceeded" | "server_error")) => true,
+        (Some(status), _) => status == 408 || status == 429 || status >= 500,
+        (None, _) => false,
+    }
+}
+
+/// `e`, of a request to `model` that async-openai made, as an [`ApiError`] if it's one of the API.
+pub fn from_openai(e: OpenAIError, model: &str) -> Box<dyn Error + Send + Sync> {
+    match e {
+        OpenAIError::ApiError(e) => {
+            debug!("API error: {e:?}");
+            Box::new(ApiError {
+                status: None,
+                code: e
+                    .code
+                    .as_ref()
+                    .and_then(Value::as_str)
+                    .or(e.r#type.as_deref())
+                    .map(str::to_string),
+                message: e.message,
+                model: model.to_string(),
+                key: rotation::last(),
+            })
+        }
+        // Streams keep only the status of an unsuccessful response, in words.
+        OpenAIError::StreamError(message) if message.starts_with("Invalid status code: ") => {
+            let status = message["Invalid status code: ".len()..]
+                .get(..3)
+                .and_then(|status| status.parse().ok())
+                .and_then(|status| StatusCode::from_u16(status).ok());
+            Box::new(ApiError {
+                status,
+                code: None,
+                message,
+                model: model.to_string(),
+                key: rotation::last(),
+            })
+        }
+        e => e.into(),
+    }
+}
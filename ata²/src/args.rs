@@ -20,11 +20,17 @@
 
 use crate::config::ConfigLocation;
 
-use clap::Parser;
+use std::path::PathBuf;
+
 use clap::{crate_authors, crate_version};
+use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::{ArgValueCompleter, Shell};
+
+use crate::completions;
+use crate::logprobs;
 
 #[derive(Parser, Debug)]
-#[command(author = crate_authors!(), version = crate_version!(),
+#[command(name = "ata2", author = crate_authors!(), version = crate_version!(),
     about, long_about = None,
     help_template = "{before-help}{name} {version} — {about}\
     \n\n\
@@ -34,8 +40,10 @@ use clap::{crate_authors, crate_version};
     \n\n\
     {all-args}{after-help}")]
 pub struct Ata2 {
-    /// Path to the configuration TOML file.
-    #[arg(short = 'c', long = "config", default_value = "")]
+    /// Path to the configuration TOML file, the name of a profile, or an https:// URL of one
+    /// shared by a team, under the local file of its name.
+    #[arg(short = 'c', long = "config", default_value = "",
+        add = ArgValueCompleter::new(completions::profiles))]
     pub config: ConfigLocation,
 
     /// Avoid printing the configuration to stdout.
@@ -46,7 +54,377 @@ pub struct Ata2 {
     #[arg(long)]
     pub print_shortcuts: bool,
 
+    /// Print every setting, with where it comes from: the default, the configuration file, an
+    /// environment variable, the credential store or a flag.
+    #[arg(long)]
+    pub dump_config: bool,
+
+    /// The model to send prompts to, over `model` in the configuration.
+    #[arg(short = 'm', long, add = ArgValueCompleter::new(completions::models))]
+    pub model: Option<String>,
+
+    /// The temperature to sample at, over `temperature` in the configuration.
+    #[arg(short = 't', long)]
+    pub temperature: Option<f64>,
+
+    /// The most tokens a response can have, over `max_tokens` in the configuration.
+    #[arg(long)]
+    pub max_tokens: Option<i64>,
+
+    /// The system message the conversation starts with, in place of the one it has.
+    #[arg(long, value_name = "text", conflicts_with = "fim")]
+    pub system: Option<String>,
+
+    /// Send each prompt on stdin in the prompt of `[templates]` named this, where it says
+    /// `{text}` or after it.
+    #[arg(long, value_name = "name", conflicts_with_all = ["fim", "tui"],
+        add = ArgValueCompleter::new(completions::templates))]
+    pub template: Option<String>,
+
+    /// Have a local server like llama.cpp's follow this grammar, of GBNF, or derived from the
+    /// JSON schema in a `.json` file.
+    #[arg(long, value_name = "file", conflicts_with = "fim")]
+    pub grammar: Option<PathBuf>,
+
+    /// Ask for how likely each token of a response was, and show the response again shaded by
+    /// it, with up to `top_k` alternatives to the tokens the model was unsure of.
+    #[arg(long, value_name = "top_k", num_args = 0..=1, default_missing_value = "0",
+        value_parser = clap::value_parser!(u8).range(0..=logprobs::MAX_TOP_K as i64),
+        conflicts_with_all = ["fim", "tui"])]
+    pub logprobs: Option<u8>,
+
+    /// Send a text file with the first prompt, or only lines a to b of it. Can be repeated.
+    #[arg(long, value_name = "file[:a-b]", conflicts_with = "fim")]
+    pub attach: Vec<String>,
+
     /// Conversation file to load.
-    #[arg(short = 'l', long = "load")]
+    #[arg(short = 'l', long = "load", add = ArgValueCompleter::new(completions::sessions))]
     pub load: Option<String>,
+
+    /// Resume the newest conversation in `ui.save_dir`, autosaving to it with `ui.autosave`.
+    #[arg(long, conflicts_with = "load")]
+    pub resume_last: bool,
+
+    /// Use the full-screen interface instead of the line-based one.
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Complete the text on stdin where it says `<CURSOR>`, with `fim_model`, and print only what
+    /// goes there. Without the marker, `suffix` comes after it.
+    #[arg(long, conflicts_with_all = ["load", "resume_last", "tui"])]
+    pub fim: bool,
+
+    /// Queue a prompt that can't be sent because there's no connection, for `ata2 flush` to send
+    /// later, instead of failing.
+    #[arg(long, conflicts_with = "fim")]
+    pub queue: bool,
+
+    /// With stdin not a terminal, send the text between lines that are just this as one prompt
+    /// each, instead of all of it as one.
+    #[arg(long, value_name = "line", conflicts_with_all = ["fim", "tui"])]
+    pub delimiter: Option<String>,
+
+    /// What stdin has, when it's not a terminal.
+    #[arg(long, value_enum, default_value_t, conflicts_with_all = ["fim", "tui"])]
+    pub stdin_format: StdinFormat,
+
+    /// Send requests even if their responses are cached, with `cache.enabled`, and cache the new
+    /// responses instead.
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Ask for the API key and store it in the system's credential store (Credential Manager on
+    /// Windows, the Keychain on macOS), for the profile of `--config`.
+    #[arg(long)]
+    pub save_key: bool,
+
+    /// Plain output for screen readers, as with `ui.screen_reader`.
+    #[arg(long)]
+    pub a11y: bool,
+
+    /// When to style the output. `auto` does if it's a terminal and `NO_COLOR` isn't set.
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the script that sets up completion in `shell`, of options as well as profiles and
+    /// saved conversations. To use it, add `source <(ata2 completions bash)` to ~/.bashrc, or the
+    /// like for your shell.
+    Completions { shell: Shell },
+    /// Print the man page, for `ata2 manpage > ata2.1`.
+    Manpage,
+    /// Find the saved conversations that have all of the words of `query`, and with
+    /// `storage = "sqlite"` the prompts of the history too.
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Continue conversation `n` of what's found, instead of listing them.
+        #[arg(long, value_name = "n")]
+        resume: Option<usize>,
+    },
+    /// The saved conversations.
+    Sessions {
+        #[command(subcommand)]
+        command: SessionsCommand,
+    },
+    /// Write a saved conversation to `<name>.html`, a page that needs nothing else to be read, or
+    /// as a Jupyter notebook or Org document, or upload it as a secret gist.
+    Share {
+        /// The conversation: its path, or its name in `ui.save_dir`.
+        #[arg(add = ArgValueCompleter::new(completions::sessions))]
+        session: String,
+        /// Where to write it. `-` for stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t)]
+        format: ShareFormat,
+        /// Upload it as a secret gist and print its URL, with the token in
+        /// `integrations.github.token`.
+        #[arg(long)]
+        gist: bool,
+    },
+    /// Send the prompts of a suite to its models and check the responses. Fails if any don't pass.
+    Eval {
+        suite: PathBuf,
+        /// Run each case this many times, instead of as many as the suite says.
+        #[arg(long, value_name = "n")]
+        repeat: Option<usize>,
+    },
+    /// Stream the response to a prompt from each of some models and compare the time to the first
+    /// token, the tokens a second after it and the total latency.
+    Bench {
+        /// Models to compare, separated by commas. The configured one if none.
+        #[arg(long, value_delimiter = ',', value_name = "model,…",
+            add = ArgValueCompleter::new(completions::models))]
+        models: Vec<String>,
+        #[arg(long)]
+        prompt: String,
+        /// How many times to send it to each model. The table shows the medians.
+        #[arg(long, value_name = "n", default_value_t = 3)]
+        runs: usize,
+    },
+    /// Send the same prompt with every combination of some parameters' values and write the
+    /// responses side by side.
+    Sweep {
+        /// A parameter and its values: `name=start:end:step` or `name=a,b,c`. Of temperature,
+        /// top_p, presence_penalty, frequency_penalty, max_tokens and model. Can be repeated.
+        #[arg(long = "param", value_name = "name=values", required = true,
+            add = ArgValueCompleter::new(completions::params))]
+        params: Vec<String>,
+        /// The prompt. `-` for stdin.
+        #[arg(long, value_name = "path")]
+        prompt_file: PathBuf,
+        /// Where to write the results. Stdout if not given.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t)]
+        format: SweepFormat,
+    },
+    /// Send the prompts that `--queue` queued, print the responses and save the conversations.
+    Flush,
+    /// List the API keys that `[rotation]` shares the requests between, with what each spent this
+    /// month.
+    Keys,
+    /// List the models the API offers, with their context sizes and what they can do where that's
+    /// known. The list is kept for when the API can't be reached, and for completing model names.
+    Models,
+    /// Whenever a file changes, send what was added to it after a prompt and print the answer, as
+    /// with `ata2 watch --file build.log --prompt "Summarize new errors"`.
+    Watch {
+        /// The file to watch. What's in it already isn't sent.
+        #[arg(long)]
+        file: PathBuf,
+        /// What to ask about each addition.
+        #[arg(long)]
+        prompt: String,
+    },
+    /// Whenever text is copied, send it with a prompt of `[templates]` and put the answer in the
+    /// clipboard in its place. Needs `clipwatch.enabled`.
+    Clipwatch {
+        /// The name of the prompt in `[templates]`.
+        #[arg(long, add = ArgValueCompleter::new(completions::templates))]
+        template: String,
+    },
+    /// Translate the Markdown document on stdin, part by part, keeping its code blocks and its
+    /// Markdown as they are, as with `ata2 translate --to ja < README.md`.
+    Translate {
+        /// The language to translate it into, like `ja` or `German`.
+        #[arg(long)]
+        to: String,
+    },
+    /// Summarize a file, a web page or stdin, in parts that are summarized at once and then
+    /// merged if it's too long for one request.
+    Summarize {
+        /// A file, the URL of a web page, or `-` for stdin.
+        input: String,
+        #[arg(long, value_enum, default_value_t)]
+        length: SummaryLength,
+    },
+    /// Check the configuration, the connection to the API, the key, the model, where ata² keeps
+    /// what it keeps and the terminal, to find what's wrong.
+    Doctor,
+    /// The configuration.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Start a conversation as a workflow says, with its system message, model and tools, and
+    /// the files and the output of commands it attaches to the first prompt.
+    New {
+        /// The workflow, a TOML file.
+        #[arg(long, value_name = "workflow.toml")]
+        from: PathBuf,
+    },
+    /// Send the prompt of a workflow, with what it attaches, and write the response, for cron:
+    /// without asking anything, sending failed requests again, and exiting with 3 if they still
+    /// fail and 4 if it took too long.
+    Run {
+        /// The workflow, a TOML file with a `prompt`.
+        #[arg(value_name = "workflow.toml")]
+        workflow: PathBuf,
+        /// Where to write the response, once it's complete. Stdout if not given.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// How many times to send a request again that failed for the rate limit, an error of the
+        /// server or the connection.
+        #[arg(long, value_name = "n", default_value_t = 3)]
+        retries: u32,
+        /// Give up after this many seconds in all.
+        #[arg(long, value_name = "seconds", default_value_t = 600)]
+        timeout: u64,
+    },
+    /// Answer the requests of an editor's plugin, of Neovim or Helix, say, on stdin and stdout:
+    /// JSON-RPC, framed as the Language Server Protocol frames it, to complete code, edit a
+    /// selection or chat, with the configuration read once for all of them.
+    Lsp,
+    /// Keep the configuration read and a connection to the API open for `ata2 ask`, listening on
+    /// a Unix socket of the profile, or on the one systemd passes it.
+    Daemon,
+    /// Send a prompt to `ata2 daemon` and print the response, sooner than ata² started anew
+    /// would, or send it as ata² does if the daemon isn't running.
+    Ask {
+        /// The prompt.
+        prompt: String,
+    },
+    /// The tools of `[tools.custom]`.
+    Tools {
+        #[command(subcommand)]
+        command: ToolsCommand,
+    },
+    /// Delete the saved conversations and prompts of the history that `ui.retention_days` and
+    /// `ui.max_sessions` don't keep, cached responses older than `cache.ttl_hours`, and files
+    /// left over by saves that didn't finish.
+    Gc {
+        /// Only list what would be deleted.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionsCommand {
+    /// List them, newest first, with their tags.
+    List {
+        /// Only those tagged so. Given more than once, only those with all of the tags.
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Merge conversations into one, in the order they're given, with the system message of the
+    /// first, for a new question that depends on all of them.
+    Merge {
+        /// The conversations: their paths, or their names in `ui.save_dir`.
+        #[arg(num_args = 2.., required = true, add = ArgValueCompleter::new(completions::sessions))]
+        sessions: Vec<String>,
+        /// Where to save it: a path, or a name in `ui.save_dir`.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print what a setting is set to by each of the default, the configuration file, the
+    /// environment and the flags that set it, the last of them the one that applies.
+    Explain {
+        /// The setting, like `model` or `ui.wrap`.
+        #[arg(add = ArgValueCompleter::new(completions::settings))]
+        key: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ToolsCommand {
+    /// Print tools of the operations of an OpenAPI spec, in YAML or JSON, each an HTTP request to
+    /// the API with the headers of its `[tools.apis]`, for `ata2 tools import openapi.yaml
+    /// --filter '/v1/users*' >> ata2.toml`.
+    Import {
+        spec: PathBuf,
+        /// Only the operations of the paths that match, where `*` is anything. Given more than
+        /// once, of the paths that match any.
+        #[arg(long, value_name = "pattern")]
+        filter: Vec<String>,
+        /// The name of the API in `[tools.apis]`, which the spec's title is made into if it's not
+        /// given.
+        #[arg(long)]
+        api: Option<String>,
+    },
+}
+
+/// What stdin has, for `--stdin-format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum StdinFormat {
+    /// Prompts, all of it one or, with `--delimiter`, separated by lines of the delimiter.
+    #[default]
+    Text,
+    /// A message a line, like `{"role": "user", "content": "…"}`. The user's are sent as prompts,
+    /// and the system's and the assistant's added to the conversation as they are.
+    Jsonl,
+    /// A conversation, as ata² saves them, ending with a prompt. The response is printed to
+    /// stderr as it streams in, and the conversation with it to stdout.
+    Conversation,
+}
+
+/// What `ata2 share` writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ShareFormat {
+    /// A page with the code highlighted.
+    #[default]
+    Html,
+    /// A Jupyter notebook, code blocks in code cells.
+    Ipynb,
+    /// An Org document, code blocks in source blocks.
+    Org,
+}
+
+/// How long a summary `ata2 summarize` writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SummaryLength {
+    /// A few sentences.
+    #[default]
+    Short,
+    /// The points of each part, with the facts and figures that matter.
+    Detailed,
+}
+
+/// What `ata2 sweep` writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SweepFormat {
+    /// A JSON object a line, with the parameters, the response and how long it took.
+    #[default]
+    Jsonl,
+    /// A section a combination, the response under its parameters.
+    Markdown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    /// Even into a pipe, say to `less -R`.
+    Always,
+    Never,
 }
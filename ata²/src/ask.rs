@@ -0,0 +1,98 @@
+//! Questions asked while the readline task reads the next prompt, like whether a tool the model
+//! called mid-response may run. The next line typed is handed over as the answer, rather than the
+//! question and rustyline both reading the terminal at once. Asked by the readline task itself,
+//! between prompts, the terminal is read directly, as rustyline is done with it.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+use std::future::Future;
+use std::io::{self, Write as _};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::i18n;
+use crate::ABORT;
+use crate::CANCEL;
+
+tokio::task_local! {
+    /// Set for the readline task, which reads the answer itself.
+    static READLINE: ();
+}
+
+/// The question waiting for the next line the readline task reads.
+static PENDING: Mutex<Option<oneshot::Sender<String>>> = Mutex::new(None);
+
+/// How often a question that's waiting looks whether the response was stopped meanwhile.
+const POLL: Duration = Duration::from_millis(50);
+
+/// Run `readline` as the readline task, which asks by reading the terminal itself.
+pub async fn reading<F: Future>(readline: F) -> F::Output {
+    READLINE.scope((), readline).await
+}
+
+/// Whether a question is waiting for the next line, which Enter then sends.
+pub fn pending() -> bool {
+    PENDING.lock().unwrap().is_some()
+}
+
+/// Hand `line`, read by the readline task, to the question waiting for it. Returns it if there's
+/// none, as it's then a prompt.
+pub fn answer(line: String) -> Option<String> {
+    match PENDING.lock().unwrap().take() {
+        Some(question) => {
+            let _ = question.send(line);
+            None
+        }
+        None => Some(line),
+    }
+}
+
+/// The line typed in answer to `question`, unless the response is stopped first.
+pub async fn line(question: &str) -> Option<String> {
+    eprint!("{question} ");
+    let _ = io::stderr().flush();
+    if READLINE.try_with(|_| ()).is_ok() {
+        let mut answer = String::new();
+        return io::stdin().read_line(&mut answer).ok().map(|_| answer);
+    }
+    let (tx, mut rx) = oneshot::channel();
+    *PENDING.lock().unwrap() = Some(tx);
+    loop {
+        match timeout(POLL, &mut rx).await {
+            Ok(answer) => return answer.ok(),
+            Err(_) if ABORT.load(Ordering::Relaxed) || CANCEL.load(Ordering::Relaxed) => {
+                PENDING.lock().unwrap().take();
+                eprintln!();
+                return None;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Whether the answer to `question` is yes, asking with `[y/N]`.
+pub async fn yes_no(question: &str) -> bool {
+    line(&format!("{question} {}", tr!("yes-no")))
+        .await
+        .is_some_and(|answer| i18n::is_yes(&answer))
+}
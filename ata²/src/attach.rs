@@ -0,0 +1,458 @@
+//! Text files for the next prompt: `/attach src/main.rs lines 40-120` or
+//! `--attach src/main.rs:40-120`. Each is sent after the prompt, fenced, under its path and the
+//! lines it's from, with their numbers, for the model to say where something is. Of a PDF or a
+//! `.docx`, the text is sent, see [`crate::extract`], and of a recording like `meeting.mp3` its
+//! transcript, see [`crate::transcribe`]. A CSV or TSV file is summed up with a sample of its
+//! rows, see [`crate::tabular`]. Together they can't be more than
+//! `attachment_max_tokens`, and `/attachments` lists them with their tokens.
+//! `/attach-tree src --glob '*.rs'` attaches a directory: the tree of its files, and as many of
+//! them as fit.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use regex::Regex;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::commands::usage;
+use crate::extract;
+use crate::paste;
+use crate::tabular;
+use crate::transcribe;
+use crate::usage::{count_tokens, tokens_text};
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+use crate::FLAGS;
+
+/// What goes with the next prompt.
+static ATTACHMENTS: Mutex<Vec<Attachment>> = Mutex::new(vec![]);
+
+struct Attachment {
+    /// As given, with the lines if only some are.
+    name: String,
+    /// As sent.
+    text: String,
+    tokens: usize,
+}
+
+/// Attach the files of `--attach`.
+pub async fn from_flags() -> TokioResult<()> {
+    for arg in &FLAGS.attach {
+        let (path, range) = match arg.rsplit_once(':') {
+            Some((path, range)) if lines(range).is_ok() => (path, Some(range)),
+            _ => (arg.as_str(), None),
+        };
+        add(path, range).await?;
+    }
+    Ok(())
+}
+
+/// `/attach <file> [lines a-b]`.
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    match args.as_slice() {
+        [path] => add(path, None).await,
+        [path, word, range] if word == "lines" => add(path, Some(range)).await,
+        [path, range] => add(path, Some(range)).await,
+        _ => Err(usage("attach")),
+    }
+}
+
+/// `/attachments [clear]`.
+pub async fn list(args: Vec<String>) -> TokioResult<()> {
+    let mut attachments = ATTACHMENTS.lock().unwrap();
+    match args.as_slice() {
+        [] if attachments.is_empty() => eprintln!("{}", tr!("attach-none")),
+        [] => {
+            for (i, attachment) in attachments.iter().enumerate() {
+                eprintln!(
+                    "{:>3}  {}  ~{}",
+                    i + 1,
+                    attachment.name,
+                    tokens_text(attachment.tokens)
+                );
+            }
+            eprintln!("{}", total(&attachments));
+        }
+        [word] if word == "clear" => {
+            attachments.clear();
+            eprintln!("{}", tr!("attach-cleared"));
+        }
+        _ => return Err(usage("attachments")),
+    }
+    Ok(())
+}
+
+/// How many tokens `attachments` are, of how many they can be.
+fn total(attachments: &[Attachment]) -> String {
+    let tokens = attachments.iter().map(|a| a.tokens).sum::<usize>();
+    match config.attachment_max_tokens {
+        0 => tr!("attach-total", tokens = tokens),
+        max => tr!("attach-total-of", tokens = tokens, max = max),
+    }
+}
+
+async fn add(path: &str, range: Option<&str>) -> TokioResult<()> {
+    let contents = if transcribe::is_audio(Path::new(path)) {
+        transcribe::transcript(Path::new(path))
+            .await
+            .map_err(|e| tr!("attach-unreadable", path = path, error = e.to_string()))?
+    } else {
+        read(Path::new(path))?
+    };
+    let (name, text) = annotate(path, &contents, range)?;
+    push(name, text)
+}
+
+/// Attach each file that `pattern` matches, like `src/**/*.rs`, or the file it is if it has no
+/// wildcards.
+pub async fn glob(pattern: &str) -> TokioResult<()> {
+    if !pattern.contains(['*', '?']) {
+        return add(pattern, None).await;
+    }
+    let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    // Walked from the directory before the first wildcard.
+    let root = pattern[..pattern.find(['*', '?']).unwrap_or_default()]
+        .rfind('/')
+        .map_or("", |end| &pattern[..=end]);
+    let regex = glob_regex(pattern)?;
+    let start = Path::new(if root.is_empty() { "." } else { root });
+    let mut files = vec![];
+    walk(start, start, &mut files);
+    let mut paths = files
+        .into_iter()
+        .map(|file| Path::new(root).join(file.relative))
+        .filter(|path| regex.is_match(&path.to_string_lossy()))
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        return Err(tr!("attach-no-match", pattern = pattern).into());
+    }
+    paths.sort();
+    for path in paths {
+        add(&path.to_string_lossy(), None).await?;
+    }
+    Ok(())
+}
+
+/// Attach what `command`, run with `sh -c`, outputs, like `git log --oneline`.
+pub async fn output(command: &str) -> TokioResult<()> {
+    let output = tokio::process::Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| tr!("run-failed", program = "sh", error = e.to_string()))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(tr!(
+            "attach-command-failed",
+            command = command,
+            error = error.trim()
+        )
+        .into());
+    }
+    let name = format!("$ {command}");
+    let text = format!(
+        "{name}:\n{}",
+        paste::fenced(&String::from_utf8_lossy(&output.stdout))
+    );
+    push(name, text)
+}
+
+/// The text of the file at `path`, or of the document it is.
+pub fn read(path: &Path) -> Result<String, String> {
+    let unreadable = |error: String| {
+        let path = path.display().to_string();
+        tr!("attach-unreadable", path = path, error = error)
+    };
+    let bytes = fs::read(path).map_err(|e| unreadable(e.to_string()))?;
+    if let Some(text) = extract::text(path, &bytes) {
+        return text.map_err(unreadable);
+    }
+    String::from_utf8(bytes).map_err(|_| tr!("attach-not-text", path = path.display().to_string()))
+}
+
+/// `contents` of `path`, or lines `range` of them, as they're sent: under the name of what they
+/// are, fenced, each line with its number. A whole table is summed up instead.
+fn annotate(path: &str, contents: &str, range: Option<&str>) -> Result<(String, String), String> {
+    if range.is_none() {
+        if let Some(summary) = tabular::summary(Path::new(path), contents) {
+            return Ok((path.to_string(), format!("{path}:\n{summary}")));
+        }
+    }
+    let count = contents.lines().count();
+    let (first, last) = match range {
+        Some(range) => lines(range)?,
+        None => (1, count.max(1)),
+    };
+    let last = last.min(count);
+    if first > last {
+        return Err(tr!("attach-no-lines", path = path, count = count));
+    }
+    let name = match range {
+        Some(_) => tr!("attach-name-lines", path = path, first = first, last = last),
+        None => path.to_string(),
+    };
+    let width = last.to_string().len();
+    let numbered = contents
+        .lines()
+        .enumerate()
+        .skip(first - 1)
+        .take(last + 1 - first)
+        .map(|(i, line)| format!("{:>width$}  {line}", i + 1))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = format!("{name}:\n{}", paste::fenced(&numbered));
+    Ok((name, text))
+}
+
+/// How many tokens can still be attached, if there's a limit.
+fn left(attachments: &[Attachment]) -> Option<usize> {
+    let max = config.attachment_max_tokens;
+    let before = attachments.iter().map(|a| a.tokens).sum::<usize>();
+    (max > 0).then(|| max.saturating_sub(before))
+}
+
+/// Attach `text` as `name`, if there are tokens left for it.
+fn push(name: String, text: String) -> TokioResult<()> {
+    let tokens = count_tokens(&config.model, &text);
+    let mut attachments = ATTACHMENTS.lock().unwrap();
+    if let Some(left) = left(&attachments).filter(|left| tokens > *left) {
+        return Err(tr!(
+            "attach-over-budget",
+            name = name.as_str(),
+            tokens = tokens,
+            left = left
+        )
+        .into());
+    }
+    info!(
+        "{}",
+        tr!("attach-added", name = name.as_str(), tokens = tokens)
+    );
+    attachments.push(Attachment { name, text, tokens });
+    eprintln!("{}", total(&attachments));
+    Ok(())
+}
+
+/// `/attach-tree <dir> [--max-files n] [--glob pattern]`: the files under `dir` as a tree, and
+/// as many of them as `--max-files` and `attachment_max_tokens` allow, newest and then smallest
+/// first. Hidden files and directories are left out.
+pub async fn tree(args: Vec<String>) -> TokioResult<()> {
+    let mut dir = None;
+    let mut max_files = 50;
+    let mut glob = None;
+    let mut args = args.iter().map(|arg| arg.trim_matches(['\'', '"']));
+    while let Some(arg) = args.next() {
+        match arg {
+            "--max-files" => {
+                max_files = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| usage("attach-tree"))?
+            }
+            "--glob" => {
+                let pattern = args.next().ok_or_else(|| usage("attach-tree"))?;
+                glob = Some(glob_regex(pattern)?);
+            }
+            _ if dir.is_none() => dir = Some(PathBuf::from(arg)),
+            _ => return Err(usage("attach-tree")),
+        }
+    }
+    let Some(dir) = dir else {
+        return Err(usage("attach-tree"));
+    };
+    if !dir.is_dir() {
+        return Err(tr!("attach-not-dir", path = dir.display().to_string()).into());
+    }
+
+    let mut files = vec![];
+    walk(&dir, &dir, &mut files);
+    if let Some(glob) = &glob {
+        files.retain(|file| {
+            let name = file
+                .relative
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            glob.is_match(&file.relative.to_string_lossy()) || glob.is_match(&name)
+        });
+    }
+    files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    let name = dir.display().to_string();
+    let listing = paste::fenced(&listing(&name, &files));
+
+    let mut left = left(&ATTACHMENTS.lock().unwrap()).unwrap_or(usize::MAX);
+    left = left.saturating_sub(count_tokens(&config.model, &listing));
+    let mut chosen = files.iter().collect::<Vec<_>>();
+    chosen.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.size.cmp(&b.size)));
+    let mut text = listing;
+    let mut included = 0;
+    for file in chosen.into_iter().take(max_files) {
+        let path = dir.join(&file.relative);
+        let Ok(contents) = read(&path) else {
+            continue;
+        };
+        let Ok((_, annotated)) = annotate(&path.display().to_string(), &contents, None) else {
+            continue;
+        };
+        let tokens = count_tokens(&config.model, &annotated);
+        if tokens > left {
+            continue;
+        }
+        left -= tokens;
+        text += "\n\n";
+        text += &annotated;
+        included += 1;
+    }
+    let name = tr!(
+        "attach-name-tree",
+        path = name.as_str(),
+        included = included,
+        count = files.len()
+    );
+    push(name, text)
+}
+
+/// A file under the directory of `/attach-tree`.
+struct File {
+    relative: PathBuf,
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<File>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        // Not following links, which could go round in circles.
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            walk(root, &path, files);
+        } else if file_type.is_file() {
+            let metadata = entry.metadata().ok();
+            files.push(File {
+                relative: path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+                modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+                size: metadata.map(|m| m.len()).unwrap_or_default(),
+            });
+        }
+    }
+}
+
+/// `files`, sorted, as an indented tree under `root`.
+fn listing(root: &str, files: &[File]) -> String {
+    let mut ret = format!("{}/", root.trim_end_matches(['/', '\\']));
+    let mut open: Vec<String> = vec![];
+    for file in files {
+        let mut components = file
+            .relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let name = components.pop().unwrap_or_default();
+        let same = open
+            .iter()
+            .zip(&components)
+            .take_while(|(a, b)| a == b)
+            .count();
+        open.truncate(same);
+        for dir in &components[same..] {
+            ret += &format!("\n{}{dir}/", "  ".repeat(open.len() + 1));
+            open.push(dir.clone());
+        }
+        ret += &format!("\n{}{name}", "  ".repeat(open.len() + 1));
+    }
+    ret
+}
+
+/// `pattern`, where `*` is anything but `/`, `**` anything, `**/` any directories, none too, and
+/// `?` one character, as a regex.
+fn glob_regex(pattern: &str) -> Result<Regex, String> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.next_if_eq(&'*').is_some() => {
+                regex += match chars.next_if_eq(&'/') {
+                    Some(_) => "(?:.*/)?",
+                    None => ".*",
+                }
+            }
+            '*' => regex += "[^/]*",
+            '?' => regex += "[^/]",
+            c => regex += &regex::escape(&c.to_string()),
+        }
+    }
+    regex += "$";
+    Regex::new(&regex).map_err(|e| e.to_string())
+}
+
+/// The lines of `range`, like `40-120`, `40-` or `40`, from 1.
+fn lines(range: &str) -> Result<(usize, usize), String> {
+    let not_valid = || tr!("attach-bad-lines", range = range);
+    let number = |s: &str| s.trim().parse::<usize>().ok().filter(|n| *n >= 1);
+    let (first, last) = match range.split_once(['-', '–']) {
+        Some((first, "")) => (number(first), Some(usize::MAX)),
+        Some((first, last)) => (number(first), number(last)),
+        None => (number(range), number(range)),
+    };
+    match (first, last) {
+        (Some(first), Some(last)) if first <= last => Ok((first, last)),
+        _ => Err(not_valid()),
+    }
+}
+
+/// Whether anything is attached to the next prompt.
+pub fn any() -> bool {
+    !ATTACHMENTS.lock().unwrap().is_empty()
+}
+
+/// `prompt` with what's attached after it, which is then no longer attached.
+pub fn take(prompt: String) -> String {
+    let attachments = std::mem::take(&mut *ATTACHMENTS.lock().unwrap());
+    with(prompt, &attachments)
+}
+
+/// `prompt` with what's attached after it, as [`take`] has it, but still attached.
+pub fn peek(prompt: String) -> String {
+    with(prompt, &ATTACHMENTS.lock().unwrap())
+}
+
+fn with(prompt: String, attachments: &[Attachment]) -> String {
+    if attachments.is_empty() {
+        return prompt;
+    }
+    let attachments = attachments
+        .iter()
+        .map(|a| a.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!("{}\n\n{attachments}", prompt.trim_end())
+}
@@ -0,0 +1,202 @@
+//! `ata2 bench`: stream the response to a prompt from each of some models, a few times, and
+//! compare how long the first token took, how many tokens a second followed and how long all of
+//! it took, from where you are.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::CreateChatCompletionRequestArgs;
+use futures_util::StreamExt as _;
+
+use std::time::{Duration, Instant};
+
+use crate::api_error;
+use crate::config::Config;
+use crate::openai;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::style::Style;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// One response.
+struct Run {
+    first_token: Duration,
+    total: Duration,
+    tokens: usize,
+}
+
+impl Run {
+    /// Tokens a second after the first.
+    fn rate(&self) -> f64 {
+        let streaming = (self.total - self.first_token).as_secs_f64();
+        if streaming > 0.0 {
+            self.tokens.saturating_sub(1) as f64 / streaming
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Send `prompt` to each of `models`, or the configured one, `runs` times, and print the medians.
+pub async fn run(models: &[String], prompt: &str, runs: usize) -> TokioResult<()> {
+    let config: &Config = &CONFIGURATION;
+    let models = if models.is_empty() {
+        vec![config.model.clone()]
+    } else {
+        models.to_vec()
+    };
+    let dim = Style::parse("dim").unwrap_or_default();
+
+    let mut rows = vec![];
+    for model in &models {
+        let mut done = vec![];
+        for i in 1..=runs.max(1) {
+            match run_once(model, prompt).await {
+                Ok(run) => {
+                    eprintln!(
+                        "{}",
+                        dim.paint_to(
+                            atty::Stream::Stderr,
+                            &tr!(
+                                "bench-run",
+                                model = model.as_str(),
+                                n = i,
+                                first_token = seconds(run.first_token),
+                                rate = format!("{:.1}", run.rate()),
+                                total = seconds(run.total),
+                                tokens = run.tokens
+                            )
+                        )
+                    );
+                    done.push(run);
+                }
+                Err(e) => {
+                    error!(
+                        "{}",
+                        tr!("bench-error", model = model.as_str(), error = e.to_string())
+                    );
+                    break;
+                }
+            }
+        }
+        rows.push((model, done));
+    }
+
+    let headers = [
+        tr!("bench-model"),
+        tr!("bench-first-token"),
+        tr!("bench-rate"),
+        tr!("bench-total"),
+        tr!("bench-tokens"),
+    ];
+    let rows = rows
+        .iter()
+        .map(|(model, runs)| {
+            let mut row = vec![model.to_string()];
+            if runs.is_empty() {
+                row.extend(["–"; 4].map(String::from));
+            } else {
+                row.push(seconds(median(runs.iter().map(|run| run.first_token))));
+                row.push(format!(
+                    "{:.1}",
+                    median(runs.iter().map(|run| Duration::from_secs_f64(run.rate())))
+                        .as_secs_f64()
+                ));
+                row.push(seconds(median(runs.iter().map(|run| run.total))));
+                row.push(median(runs.iter().map(|run| run.tokens)).to_string());
+            }
+            row
+        })
+        .collect::<Vec<_>>();
+    let widths = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([headers[i].chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let bold = Style::parse("bold").unwrap_or_default();
+    let line = |row: &[String]| {
+        row.iter()
+            .enumerate()
+            .map(|(i, cell)| match i {
+                0 => format!("{cell:<width$}", width = widths[i]),
+                _ => format!("{cell:>width$}", width = widths[i]),
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    println!(
+        "{}",
+        bold.paint_to(atty::Stream::Stdout, line(&headers).trim_end())
+    );
+    for row in &rows {
+        println!("{}", line(row).trim_end());
+    }
+    Ok(())
+}
+
+/// Stream the response of `model` to `prompt`, with the configuration's parameters.
+async fn run_once(model: &str, prompt: &str) -> TokioResult<Run> {
+    let config: &Config = &CONFIGURATION;
+    let messages = vec![string_to_chat_completion_request_user_message(
+        prompt.to_string(),
+    )];
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    let request = request
+        .model(model)
+        .n(1)
+        .messages(messages.clone())
+        .build()?;
+    let start = Instant::now();
+    let report = usage::Report::default();
+    let mut stream = openai::chat_stream(request, &report).await?;
+    let mut first_token = None;
+    let mut response = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| api_error::from_openai(e, model))?;
+        for choice in chunk.choices {
+            if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+                first_token.get_or_insert_with(|| start.elapsed());
+                response += &text;
+            }
+        }
+    }
+    let total = start.elapsed();
+    usage::record(model, &messages, &response, &report);
+    Ok(Run {
+        first_token: first_token.unwrap_or(total),
+        total,
+        tokens: usage::count_tokens(model, &response),
+    })
+}
+
+/// The middle of `values`, the lower of the two middle ones of an even number of them.
+fn median<T: Ord + Default>(values: impl Iterator<Item = T>) -> T {
+    let mut values = values.collect::<Vec<_>>();
+    values.sort_unstable();
+    let middle = values.len().saturating_sub(1) / 2;
+    values.into_iter().nth(middle).unwrap_or_default()
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.2} s", duration.as_secs_f64())
+}
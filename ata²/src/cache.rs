@@ -0,0 +1,107 @@
+//! `[cache] enabled = true`: responses kept on disk by a hash of the request they answer, so that
+//! one-shot invocations that send the same request again, as from a build script, are answered at
+//! once without it. Only `--fim`, `ata2 sweep` and `ata2 eval` use it, which send a request once
+//! rather than continue a conversation.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use log::debug;
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::CONFIGURATION;
+use crate::FLAGS;
+
+/// The cached response to `request` to `endpoint` of the API, unless it's expired or `--no-cache`
+/// was given.
+pub fn get(endpoint: &str, request: &impl Serialize) -> Option<String> {
+    if FLAGS.no_cache {
+        return None;
+    }
+    let path = path(endpoint, request)?;
+    if expired(&path) {
+        return None;
+    }
+    let response = fs::read_to_string(&path).ok()?;
+    debug!("Cached response {}", path.display());
+    Some(response)
+}
+
+/// Cache `response` to `request` to `endpoint` of the API.
+pub fn put(endpoint: &str, request: &impl Serialize, response: &str) {
+    let Some(path) = path(endpoint, request) else {
+        return;
+    };
+    // Through a temporary file, for another ata² never to read half of it.
+    let tmp = path.with_extension("tmp");
+    let result = fs::create_dir_all(&CONFIGURATION.cache.dir)
+        .and_then(|()| fs::write(&tmp, response))
+        .and_then(|()| fs::rename(&tmp, &path));
+    if let Err(e) = result {
+        error!(
+            "{}",
+            tr!(
+                "cache-write-failed",
+                path = path.display().to_string(),
+                error = e.to_string()
+            )
+        );
+    }
+}
+
+/// The cached responses that are expired, for `ata2 gc`.
+pub fn expired_entries() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(&CONFIGURATION.cache.dir) else {
+        return vec![];
+    };
+    let mut ret = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .filter(|path| expired(path))
+        .collect::<Vec<_>>();
+    ret.sort_unstable();
+    ret
+}
+
+/// Where the response to `request` to `endpoint` is cached, if the cache is enabled. The same
+/// request always serializes the same, so its hash is its address.
+fn path(endpoint: &str, request: &impl Serialize) -> Option<PathBuf> {
+    if !CONFIGURATION.cache.enabled {
+        return None;
+    }
+    let request = serde_json::to_string(request).ok()?;
+    let hash = Sha256::digest(format!("{endpoint}\n{request}"));
+    Some(CONFIGURATION.cache.dir.join(format!("{hash:x}.txt")))
+}
+
+/// Was the response in `path` cached longer ago than `cache.ttl_hours`?
+fn expired(path: &Path) -> bool {
+    let ttl_hours = CONFIGURATION.cache.ttl_hours;
+    ttl_hours > 0
+        && fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > Duration::from_secs(ttl_hours * 60 * 60))
+}
@@ -0,0 +1,177 @@
+//! Text longer than a request can take, cut into chunks and worked through map-reduce: each chunk
+//! is sent on its own with the same instructions, as many at once as `[scheduler]` allows, and
+//! then what came of them, in groups that fit, with the instructions to merge them, again and
+//! again until one is left. For `ata2 summarize`, and whatever else has to go over more than fits.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    CreateChatCompletionRequestArgs, Role,
+};
+use futures_util::StreamExt as _;
+
+use crate::cache;
+use crate::config::Config;
+use crate::openai;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::scheduler;
+use crate::usage::{self, count_tokens};
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// How to work through text that doesn't fit.
+pub struct MapReduce<'a> {
+    /// How many tokens of text a request can have.
+    pub max_tokens: usize,
+    /// What's sent with text that fits in one request.
+    pub whole: &'a str,
+    /// What's sent with each chunk of text that doesn't.
+    pub map: &'a str,
+    /// What's sent with what came of consecutive chunks, separated by `---`, to merge them.
+    pub reduce: &'a str,
+}
+
+impl MapReduce<'_> {
+    /// What comes of `text`.
+    pub async fn run(&self, text: &str) -> TokioResult<String> {
+        let chunks = split(text, self.max_tokens);
+        if chunks.len() <= 1 {
+            return ask(self.whole, text).await;
+        }
+        let mut results = all(self.map, chunks).await?;
+        while results.len() > 1 {
+            let groups = group(&results, self.max_tokens);
+            results = all(self.reduce, groups).await?;
+        }
+        Ok(results.pop().unwrap_or_default())
+    }
+}
+
+/// `text` in chunks of at most `max_tokens`, of whole paragraphs, or of whole lines of one that's
+/// longer, or of as much of a line that's longer still.
+pub fn split(text: &str, max_tokens: usize) -> Vec<String> {
+    let model = &CONFIGURATION.model;
+    let mut ret: Vec<String> = vec![];
+    let mut push = |piece: &str| match ret.last_mut() {
+        Some(last) if count_tokens(model, last) + count_tokens(model, piece) <= max_tokens => {
+            *last += piece;
+        }
+        _ => ret.push(piece.to_string()),
+    };
+    for paragraph in text.split_inclusive("\n\n") {
+        if count_tokens(model, paragraph) <= max_tokens {
+            push(paragraph);
+            continue;
+        }
+        for line in paragraph.split_inclusive('\n') {
+            if count_tokens(model, line) <= max_tokens {
+                push(line);
+                continue;
+            }
+            // About four characters a token.
+            let chars = line.chars().collect::<Vec<_>>();
+            for piece in chars.chunks(max_tokens.max(1) * 4) {
+                push(&piece.iter().collect::<String>());
+            }
+        }
+    }
+    ret.retain(|chunk| !chunk.trim().is_empty());
+    ret
+}
+
+/// `results` joined in groups of at most `max_tokens`, at least two to a group for there to be
+/// fewer of them.
+fn group(results: &[String], max_tokens: usize) -> Vec<String> {
+    let model = &CONFIGURATION.model;
+    let mut ret: Vec<(String, usize)> = vec![];
+    for result in results {
+        match ret.last_mut() {
+            Some((group, count))
+                if *count < 2
+                    || count_tokens(model, group) + count_tokens(model, result) <= max_tokens =>
+            {
+                *group += "\n\n---\n\n";
+                *group += result;
+                *count += 1;
+            }
+            _ => ret.push((result.clone(), 1)),
+        }
+    }
+    ret.into_iter().map(|(group, _)| group).collect()
+}
+
+/// What comes of each of `texts` with `instructions`, in their order, as many at once as
+/// `[scheduler]` allows.
+async fn all(instructions: &str, texts: Vec<String>) -> TokioResult<Vec<String>> {
+    let jobs = texts
+        .into_iter()
+        .map(|text| {
+            let job = async move { ask(instructions, &text).await };
+            (CONFIGURATION.model.clone(), job)
+        })
+        .collect();
+    let mut results = scheduler::run(jobs)?;
+    let mut ret = vec![];
+    while let Some(result) = results.next().await {
+        ret.push(result?);
+    }
+    Ok(ret)
+}
+
+/// The model's answer to `text` after `instructions`, from the cache if it's there.
+async fn ask(instructions: &str, text: &str) -> TokioResult<String> {
+    let config: &Config = &CONFIGURATION;
+    let messages = vec![
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            role: Role::System,
+            content: Some(instructions.to_string()),
+        }),
+        string_to_chat_completion_request_user_message(text.to_string()),
+    ];
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    let request = request
+        .n(1)
+        .stream(false)
+        .messages(messages.clone())
+        .build()?;
+    if let Some(text) = cache::get("/chat/completions", &request) {
+        return Ok(text);
+    }
+    let response = openai::chat(request.clone()).await?;
+    let text = response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+    let (prompt_tokens, completion_tokens) = match response.usage {
+        Some(usage) => (
+            usage.prompt_tokens as usize,
+            usage.completion_tokens as usize,
+        ),
+        None => (
+            usage::conversation_tokens(&config.model, &messages),
+            count_tokens(&config.model, &text),
+        ),
+    };
+    usage::record_tokens(&config.model, prompt_tokens, completion_tokens);
+    cache::put("/chat/completions", &request, &text);
+    Ok(text)
+}
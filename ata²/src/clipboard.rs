@@ -0,0 +1,119 @@
+//! Copying to the clipboard with OSC 52, which works over SSH and in tmux too, as long as the
+//! terminal allows it. `ata2 clipwatch` reads and writes the clipboard of the system it runs on
+//! instead, with the programs of the platform.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use base64::Engine as _;
+use tokio::io::AsyncWriteExt as _;
+use tokio::process::Command;
+
+use std::io::{self, Write as _};
+use std::process::Stdio;
+
+use crate::TokioResult;
+
+/// Programs that print the clipboard, the first that works of them used.
+#[cfg(target_os = "macos")]
+const PASTE: &[&[&str]] = &[&["pbpaste"]];
+#[cfg(windows)]
+const PASTE: &[&[&str]] = &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]];
+#[cfg(not(any(windows, target_os = "macos")))]
+const PASTE: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+    &["xsel", "--clipboard", "--output"],
+];
+
+/// Programs that put what they read into the clipboard, like [`PASTE`].
+#[cfg(target_os = "macos")]
+const COPY: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(windows)]
+const COPY: &[&[&str]] = &[&[
+    "powershell",
+    "-NoProfile",
+    "-Command",
+    "Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+]];
+#[cfg(not(any(windows, target_os = "macos")))]
+const COPY: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard", "-in"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+pub fn copy(text: &str) {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout().lock();
+    let _ = write!(stdout, "\x1b]52;c;{encoded}\x07");
+    let _ = stdout.flush();
+}
+
+/// The text in the system clipboard. Empty if there's none, or it holds something else.
+pub async fn read() -> TokioResult<String> {
+    Ok(run(PASTE, None).await?.unwrap_or_default())
+}
+
+/// Put `text` in the system clipboard.
+pub async fn write(text: &str) -> TokioResult<()> {
+    run(COPY, Some(text)).await.map(|_| ())
+}
+
+/// What the first of `programs` that succeeds prints, given `stdin`. None if those there are all
+/// failed, which the likes of `wl-paste` do for an empty clipboard.
+async fn run(programs: &[&[&str]], stdin: Option<&str>) -> TokioResult<Option<String>> {
+    let mut found = false;
+    for program in programs {
+        let child = Command::new(program[0])
+            .args(&program[1..])
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            // xclip stays in the background to hold what it copied, and with it the pipe.
+            .stdout(if stdin.is_some() {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(tr!("run-failed", program = program[0], error = e.to_string()).into())
+            }
+        };
+        found = true;
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if output.status.success() {
+            return Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()));
+        }
+    }
+    if !found {
+        let names = programs.iter().map(|p| p[0]).collect::<Vec<_>>().join(", ");
+        return Err(tr!("clipboard-no-program", programs = names).into());
+    }
+    Ok(None)
+}
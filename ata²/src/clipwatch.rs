@@ -0,0 +1,106 @@
+//! `ata2 clipwatch`: whenever text is copied, send it with a prompt of `[templates]` and put the
+//! answer in the clipboard in its place, to translate or tidy up what's copied, say. Only with
+//! `clipwatch.enabled`, and not for text that's longer than `clipwatch.max_chars` or that
+//! `clipwatch.exclude` matches.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::CreateChatCompletionRequestArgs;
+use regex::Regex;
+
+use std::time::Duration;
+
+use crate::clipboard;
+use crate::config::Config;
+use crate::openai;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::sink::{self, Sinks};
+use crate::template;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+pub async fn run(template: &str) -> TokioResult<()> {
+    let config: &Config = &CONFIGURATION;
+    let settings = &config.clipwatch;
+    if !settings.enabled {
+        return Err(tr!("clipwatch-disabled").into());
+    }
+    let template = config.template(template)?;
+    let exclude = Some(&settings.exclude)
+        .filter(|exclude| !exclude.is_empty())
+        .map(|exclude| Regex::new(exclude))
+        .transpose()
+        .map_err(|e| format!("clipwatch.exclude: {e}"))?;
+    let interval = Duration::from_millis(settings.interval_ms.max(1));
+
+    // What was copied before isn't new.
+    let mut last = clipboard::read().await?;
+    info!("{}", tr!("clipwatch-started"));
+    loop {
+        tokio::time::sleep(interval).await;
+        let text = match clipboard::read().await {
+            Ok(text) => text,
+            Err(e) => {
+                error!("{e}");
+                continue;
+            }
+        };
+        if text == last || text.trim().is_empty() {
+            continue;
+        }
+        last = text.clone();
+        let chars = text.chars().count();
+        if chars > settings.max_chars {
+            info!("{}", tr!("clipwatch-too-long", chars = chars));
+            continue;
+        }
+        if exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(&text))
+        {
+            info!("{}", tr!("clipwatch-excluded"));
+            continue;
+        }
+        let answer = match ask(template, &text).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                error!("{e}");
+                continue;
+            }
+        };
+        clipboard::write(&answer).await?;
+        // As the clipboard has it, which may not be quite what was written.
+        last = clipboard::read().await.unwrap_or(answer);
+        info!("{}", tr!("clipwatch-replaced", chars = chars));
+    }
+}
+
+/// The model's answer to `template` with `text` in it.
+async fn ask(template: &str, text: &str) -> TokioResult<String> {
+    let config: &Config = &CONFIGURATION;
+    let prompt = template::prompt(template, text);
+    let messages = vec![string_to_chat_completion_request_user_message(prompt)];
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    let report = usage::Report::default();
+    let stream = openai::chat_stream(request.messages(messages.clone()).build()?, &report).await?;
+    let answer = sink::read(stream, &config.model, &mut Sinks::default()).await?;
+    usage::record(&config.model, &messages, &answer.text, &report);
+    Ok(answer.text)
+}
@@ -0,0 +1,263 @@
+//! Slash commands, i.e. REPL input starting with `/` which ata² handles itself instead of sending
+//! it to the model.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt as _;
+
+use crate::attach;
+use crate::diff;
+use crate::find;
+use crate::help;
+use crate::links;
+use crate::models;
+use crate::pin;
+use crate::policy;
+use crate::prompt;
+use crate::regen;
+use crate::responses;
+use crate::scrollback;
+use crate::sessions;
+use crate::tmux;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// Handlers get the words after the command name. A handler which can't make sense of them should
+/// fail with [`usage`].
+type Handler = fn(Vec<String>) -> BoxFuture<'static, TokioResult<()>>;
+
+pub struct Command {
+    /// Name, without the leading slash.
+    pub name: &'static str,
+    /// Arguments, as shown in help output. The description is the message `command-<name>`.
+    pub usage: &'static str,
+    pub completion: Completion,
+    /// Run where prompts are sent rather than where they're read, as it streams a response,
+    /// which Ctrl-C has to be able to cancel while the next prompt is read.
+    pub streams: bool,
+    handler: Handler,
+}
+
+/// How Tab completes a command's arguments, see [`crate::helper`].
+pub enum Completion {
+    None,
+    /// The first argument is one of these.
+    Words(&'static [&'static str]),
+    /// Every argument is a path.
+    Path,
+    /// The first argument is one of the models `/models` listed last.
+    Models,
+}
+
+/// Every slash command ata² knows about.
+pub static COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "",
+        completion: Completion::None,
+        streams: false,
+        handler: |_| help().boxed(),
+    },
+    Command {
+        name: "continue",
+        usage: "",
+        completion: Completion::None,
+        streams: true,
+        handler: |args| prompt::continue_response(args).boxed(),
+    },
+    Command {
+        name: "regen",
+        usage: "[k]",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| regen::command(args).boxed(),
+    },
+    Command {
+        name: "diff",
+        usage: "",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| diff::command(args).boxed(),
+    },
+    Command {
+        name: "load",
+        usage: "<file>",
+        completion: Completion::Path,
+        streams: false,
+        handler: |args| load(args).boxed(),
+    },
+    Command {
+        name: "links",
+        usage: "[n]",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| links::command(args).boxed(),
+    },
+    Command {
+        name: "model",
+        usage: "[model]",
+        completion: Completion::Models,
+        streams: false,
+        handler: |args| models::switch(args).boxed(),
+    },
+    Command {
+        name: "models",
+        usage: "",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| models::command(args).boxed(),
+    },
+    Command {
+        name: "search",
+        usage: "<words>",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| find::command(args).boxed(),
+    },
+    Command {
+        name: "resume",
+        usage: "[n]",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| find::resume(args).boxed(),
+    },
+    Command {
+        name: "show",
+        usage: "[n]",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| scrollback::show(args).boxed(),
+    },
+    Command {
+        name: "append",
+        usage: "<conversation>",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| sessions::append(args).boxed(),
+    },
+    Command {
+        name: "tag",
+        usage: "[tag | -tag]…",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| sessions::tag(args).boxed(),
+    },
+    Command {
+        name: "pin",
+        usage: "[n | -n]",
+        completion: Completion::None,
+        streams: false,
+        handler: |args| pin::command(args).boxed(),
+    },
+    Command {
+        name: "trust",
+        usage: "[tool]",
+        completion: Completion::Words(policy::TOOLS),
+        streams: false,
+        handler: |args| policy::command(args).boxed(),
+    },
+    Command {
+        name: "tmux",
+        usage: "capture [pane] [lines] | send [pane]",
+        completion: Completion::Words(&["capture", "send"]),
+        streams: false,
+        handler: |args| tmux::command(args).boxed(),
+    },
+    Command {
+        name: "attach",
+        usage: "<file> [lines a-b]",
+        completion: Completion::Path,
+        streams: false,
+        handler: |args| attach::command(args).boxed(),
+    },
+    Command {
+        name: "attach-tree",
+        usage: "<dir> [--max-files n] [--glob pattern]",
+        completion: Completion::Path,
+        streams: false,
+        handler: |args| attach::tree(args).boxed(),
+    },
+    Command {
+        name: "attachments",
+        usage: "[clear]",
+        completion: Completion::Words(&["clear"]),
+        streams: false,
+        handler: |args| attach::list(args).boxed(),
+    },
+    Command {
+        name: "upload",
+        usage: "<file>",
+        completion: Completion::Path,
+        streams: false,
+        handler: |args| responses::upload(args).boxed(),
+    },
+];
+
+async fn help() -> TokioResult<()> {
+    eprint!("{}", help::text(&config.ui));
+    Ok(())
+}
+
+async fn load(args: Vec<String>) -> TokioResult<()> {
+    let [path] = args.as_slice() else {
+        return Err(usage("load"));
+    };
+    prompt::load_conversation(path).await?;
+    info!("{}", tr!("conversation-loaded", path = path.as_str()));
+    Ok(())
+}
+
+fn find(line: &str) -> Option<(&'static Command, Vec<String>)> {
+    let mut words = line.strip_prefix('/')?.split_whitespace();
+    let name = words.next()?;
+    let command = COMMANDS.iter().find(|c| c.name == name)?;
+    Some((command, words.map(str::to_string).collect()))
+}
+
+/// Is `line` an invocation of a known slash command? Input which merely starts with a slash, such
+/// as a path, is sent to the model as usual.
+pub fn is_command(line: &str) -> bool {
+    find(line.trim()).is_some()
+}
+
+/// Does the slash command in `line` stream a response?
+pub fn streams(line: &str) -> bool {
+    find(line.trim()).is_some_and(|(command, _)| command.streams)
+}
+
+/// Run the slash command in `line`.
+pub async fn dispatch(line: &str) -> TokioResult<()> {
+    match find(line.trim()) {
+        Some((command, args)) => (command.handler)(args)
+            .await
+            .map_err(|e| format!("/{}: {e}", command.name).into()),
+        None => Err(tr!("command-unknown", line = line).into()),
+    }
+}
+
+/// The error a handler returns on bad arguments.
+pub fn usage(name: &str) -> Box<dyn std::error::Error + Send + Sync> {
+    let usage = COMMANDS
+        .iter()
+        .find(|c| c.name == name)
+        .map(|c| c.usage)
+        .unwrap_or_default();
+    tr!("command-usage", name = name, usage = usage).into()
+}
@@ -0,0 +1,180 @@
+//! `ata2 completions <shell>` and `ata2 manpage`. The completion script asks ata2 itself what to
+//! complete whenever Tab is pressed (`COMPLETE=<shell> ata2 -- …`), so profiles and saved
+//! conversations are offered as they are at the time.
+//!
+//! What's completed at runtime must not touch [`crate::CONFIGURATION`], which would ask to create
+//! a missing configuration file in the middle of the user's command line.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use clap::CommandFactory as _;
+use clap_complete::engine::ValueCompleter as _;
+use clap_complete::env::Shells;
+use clap_complete::{CompleteEnv, CompletionCandidate, PathCompleter, Shell};
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::str::FromStr as _;
+
+use crate::args::Ata2;
+use crate::config::{self, Config, ConfigLocation, Storage};
+use crate::models;
+use crate::readline::saved_conversations_in;
+use crate::sources;
+use crate::store;
+use crate::TokioResult;
+
+/// The variable the completion script sets to ask for completions.
+const VAR: &str = "COMPLETE";
+const BIN: &str = "ata2";
+
+/// Answer the completion script and exit, if that's what ata2 was run for.
+pub fn complete() {
+    CompleteEnv::with_factory(Ata2::command).var(VAR).complete();
+}
+
+/// `ata2 completions`: print the script that sets up completion in `shell`.
+pub fn script(shell: Shell) -> TokioResult<()> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| tr!("completions-none", shell = shell.to_string()))?;
+    completer.write_registration(VAR, BIN, BIN, BIN, &mut io::stdout())?;
+    Ok(())
+}
+
+/// `ata2 manpage`: print the man page.
+pub fn manpage() -> TokioResult<()> {
+    clap_mangen::Man::new(Ata2::command()).render(&mut io::stdout())?;
+    Ok(())
+}
+
+/// `--config`: the profiles in the configuration directory, or a path.
+pub fn profiles(current: &OsStr) -> Vec<CompletionCandidate> {
+    let typed = current.to_string_lossy();
+    if typed.contains(['.', '/', '\\']) {
+        return PathCompleter::file().complete(current);
+    }
+    let Ok(entries) = fs::read_dir(config::get_config_dir::<2>()) else {
+        return vec![];
+    };
+    let mut profiles = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .filter(|profile| profile.starts_with(typed.as_ref()))
+        .collect::<Vec<_>>();
+    profiles.sort_unstable();
+    profiles.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// `--load`: the conversations saved in the database or `ui.save_dir`, newest first, then any
+/// other file.
+pub fn sessions(current: &OsStr) -> Vec<CompletionCandidate> {
+    let typed = current.to_string_lossy();
+    let ui = profile().ui;
+    let mut saved = match ui.storage {
+        Storage::Sqlite => store::conversations_in(&ui.database_file),
+        Storage::Files => vec![],
+    };
+    for path in saved_conversations_in(&ui.save_dir) {
+        if !saved.contains(&path) {
+            saved.push(path);
+        }
+    }
+    let mut ret = saved
+        .into_iter()
+        .map(|path| {
+            let path = path.strip_prefix(".").unwrap_or(&path).to_path_buf();
+            path.to_string_lossy().into_owned()
+        })
+        .filter(|path| path.starts_with(typed.as_ref()))
+        .map(|path| CompletionCandidate::new(path).display_order(Some(0)))
+        .collect::<Vec<_>>();
+    for candidate in PathCompleter::file().complete(current) {
+        if !ret.iter().any(|c| c.get_value() == candidate.get_value()) {
+            ret.push(candidate.display_order(Some(1)));
+        }
+    }
+    ret
+}
+
+/// `ata2 bench --models`: the models `ata2 models` listed last, each after those already typed.
+pub fn models(current: &OsStr) -> Vec<CompletionCandidate> {
+    model_list(&current.to_string_lossy())
+}
+
+/// `ata2 sweep --param`: for `model=`, the models as for [`models`].
+pub fn params(current: &OsStr) -> Vec<CompletionCandidate> {
+    let typed = current.to_string_lossy();
+    let Some(list) = typed.strip_prefix("model=") else {
+        return vec![];
+    };
+    let mut ret = model_list(list);
+    for candidate in &mut ret {
+        *candidate =
+            CompletionCandidate::new(format!("model={}", candidate.get_value().to_string_lossy()));
+    }
+    ret
+}
+
+/// `ata2 clipwatch --template`: the names in `[templates]`.
+pub fn templates(current: &OsStr) -> Vec<CompletionCandidate> {
+    let typed = current.to_string_lossy();
+    let mut names = profile()
+        .templates
+        .into_keys()
+        .filter(|name| name.starts_with(typed.as_ref()))
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// The settings that `typed` begins, dotted like `ui.wrap`.
+pub fn settings(current: &OsStr) -> Vec<CompletionCandidate> {
+    let typed = current.to_string_lossy();
+    sources::paths()
+        .into_iter()
+        .filter(|path| path.starts_with(typed.as_ref()))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// The models kept by `ata2 models` that the last of the comma-separated `typed` begins, each
+/// after the others.
+fn model_list(typed: &str) -> Vec<CompletionCandidate> {
+    let (before, last) = match typed.rfind(',') {
+        Some(i) => typed.split_at(i + 1),
+        None => ("", typed),
+    };
+    models::completions(&profile().cache.dir, last)
+        .into_iter()
+        .map(|id| CompletionCandidate::new(format!("{before}{id}")))
+        .collect()
+}
+
+/// The default profile, read without the fuss of loading the configuration.
+fn profile() -> Config {
+    fs::read_to_string(ConfigLocation::Auto.location())
+        .ok()
+        .and_then(|contents| Config::from_str(&contents).ok())
+        .unwrap_or_default()
+}
@@ -23,13 +23,14 @@ use std::convert::Infallible;
 use std::env;
 use std::ffi::OsString;
 use std::fmt::{self, Display};
+use std::fs;
 
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
-use ansi_colors::ColouredStr;
-use async_openai::{config::OpenAIConfig, types::CreateChatCompletionRequestArgs};
-use bevy_reflect::{Reflect, Struct};
+use async_openai::types::CreateChatCompletionRequestArgs;
+use bevy_reflect::{FromReflect, Reflect, ReflectRef, Struct};
 use bevy_utils::HashMap;
 use directories::ProjectDirs;
 use os_str_bytes::OsStrBytes as _;
@@ -37,6 +38,13 @@ use os_str_bytes::OsStringBytes as _;
 use serde::{Deserialize, Serialize};
 use serde_json::{Number, Value};
 use toml::de::Error as TomlError;
+use toml::value::Table;
+
+use crate::environment;
+use crate::policy;
+use crate::prompt::BANNER_PLACEHOLDERS;
+use crate::style;
+use crate::template;
 
 lazy_static! {
     pub(crate) static ref DEFAULT_CONFIG_FILENAME: PathBuf = "ata2.toml".into();
@@ -50,6 +58,8 @@ lazy_static! {
 pub struct UiConfig {
     /// Require user to press ^C twice?
     pub double_ctrlc: bool,
+    /// How soon the second ^C has to follow the first, in milliseconds. 0 for no limit.
+    pub double_ctrlc_window_ms: u64,
     /// Hide config on run?
     pub hide_config: bool,
     /// Redact API key?
@@ -58,8 +68,564 @@ pub struct UiConfig {
     pub multiline_insertions: bool,
     /// Save history?
     pub save_history: bool,
-    /// History file
+    /// History file. `{profile}` is replaced by the name of the profile, so each has its own.
     pub history_file: PathBuf,
+    /// How many prompts the history keeps, dropping the oldest.
+    pub history_max_entries: usize,
+    /// How many of the newest prompts of the history the REPL loads. 0 to load all of them.
+    pub history_load_max: usize,
+    /// Keep only the newest copy of a prompt sent more than once?
+    pub history_dedup: bool,
+    /// Where conversations are saved, created if it doesn't exist.
+    pub save_dir: PathBuf,
+    /// Name of a saved conversation. `{date}` is replaced by the date and time of saving and
+    /// `{title}` by the start of the first prompt.
+    pub save_filename: String,
+    /// Save the conversation after every response and on exit, to a file named like the above.
+    pub autosave: bool,
+    /// Where conversations, the history and the usage ledger are kept.
+    pub storage: Storage,
+    /// The database of `storage = "sqlite"`.
+    pub database_file: PathBuf,
+    /// `ata2 gc` deletes saved conversations and prompts of the history older than this many
+    /// days. 0 to keep them however old.
+    pub retention_days: u64,
+    /// `ata2 gc` deletes all but this many of the newest saved conversations. 0 to keep all.
+    pub max_sessions: usize,
+    /// How to tell the user a response finished while the terminal didn't have focus.
+    pub notify_on_complete: Notify,
+    /// How streamed responses are printed.
+    pub stream_pacing: StreamPacing,
+    /// Characters per second in [`StreamPacing::Typewriter`] mode.
+    pub typewriter_cps: u64,
+    /// Where to wrap responses: at the terminal's width, not at all, or at a number of columns.
+    pub wrap: Wrap,
+    /// Make links in responses clickable, on terminals known to support it?
+    pub hyperlinks: bool,
+    /// Show code a response revises as a diff against the version earlier in the conversation?
+    pub diff_code: bool,
+    /// What to do with a response that's cut off at `max_tokens`.
+    pub continue_truncated: ContinueTruncated,
+    /// Show elapsed time and throughput while a response streams in? (Only if stderr is a TTY.)
+    pub show_progress: bool,
+    /// Show model, profile, context usage and session cost above each prompt?
+    pub status_line: bool,
+    /// Show the model, why it stopped, the tokens, how long it took and the system fingerprint
+    /// below each response?
+    pub show_meta: bool,
+    /// Show a spinner while waiting for the first token? (Only if stderr is a TTY.)
+    pub spinner: bool,
+    /// Text next to the spinner.
+    pub spinner_text: String,
+    /// Line above the input, in `theme.prompt`. `{model}`, `{profile}`, `{time}`, `{turn}` and
+    /// `{cost}` are replaced, and style tags like `<bold>…</bold>` or `<cyan>…</cyan>` apply.
+    pub prompt_format: String,
+    /// Line above a response, in `theme.response`, like `prompt_format`.
+    pub response_format: String,
+    /// Command `/show` pipes its output to. Empty to print it directly.
+    pub pager: String,
+    /// Suggest the rest of the prompt being typed, as dim text after the cursor?
+    pub ghost_text: bool,
+    /// Model that makes the suggestions. It's asked on every typing pause, so it should be cheap.
+    pub ghost_text_model: String,
+    /// How long a typing pause is, in milliseconds.
+    pub ghost_text_delay: u64,
+    /// Key bindings of the line editor.
+    pub edit_mode: EditMode,
+    /// Show whether vi is in insert (`[I]`) or normal (`[N]`) mode in front of the input?
+    pub vi_mode_indicator: bool,
+    /// What to do with a large paste.
+    pub large_paste: LargePaste,
+    /// How many characters make a paste large. 0 for none to be.
+    pub large_paste_size: usize,
+    /// Color code blocks and inline code in the input?
+    pub highlight_input: bool,
+    /// Show an estimate of the input's tokens after it?
+    pub input_tokens: bool,
+    /// Ask before sending input of more tokens than this. 0 to never ask.
+    pub confirm_above_tokens: usize,
+    /// Show an estimate of each request's prompt tokens and their cost before it's sent, with the
+    /// conversation, system message and attachments that go with the input?
+    pub forecast: bool,
+    /// Ask before sending a request whose prompt tokens are estimated to cost more USD than this.
+    /// 0 to never ask.
+    pub confirm_above_cost: f64,
+    /// Plain output for screen readers and braille displays: no spinner, progress, styles,
+    /// typewriter pacing or anything else that redraws or moves the cursor. See
+    /// [`UiConfig::for_screen_reader`].
+    pub screen_reader: bool,
+    pub keybindings: KeyBindings,
+    pub theme: Theme,
+}
+
+/// Key chords of the line editor's actions, like `ctrl-d`, `alt-enter`, `f2` or `ctrl-x ctrl-e`.
+/// An empty chord leaves the action unbound. See [`crate::keys`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct KeyBindings {
+    /// Send the input. Only bound with `multiline_insertions`, otherwise Enter sends.
+    pub accept_line: String,
+    /// Start a new line. Only bound with `multiline_insertions`.
+    pub newline: String,
+    /// Save the conversation to a file.
+    pub save_conversation: String,
+    /// Stop the response that is streaming in.
+    pub cancel_generation: String,
+    /// Copy the last response to the clipboard.
+    pub copy_response: String,
+    /// Edit the input in `$VISUAL` or `$EDITOR`.
+    pub open_editor: String,
+}
+
+/// Styles of the output, like `bold`, `cyan`, `208`, `#ffaf00` or `italic white on blue`. An
+/// empty style leaves the text as is. See [`crate::style`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct Theme {
+    /// The line above the input, `prompt_format`.
+    pub prompt: String,
+    /// The line above a response, `response_format`.
+    pub response: String,
+    /// Errors, like those of the API.
+    pub error: String,
+    /// Code blocks and inline code in the input.
+    pub code: String,
+    /// The input.
+    pub user: String,
+}
+
+/// See [`crate::readline`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum EditMode {
+    /// Whatever `set editing-mode` in `$INPUTRC` or `~/.inputrc` says, as in Bash. Emacs if it
+    /// says nothing.
+    #[default]
+    Auto,
+    Emacs,
+    Vi,
+}
+
+/// See [`crate::paste`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum LargePaste {
+    /// Ask which of the others to do.
+    #[default]
+    Ask,
+    /// Send it as pasted.
+    Inline,
+    /// Put it in a code fence.
+    Fence,
+    /// Send it after the rest of the input, in a code fence named like a file.
+    Attach,
+}
+
+/// See [`crate::pacing`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamPacing {
+    /// Print chunks as soon as they arrive.
+    #[default]
+    Instant,
+    /// Print whole words only.
+    Words,
+    /// Print at a fixed rate of `typewriter_cps` characters per second.
+    Typewriter,
+}
+
+/// See [`crate::continuation`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum ContinueTruncated {
+    /// Say that `/continue` continues it.
+    #[default]
+    Offer,
+    /// Continue it right away.
+    Auto,
+}
+
+/// See [`crate::wrap`]. Written `auto`, `off` or as a number of columns.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(try_from = "WrapSetting", into = "WrapSetting")]
+pub enum Wrap {
+    /// At the terminal's width, if stdout is a terminal.
+    #[default]
+    Auto,
+    Off,
+    Columns(usize),
+}
+
+/// [`Wrap`] as in the config file, a name or a number.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum WrapSetting {
+    Name(String),
+    Columns(usize),
+}
+
+impl FromStr for Wrap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Wrap::Auto),
+            "off" => Ok(Wrap::Off),
+            _ => s
+                .parse()
+                .ok()
+                .filter(|&columns| columns > 0)
+                .map(Wrap::Columns)
+                .ok_or_else(|| tr!("config-wrap", value = s)),
+        }
+    }
+}
+
+impl TryFrom<WrapSetting> for Wrap {
+    type Error = String;
+
+    fn try_from(setting: WrapSetting) -> Result<Self, Self::Error> {
+        match setting {
+            WrapSetting::Name(name) => name.parse(),
+            WrapSetting::Columns(columns) => columns.to_string().parse(),
+        }
+    }
+}
+
+impl From<Wrap> for WrapSetting {
+    fn from(wrap: Wrap) -> Self {
+        match wrap {
+            Wrap::Auto => WrapSetting::Name(String::from("auto")),
+            Wrap::Off => WrapSetting::Name(String::from("off")),
+            Wrap::Columns(columns) => WrapSetting::Columns(columns),
+        }
+    }
+}
+
+/// See [`crate::notify`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum Notify {
+    #[default]
+    Off,
+    /// Ring the terminal bell.
+    Bell,
+    /// Show a desktop notification.
+    Desktop,
+    /// Bell and desktop notification.
+    Both,
+}
+
+/// See [`crate::store`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum Storage {
+    /// Conversations as JSON files in `save_dir`, the history in `history_file`.
+    #[default]
+    Files,
+    /// All of it in `database_file`, searchable with `ata2 search`.
+    Sqlite,
+}
+
+/// The API conversations go through.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum Api {
+    /// Chat completions, with the whole conversation sent every time.
+    #[default]
+    Chat,
+    /// Responses, with the conversation kept by OpenAI and its built-in tools, see
+    /// [`crate::responses`].
+    Responses,
+    /// The GGUF model of `[provider.local]`, run by ata² itself, see [`crate::local`].
+    Local,
+}
+
+/// How requests authenticate with the API.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "lowercase")]
+pub enum Auth {
+    /// With `api_key`, or the keys of `[rotation]`.
+    #[default]
+    Key,
+    /// With no key at all, for a local server like Ollama's or llama.cpp's.
+    None,
+}
+
+/// Config of `api = "responses"`.
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct ResponsesConfig {
+    /// Built-in tools the model may use: `file_search` and `code_interpreter`.
+    pub tools: Vec<String>,
+    /// The vector stores `file_search` searches.
+    pub vector_store_ids: Vec<String>,
+}
+
+/// Config of `api = "local"`, see [`crate::local`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct LocalConfig {
+    /// The GGUF file of the model.
+    pub model: PathBuf,
+    /// How many tokens the prompt and the response can be together. 0 for as many as the model
+    /// was trained on.
+    pub context_size: u32,
+    /// How many of the model's layers are offloaded to the GPU, for a build with a GPU backend.
+    pub gpu_layers: u32,
+    /// How many threads generate the response. 0 for as many as llama.cpp picks.
+    pub threads: usize,
+}
+
+/// Models that ata² runs itself.
+#[repr(C)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct ProviderConfig {
+    pub local: LocalConfig,
+}
+
+/// Config of the routing of prompts between a cheap model and a strong one, see
+/// [`crate::router`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct RouterConfig {
+    /// Route each prompt to `cheap` or `strong`, instead of sending them all to `model`?
+    pub enabled: bool,
+    /// The model of the prompts that aren't for the strong one.
+    pub cheap: String,
+    /// The model of prompts that ask it to think hard, have files attached, have code in them or
+    /// are long.
+    pub strong: String,
+    /// Prompts of more tokens than this go to the strong model. 0 for length not to matter.
+    pub max_cheap_tokens: usize,
+    /// Prompts with code in them go to the strong model?
+    pub code: bool,
+    /// Prompts with files attached go to the strong model?
+    pub attachments: bool,
+    /// Prompts with any of these in them, whatever their case, go to the strong model.
+    pub markers: Vec<String>,
+}
+
+/// Config of drafts, see [`crate::draft`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct DraftConfig {
+    /// Answer each prompt with `model` at once, while the model it's for answers in the
+    /// background, to replace the draft with or add to it? Experimental.
+    pub enabled: bool,
+    /// The fast model that drafts answers.
+    pub model: String,
+}
+
+/// Response cache config, see [`crate::cache`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Answer a request the same as one made before from the cache, without sending it? Only for
+    /// `--fim`, `ata2 sweep` and `ata2 eval`.
+    pub enabled: bool,
+    /// How many hours a cached response is used for. 0 for as long as it's there.
+    pub ttl_hours: u64,
+    /// Where cached responses are kept.
+    pub dir: PathBuf,
+}
+
+/// Config of the requests `ata2 sweep` and `ata2 eval` send at once, see [`crate::scheduler`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// How many requests are sent at once, at most.
+    pub concurrency: usize,
+    /// How many requests to a model are sent at once, at most, for models with their own rate
+    /// limits lower than the others'.
+    pub per_model: HashMap<String, usize>,
+}
+
+/// Config of `ata2 clipwatch`, see [`crate::clipwatch`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct ClipwatchConfig {
+    /// Let `ata2 clipwatch` send what's copied to the API? Off, for it not to be run by mistake on
+    /// a clipboard that passwords go through.
+    pub enabled: bool,
+    /// Text of more characters than this is left alone.
+    pub max_chars: usize,
+    /// Text this regular expression matches is left alone. Empty for none to be.
+    pub exclude: String,
+    /// How often the clipboard is looked at, in milliseconds.
+    pub interval_ms: u64,
+}
+
+/// Which of the API keys a request is sent with, see [`crate::rotation`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// The same key until it's rate limited, then the next.
+    #[default]
+    Failover,
+    /// Each request the next key, and the next after that one if it's rate limited.
+    RoundRobin,
+}
+
+/// API key rotation config, see [`crate::rotation`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct RotationConfig {
+    /// API keys to share the requests between, after `api_key`.
+    pub keys: Vec<String>,
+    pub strategy: Strategy,
+    /// USD a key may spend in a calendar month before it's passed over, 0 for no limit. Needs
+    /// `storage = "sqlite"`, whose usage ledger says what each key spent.
+    pub monthly_limit: f64,
+}
+
+/// tmux integration config, see [`crate::tmux`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct TmuxConfig {
+    /// Pane `/tmux capture` reads from when none is given. Defaults to ata²'s own pane.
+    pub capture_pane: Option<String>,
+    /// How many lines of scrollback `/tmux capture` reads.
+    pub capture_lines: u64,
+    /// Pane `/tmux send` pastes into when none is given.
+    pub send_pane: String,
+    /// Press Enter in the target pane after pasting?
+    pub send_enter: bool,
+}
+
+/// GitHub integration config, for `ata2 share --gist`.
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct GithubConfig {
+    /// Token that gists are created with. It needs the `gist` scope.
+    pub token: Option<String>,
+}
+
+/// Whether a tool is used without asking, see [`crate::policy`].
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize, Reflect, FromReflect,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToolMode {
+    /// Ask each time, unless it's trusted with `/trust`.
+    #[default]
+    AlwaysAsk,
+    Allow,
+    Deny,
+}
+
+/// A rule of `[tools.policy]`: the mode of a tool when what it's given matches a pattern.
+#[repr(C)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Reflect, FromReflect)]
+#[serde(default)]
+pub struct PolicyRule {
+    pub tool: String,
+    /// A regular expression, which has to match all of each command of the code `/tmux send`
+    /// pastes or the command a tool of `[tools.custom]` runs, or the prompt for the tools of
+    /// `[responses]`, which run at OpenAI.
+    pub pattern: String,
+    pub mode: ToolMode,
+}
+
+/// Whether each tool is used without asking, see [`crate::policy`].
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// `/tmux send`, which pastes the model's code into a pane, and runs it with `send_enter`.
+    pub tmux_send: ToolMode,
+    pub code_interpreter: ToolMode,
+    pub file_search: ToolMode,
+    /// Modes for what matches a pattern, over those above. Of the rules that match, one that
+    /// denies goes over one that asks, which goes over one that allows.
+    pub rules: Vec<PolicyRule>,
+}
+
+/// A tool of `[tools.custom]`, a command the model may run, see [`crate::external`].
+#[repr(C)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Reflect, FromReflect)]
+#[serde(default)]
+pub struct CustomTool {
+    /// What it does, for the model to know when to use it.
+    pub description: String,
+    /// The JSON schema of what it takes, an object whose properties fill in `command`. Empty for
+    /// nothing.
+    pub parameters: String,
+    /// Run with `sh -c`, with each `{name}` replaced by the parameter of that name, quoted.
+    pub command: String,
+    /// Instead of a command, a request to the API of `[tools.apis]` of this name: `method` of
+    /// `path`, with each `{name}` replaced by the parameter of that name, the others in its query
+    /// but `body`, the JSON that's sent.
+    pub api: String,
+    pub method: String,
+    pub path: String,
+    /// Whether it's run without asking, over which `[tools.policy]` rules go.
+    pub mode: ToolMode,
+}
+
+/// An API of `[tools.apis]`, which tools of `[tools.custom]` make requests to, as `ata2 tools
+/// import` makes them.
+#[repr(C)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Reflect, FromReflect)]
+#[serde(default)]
+pub struct ToolApi {
+    pub base_url: String,
+    /// Sent with each request, for its auth, like `Authorization = "Bearer …"`.
+    pub headers: HashMap<String, String>,
+}
+
+/// How many seconds each tool may run before it's given up on, 0 for as long as it takes.
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct TimeoutsConfig {
+    /// Each tmux command of `/tmux capture` and `/tmux send`.
+    pub tmux: u64,
+    pub code_interpreter: u64,
+    pub file_search: u64,
+    /// Each tool of `[tools.custom]`.
+    pub custom: u64,
+}
+
+/// Config of the tools ata² and the model use.
+#[repr(C)]
+#[derive(Clone, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct ToolsConfig {
+    pub policy: PolicyConfig,
+    pub timeouts: TimeoutsConfig,
+    /// The most bytes of what a tool outputs that are kept in the conversation, its start and its
+    /// end. 0 for all of it.
+    pub max_output_bytes: usize,
+    /// The directory tools are confined to: files are only uploaded from it, and `/tmux send` only
+    /// pastes into a pane that's in it. Empty for anywhere.
+    pub workdir: PathBuf,
+    /// Tools defined here, by name, for the model to call with chat completions.
+    pub custom: HashMap<String, CustomTool>,
+    /// The APIs tools of `custom` make requests to, by name.
+    pub apis: HashMap<String, ToolApi>,
+}
+
+/// Integrations with other programs
+#[repr(C)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Reflect)]
+#[serde(default)]
+pub struct IntegrationsConfig {
+    pub tmux: TmuxConfig,
+    pub github: GithubConfig,
 }
 
 /// For definitions, see <https://platform.openai.com/docs/api-reference/completions/create>.
@@ -68,10 +634,15 @@ pub struct UiConfig {
 #[serde(default)]
 pub struct Config {
     pub api_key: Option<String>,
+    pub auth: Auth,
+    /// Where the API is, up to and including `/v1`.
+    pub api_base: String,
     pub model: String,
     pub max_tokens: i64,
     pub temperature: f64,
     pub suffix: Option<String>,
+    /// The model of `--fim`, which has to be one that does completions rather than chat.
+    pub fim_model: String,
     pub top_p: f64,
     pub n: u64,
     pub stream: bool,
@@ -80,233 +651,834 @@ pub struct Config {
     pub frequency_penalty: f64,
     pub logit_bias: HashMap<String, f64>,
     pub user_id: Option<String>,
+    pub api: Api,
+    pub responses: ResponsesConfig,
+    pub provider: ProviderConfig,
+    pub router: RouterConfig,
+    pub draft: DraftConfig,
+    pub cache: CacheConfig,
+    pub rotation: RotationConfig,
+    pub scheduler: SchedulerConfig,
+    /// Prompts by name, for `--template` and `ata2 clipwatch --template`. `{text}` in one is
+    /// replaced by the text it's used on, which comes after it if it doesn't say where.
+    pub templates: HashMap<String, String>,
+    /// What a prompt piped to stdin is sent in, where it says `{input}`, with `{filename}` the
+    /// name of the file that stdin is, or `stdin`. Empty for the prompt as it is.
+    pub stdin_wrapper: String,
+    /// How many tokens the files of `/attach` and `--attach` can be, all together. 0 for no limit.
+    pub attachment_max_tokens: usize,
+    /// How many rows of an attached CSV or TSV file are sent, taken evenly from it, with its
+    /// header, the type of each column and how many rows it has. 0 to send the whole file.
+    pub attachment_sample_rows: usize,
+    /// The model that transcribes the recordings of `/attach`.
+    pub transcription_model: String,
+    /// The language the model is told to reply in, like `German`, or `auto` for the one the
+    /// prompt is written in, else of the locale. Empty to leave it to the model.
+    pub reply_language: String,
+    pub clipwatch: ClipwatchConfig,
     pub ui: UiConfig,
+    pub integrations: IntegrationsConfig,
+    pub tools: ToolsConfig,
+    /// What of `api_key`, `api_base`, `auth`, `provider`, `rotation`, `integrations` and `tools`
+    /// the configuration of `--config https://…` may set, as the local file of the same name
+    /// says. What it sets of the rest is used anyway.
+    pub trust_remote: Vec<String>,
 }
 
 impl Config {
+    /// The prompt of `[templates]` named `name`.
+    pub fn template(&self, name: &str) -> Result<&str, String> {
+        self.templates.get(name).map(String::as_str).ok_or_else(|| {
+            let mut names: Vec<_> = self.templates.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            tr!(
+                "template-unknown",
+                name = name,
+                templates = names.join(", ")
+            )
+        })
+    }
+
     pub fn validate(&self) -> Result<(), String> {
-        match self.api_key.as_ref().map(|s| s.as_str()) {
-            Some("") | None => return Err(String::from("API key is missing")),
+        match self.api_key.as_deref() {
+            Some("") | None
+                if self.auth == Auth::Key
+                    && self.api != Api::Local
+                    && self.rotation.keys.is_empty() =>
+            {
+                return Err(tr!("config-no-api-key"))
+            }
             _ => {}
         }
 
         if self.model.is_empty() {
-            return Err(String::from("Model ID is missing"));
+            return Err(tr!("config-no-model"));
         }
 
         if self.max_tokens < 1 || self.max_tokens > 2048 {
-            return Err(String::from("Max tokens must be between 1 and 2048"));
+            return Err(tr!("config-max-tokens"));
         }
 
         if self.temperature < 0.0 || self.temperature > 1.0 {
-            return Err(String::from("Temperature must be between 0.0 and 1.0"));
+            return Err(tr!("config-temperature"));
         }
 
         if let Some(suffix) = &self.suffix {
             if suffix.is_empty() {
-                return Err(String::from("Suffix cannot be an empty string"));
+                return Err(tr!("config-suffix"));
             }
         }
 
         if self.top_p < 0.0 || self.top_p > 1.0 {
-            return Err(String::from("Top-p must be between 0.0 and 1.0"));
+            return Err(tr!("config-top-p"));
         }
 
         if self.n < 1 || self.n > 10 {
-            return Err(String::from("n must be between 1 and 10"));
+            return Err(tr!("config-n"));
         }
 
         if self.stop.iter().any(|stop| stop.is_empty()) || self.stop.len() > 4 {
-            return Err(String::from("Stop phrases cannot contain empties"));
+            return Err(tr!("config-stop"));
         }
 
         if self.presence_penalty < 0.0 || self.presence_penalty > 1.0 {
-            return Err(String::from("Presence penalty must be between 0.0 and 1.0"));
+            return Err(tr!("config-presence-penalty"));
         }
 
         if self.frequency_penalty < 0.0 || self.frequency_penalty > 1.0 {
-            return Err(String::from(
-                "Frequency penalty must be between 0.0 and 1.0",
-            ));
+            return Err(tr!("config-frequency-penalty"));
         }
 
-        match self.user_id.as_ref().map(|s| s.as_str()) {
-            Some("") => return Err(String::from("User ID cannot be an empty string")),
-            _ => {}
+        if let Some("") = self.user_id.as_deref() {
+            return Err(tr!("config-user-id"));
         }
 
         for (key, value) in &self.logit_bias {
-            if value < &-2.0 || value > &2.0 {
-                return Err(format!(
-                    "logit_bias for {} must be between -2.0 and 2.0",
-                    key
-                ));
+            if !(-2.0..=2.0).contains(value) {
+                return Err(tr!("config-logit-bias", token = key.as_str()));
             }
         }
 
-        Ok(self.ui.validate()?)
+        self.responses.validate()?;
+        if self.api == Api::Local && self.provider.local.model.as_os_str().is_empty() {
+            return Err(tr!("config-local-model"));
+        }
+        if self.rotation.monthly_limit < 0.0 {
+            return Err(tr!("config-monthly-limit"));
+        }
+        if self.rotation.monthly_limit > 0.0 && self.ui.storage != Storage::Sqlite {
+            return Err(tr!("config-monthly-limit-storage"));
+        }
+        if self.router.enabled && (self.router.cheap.is_empty() || self.router.strong.is_empty()) {
+            return Err(tr!("config-router"));
+        }
+        if self.draft.enabled && self.draft.model.is_empty() {
+            return Err(tr!("config-draft"));
+        }
+        if self.scheduler.concurrency < 1 || self.scheduler.per_model.values().any(|n| *n < 1) {
+            return Err(tr!("scheduler-zero"));
+        }
+        if let Err(e) = regex::Regex::new(&self.clipwatch.exclude) {
+            return Err(tr!("config-clipwatch-exclude", error = e.to_string()));
+        }
+        template::validate(&self.stdin_wrapper, &["input", "filename"])
+            .map_err(|e| format!("stdin_wrapper: {e}"))?;
+        if self.clipwatch.interval_ms < 1 {
+            return Err(tr!("config-clipwatch-interval"));
+        }
+        self.ui.validate()?;
+        self.tools.validate()?;
+        self.integrations.tmux.validate()
     }
 }
 
-/// Note: the result is heavily based on the environment variables.
+/// Environment variables set its fields over the config file, see [`crate::environment`].
 ///
 /// * `ATA2_MODEL` sets the model ID. Default: `gpt-3.5-turbo`.
 /// * `ATA2_MAX_TOKENS` sets the maximum amount of tokens that the server can answer with. Longer answers will be truncated. Default: `2048`.
 /// * `ATA2_TEMPERATURE`. Default: `0.8`.
 /// * `ATA2_SUFFIX` sets the suffix. Default: `None`.
+/// * `ATA2_FIM_MODEL` sets the model of `--fim`. Default: `gpt-3.5-turbo-instruct`.
 /// * `ATA2_TOP_P`. Default: `1.0`.
 /// * `ATA2_N`. Default: `1`.
 /// * `ATA2_STOP` sets the stop phrases. Default: `[]`.
 /// * `ATA2_PRESENCE_PENALTY`. Default: `0.0`.
 /// * `ATA2_FREQUENCY_PENALTY`. Default: `0.0`.
 /// * `ATA2_LOGIT_BIAS` sets the logit bias. Default: `{}`.
+/// * `ATA2_API` sets the API, `chat` or `responses`. Default: `chat`.
+/// * `ATA2_AUTH` sets how requests authenticate, `key` or `none`. Default: `key`.
+/// * `OPENAI_API_BASE` sets where the API is. Default: `https://api.openai.com/v1`.
+/// * `ATA2_STDIN_WRAPPER` sets what a prompt piped to stdin is sent in. Default: empty, as it is.
+/// * `ATA2_ATTACHMENT_MAX_TOKENS` sets how many tokens attached files can be. Default: `8000`.
+/// * `ATA2_ATTACHMENT_SAMPLE_ROWS` sets how many rows of an attached table are sent. Default: `20`.
+/// * `ATA2_TRANSCRIPTION_MODEL` sets the model that transcribes attached recordings. Default: `whisper-1`.
+/// * `ATA2_REPLY_LANGUAGE` sets the language to reply in, or `auto`. Default: empty, any.
 impl Default for Config {
     fn default() -> Self {
         Self {
-            model: env::var("ATA2_MODEL")
-                .ok()
-                .unwrap_or_else(|| "gpt-3.5-turbo".to_string()),
-            max_tokens: env::var("ATA2_MAX_TOKENS")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(2048),
-            temperature: env::var("ATA2_TEMPERATURE")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.8),
-            suffix: env::var("ATA2_SUFFIX").ok(),
-            top_p: env::var("ATA2_TOP_P")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(1.0),
-            n: env::var("ATA2_N")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(1),
+            model: "gpt-3.5-turbo".to_string(),
+            max_tokens: 2048,
+            temperature: 0.8,
+            suffix: None,
+            fim_model: "gpt-3.5-turbo-instruct".to_string(),
+            top_p: 1.0,
+            n: 1,
             stream: true,
-            stop: env::var("ATA2_STOP")
-                .ok()
-                .map(|s| serde_json::from_str(&s).unwrap())
-                .unwrap_or_else(|| vec![]),
-            presence_penalty: env::var("ATA2_PRESENCE_PENALTY")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0),
-            frequency_penalty: env::var("ATA2_FREQUENCY_PENALTY")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0),
-            logit_bias: env::var("ATA2_LOGIT_BIAS")
-                .ok()
-                .map(|s| serde_json::from_str(&s).unwrap())
-                .unwrap_or_else(|| HashMap::default()),
+            stop: Default::default(),
+            presence_penalty: 0.0,
+            frequency_penalty: 0.0,
+            logit_bias: Default::default(),
             api_key: env::var("OPENAI_API_KEY").ok(),
-            user_id: env::var("ATA2_USER_ID").ok(),
+            auth: Default::default(),
+            api_base: env::var("OPENAI_API_BASE")
+                .ok()
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
+            user_id: None,
+            api: Default::default(),
+            responses: ResponsesConfig::default(),
+            provider: ProviderConfig::default(),
+            router: RouterConfig::default(),
+            draft: DraftConfig::default(),
+            cache: CacheConfig::default(),
+            rotation: RotationConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            templates: HashMap::default(),
+            stdin_wrapper: String::new(),
+            attachment_max_tokens: 8000,
+            attachment_sample_rows: 20,
+            transcription_model: String::from("whisper-1"),
+            reply_language: String::new(),
+            clipwatch: ClipwatchConfig::default(),
             ui: UiConfig::default(),
+            integrations: IntegrationsConfig::default(),
+            tools: ToolsConfig::default(),
+            trust_remote: vec![],
         }
     }
 }
 
-/// Note: the result is heavily based on the environment variables.
+/// Environment variables set its fields over the config file, see [`crate::environment`], here
+/// without the `UI_` that may follow `ATA2_`.
 ///
 /// * `ATA2_DOUBLE_CTRLC` sets whether to require user to press ^C twice. Default: `true`.
+/// * `ATA2_DOUBLE_CTRLC_WINDOW_MS` sets how many milliseconds the second ^C has to follow the first in. Default: `2000`.
 /// * `ATA2_HIDE_CONFIG` sets whether to hide config on run. Default: `false`.
 /// * `ATA2_REDACT_API_KEY` sets whether to redact API key. Default: `true`.
 /// * `ATA2_MULTILINE_INSERTIONS` sets whether to allow multiline insertions. Default: `true`.
 /// * `ATA2_SAVE_HISTORY` sets whether to save history. Default: `true`.
-/// * `ATA2_HISTORY_FILE` sets the history file. Default: `~/.config/ata2/history`.
+/// * `ATA2_HISTORY_FILE` sets the history file. Default: `history-{profile}` in ata²'s state directory, `~/.local/state/ata2` on Linux, or its data directory elsewhere.
+/// * `ATA2_HISTORY_MAX_ENTRIES` sets how many prompts the history keeps. Default: `1000`.
+/// * `ATA2_HISTORY_DEDUP` sets whether to keep only the newest copy of a prompt. Default: `true`.
+/// * `ATA2_SAVE_DIR` sets where conversations are saved. Default: `.`, on Windows `conversations` in ata²'s data directory under `%APPDATA%`.
+/// * `ATA2_SAVE_FILENAME` sets the name of a saved conversation. Default: `{date}-{title}.json`.
+/// * `ATA2_AUTOSAVE` sets whether to save the conversation after every response and on exit. Default: `false`.
+/// * `ATA2_STORAGE` sets where conversations, history and usage are kept (`files` or `sqlite`). Default: `files`.
+/// * `ATA2_DATABASE_FILE` sets the database of `sqlite` storage. Default: `ata2.db` in ata²'s data directory, `~/.local/share/ata2` on Linux.
+/// * `ATA2_RETENTION_DAYS` sets after how many days `ata2 gc` deletes conversations and prompts. Default: `0`, never.
+/// * `ATA2_MAX_SESSIONS` sets how many saved conversations `ata2 gc` keeps. Default: `0`, all.
+/// * `ATA2_NOTIFY_ON_COMPLETE` sets how to notify about finished responses (`off`, `bell`, `desktop` or `both`). Default: `off`.
+/// * `ATA2_STREAM_PACING` sets how streamed responses are printed (`instant`, `words` or `typewriter`). Default: `instant`.
+/// * `ATA2_TYPEWRITER_CPS` sets the characters per second of the typewriter. Default: `200`.
+/// * `ATA2_WRAP` sets where to wrap responses (`auto`, `off` or a number of columns). Default: `auto`.
+/// * `ATA2_HYPERLINKS` sets whether to make links in responses clickable. Default: `true`.
+/// * `ATA2_CONTINUE_TRUNCATED` sets what to do with a response cut off at `max_tokens` (`offer` or `auto`). Default: `offer`.
+/// * `ATA2_SHOW_PROGRESS` sets whether to show a status line while streaming. Default: `true`.
+/// * `ATA2_STATUS_LINE` sets whether to show model, profile, tokens and cost above each prompt. Default: `false`.
+/// * `ATA2_SHOW_META` sets whether to show model, finish reason, tokens, latency and system fingerprint below each response. Default: `false`.
+/// * `ATA2_SPINNER` sets whether to show a spinner while waiting for the first token. Default: `true`.
+/// * `ATA2_SPINNER_TEXT` sets the text next to the spinner. Default: `thinking…`.
+/// * `ATA2_PROMPT_FORMAT` sets the line above the input. Default: `Prompt:`.
+/// * `ATA2_RESPONSE_FORMAT` sets the line above a response. Default: `Response:`.
+/// * `ATA2_PAGER` sets the pager. Default: `$PAGER`, or `less -R` if that's not set.
+/// * `ATA2_GHOST_TEXT` sets whether to suggest the rest of the prompt being typed. Default: `false`.
+/// * `ATA2_GHOST_TEXT_MODEL` sets the model making the suggestions. Default: `gpt-4o-mini`.
+/// * `ATA2_GHOST_TEXT_DELAY` sets how many milliseconds of not typing make a pause. Default: `500`.
+/// * `ATA2_EDIT_MODE` sets the key bindings (`auto`, `emacs` or `vi`). Default: `auto`.
+/// * `ATA2_VI_MODE_INDICATOR` sets whether to show the vi mode in front of the input. Default: `true`.
+/// * `ATA2_LARGE_PASTE` sets what to do with a large paste (`ask`, `inline`, `fence` or `attach`). Default: `ask`.
+/// * `ATA2_LARGE_PASTE_SIZE` sets how many characters make a paste large. Default: `2000`.
+/// * `ATA2_HIGHLIGHT_INPUT` sets whether to color code in the input. Default: `true`.
+/// * `ATA2_INPUT_TOKENS` sets whether to show an estimate of the input's tokens. Default: `true`.
+/// * `ATA2_CONFIRM_ABOVE_TOKENS` sets how many tokens of input to ask about before sending. Default: `10000`.
+/// * `ATA2_FORECAST` sets whether to show an estimate of each request's tokens and cost before sending it. Default: `false`.
+/// * `ATA2_CONFIRM_ABOVE_COST` sets how many USD a request's prompt can cost before asking about it. Default: `0`, never.
+/// * `ATA2_SCREEN_READER` sets whether to keep the output plain for screen readers. Default: `false`.
 impl Default for UiConfig {
     fn default() -> Self {
         Self {
-            double_ctrlc: env::var("ATA2_DOUBLE_CTRLC")
-                .ok()
-                .map(|s| s.len() > 0)
-                .unwrap_or(true),
-            hide_config: env::var("ATA2_HIDE_CONFIG")
-                .ok()
-                .map(|s| s.len() > 0)
-                .unwrap_or(false),
-            redact_api_key: env::var("ATA2_REDACT_API_KEY")
-                .ok()
-                .map(|s| s.len() > 0)
-                .unwrap_or(true),
-            multiline_insertions: env::var("ATA2_MULTILINE_INSERTIONS")
-                .ok()
-                .map(|s| s.len() > 0)
-                .unwrap_or(true),
-            save_history: env::var("ATA2_SAVE_HISTORY")
-                .ok()
-                .map(|s| s.len() > 0)
-                .unwrap_or(true),
-            history_file: env::var("ATA2_HISTORY_FILE")
+            double_ctrlc: true,
+            double_ctrlc_window_ms: 2000,
+            hide_config: false,
+            redact_api_key: true,
+            multiline_insertions: true,
+            save_history: true,
+            history_file: get_state_dir().join("history-{profile}"),
+            history_max_entries: 1000,
+            history_load_max: 0,
+            history_dedup: true,
+            // A console on Windows is as likely as not to start in System32.
+            save_dir: if cfg!(windows) {
+                get_data_dir().join("conversations")
+            } else {
+                PathBuf::from(".")
+            },
+            save_filename: "{date}-{title}.json".to_string(),
+            autosave: false,
+            storage: Default::default(),
+            database_file: get_data_dir().join("ata2.db"),
+            retention_days: 0,
+            max_sessions: 0,
+            notify_on_complete: Default::default(),
+            stream_pacing: Default::default(),
+            typewriter_cps: 200,
+            wrap: Default::default(),
+            hyperlinks: true,
+            diff_code: true,
+            continue_truncated: Default::default(),
+            show_progress: true,
+            status_line: false,
+            show_meta: false,
+            spinner: true,
+            spinner_text: "thinking…".to_string(),
+            prompt_format: "Prompt:".to_string(),
+            response_format: "Response:".to_string(),
+            pager: env::var("PAGER").unwrap_or_else(|_| "less -R".to_string()),
+            ghost_text: false,
+            ghost_text_model: "gpt-4o-mini".to_string(),
+            ghost_text_delay: 500,
+            edit_mode: Default::default(),
+            vi_mode_indicator: true,
+            large_paste: Default::default(),
+            large_paste_size: 2000,
+            highlight_input: true,
+            input_tokens: true,
+            confirm_above_tokens: 10000,
+            forecast: false,
+            confirm_above_cost: 0.0,
+            screen_reader: false,
+            keybindings: KeyBindings::default(),
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_KEY_ACCEPT_LINE` sets the key that sends the input. Default: `ctrl-d`.
+/// * `ATA2_KEY_NEWLINE` sets the key that starts a new line. Default: `enter`.
+/// * `ATA2_KEY_SAVE_CONVERSATION` sets the key that saves the conversation. Default: `f2`.
+/// * `ATA2_KEY_CANCEL_GENERATION` sets the key that stops the response. Default: `ctrl-g`.
+/// * `ATA2_KEY_COPY_RESPONSE` sets the key that copies the last response. Default: `alt-w`.
+/// * `ATA2_KEY_OPEN_EDITOR` sets the key that opens the input in an editor. Default: `ctrl-x ctrl-e`.
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            accept_line: "ctrl-d".to_string(),
+            newline: "enter".to_string(),
+            save_conversation: "f2".to_string(),
+            cancel_generation: "ctrl-g".to_string(),
+            copy_response: "alt-w".to_string(),
+            open_editor: "ctrl-x ctrl-e".to_string(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn validate(&self) -> Result<(), String> {
+        for chord in [
+            &self.accept_line,
+            &self.newline,
+            &self.save_conversation,
+            &self.cancel_generation,
+            &self.copy_response,
+            &self.open_editor,
+        ] {
+            crate::keys::parse(chord).map_err(|e| format!("ui.keybindings: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_THEME_PROMPT` sets the style of the line above the input. Default: `bold`.
+/// * `ATA2_THEME_RESPONSE` sets the style of the line above a response. Default: `bold`.
+/// * `ATA2_THEME_ERROR` sets the style of errors. Default: `bold red`.
+/// * `ATA2_THEME_CODE` sets the style of code in the input. Default: `cyan`.
+/// * `ATA2_THEME_USER` sets the style of the input. Default: `` (none).
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            prompt: "bold".to_string(),
+            response: "bold".to_string(),
+            error: "bold red".to_string(),
+            code: "cyan".to_string(),
+            user: "".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn validate(&self) -> Result<(), String> {
+        for style in [
+            &self.prompt,
+            &self.response,
+            &self.error,
+            &self.code,
+            &self.user,
+        ] {
+            crate::style::Style::parse(style).map_err(|e| format!("ui.theme: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_GITHUB_TOKEN`, or else `GITHUB_TOKEN`, sets the token gists are created with. Default: `None`.
+impl Default for GithubConfig {
+    fn default() -> Self {
+        Self {
+            token: env::var("GITHUB_TOKEN")
                 .ok()
-                .map(|s| PathBuf::from(s))
-                .unwrap_or_else(|| {
-                    get_config_dir::<2>()
-                        .join("history")
-                        .to_string_lossy()
-                        .to_string()
-                        .into()
-                }),
+                .filter(|token| !token.is_empty()),
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_RESPONSES_TOOLS` sets the built-in tools, separated by commas. Default: none.
+/// * `ATA2_RESPONSES_VECTOR_STORE_IDS` sets the vector stores of `file_search`, separated by commas. Default: none.
+impl Default for ResponsesConfig {
+    fn default() -> Self {
+        Self {
+            tools: vec![],
+            vector_store_ids: vec![],
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_PROVIDER_LOCAL_MODEL` sets the GGUF file of the model of `api = "local"`. Default: none.
+/// * `ATA2_PROVIDER_LOCAL_CONTEXT_SIZE` sets how many tokens its context has. Default: `0`, as many as the model was trained on.
+/// * `ATA2_PROVIDER_LOCAL_GPU_LAYERS` sets how many of its layers are offloaded to the GPU. Default: `0`.
+/// * `ATA2_PROVIDER_LOCAL_THREADS` sets how many threads it runs on. Default: `0`, as many as llama.cpp picks.
+impl Default for LocalConfig {
+    fn default() -> Self {
+        Self {
+            model: PathBuf::new(),
+            context_size: 0,
+            gpu_layers: 0,
+            threads: 0,
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_ROUTER_ENABLED` sets whether to route prompts between a cheap model and a strong one. Default: `false`.
+/// * `ATA2_ROUTER_CHEAP` sets the cheap model. Default: `gpt-4o-mini`.
+/// * `ATA2_ROUTER_STRONG` sets the strong model. Default: `gpt-4o`.
+/// * `ATA2_ROUTER_MAX_CHEAP_TOKENS` sets how many tokens a prompt for the cheap model can be. Default: `400`.
+/// * `ATA2_ROUTER_CODE` sets whether prompts with code go to the strong model. Default: `true`.
+/// * `ATA2_ROUTER_ATTACHMENTS` sets whether prompts with files attached go to the strong model. Default: `true`.
+/// * `ATA2_ROUTER_MARKERS` sets what, in a prompt, sends it to the strong model, separated by commas. Default: `think hard`, `think carefully`, `step by step`.
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cheap: String::from("gpt-4o-mini"),
+            strong: String::from("gpt-4o"),
+            max_cheap_tokens: 400,
+            code: true,
+            attachments: true,
+            markers: ["think hard", "think carefully", "step by step"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_DRAFT_ENABLED` sets whether a fast model drafts each answer. Default: `false`.
+/// * `ATA2_DRAFT_MODEL` sets the model that drafts them. Default: `gpt-4o-mini`.
+impl Default for DraftConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: String::from("gpt-4o-mini"),
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_CACHE` sets whether to cache responses. Default: `false`.
+/// * `ATA2_CACHE_TTL_HOURS` sets how many hours a cached response is used for. Default: `24`.
+/// * `ATA2_CACHE_DIR` sets where cached responses are kept. Default: `~/.cache/ata2`, on Windows `cache` in ata²'s data directory under `%LOCALAPPDATA%`.
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_hours: 24,
+            dir: get_cache_dir(),
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_ROTATION_KEYS` sets the API keys to rotate through after `api_key`, separated by commas. Default: none.
+/// * `ATA2_ROTATION_STRATEGY` sets which key a request is sent with, `failover` or `round-robin`. Default: `failover`.
+/// * `ATA2_ROTATION_MONTHLY_LIMIT` sets how many USD a key may spend in a month. Default: `0`, no limit.
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            keys: vec![],
+            strategy: Default::default(),
+            monthly_limit: 0.0,
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_CONCURRENCY` sets how many requests `ata2 sweep` and `ata2 eval` send at once. Default: `4`.
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            per_model: HashMap::default(),
         }
     }
 }
 
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_CLIPWATCH` sets whether `ata2 clipwatch` may run. Default: `false`.
+/// * `ATA2_CLIPWATCH_MAX_CHARS` sets how many characters of copied text are too many. Default: `4000`.
+/// * `ATA2_CLIPWATCH_EXCLUDE` sets the regular expression of text to leave alone. Default: none.
+/// * `ATA2_CLIPWATCH_INTERVAL_MS` sets how often the clipboard is looked at. Default: `500`.
+impl Default for ClipwatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_chars: 4000,
+            exclude: String::new(),
+            interval_ms: 500,
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_TMUX_CAPTURE_PANE` sets the pane `/tmux capture` reads from. Default: `None` (ata²'s pane).
+/// * `ATA2_TMUX_CAPTURE_LINES` sets how many lines of scrollback to capture. Default: `200`.
+/// * `ATA2_TMUX_SEND_PANE` sets the pane `/tmux send` pastes into. Default: `{last}`.
+/// * `ATA2_TMUX_SEND_ENTER` sets whether to press Enter after pasting. Default: `false`.
+impl Default for TmuxConfig {
+    fn default() -> Self {
+        Self {
+            capture_pane: None,
+            capture_lines: 200,
+            send_pane: "{last}".to_string(),
+            send_enter: false,
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_TOOLS_POLICY_TMUX_SEND` sets whether `/tmux send` asks first, `always-ask`, `allow` or `deny`. Default: `allow`.
+/// * `ATA2_TOOLS_POLICY_CODE_INTERPRETER` sets whether the model may use `code_interpreter`. Default: `allow`.
+/// * `ATA2_TOOLS_POLICY_FILE_SEARCH` sets whether the model may use `file_search`. Default: `allow`.
+/// * `ATA2_TOOLS_POLICY_RULES` sets the rules, as JSON. Default: none.
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            tmux_send: ToolMode::Allow,
+            code_interpreter: ToolMode::Allow,
+            file_search: ToolMode::Allow,
+            rules: vec![],
+        }
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_TOOLS_TIMEOUTS_TMUX` sets how many seconds a tmux command may take. Default: `10`.
+/// * `ATA2_TOOLS_TIMEOUTS_CODE_INTERPRETER` sets how many seconds `code_interpreter` may run. Default: `300`.
+/// * `ATA2_TOOLS_TIMEOUTS_FILE_SEARCH` sets how many seconds `file_search` may run. Default: `60`.
+/// * `ATA2_TOOLS_TIMEOUTS_CUSTOM` sets how many seconds a tool of `[tools.custom]` may run. Default: `60`.
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            tmux: 10,
+            code_interpreter: 300,
+            file_search: 60,
+            custom: 60,
+        }
+    }
+}
+
+impl TimeoutsConfig {
+    /// How long `tool` may run, if there's a limit.
+    pub fn of(&self, tool: &str) -> Option<Duration> {
+        let secs = match tool {
+            "tmux" => self.tmux,
+            "code_interpreter" => self.code_interpreter,
+            "file_search" => self.file_search,
+            "custom" => self.custom,
+            _ => 0,
+        };
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+}
+
+/// Environment variables set its fields over the config file, see [`crate::environment`].
+///
+/// * `ATA2_TOOLS_MAX_OUTPUT_BYTES` sets how much of a tool's output is kept. Default: `4000`.
+/// * `ATA2_TOOLS_WORKDIR` sets the directory tools are confined to. Default: empty, none.
+/// * `ATA2_TOOLS_CUSTOM` sets the tools of `[tools.custom]`, as JSON. Default: none.
+/// * `ATA2_TOOLS_APIS` sets the APIs of `[tools.apis]`, as JSON. Default: none.
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            policy: PolicyConfig::default(),
+            timeouts: TimeoutsConfig::default(),
+            max_output_bytes: 4000,
+            workdir: PathBuf::new(),
+            custom: HashMap::default(),
+            apis: HashMap::default(),
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// The mode of `tool` by itself, before the rules.
+    pub fn mode(&self, tool: &str) -> Option<ToolMode> {
+        match tool {
+            "tmux_send" => Some(self.tmux_send),
+            "code_interpreter" => Some(self.code_interpreter),
+            "file_search" => Some(self.file_search),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if let Err(e) = policy::pattern(&rule.pattern) {
+                return Err(tr!(
+                    "config-policy-pattern",
+                    pattern = format!("{:?}", rule.pattern),
+                    error = e.to_string()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ToolsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, tool) in &self.custom {
+            if name.is_empty()
+                || name.len() > 64
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(tr!("config-tool-name", name = format!("{name:?}")));
+            }
+            if self.policy.mode(name).is_some() {
+                return Err(tr!("config-tool-built-in", name = name.as_str()));
+            }
+            match (tool.command.trim().is_empty(), tool.api.is_empty()) {
+                (true, true) => return Err(tr!("config-tool-no-command", name = name.as_str())),
+                (false, false) => {
+                    return Err(tr!("config-tool-command-and-api", name = name.as_str()))
+                }
+                (true, false) if !self.apis.contains_key(&tool.api) => {
+                    return Err(tr!(
+                        "config-tool-api",
+                        name = name.as_str(),
+                        api = format!("{:?}", tool.api)
+                    ))
+                }
+                (true, false)
+                    if reqwest::Method::from_str(&tool.method.to_uppercase()).is_err() =>
+                {
+                    return Err(tr!(
+                        "config-tool-method",
+                        name = name.as_str(),
+                        method = format!("{:?}", tool.method)
+                    ))
+                }
+                _ => {}
+            }
+            if !tool.parameters.trim().is_empty() {
+                match serde_json::from_str::<Value>(&tool.parameters) {
+                    Ok(Value::Object(_)) => {}
+                    Ok(_) => return Err(tr!("config-tool-parameters", name = name.as_str())),
+                    Err(e) => {
+                        return Err(tr!(
+                            "config-tool-parameters-json",
+                            name = name.as_str(),
+                            error = e.to_string()
+                        ))
+                    }
+                }
+            }
+        }
+        for (name, api) in &self.apis {
+            if let Err(e) = reqwest::Url::parse(&api.base_url) {
+                return Err(tr!(
+                    "config-tool-base-url",
+                    name = name.as_str(),
+                    url = format!("{:?}", api.base_url),
+                    error = e.to_string()
+                ));
+            }
+        }
+        for rule in &self.policy.rules {
+            if self.policy.mode(&rule.tool).is_none() && !self.custom.contains_key(&rule.tool) {
+                return Err(tr!("config-policy-tool", tool = format!("{:?}", rule.tool)));
+            }
+        }
+        self.policy.validate()
+    }
+}
+
+impl ResponsesConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for tool in &self.tools {
+            if !["file_search", "code_interpreter"].contains(&tool.as_str()) {
+                return Err(tr!("config-responses-tool", tool = format!("{tool:?}")));
+            }
+        }
+
+        if self.tools.iter().any(|tool| tool == "file_search") && self.vector_store_ids.is_empty() {
+            return Err(tr!("config-responses-vector-stores"));
+        }
+
+        Ok(())
+    }
+}
+
+impl TmuxConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capture_lines < 1 {
+            return Err(tr!("config-tmux-capture-lines"));
+        }
+
+        if self.send_pane.is_empty() {
+            return Err(tr!("config-tmux-send-pane"));
+        }
+
+        Ok(())
+    }
+}
+
 impl UiConfig {
+    /// Turn off whatever redraws or moves the cursor, for `screen_reader`. Styles are off too,
+    /// see [`crate::style::enabled`].
+    pub fn for_screen_reader(&mut self) {
+        self.screen_reader = true;
+        self.spinner = false;
+        self.show_progress = false;
+        if self.stream_pacing == StreamPacing::Typewriter {
+            self.stream_pacing = StreamPacing::Instant;
+        }
+        self.wrap = Wrap::Off;
+        self.hyperlinks = false;
+        self.ghost_text = false;
+        self.vi_mode_indicator = false;
+        self.highlight_input = false;
+        self.input_tokens = false;
+    }
+
     pub fn validate(&self) -> Result<(), String> {
+        if self.history_file.parent().is_none() {
+            return Err(tr!("config-history-no-parent"));
+        }
+
+        if self.history_max_entries < 1 {
+            return Err(tr!("config-history-max-entries"));
+        }
+
+        if self.typewriter_cps < 1 {
+            return Err(tr!("config-typewriter-cps"));
+        }
+
+        if self.save_filename.is_empty() || self.save_filename.contains(std::path::is_separator) {
+            return Err(tr!("config-save-filename"));
+        }
+
+        template::validate(&self.save_filename, &["date", "title"])
+            .map_err(|e| format!("save_filename: {e}"))?;
+        template::validate(&self.prompt_format, BANNER_PLACEHOLDERS)
+            .map_err(|e| format!("prompt_format: {e}"))?;
+        template::validate(&self.response_format, BANNER_PLACEHOLDERS)
+            .map_err(|e| format!("response_format: {e}"))?;
+
+        if self.confirm_above_cost < 0.0 {
+            return Err(tr!("config-confirm-above-cost"));
+        }
+        self.keybindings.validate()?;
+        self.theme.validate()
+    }
+
+    /// Whether the directory of `history_file` is there to save the history in. Unlike
+    /// [`UiConfig::validate`], it takes the filesystem, so the REPL checks it once the prompt is
+    /// shown rather than before.
+    pub fn check_history_dir(&self) -> Result<(), String> {
         let history_dir = match self.history_file.parent() {
             Some(dir) => dir,
-            None => return Err(String::from("History file has no parent")),
+            None => return Err(tr!("config-history-no-parent")),
         };
 
         let history_metadata = match history_dir.metadata() {
             Ok(metadata) => metadata,
-            Err(e) => return Err(format!("History file metadata error: {}", e)),
+            Err(e) => return Err(tr!("config-history-metadata", error = e.to_string())),
         };
 
-        if history_metadata.permissions().readonly() {
-            return Err(String::from("History file dir is read-only"));
+        // Windows sets the read-only attribute of folders for other reasons, and ignores it.
+        if !cfg!(windows) && history_metadata.permissions().readonly() {
+            return Err(tr!("config-history-read-only"));
         }
-
         Ok(())
     }
 }
 
-impl<'a> Into<OpenAIConfig> for &'a Config {
-    fn into(self) -> OpenAIConfig {
-        let mut ret = OpenAIConfig::new();
-        if let Some(api_key) = &self.api_key {
-            ret = ret.with_api_key(api_key.to_owned());
-        }
-        ret
-    }
-}
-
-impl<'a> Into<CreateChatCompletionRequestArgs> for &'a Config {
-    fn into(self) -> CreateChatCompletionRequestArgs {
-        if !self.stream {
-            warn!("Stream is disabled. This is not supported anymore and will be ignored.");
+impl From<&Config> for CreateChatCompletionRequestArgs {
+    fn from(config: &Config) -> Self {
+        if !config.stream {
+            warn!("{}", tr!("config-stream-ignored"));
         }
         let mut args = CreateChatCompletionRequestArgs::default()
-            .n(self.n as u8)
-            .model(&self.model)
-            .max_tokens(self.max_tokens as u16)
-            .temperature(self.temperature as f32)
-            .frequency_penalty(self.frequency_penalty as f32)
-            .presence_penalty(self.presence_penalty as f32)
+            .n(config.n as u8)
+            .model(&config.model)
+            .max_tokens(config.max_tokens as u16)
+            .temperature(config.temperature as f32)
+            .frequency_penalty(config.frequency_penalty as f32)
+            .presence_penalty(config.presence_penalty as f32)
             .logit_bias(
-                self.logit_bias
+                config
+                    .logit_bias
                     .clone()
                     .into_iter()
                     .map(|(k, v)| (k, serde_json::Value::Number(Number::from_f64(v).unwrap())))
                     .collect::<StdHashMap<String, Value>>(),
             )
-            .top_p(self.top_p as f32)
-            .stop(self.stop.clone())
+            .top_p(config.top_p as f32)
+            .stop(config.stop.clone())
             .stream(true)
             .to_owned();
 
-        if let Some(user_id) = &self.user_id {
+        if let Some(user_id) = &config.user_id {
             args = args.user(user_id).to_owned();
         }
 
@@ -319,15 +1491,41 @@ fn fmt_reflectable(f: &mut fmt::Formatter<'_>, value: &dyn Struct) -> Result<(),
     let num_fields = value.iter_fields().count();
     for (i, v) in value.iter_fields().enumerate() {
         let key = value.name_at(i).unwrap();
-        if i == num_fields - 1 {
-            write!(f, "{}: {:?}", key, v)?;
-        } else {
-            write!(f, "{}: {:?}, ", key, v)?;
+        write!(f, "{}: ", key)?;
+        match v.reflect_ref() {
+            ReflectRef::Struct(nested) => fmt_reflectable(f, nested)?,
+            // `integrations.github.token`.
+            _ if key == "token"
+                && v.downcast_ref::<Option<String>>()
+                    .is_some_and(Option::is_some) =>
+            {
+                write!(f, "{}", style::paint("red", "[redacted]"))?
+            }
+            // `rotation.keys`.
+            _ if key == "keys"
+                && v.downcast_ref::<Vec<String>>()
+                    .is_some_and(|keys| !keys.is_empty()) =>
+            {
+                write!(f, "{}", style::paint("red", "[redacted]"))?
+            }
+            _ => write!(f, "{:?}", v)?,
+        }
+        if i != num_fields - 1 {
+            write!(f, ", ")?;
         }
     }
     write!(f, "}}")
 }
 
+/// Displays a nested config table with [`fmt_reflectable`].
+struct Reflectable<'a>(&'a dyn Struct);
+
+impl Display for Reflectable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        fmt_reflectable(f, self.0)
+    }
+}
+
 impl Display for UiConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         fmt_reflectable(f, self)
@@ -340,15 +1538,19 @@ pub enum ConfigLocation {
     Auto,
     Path(PathBuf),
     Named(PathBuf),
+    /// Downloaded, under the local file of its name, see [`crate::remote`].
+    Url(String),
 }
 
 impl FromStr for ConfigLocation {
     type Err = Infallible;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(if !s.contains(".") && s.len() > 0 {
+        Ok(if s.starts_with("https://") {
+            Self::Url(s.to_string())
+        } else if !s.contains(".") && !s.is_empty() {
             Self::Named(s.into())
-        } else if s.trim().len() > 0 {
+        } else if !s.trim().is_empty() {
             Self::Path(s.into())
         } else if s.trim().is_empty() {
             Self::Auto
@@ -367,7 +1569,7 @@ where
     }
 }
 
-fn get_config_dir<const V: usize>() -> PathBuf {
+pub fn get_config_dir<const V: usize>() -> PathBuf {
     ProjectDirs::from(
         if V == 1 {
             "ata"
@@ -390,16 +1592,126 @@ fn get_config_dir<const V: usize>() -> PathBuf {
     .into()
 }
 
+/// Where ata² keeps what isn't configuration: `~/.local/share/ata2` on Linux,
+/// `%APPDATA%\Ask the Terminal Anything (ATA) Project Authors\ata2\data` on Windows.
+pub fn get_data_dir() -> PathBuf {
+    ProjectDirs::from(
+        "ata2",
+        "Ask the Terminal Anything (ATA) Project Authors",
+        "ata2",
+    )
+    .unwrap()
+    .data_dir()
+    .into()
+}
+
+/// Where ata² keeps the history and the queue, which are worth keeping but not as much as the
+/// data: `~/.local/state/ata2` on Linux, and the data directory where there's no such thing.
+pub fn get_state_dir() -> PathBuf {
+    let dirs = ProjectDirs::from(
+        "ata2",
+        "Ask the Terminal Anything (ATA) Project Authors",
+        "ata2",
+    )
+    .unwrap();
+    dirs.state_dir().unwrap_or(dirs.data_dir()).into()
+}
+
+/// Move the histories, queues and database that were kept next to `ata2.toml`, before there were
+/// directories for them, to where `config` keeps them now, if that's where they're kept by default
+/// and nothing's there yet. Once they're moved, there's nothing left to move.
+///
+/// Called while the configuration is loaded, so not using it.
+pub fn migrate(config: &Config) {
+    let from = get_config_dir::<2>();
+    let Ok(entries) = fs::read_dir(&from) else {
+        return;
+    };
+    let state_dir = get_state_dir();
+    let data_dir = get_data_dir();
+    // A `history_file` or `database_file` of the user's own may well be next to `ata2.toml`.
+    let history = config.ui.history_file.parent() == Some(&state_dir);
+    let database = config.ui.database_file == data_dir.join("ata2.db");
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let text = name.to_string_lossy();
+        let to = if text == "history" || text.starts_with("history-") || text.starts_with("queue-")
+        {
+            if !history {
+                continue;
+            }
+            &state_dir
+        } else if text == "ata2.db" || text.starts_with("ata2.db-") {
+            if !database {
+                continue;
+            }
+            &data_dir
+        } else {
+            continue;
+        };
+        let (old, new) = (entry.path(), to.join(&name));
+        // As on macOS, where they're all the same directory.
+        if *to == from || new.exists() || !old.is_file() {
+            continue;
+        }
+        let moved = fs::create_dir_all(to).and_then(|()| {
+            // Across file systems, it's copied and removed.
+            fs::rename(&old, &new)
+                .or_else(|_| fs::copy(&old, &new).and_then(|_| fs::remove_file(&old)))
+        });
+        match moved {
+            Ok(()) => info!(
+                "{}",
+                tr!(
+                    "history-moved",
+                    from = old.display().to_string(),
+                    to = new.display().to_string()
+                )
+            ),
+            Err(e) => warn!(
+                "{}",
+                tr!(
+                    "history-move-failed",
+                    from = old.display().to_string(),
+                    to = new.display().to_string(),
+                    error = e.to_string()
+                )
+            ),
+        }
+    }
+}
+
+/// Where cached responses are kept by default: `~/.cache/ata2` on Linux,
+/// `%LOCALAPPDATA%\Ask the Terminal Anything (ATA) Project Authors\ata2\cache` on Windows.
+pub fn get_cache_dir() -> PathBuf {
+    ProjectDirs::from(
+        "ata2",
+        "Ask the Terminal Anything (ATA) Project Authors",
+        "ata2",
+    )
+    .unwrap()
+    .cache_dir()
+    .into()
+}
+
+/// Where `ata2 daemon` listens: `$XDG_RUNTIME_DIR/ata2` on Linux, and the cache directory where
+/// there's no such thing.
+pub fn get_runtime_dir() -> PathBuf {
+    let dirs = ProjectDirs::from(
+        "ata2",
+        "Ask the Terminal Anything (ATA) Project Authors",
+        "ata2",
+    )
+    .unwrap();
+    dirs.runtime_dir().unwrap_or(dirs.cache_dir()).into()
+}
+
 pub fn default_path<const V: usize>(name: Option<&Path>) -> PathBuf {
     let mut config_file = get_config_dir::<V>().to_path_buf();
     let file: Vec<_> = if let Some(name) = name {
         let mut name = name.to_path_buf();
         name.set_extension("toml");
-        name.as_os_str()
-            .to_raw_bytes()
-            .into_iter()
-            .map(|i| *i)
-            .collect()
+        name.as_os_str().to_raw_bytes().iter().copied().collect()
     } else {
         let name = DEFAULT_CONFIG_FILENAME.to_string_lossy();
         name.bytes().collect()
@@ -428,11 +1740,32 @@ impl ConfigLocation {
             }
             ConfigLocation::Path(pb) => pb.clone(),
             ConfigLocation::Named(name) => default_path::<2>(Some(name)),
+            // What's set over the downloaded one.
+            ConfigLocation::Url(_) => default_path::<2>(Some(Path::new(&self.profile()))),
+        }
+    }
+
+    /// Name of the profile this configuration is, i.e. `work` for `--config work`.
+    pub fn profile(&self) -> String {
+        match self {
+            ConfigLocation::Auto => String::from("default"),
+            ConfigLocation::Named(name) => name.to_string_lossy().into_owned(),
+            ConfigLocation::Path(pb) => pb
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("default")),
+            // `team` for `https://intranet/ata2/team.toml?v=2`.
+            ConfigLocation::Url(url) => url
+                .split(['?', '#'])
+                .next()
+                .and_then(|url| Path::new(url.trim_end_matches('/')).file_stem())
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("default")),
         }
     }
 
     pub fn location_v1(&self) -> PathBuf {
-        default_path::<1>(Some(&Path::new("ata.toml")))
+        default_path::<1>(Some(Path::new("ata.toml")))
     }
 }
 
@@ -440,7 +1773,9 @@ impl FromStr for Config {
     type Err = TomlError;
 
     fn from_str(contents: &str) -> Result<Self, Self::Err> {
-        toml::from_str(&contents)
+        let mut table: Table = toml::from_str(contents)?;
+        environment::apply(&Config::default(), &mut table);
+        toml::Value::Table(table).try_into()
     }
 }
 
@@ -455,18 +1790,16 @@ where
 
 impl Display for Config {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        let mut header = ColouredStr::new("Configuration:");
-        header.underline();
-        let mut ok = writeln!(f, "{}", header);
+        let mut ok = writeln!(f, "{}", style::paint("underline", "Configuration:"));
         for (i, value) in self.iter_fields().enumerate() {
-            if !ok.is_ok() {
+            if ok.is_err() {
                 break;
             }
             let key = self.name_at(i).unwrap();
-            let mut value2 = match value.downcast_ref::<UiConfig>() {
-                Some(ui) => Some(ui.to_string()),
+            let mut value2 = match value.reflect_ref() {
+                ReflectRef::Struct(table) => Some(Reflectable(table).to_string()),
                 // Doing this eliminates quotes around strings
-                None => match value.downcast_ref::<String>() {
+                _ => match value.downcast_ref::<String>() {
                     Some(s) => match key {
                         "model" => Some(s.to_uppercase()),
                         _ => Some(s.to_string()),
@@ -475,9 +1808,7 @@ impl Display for Config {
                 },
             };
             if self.ui.redact_api_key && key == "api_key" {
-                let mut redacted = ColouredStr::new("[redacted]");
-                redacted.red();
-                value2 = Some(redacted.to_string());
+                value2 = Some(style::paint("red", "[redacted]"));
             }
 
             if let Some(v) = value2 {
@@ -489,3 +1820,20 @@ impl Display for Config {
         ok
     }
 }
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_is_kept_under_appdata() {
+        let appdata = PathBuf::from(std::env::var_os("APPDATA").unwrap());
+        let ui = UiConfig::default();
+        for path in [&ui.history_file, &ui.save_dir, &ui.database_file] {
+            assert!(path.starts_with(&appdata), "{}", path.display());
+        }
+        assert!(ui.save_dir.starts_with(get_data_dir()));
+        // Beside the folder `ata2.toml` is in.
+        assert_eq!(get_data_dir().parent(), get_config_dir::<2>().parent());
+    }
+}
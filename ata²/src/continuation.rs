@@ -0,0 +1,130 @@
+//! Responses cut off at `max_tokens`: the model is asked to continue, and what it answers is
+//! stitched onto what it had written, without what it repeats of it. With
+//! `ui.continue_truncated = "auto"` that happens right away, and otherwise `/continue` does it.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::ChatCompletionRequestMessage;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::readline::{
+    string_to_chat_completion_assistant_message, string_to_chat_completion_request_user_message,
+};
+
+/// Asks for the rest of a response. It's only sent, and not kept in the conversation.
+const PROMPT: &str = "Your response was cut off. Continue exactly where it ends, without \
+                      repeating anything or saying that you're continuing.";
+
+/// How many times a response is continued automatically, for one that goes on and on not to.
+pub const MAX_AUTO: usize = 5;
+
+/// How much of the end of a response its continuation is compared with, in characters.
+const WINDOW: usize = 200;
+
+/// Was the last response cut off?
+static TRUNCATED: AtomicBool = AtomicBool::new(false);
+
+pub fn truncated() -> bool {
+    TRUNCATED.load(Ordering::Relaxed)
+}
+
+pub fn set_truncated(truncated: bool) {
+    TRUNCATED.store(truncated, Ordering::Relaxed);
+}
+
+/// What to send for the rest of `response`, which `messages` were answered with.
+pub fn messages(
+    messages: &[ChatCompletionRequestMessage],
+    response: &str,
+) -> Vec<ChatCompletionRequestMessage> {
+    let mut ret = messages.to_vec();
+    ret.push(string_to_chat_completion_assistant_message(
+        response.to_string(),
+    ));
+    ret.push(string_to_chat_completion_request_user_message(
+        PROMPT.to_string(),
+    ));
+    ret
+}
+
+/// Holds back the start of a continuation as it streams in, until it's long enough to tell how
+/// much of it repeats the end of what it continues, and drops that.
+pub struct Stitcher {
+    /// The end of what's continued.
+    tail: String,
+    held: String,
+    done: bool,
+}
+
+impl Stitcher {
+    pub fn new(previous: &str) -> Self {
+        let skip = previous.chars().count().saturating_sub(WINDOW);
+        Self {
+            tail: previous.chars().skip(skip).collect(),
+            held: String::new(),
+            done: false,
+        }
+    }
+
+    /// `text` of the continuation, and what of it to print now.
+    pub fn push(&mut self, text: &str) -> String {
+        if self.done {
+            return text.to_string();
+        }
+        self.held += text;
+        // One more than the tail, to see whether the overlap ends with a word.
+        if self.held.chars().count() > self.tail.chars().count() {
+            self.finish()
+        } else {
+            String::new()
+        }
+    }
+
+    /// What's still held back, as the continuation ended.
+    pub fn finish(&mut self) -> String {
+        if self.done {
+            return String::new();
+        }
+        self.done = true;
+        let held = std::mem::take(&mut self.held);
+        held[overlap(&self.tail, &held)..].to_string()
+    }
+}
+
+/// How long, in bytes, the longest start of `next` is that `previous` ends with. Only whole words
+/// count, for "and" after "…an" not to lose its "an".
+fn overlap(previous: &str, next: &str) -> usize {
+    let word = |c: char| c.is_alphanumeric();
+    next.char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .rev()
+        .find(|&len| {
+            let repeated = &next[..len];
+            if !previous.ends_with(repeated) || repeated.trim().is_empty() {
+                return false;
+            }
+            let before = previous[..previous.len() - len].chars().last();
+            let after = next[len..].chars().next();
+            let splits_word = before.is_some_and(word) && repeated.starts_with(word)
+                || after.is_some_and(word) && repeated.ends_with(word);
+            !splits_word
+        })
+        .unwrap_or(0)
+}
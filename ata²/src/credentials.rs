@@ -0,0 +1,113 @@
+//! The API key in the system's credential store: Credential Manager on Windows, the Keychain on
+//! macOS. It's used when neither `api_key` nor `OPENAI_API_KEY` sets one, and `ata2 --save-key`
+//! puts it there. Each profile has its own, stored for the service `ata2` and the profile's name
+//! as the user.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+
+use std::io::{self, Write as _};
+
+use crate::TokioResult;
+
+#[cfg(any(windows, target_os = "macos"))]
+const SERVICE: &str = "ata2";
+
+/// The key stored for `profile`, if any.
+#[cfg(any(windows, target_os = "macos"))]
+pub fn api_key(profile: &str) -> Option<String> {
+    let entry = keyring::Entry::new(SERVICE, profile)
+        .map_err(|e| warn!("{}", tr!("credentials-read-failed", error = e.to_string())))
+        .ok()?;
+    match entry.get_password() {
+        Ok(key) => Some(key),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            warn!("{}", tr!("credentials-read-failed", error = e.to_string()));
+            None
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn api_key(_profile: &str) -> Option<String> {
+    None
+}
+
+/// `--save-key`: ask for the key and store it for `profile`.
+pub fn save_key(profile: &str) -> TokioResult<()> {
+    let key = read_key()?;
+    if key.is_empty() {
+        return Err(tr!("credentials-no-key").into());
+    }
+    store(profile, &key)?;
+    eprintln!("{}", tr!("credentials-saved", profile = profile));
+    Ok(())
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn store(profile: &str, key: &str) -> TokioResult<()> {
+    keyring::Entry::new(SERVICE, profile)?.set_password(key)?;
+    Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn store(_profile: &str, _key: &str) -> TokioResult<()> {
+    Err(tr!("credentials-unsupported").into())
+}
+
+/// The key, typed without showing it, or piped in.
+fn read_key() -> io::Result<String> {
+    let mut key = String::new();
+    if !atty::is(atty::Stream::Stdin) {
+        io::stdin().read_line(&mut key)?;
+        return Ok(key.trim().to_string());
+    }
+    eprint!("{} ", tr!("credentials-prompt"));
+    io::stderr().flush()?;
+    terminal::enable_raw_mode()?;
+    let read = read_hidden(&mut key);
+    terminal::disable_raw_mode()?;
+    eprintln!();
+    read.map(|()| key.trim().to_string())
+}
+
+fn read_hidden(key: &mut String) -> io::Result<()> {
+    loop {
+        let Event::Key(press) = event::read()? else {
+            continue;
+        };
+        if press.kind != KeyEventKind::Press {
+            continue;
+        }
+        match press.code {
+            KeyCode::Enter => return Ok(()),
+            KeyCode::Char('c') if press.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            KeyCode::Char(c) => key.push(c),
+            KeyCode::Backspace => {
+                key.pop();
+            }
+            _ => {}
+        }
+    }
+}
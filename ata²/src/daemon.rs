@@ -0,0 +1,275 @@
+//! `ata2 daemon` and `ata2 ask`: a process that keeps the configuration read and a connection to
+//! the API open, warmed up again before it's closed for being idle, and a client of it that's
+//! quick to start, for an editor like Emacs or Vim to run for each prompt. They talk over a Unix
+//! socket in the runtime directory, named after the profile: a line of JSON with the prompt, and
+//! lines of JSON with the text of the response as it comes, or what went wrong. With systemd, the
+//! socket can be one that a socket unit listens on, for the daemon to be started by the first
+//! `ata2 ask`. Without a daemon, `ata2 ask` sends the prompt itself.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::CreateChatCompletionRequestArgs;
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use serde_json::json;
+#[cfg(unix)]
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+#[cfg(unix)]
+use tokio::sync::mpsc::unbounded_channel;
+
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::DirBuilderExt as _;
+#[cfg(unix)]
+use std::os::unix::io::FromRawFd as _;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::config;
+use crate::config::Config;
+use crate::language;
+use crate::openai;
+use crate::output;
+use crate::prompt;
+use crate::readline::string_to_chat_completion_request_user_message;
+#[cfg(unix)]
+use crate::sink::Channel;
+use crate::sink::{self, Plain, Sinks};
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+use crate::FLAGS;
+
+/// How often the daemon warms the connection to the API up, for it to stay open: more often than
+/// servers close idle ones.
+#[cfg(unix)]
+const WARM_EVERY: Duration = Duration::from_secs(45);
+
+/// A prompt for the daemon.
+#[derive(Deserialize, Serialize)]
+struct Ask {
+    prompt: String,
+    /// Over `model` in the daemon's configuration, as `--model` of `ata2 ask` says.
+    model: Option<String>,
+}
+
+/// What the daemon says, a line each.
+#[cfg(unix)]
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Said {
+    /// The next of the text of the response.
+    Text(String),
+    /// Why there's no more of it.
+    Error(String),
+}
+
+/// The socket of the daemon of the profile of `--config`.
+#[cfg(unix)]
+fn socket() -> PathBuf {
+    config::get_runtime_dir().join(format!("{}.sock", FLAGS.config.profile()))
+}
+
+#[cfg(unix)]
+pub async fn run() -> TokioResult<()> {
+    let (listener, bound) = match activated()? {
+        Some(listener) => (listener, None),
+        None => {
+            let path = socket();
+            (bind(&path).await?, Some(path))
+        }
+    };
+    info!(
+        "{}",
+        tr!(
+            "daemon-listening",
+            socket = bound.as_ref().map_or_else(
+                || String::from("systemd"),
+                |path| path.display().to_string()
+            )
+        )
+    );
+    tokio::spawn(async {
+        loop {
+            openai::warm().await;
+            tokio::time::sleep(WARM_EVERY).await;
+        }
+    });
+
+    let serve = async {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            tokio::spawn(async move {
+                if let Err(e) = serve(stream).await {
+                    debug!("A client of the daemon: {e}");
+                }
+            });
+        }
+    };
+    // As systemd stops it.
+    let mut terminate = signal(SignalKind::terminate())?;
+    let ret: TokioResult<()> = tokio::select! {
+        ret = serve => ret,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+        _ = terminate.recv() => Ok(()),
+    };
+    // Not the socket of systemd's unit, which is its to remove.
+    if let Some(path) = bound {
+        let _ = fs::remove_file(path);
+    }
+    ret
+}
+
+#[cfg(not(unix))]
+pub async fn run() -> TokioResult<()> {
+    Err(tr!("daemon-unix-only").into())
+}
+
+/// The socket that systemd passes for socket activation, if it passes one.
+#[cfg(unix)]
+fn activated() -> TokioResult<Option<UnixListener>> {
+    /// The first of the descriptors that systemd passes.
+    const SD_LISTEN_FDS_START: i32 = 3;
+
+    let for_us = std::env::var("LISTEN_PID").is_ok_and(|pid| pid == std::process::id().to_string());
+    let fds = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|fds| fds.parse::<i32>().ok());
+    if !for_us || fds.unwrap_or_default() < 1 {
+        return Ok(None);
+    }
+    // SAFETY: systemd passes the listening socket as this descriptor, which nothing else owns.
+    let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(UnixListener::from_std(listener)?))
+}
+
+/// Listen on `path`, in place of a socket that's left over from a daemon that's gone, but not of
+/// one that's running.
+#[cfg(unix)]
+async fn bind(path: &Path) -> TokioResult<UnixListener> {
+    if let Some(dir) = path.parent() {
+        // Only its user may send the daemon prompts, to be paid for with their key.
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            let socket = path.display().to_string();
+            return Err(tr!("daemon-running", socket = socket).into());
+        }
+        fs::remove_file(path)?;
+    }
+    Ok(UnixListener::bind(path)?)
+}
+
+/// Answer the prompt of a client on `stream`.
+#[cfg(unix)]
+async fn serve(stream: UnixStream) -> TokioResult<()> {
+    let (read, mut write) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(read).read_line(&mut line).await?;
+    let ask: Ask = serde_json::from_str(&line)?;
+    let (tx, mut rx) = unbounded_channel();
+    let answered = tokio::spawn(answer(ask, Sinks::new(vec![Box::new(Channel(tx))])));
+    while let Some(text) = rx.recv().await {
+        write
+            .write_all(format!("{}\n", json!({ "text": text })).as_bytes())
+            .await?;
+    }
+    if let Err(e) = answered.await? {
+        let error = e.to_string();
+        write
+            .write_all(format!("{}\n", json!({ "error": error })).as_bytes())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Tell `sinks` the response to `ask`, as it comes. Returns it.
+async fn answer(ask: Ask, mut sinks: Sinks) -> TokioResult<String> {
+    let config: &Config = &CONFIGURATION;
+    let model = ask.model.unwrap_or_else(|| config.model.clone());
+    let mut messages = vec![];
+    prompt::set_system(&mut messages);
+    messages.push(string_to_chat_completion_request_user_message(ask.prompt));
+    let messages = language::instructed(&messages);
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    request.model(&model).messages(messages.clone());
+    let report = usage::Report::default();
+    let stream = openai::chat_stream(request.build()?, &report).await?;
+    let response = sink::read(stream, &model, &mut sinks).await?;
+    usage::record(&model, &messages, &response.text, &report);
+    Ok(response.text)
+}
+
+/// `ata2 ask`: the response to `prompt`, from the daemon if it's running.
+pub async fn ask(prompt: &str) -> TokioResult<()> {
+    let ask = Ask {
+        prompt: prompt.to_string(),
+        model: FLAGS.model.clone(),
+    };
+    #[cfg(unix)]
+    if let Ok(stream) = UnixStream::connect(socket()).await {
+        return relay(stream, &ask).await;
+    }
+    let response = answer(ask, Sinks::new(vec![Box::new(Plain::stdout())])).await?;
+    end(&response).await;
+    Ok(())
+}
+
+/// Send `ask` to the daemon on `stream`, and print what it says.
+#[cfg(unix)]
+async fn relay(stream: UnixStream, ask: &Ask) -> TokioResult<()> {
+    let (read, mut write) = stream.into_split();
+    write
+        .write_all(format!("{}\n", serde_json::to_string(ask)?).as_bytes())
+        .await?;
+    let mut lines = BufReader::new(read).lines();
+    let mut last = String::new();
+    while let Some(line) = lines.next_line().await? {
+        match serde_json::from_str(&line)? {
+            Said::Text(text) => {
+                output::write(&text).await;
+                last = text;
+            }
+            Said::Error(error) => return Err(error.into()),
+        }
+    }
+    end(&last).await;
+    Ok(())
+}
+
+/// The shell's prompt on a line of its own after the response, which ended with `last`.
+async fn end(last: &str) {
+    if !last.ends_with('\n') {
+        output::write("\n").await;
+    }
+    output::flush().await;
+}
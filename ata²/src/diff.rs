@@ -0,0 +1,275 @@
+//! Code a response revises, shown as a unified diff against the version earlier in the
+//! conversation rather than in full (`ui.diff_code`). `/diff` turns that off and on again.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::ChatCompletionRequestMessage;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::commands;
+use crate::markdown::{self, CodeBlock};
+use crate::readline::chat_completion_message_text;
+use crate::style::Style;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// Lines of context around each change.
+const CONTEXT: usize = 3;
+
+/// How much two blocks have to have in common for one to be a revision of the other: twice the
+/// lines they share over the lines of both.
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// Longer blocks are printed in full, as comparing them would hold up the response.
+const MAX_CELLS: usize = 4_000_000;
+
+/// Has `/diff` turned `ui.diff_code` the other way?
+static TOGGLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enabled() -> bool {
+    config.ui.diff_code != TOGGLED.load(Ordering::Relaxed)
+}
+
+/// `/diff`: show revised code in full, or as a diff again.
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    if !args.is_empty() {
+        return Err(commands::usage("diff"));
+    }
+    TOGGLED.fetch_xor(true, Ordering::Relaxed);
+    if enabled() {
+        info!("{}", tr!("diff-on"));
+    } else {
+        info!("{}", tr!("diff-off"));
+    }
+    Ok(())
+}
+
+/// Holds back each code block of one response as it streams in, and prints it as a diff if it
+/// revises one earlier in the conversation. Everything else goes through as it comes.
+pub struct Differ {
+    /// Code blocks of the conversation before the response. Empty if it isn't to be diffed.
+    earlier: Vec<CodeBlock>,
+    /// The start of a line that may be a fence.
+    line: String,
+    /// The fence and language of the block coming in, and its code so far.
+    block: Option<(String, CodeBlock)>,
+}
+
+impl Differ {
+    /// For the response to `messages`.
+    pub fn new(messages: &[ChatCompletionRequestMessage]) -> Self {
+        let earlier = if enabled() {
+            messages
+                .iter()
+                .filter_map(chat_completion_message_text)
+                .flat_map(|text| markdown::code_blocks(&text))
+                .collect()
+        } else {
+            vec![]
+        };
+        Self {
+            earlier,
+            line: String::new(),
+            block: None,
+        }
+    }
+
+    /// What to print of `text`.
+    pub fn push(&mut self, text: &str) -> String {
+        if self.earlier.is_empty() {
+            return text.to_string();
+        }
+        let mut ret = String::new();
+        for c in text.chars() {
+            self.line.push(c);
+            match self.block.take() {
+                None if c == '\n' => {
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(fence) = markdown::opening_fence(line.trim_start()) {
+                        let info = line.trim_start()[fence.len()..].trim();
+                        let lang = info.split_whitespace().next().map(str::to_string);
+                        let code = String::new();
+                        self.block = Some((fence, CodeBlock { lang, code }));
+                    }
+                    ret += &line;
+                }
+                None => {
+                    let trimmed = self.line.trim_start();
+                    let maybe_fence = trimmed.chars().all(|c| c == '`' || c == '~')
+                        || markdown::opening_fence(trimmed).is_some();
+                    if !maybe_fence {
+                        ret += &std::mem::take(&mut self.line);
+                    }
+                }
+                Some((fence, mut block)) if c == '\n' => {
+                    let line = std::mem::take(&mut self.line);
+                    let trimmed = line.trim_start();
+                    if trimmed.starts_with(&fence) && trimmed[fence.len()..].trim().is_empty() {
+                        ret += &self.render(&block);
+                        ret += &line;
+                    } else {
+                        block.code += &line;
+                        self.block = Some((fence, block));
+                    }
+                }
+                block => self.block = block,
+            }
+        }
+        ret
+    }
+
+    /// What's still held back, as the response ended. A block cut off isn't diffed.
+    pub fn finish(&mut self) -> String {
+        let line = std::mem::take(&mut self.line);
+        match self.block.take() {
+            Some((_, block)) => block.code + &line,
+            None => line,
+        }
+    }
+
+    /// `block` as a diff against the earlier block it revises, or as it is if there's none.
+    fn render(&self, block: &CodeBlock) -> String {
+        let new: Vec<&str> = block.code.lines().collect();
+        let revised = self
+            .earlier
+            .iter()
+            .rev()
+            .filter(|earlier| {
+                earlier.code != block.code
+                    && (earlier.lang.is_none()
+                        || block.lang.is_none()
+                        || earlier.lang == block.lang)
+            })
+            .filter_map(|earlier| {
+                let old: Vec<&str> = earlier.code.lines().collect();
+                let ops = edits(&old, &new)?;
+                let same = ops.iter().filter(|op| matches!(op, Edit::Same(_))).count();
+                let similarity = 2.0 * same as f64 / (old.len() + new.len()) as f64;
+                (similarity >= MIN_SIMILARITY).then_some((similarity, ops))
+            })
+            // The latest of equally similar blocks, which come first.
+            .reduce(|best, next| if next.0 > best.0 { next } else { best });
+        match revised {
+            Some((_, ops)) => unified(&ops),
+            None => block.code.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edit<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The shortest way from `old` to `new`, line by line, or None if they're too long to tell.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Option<Vec<Edit<'a>>> {
+    let (n, m) = (old.len(), new.len());
+    if n == 0 || m == 0 || (n + 1) * (m + 1) > MAX_CELLS {
+        return None;
+    }
+    // lcs[i][j]: how many lines `old[i..]` and `new[j..]` have in common.
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ret = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            ret.push(Edit::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if j == m || i < n && lcs[i + 1][j] >= lcs[i][j + 1] {
+            ret.push(Edit::Removed(old[i]));
+            i += 1;
+        } else {
+            ret.push(Edit::Added(new[j]));
+            j += 1;
+        }
+    }
+    Some(ret)
+}
+
+/// `ops` as the hunks of a unified diff, with `CONTEXT` lines around each change.
+fn unified(ops: &[Edit]) -> String {
+    let header = Style::parse("cyan").unwrap_or_default();
+    let removed = Style::parse("red").unwrap_or_default();
+    let added = Style::parse("green").unwrap_or_default();
+    let paint = |style: &Style, line: String| style.paint_to(atty::Stream::Stdout, &line);
+
+    let changed: Vec<usize> = (0..ops.len())
+        .filter(|&i| !matches!(ops[i], Edit::Same(_)))
+        .collect();
+    // Changes close enough together for their context to touch go in one hunk.
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &i in &changed {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut ret = String::new();
+    for (start, end) in hunks {
+        let (old_start, new_start) = sides(&ops[..start]);
+        let (old_len, new_len) = sides(&ops[start..end]);
+        ret += &paint(
+            &header,
+            format!(
+                "@@ -{},{old_len} +{},{new_len} @@",
+                old_start + usize::from(old_len > 0),
+                new_start + usize::from(new_len > 0)
+            ),
+        );
+        ret.push('\n');
+        for op in &ops[start..end] {
+            match op {
+                Edit::Same(line) => ret += &format!(" {line}"),
+                Edit::Removed(line) => ret += &paint(&removed, format!("-{line}")),
+                Edit::Added(line) => ret += &paint(&added, format!("+{line}")),
+            }
+            ret.push('\n');
+        }
+    }
+    ret
+}
+
+/// How many lines of the old and of the new version `ops` take up.
+fn sides(ops: &[Edit]) -> (usize, usize) {
+    let old = ops
+        .iter()
+        .filter(|op| !matches!(op, Edit::Added(_)))
+        .count();
+    let new = ops
+        .iter()
+        .filter(|op| !matches!(op, Edit::Removed(_)))
+        .count();
+    (old, new)
+}
@@ -0,0 +1,354 @@
+//! `ata2 doctor`: what might be wrong, for when “it doesn't work”. Each check passes, fails, or
+//! warns of what may get in the way: whether the configuration is read and valid, the API can be
+//! reached through whatever proxy there is, the key works, the model is there, what ata² keeps can
+//! be written where it goes, and what the terminal can do.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use atty::Stream;
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr as _;
+use std::time::Duration;
+
+use crate::config::{Auth, Config, ConfigLocation, Storage};
+use crate::local;
+use crate::models::{self, Model};
+use crate::style::{self, Style};
+use crate::TokioResult;
+use crate::CONFIGURATION;
+use crate::FLAGS;
+
+/// How long the API has to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The variables reqwest takes a proxy from.
+const PROXY_VARS: &[&str] = &[
+    "HTTPS_PROXY",
+    "https_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+];
+
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    status: Status,
+    name: String,
+    detail: String,
+}
+
+impl Check {
+    fn new(status: Status, name: &str, detail: String) -> Self {
+        Self {
+            status,
+            name: tr!(name),
+            detail,
+        }
+    }
+}
+
+pub async fn run() -> TokioResult<()> {
+    let mut checks = vec![];
+    let (check, loaded) = configuration();
+    checks.push(check);
+    if loaded {
+        if local::enabled() {
+            checks.push(local_model());
+        } else {
+            checks.push(connection().await);
+            let listing = models::fetch().await.map_err(|e| e.to_string());
+            checks.push(key(&listing));
+            checks.push(model(&listing));
+        }
+        checks.extend(directories());
+    }
+    checks.extend(terminal(loaded));
+
+    for check in &checks {
+        let mark = match check.status {
+            Status::Pass => ("green", "✓"),
+            Status::Warn => ("yellow", "!"),
+            Status::Fail => ("red", "✗"),
+        };
+        // Whether to style it is in the configuration, which is only read if it's there.
+        let mark = if loaded {
+            Style::parse(mark.0)
+                .unwrap_or_default()
+                .paint_to(Stream::Stdout, mark.1)
+        } else {
+            mark.1.to_string()
+        };
+        println!("{mark} {}: {}", check.name, check.detail);
+    }
+    let failed = checks
+        .iter()
+        .filter(|check| matches!(check.status, Status::Fail))
+        .count();
+    if failed > 0 {
+        return Err(tr!("doctor-failed", count = failed).into());
+    }
+    Ok(())
+}
+
+/// Whether the configuration file is there, is read, and is valid, and whether it's loaded, for
+/// the checks that go by it.
+fn configuration() -> (Check, bool) {
+    const NAME: &str = "doctor-configuration";
+    let path = match &FLAGS.config {
+        // Fetched as it's loaded, which exits if it can't be.
+        ConfigLocation::Url(url) => url.to_string(),
+        location => {
+            let path = location.location();
+            if !path.exists() {
+                let detail = tr!("doctor-config-missing", path = path.display().to_string());
+                return (Check::new(Status::Fail, NAME, detail), false);
+            }
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| Config::from_str(&contents).map_err(|e| e.to_string()));
+            if let Err(e) = parsed {
+                let detail = format!("{}: {e}", path.display());
+                return (Check::new(Status::Fail, NAME, detail), false);
+            }
+            path.display().to_string()
+        }
+    };
+    let check = match CONFIGURATION
+        .validate()
+        .and_then(|()| CONFIGURATION.ui.check_history_dir())
+    {
+        Ok(()) => Check::new(Status::Pass, NAME, path),
+        Err(e) => Check::new(Status::Fail, NAME, format!("{path}: {e}")),
+    };
+    (check, true)
+}
+
+/// Whether anything answers at `api_base`, through the proxy of the environment if there's one.
+async fn connection() -> Check {
+    const NAME: &str = "doctor-connection";
+    let url = &CONFIGURATION.api_base;
+    let proxy = PROXY_VARS
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()));
+    let via = proxy
+        .map(|proxy| format!(" {}", tr!("doctor-via-proxy", proxy = proxy)))
+        .unwrap_or_default();
+    let response = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| causes(&e))
+        .map(|client| client.get(url.as_str()).send());
+    let response = match response {
+        Ok(response) => response.await.map_err(|e| causes(&e)),
+        Err(e) => Err(e),
+    };
+    match response {
+        Ok(response) => Check::new(
+            Status::Pass,
+            NAME,
+            tr!(
+                "doctor-connection-ok",
+                url = url.as_str(),
+                status = response.status().as_u16()
+            ) + &via,
+        ),
+        Err(e) => Check::new(Status::Fail, NAME, format!("{url}{via}: {e}")),
+    }
+}
+
+/// `e` with what caused it, which is where reqwest says what went wrong with TLS or a proxy.
+fn causes(e: &dyn Error) -> String {
+    let mut ret = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        // hyper and reqwest repeat what they wrap.
+        let cause = e.to_string();
+        if !ret.contains(&cause) {
+            ret += &format!(": {cause}");
+        }
+        source = e.source();
+    }
+    ret
+}
+
+/// Whether there's a key, and the API takes it, as it lists its models, which costs nothing.
+fn key(listing: &Result<Vec<Model>, String>) -> Check {
+    const NAME: &str = "doctor-key";
+    let config: &Config = &CONFIGURATION;
+    if config.auth == Auth::None {
+        return Check::new(Status::Pass, NAME, tr!("doctor-key-none"));
+    }
+    if config.api_key.as_deref().is_none_or(str::is_empty) && config.rotation.keys.is_empty() {
+        return Check::new(Status::Fail, NAME, tr!("doctor-key-missing"));
+    }
+    match listing {
+        Ok(models) => Check::new(
+            Status::Pass,
+            NAME,
+            tr!("doctor-key-ok", count = models.len()),
+        ),
+        Err(e) => Check::new(Status::Fail, NAME, e.clone()),
+    }
+}
+
+/// Whether the API offers the configured model.
+fn model(listing: &Result<Vec<Model>, String>) -> Check {
+    const NAME: &str = "doctor-model";
+    let model = CONFIGURATION.model.as_str();
+    match listing {
+        Ok(models) if models.iter().any(|m| m.id == model) => {
+            Check::new(Status::Pass, NAME, model.to_string())
+        }
+        Ok(_) => Check::new(
+            Status::Fail,
+            NAME,
+            tr!("doctor-model-missing", model = model),
+        ),
+        Err(_) => Check::new(
+            Status::Warn,
+            NAME,
+            tr!("doctor-model-unchecked", model = model),
+        ),
+    }
+}
+
+/// Whether there's the GGUF file of `api = "local"`, and ata² can run it.
+fn local_model() -> Check {
+    const NAME: &str = "doctor-model";
+    let path = &CONFIGURATION.provider.local.model;
+    if !cfg!(feature = "local") {
+        return Check::new(Status::Fail, NAME, tr!("local-not-built"));
+    }
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => {
+            Check::new(Status::Pass, NAME, path.display().to_string())
+        }
+        Ok(_) => Check::new(
+            Status::Fail,
+            NAME,
+            tr!("doctor-not-a-file", path = path.display().to_string()),
+        ),
+        Err(e) => Check::new(Status::Fail, NAME, format!("{}: {e}", path.display())),
+    }
+}
+
+/// Whether the directories of the history, the conversations and the cache can be written to.
+fn directories() -> Vec<Check> {
+    let config: &Config = &CONFIGURATION;
+    let history = config.ui.history_file.parent().unwrap_or(Path::new("."));
+    let conversations = match config.ui.storage {
+        Storage::Files => config.ui.save_dir.as_path(),
+        Storage::Sqlite => config.ui.database_file.parent().unwrap_or(Path::new(".")),
+    };
+    [
+        ("doctor-history", history),
+        ("doctor-conversations", conversations),
+        ("doctor-cache", config.cache.dir.as_path()),
+    ]
+    .into_iter()
+    .map(|(name, dir)| match writable(dir) {
+        Ok(()) => Check::new(Status::Pass, name, dir.display().to_string()),
+        Err(e) => Check::new(Status::Fail, name, format!("{}: {e}", dir.display())),
+    })
+    .collect()
+}
+
+/// Make `dir` if it isn't there, and write a file in it, which is removed again.
+fn writable(dir: &Path) -> std::io::Result<()> {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!(".ata2-doctor-{}", std::process::id()));
+    fs::write(&path, b"")?;
+    fs::remove_file(&path)
+}
+
+/// What the terminal can do: colors, unless they're off by the configuration, if it's `loaded`,
+/// and setting the clipboard with OSC 52, which `/copy` does.
+fn terminal(loaded: bool) -> Vec<Check> {
+    if !atty::is(Stream::Stdout) {
+        return vec![Check::new(
+            Status::Warn,
+            "doctor-terminal",
+            tr!("doctor-not-a-terminal"),
+        )];
+    }
+    let term = env::var("TERM").unwrap_or_default();
+    let program = env::var("TERM_PROGRAM").unwrap_or_default();
+    let colors = if loaded && !style::enabled(Stream::Stdout) {
+        Check::new(Status::Warn, "doctor-colors", tr!("doctor-colors-off"))
+    } else if env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit") {
+        Check::new(Status::Pass, "doctor-colors", tr!("doctor-truecolor"))
+    } else {
+        Check::new(
+            Status::Warn,
+            "doctor-colors",
+            tr!("doctor-no-truecolor", term = term.as_str()),
+        )
+    };
+
+    const OSC52_TERMS: &[&str] = &[
+        "kitty",
+        "alacritty",
+        "foot",
+        "wezterm",
+        "ghostty",
+        "contour",
+    ];
+    const OSC52_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "ghostty", "tmux"];
+    let osc52 = if env::var_os("TMUX").is_some() {
+        Check::new(Status::Warn, "doctor-osc52", tr!("doctor-osc52-tmux"))
+    } else if program == "Apple_Terminal" {
+        Check::new(
+            Status::Warn,
+            "doctor-osc52",
+            tr!("doctor-osc52-no", term = program.as_str()),
+        )
+    } else if env::var_os("WT_SESSION").is_some()
+        || OSC52_PROGRAMS.contains(&program.as_str())
+        || OSC52_TERMS.iter().any(|known| term.contains(known))
+    {
+        let name = if program.is_empty() { &term } else { &program };
+        Check::new(
+            Status::Pass,
+            "doctor-osc52",
+            tr!("doctor-osc52-yes", term = name.as_str()),
+        )
+    } else {
+        Check::new(
+            Status::Warn,
+            "doctor-osc52",
+            tr!("doctor-osc52-unknown", term = term.as_str()),
+        )
+    };
+    vec![colors, osc52]
+}
@@ -0,0 +1,169 @@
+//! `[draft]`, an experiment: the answer of a fast model, streamed at once, while the configured
+//! model, which may take a while to think, answers in the background. Its answer is then shown
+//! too, to replace the draft with or to add to it, for the conversation to go on from the better
+//! one without waiting for it to start.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, CreateChatCompletionRequestArgs};
+use tokio::task::JoinHandle;
+
+use std::io::{self, Write as _};
+
+use crate::config::Config;
+use crate::language;
+use crate::local;
+use crate::openai;
+use crate::output::print_and_flush;
+use crate::overrides;
+use crate::prompt::CONVERSATION;
+use crate::readline::{
+    autosave, chat_completion_message_text, string_to_chat_completion_assistant_message,
+};
+use crate::responses;
+use crate::spinner::Spinner;
+use crate::style::theme;
+use crate::tools;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// What's done with the answer of the configured model.
+enum Choice {
+    Replace,
+    Append,
+    Keep,
+}
+
+/// Whether prompts are answered by a draft first. Not with the responses API, where there'd be
+/// two responses to continue from, nor with a model of ata²'s own, which answers one at a time.
+pub fn enabled() -> bool {
+    CONFIGURATION.draft.enabled && !responses::enabled() && !local::enabled()
+}
+
+/// The model that drafts answers.
+pub fn model() -> &'static str {
+    &CONFIGURATION.draft.model
+}
+
+/// Start asking the model the last prompt was sent to for its answer to `messages`, in the
+/// background.
+pub fn refine(
+    messages: &[ChatCompletionRequestMessage],
+) -> TokioResult<JoinHandle<TokioResult<String>>> {
+    let config: &Config = &CONFIGURATION;
+    let model = overrides::model();
+    let messages = language::instructed(&tools::without(messages));
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    overrides::last().chat(&mut request);
+    let request = request
+        .n(1)
+        .stream(false)
+        .messages(messages.clone())
+        .build()?;
+    Ok(tokio::spawn(async move {
+        let response = openai::chat(request).await?;
+        let text = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+        let (prompt_tokens, completion_tokens) = match response.usage {
+            Some(usage) => (
+                usage.prompt_tokens as usize,
+                usage.completion_tokens as usize,
+            ),
+            None => (
+                usage::conversation_tokens(&model, &messages),
+                usage::count_tokens(&model, &text),
+            ),
+        };
+        usage::record_tokens(&model, prompt_tokens, completion_tokens);
+        Ok(text)
+    }))
+}
+
+/// Wait for the answer of `refining`, print it, and replace the draft, the last message of the
+/// conversation, with it or add it to the draft, as the user chooses. Without a terminal to ask
+/// on, it replaces the draft.
+pub async fn offer(refining: JoinHandle<TokioResult<String>>) -> TokioResult<()> {
+    let model = overrides::model();
+    info!("{}", tr!("draft-waiting", model = model.as_str()));
+    let mut spinner = Spinner::start();
+    let refined = refining.await?;
+    spinner.stop();
+    let refined = match refined {
+        Ok(refined) => refined,
+        Err(e) => {
+            error!(
+                "{}",
+                tr!(
+                    "draft-failed",
+                    model = model.as_str(),
+                    error = e.to_string()
+                )
+            );
+            return Ok(());
+        }
+    };
+    let header = tr!("draft-refined", model = model.as_str());
+    print_and_flush(&format!(
+        "\n{}\n{}\n\n",
+        theme().response.paint_to(atty::Stream::Stdout, &header),
+        refined.trim_end()
+    ));
+    let choice = if atty::is(atty::Stream::Stdin) {
+        choose()
+    } else {
+        Choice::Replace
+    };
+    {
+        let mut conversation = CONVERSATION.lock().await;
+        let draft = conversation
+            .last()
+            .and_then(chat_completion_message_text)
+            .unwrap_or_default();
+        let text = match choice {
+            Choice::Replace => refined,
+            Choice::Append => format!("{}\n\n{}", draft.trim_end(), refined.trim_start()),
+            Choice::Keep => return Ok(()),
+        };
+        conversation.pop();
+        conversation.push(string_to_chat_completion_assistant_message(text));
+    }
+    autosave().await;
+    Ok(())
+}
+
+/// What the user chooses to do with the answer.
+fn choose() -> Choice {
+    eprint!("{} ", tr!("draft-choose"));
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    // rustyline is done with the terminal, so it's back to reading whole lines.
+    if io::stdin().read_line(&mut answer).is_err() {
+        return Choice::Keep;
+    }
+    match answer.trim().to_lowercase().chars().next() {
+        Some('r') => Choice::Replace,
+        Some('a') => Choice::Append,
+        _ => Choice::Keep,
+    }
+}
@@ -0,0 +1,163 @@
+//! Environment variables that set any field of the configuration over what the config file says:
+//! `ATA2_` and the field's path in capitals, joined by `_`, like `ATA2_MODEL`, `ATA2_UI_WRAP` or
+//! `ATA2_UI_THEME_ERROR`. The fields are found by reflection, so a new one can be set this way as
+//! soon as it's added.
+//!
+//! Values are written as in the config file, without quotes: `1`, `true`, `yes` or `on` for true,
+//! and `0`, `false`, `no`, `off` or nothing for false; a list as JSON or separated by commas; a
+//! table as JSON. An empty value unsets an optional field.
+//!
+//! The names from before, like `ATA2_WRAP` for `ui.wrap` or `ATA2_KEY_NEWLINE` for
+//! `ui.keybindings.newline`, still work, see [`LEGACY`].
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use bevy_reflect::{Reflect, ReflectRef, Struct};
+use toml::value::{Table, Value};
+
+use std::env;
+
+const PREFIX: &str = "ATA2_";
+
+/// The start of a name, after [`PREFIX`], and what it was before fields were named by their path.
+/// The first that a name starts with is the one that applies.
+const LEGACY: &[(&str, &str)] = &[
+    ("UI_KEYBINDINGS_", "KEY_"),
+    ("UI_", ""),
+    ("INTEGRATIONS_", ""),
+    ("SCHEDULER_", ""),
+    ("CACHE_ENABLED", "CACHE"),
+    ("CLIPWATCH_ENABLED", "CLIPWATCH"),
+];
+
+/// Set the fields of `table`, as read from the config file, that an environment variable sets.
+/// `shape` is the struct it's deserialized into, for its fields and what each of them takes.
+pub fn apply(shape: &dyn Struct, table: &mut Table) {
+    apply_to(shape, "", table);
+}
+
+fn apply_to(shape: &dyn Struct, path: &str, table: &mut Table) {
+    for (i, field) in shape.iter_fields().enumerate() {
+        let Some(name) = shape.name_at(i) else {
+            continue;
+        };
+        let path = format!("{path}{}_", name.to_uppercase());
+        if let ReflectRef::Struct(shape) = field.reflect_ref() {
+            let table = table
+                .entry(name)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if let Value::Table(table) = table {
+                apply_to(shape, &path, table);
+            }
+            continue;
+        }
+        let Some((var, text)) = set(path.trim_end_matches('_')) else {
+            continue;
+        };
+        match value(field, &text) {
+            Ok(Some(value)) => {
+                table.insert(name.to_string(), value);
+            }
+            Ok(None) => {
+                table.remove(name);
+            }
+            Err(e) => warn!(
+                "{}",
+                tr!(
+                    "config-env-ignored",
+                    var = var,
+                    value = format!("{text:?}"),
+                    error = e
+                )
+            ),
+        }
+    }
+}
+
+/// The environment variable that sets the field at `path`, like `ui.wrap`, and what it says.
+pub fn setting(path: &str) -> Option<(String, String)> {
+    set(&path.replace('.', "_").to_uppercase())
+}
+
+/// [`setting`] of a path in capitals, joined by `_`.
+fn set(path: &str) -> Option<(String, String)> {
+    names(path)
+        .into_iter()
+        .find_map(|var| env::var(&var).ok().map(|text| (var, text)))
+}
+
+/// The environment variables of the field at `path`, the one named after it first.
+fn names(path: &str) -> Vec<String> {
+    let mut ret = vec![format!("{PREFIX}{path}")];
+    if let Some((start, legacy)) = LEGACY.iter().find(|(start, _)| path.starts_with(start)) {
+        ret.push(format!("{PREFIX}{legacy}{}", &path[start.len()..]));
+    }
+    ret
+}
+
+/// `text` as what the config file would have for `field`. `None` for an optional field that's
+/// unset.
+fn value(field: &dyn Reflect, text: &str) -> Result<Option<Value>, String> {
+    let type_name = field.type_name();
+    if type_name.starts_with("core::option::Option<") && text.is_empty() {
+        return Ok(None);
+    }
+    let value = match field.reflect_ref() {
+        ReflectRef::List(_) if text.trim_start().starts_with('[') => json(text)?,
+        ReflectRef::List(_) => Value::Array(
+            text.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| Value::String(s.to_string()))
+                .collect(),
+        ),
+        ReflectRef::Map(_) => json(text)?,
+        _ if field.is::<bool>() => Value::Boolean(boolean(text)?),
+        _ if is_integer(type_name) => {
+            Value::Integer(text.trim().parse().map_err(|e| format!("{e}"))?)
+        }
+        _ if type_name == "f64" || type_name == "f32" => {
+            Value::Float(text.trim().parse().map_err(|e| format!("{e}"))?)
+        }
+        // Strings, paths and the names of variants, which the config file has as strings too.
+        _ => Value::String(text.to_string()),
+    };
+    Ok(Some(value))
+}
+
+fn is_integer(type_name: &str) -> bool {
+    [
+        "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize",
+    ]
+    .contains(&type_name)
+}
+
+/// Not just whether there's something there, for `ATA2_UI_SPINNER=0` to turn it off.
+fn boolean(text: &str) -> Result<bool, String> {
+    match text.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "" | "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(tr!("config-env-not-boolean")),
+    }
+}
+
+fn json(text: &str) -> Result<Value, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    Value::try_from(value).map_err(|e| e.to_string())
+}
@@ -0,0 +1,322 @@
+//! `ata2 eval suite.toml`: send the prompts of a suite to each of its models, check the responses
+//! against regular expressions and JSON values, and report what passed, how long it took and what
+//! it cost. Fails if anything did, for CI.
+//!
+//! ```toml
+//! models = ["gpt-4o-mini", "gpt-4o"]
+//! repeat = 3
+//!
+//! [[case]]
+//! name = "capital"
+//! prompt = "What's the capital of France? Answer in one word."
+//! matches = ["(?i)^paris\\.?$"]
+//!
+//! [[case]]
+//! name = "json"
+//! prompt = 'Reply with {"answer": …}, the answer being 6 × 7.'
+//! json = { "/answer" = 42 }
+//! ```
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    CreateChatCompletionRequestArgs, Role,
+};
+use futures_util::StreamExt as _;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::cache;
+use crate::config::Config;
+use crate::markdown;
+use crate::openai;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::scheduler;
+use crate::style::Style;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Suite {
+    /// Models to run the cases with. The configured one if none.
+    #[serde(default)]
+    models: Vec<String>,
+    /// How many times to run each case with each model.
+    #[serde(default = "one")]
+    repeat: usize,
+    /// System prompt of the cases that don't have their own.
+    system: Option<String>,
+    #[serde(rename = "case")]
+    cases: Vec<Case>,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    name: String,
+    prompt: String,
+    system: Option<String>,
+    /// Regular expressions the response has to match.
+    #[serde(default)]
+    matches: Vec<String>,
+    /// Regular expressions the response mustn't match.
+    #[serde(default)]
+    not_matches: Vec<String>,
+    /// The response has to be JSON, all of it or its first code block, with these values at these
+    /// JSON pointers. `""` is the whole of it.
+    json: Option<BTreeMap<String, toml::Value>>,
+}
+
+/// A case with its regular expressions compiled.
+struct Checks<'a> {
+    case: &'a Case,
+    matches: Vec<Regex>,
+    not_matches: Vec<Regex>,
+}
+
+/// How the runs with a model went.
+#[derive(Default)]
+struct Tally {
+    passed: usize,
+    runs: usize,
+    latency: Duration,
+    /// In USD. `None` if the model's price isn't known.
+    cost: Option<f64>,
+}
+
+/// Run the suite in `path`, each case `repeat` times or as many as the suite says.
+pub async fn run(path: &Path, repeat: Option<usize>) -> TokioResult<()> {
+    let suite: Suite = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let checks = suite
+        .cases
+        .iter()
+        .map(|case| {
+            let compile = |regexes: &[String]| {
+                regexes
+                    .iter()
+                    .map(|regex| Regex::new(regex).map_err(|e| format!("{}: {e}", case.name)))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            Ok(Checks {
+                case,
+                matches: compile(&case.matches)?,
+                not_matches: compile(&case.not_matches)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let config: &Config = &CONFIGURATION;
+    let models = if suite.models.is_empty() {
+        vec![config.model.clone()]
+    } else {
+        suite.models.clone()
+    };
+    let repeat = repeat.unwrap_or(suite.repeat).max(1);
+    let pass = Style::parse("green").unwrap_or_default();
+    let fail = Style::parse("red").unwrap_or_default();
+
+    let mut jobs = vec![];
+    for (m, model) in models.iter().enumerate() {
+        for checks in &checks {
+            for i in 1..=repeat {
+                let mut name = checks.case.name.clone();
+                if repeat > 1 {
+                    name += &format!(" #{i}");
+                }
+                // Repeated for responses that differ, which cached ones wouldn't.
+                let cached = repeat == 1;
+                let system = suite.system.as_deref();
+                let job = async move {
+                    let start = Instant::now();
+                    let result = complete(model, system, checks.case, cached).await;
+                    (m, checks, name, result, start.elapsed())
+                };
+                jobs.push((model.clone(), job));
+            }
+        }
+    }
+
+    let mut tallies = models
+        .iter()
+        .map(|model| (model, Tally::default()))
+        .collect::<Vec<_>>();
+    let mut results = scheduler::run(jobs)?;
+    while let Some((m, checks, name, result, latency)) = results.next().await {
+        let (model, tally) = &mut tallies[m];
+        tally.runs += 1;
+        tally.latency += latency;
+        let failure = match result {
+            Ok((response, cost)) => {
+                if let Some(cost) = cost {
+                    *tally.cost.get_or_insert(0.0) += cost;
+                }
+                check(checks, &response)
+            }
+            Err(e) => Some(tr!("eval-error", error = e.to_string())),
+        };
+        let latency = format!("{:.2} s", latency.as_secs_f64());
+        match failure {
+            None => {
+                tally.passed += 1;
+                println!(
+                    "{} {model} · {name} · {latency}",
+                    pass.paint_to(atty::Stream::Stdout, &tr!("eval-pass"))
+                );
+            }
+            Some(failure) => println!(
+                "{} {model} · {name} · {latency}: {failure}",
+                fail.paint_to(atty::Stream::Stdout, &tr!("eval-fail"))
+            ),
+        }
+    }
+
+    println!();
+    let mut failed = 0;
+    for (model, tally) in &tallies {
+        failed += tally.runs - tally.passed;
+        let latency = tally.latency.as_secs_f64() / tally.runs.max(1) as f64;
+        println!(
+            "{}",
+            tr!(
+                "eval-summary",
+                model = model.as_str(),
+                passed = tally.passed,
+                runs = tally.runs,
+                latency = format!("{latency:.2}"),
+                cost = match tally.cost {
+                    Some(cost) => format!("${cost:.4}"),
+                    None => tr!("eval-cost-unknown"),
+                }
+            )
+        );
+    }
+    if failed > 0 {
+        return Err(tr!("eval-failed", count = failed).into());
+    }
+    Ok(())
+}
+
+/// The response of `model` to `case`, and what it cost if that's known. With the configuration's
+/// parameters, but not streamed, so the API says how many tokens it was. From the cache, if
+/// `cached` and it's enabled, at no cost.
+async fn complete(
+    model: &str,
+    system: Option<&str>,
+    case: &Case,
+    cached: bool,
+) -> TokioResult<(String, Option<f64>)> {
+    let config: &Config = &CONFIGURATION;
+    let mut messages = vec![];
+    if let Some(system) = case.system.as_deref().or(system) {
+        messages.push(ChatCompletionRequestMessage::System(
+            ChatCompletionRequestSystemMessage {
+                role: Role::System,
+                content: Some(system.to_string()),
+            },
+        ));
+    }
+    messages.push(string_to_chat_completion_request_user_message(
+        case.prompt.clone(),
+    ));
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    let request = request
+        .model(model)
+        .n(1)
+        .stream(false)
+        .messages(messages.clone())
+        .build()?;
+    if cached {
+        if let Some(text) = cache::get("/chat/completions", &request) {
+            return Ok((text, Some(0.0)));
+        }
+    }
+    let response = openai::chat(request.clone()).await?;
+    let text = response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+    let (prompt_tokens, completion_tokens) = match response.usage {
+        Some(usage) => (
+            usage.prompt_tokens as usize,
+            usage.completion_tokens as usize,
+        ),
+        None => (
+            usage::conversation_tokens(model, &messages),
+            usage::count_tokens(model, &text),
+        ),
+    };
+    let cost = usage::record_tokens(model, prompt_tokens, completion_tokens);
+    if cached {
+        cache::put("/chat/completions", &request, &text);
+    }
+    Ok((text, cost))
+}
+
+/// Why `response` fails the checks, if it does.
+fn check(checks: &Checks, response: &str) -> Option<String> {
+    if let Some(regex) = checks.matches.iter().find(|r| !r.is_match(response)) {
+        return Some(tr!("eval-no-match", regex = regex.as_str()));
+    }
+    if let Some(regex) = checks.not_matches.iter().find(|r| r.is_match(response)) {
+        return Some(tr!("eval-matches", regex = regex.as_str()));
+    }
+    let expected = checks.case.json.as_ref()?;
+    let Some(json) = json(response) else {
+        return Some(tr!("eval-not-json"));
+    };
+    for (pointer, expected) in expected {
+        let expected = serde_json::to_value(expected).unwrap_or_default();
+        match json.pointer(pointer) {
+            None => return Some(tr!("eval-json-missing", pointer = pointer.as_str())),
+            Some(actual) if *actual != expected => {
+                return Some(tr!(
+                    "eval-json-differs",
+                    pointer = pointer.as_str(),
+                    actual = actual.to_string(),
+                    expected = expected.to_string()
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+    None
+}
+
+/// The JSON in `response`: all of it, or else its first code block.
+fn json(response: &str) -> Option<Value> {
+    serde_json::from_str(response.trim()).ok().or_else(|| {
+        let block = markdown::code_blocks(response).into_iter().next()?;
+        serde_json::from_str(&block.code).ok()
+    })
+}
@@ -0,0 +1,400 @@
+//! `[tools.custom]`: tools defined in the configuration, each a command that the model may run
+//! with what it gives it, or a request to an API of `[tools.apis]`, with nothing to be built for
+//! them. They're offered to the model with chat completions, and each it calls runs as
+//! `[tools.policy]` says, with what the model gave in place of each `{name}`, quoted for the shell
+//! or the URL. What it outputs goes back to the model, which answers on from there.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionFunctions, ChatCompletionMessageToolCall, ChatCompletionMessageToolCallChunk,
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestToolMessage, ChatCompletionTool, ChatCompletionToolType, FunctionCall,
+    Role,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::Method;
+use serde_json::{json, Map, Value};
+use tokio::io::AsyncReadExt as _;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use std::error::Error;
+use std::future::Future;
+use std::io;
+use std::process::Stdio;
+use std::str::FromStr as _;
+use std::time::Instant;
+
+use crate::config::CustomTool;
+use crate::local;
+use crate::policy;
+use crate::responses;
+use crate::sink::{Event, Sinks};
+use crate::template;
+use crate::tools::{self, Capped};
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// How many times in a row the model may call tools, for it not to go on calling them forever.
+pub const MAX_ROUNDS: usize = 8;
+
+/// The tool calls of a response, put together from the pieces they're streamed in.
+#[derive(Default)]
+pub struct Calls(Vec<ChatCompletionMessageToolCall>);
+
+impl Calls {
+    pub fn push(&mut self, chunks: &[ChatCompletionMessageToolCallChunk]) {
+        for chunk in chunks {
+            let index = chunk.index as usize;
+            while self.0.len() <= index {
+                self.0.push(ChatCompletionMessageToolCall {
+                    id: String::new(),
+                    r#type: ChatCompletionToolType::Function,
+                    function: FunctionCall {
+                        name: String::new(),
+                        arguments: String::new(),
+                    },
+                });
+            }
+            let call = &mut self.0[index];
+            if let Some(id) = &chunk.id {
+                call.id += id;
+            }
+            if let Some(function) = &chunk.function {
+                call.function.name += function.name.as_deref().unwrap_or_default();
+                call.function.arguments += function.arguments.as_deref().unwrap_or_default();
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Whether the model is offered the tools of `[tools.custom]`. Not with the Responses API, whose
+/// tools run at OpenAI, nor with a model of ata²'s own, which doesn't call any.
+pub fn enabled() -> bool {
+    !CONFIGURATION.tools.custom.is_empty() && !responses::enabled() && !local::enabled()
+}
+
+/// The tools of `[tools.custom]`, as the model's told of them.
+pub fn tools() -> Vec<ChatCompletionTool> {
+    let mut tools = CONFIGURATION
+        .tools
+        .custom
+        .iter()
+        .map(|(name, tool)| ChatCompletionTool {
+            r#type: ChatCompletionToolType::Function,
+            function: ChatCompletionFunctions {
+                name: name.clone(),
+                description: Some(tool.description.clone()).filter(|d| !d.is_empty()),
+                parameters: parameters(tool),
+            },
+        })
+        .collect::<Vec<_>>();
+    tools.sort_by(|a, b| a.function.name.cmp(&b.function.name));
+    tools
+}
+
+/// The JSON schema of what `tool` takes, which validation made sure is an object, if it's there.
+fn parameters(tool: &CustomTool) -> Value {
+    serde_json::from_str(&tool.parameters)
+        .unwrap_or_else(|_| json!({ "type": "object", "properties": {} }))
+}
+
+/// Run the tools of `calls`, which the model made after saying `said`, showing each in `sinks` as
+/// it's done. Returns the messages that the model's answer goes on from: its calls, and what each
+/// tool output.
+pub async fn run(said: &str, calls: Calls, sinks: &mut Sinks) -> Vec<ChatCompletionRequestMessage> {
+    #[allow(deprecated)]
+    let mut ret = vec![ChatCompletionRequestMessage::Assistant(
+        ChatCompletionRequestAssistantMessage {
+            content: Some(said.to_string()).filter(|said| !said.is_empty()),
+            role: Role::Assistant,
+            tool_calls: Some(calls.0.clone()),
+            function_call: None,
+        },
+    )];
+    tools::called(&calls.0);
+    for call in calls.0 {
+        let started = Instant::now();
+        let (ran, language, output) = call_tool(&call.function).await;
+        let output = tools::ran(
+            &call.id,
+            &call.function.name,
+            (&ran, language),
+            &output,
+            started.elapsed(),
+        );
+        let blocks = [tools::block(&call.id).unwrap_or_default()];
+        sinks
+            .push(Event::Tools {
+                blocks: &blocks,
+                paragraph: false,
+            })
+            .await;
+        ret.push(ChatCompletionRequestMessage::Tool(
+            ChatCompletionRequestToolMessage {
+                role: Role::Tool,
+                content: Some(output),
+                tool_call_id: call.id,
+            },
+        ));
+    }
+    ret
+}
+
+/// Run the tool `call` is of, if it's one and it may be. Returns what it ran, in the language of
+/// its code block, and what it output, or why it didn't, for the model.
+async fn call_tool(call: &FunctionCall) -> (String, &'static str, String) {
+    let name = call.name.as_str();
+    let Some(tool) = CONFIGURATION.tools.custom.get(name) else {
+        return (String::new(), "sh", tr!("tool-unknown", tool = name));
+    };
+    let language = if tool.api.is_empty() { "sh" } else { "http" };
+    let arguments = match serde_json::from_str::<Value>(&call.arguments) {
+        Ok(Value::Object(arguments)) => arguments,
+        // As models call a tool that takes nothing.
+        _ if call.arguments.trim().is_empty() => Default::default(),
+        Ok(_) => {
+            let error = String::from("not an object");
+            return (
+                String::new(),
+                language,
+                tr!("tool-bad-arguments", tool = name, error = error),
+            );
+        }
+        Err(e) => {
+            let error = e.to_string();
+            return (
+                String::new(),
+                language,
+                tr!("tool-bad-arguments", tool = name, error = error),
+            );
+        }
+    };
+    let (ran, output) = if tool.api.is_empty() {
+        command(name, tool, &arguments).await
+    } else {
+        request(name, tool, &arguments).await
+    };
+    (ran, language, output)
+}
+
+/// `template` with each `{name}` replaced by the parameter of that name of `tool`, as `arguments`
+/// have it, or by nothing, in the form `escape` puts it in.
+fn fill(
+    template: &str,
+    tool: &CustomTool,
+    arguments: &Map<String, Value>,
+    escape: fn(&str) -> String,
+) -> String {
+    let schema = parameters(tool);
+    let mut values = schema["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(key, _)| (key.as_str(), escape("")))
+        .collect::<Vec<_>>();
+    for (key, value) in arguments {
+        let value = escape(&text(value));
+        match values.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => values.push((key.as_str(), value)),
+        }
+    }
+    template::render(template, &values, false)
+}
+
+/// An argument as text: a string as it is, and anything else as JSON.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Run the command of `tool`, as `policy` says. Returns it and what it output.
+async fn command(
+    name: &str,
+    tool: &CustomTool,
+    arguments: &Map<String, Value>,
+) -> (String, String) {
+    let command = fill(&tool.command, tool, arguments, quote);
+    let question = tr!(
+        "policy-ask-command",
+        tool = name,
+        command = command.as_str()
+    );
+    if !policy::permit(name, &command, &question).await {
+        return (command, tr!("tool-declined", tool = name));
+    }
+    let output = execute(name, &command)
+        .await
+        .unwrap_or_else(|e| e.to_string());
+    (command, output)
+}
+
+/// Run `command` of the tool `name` in `tools.workdir`, if there's one, for as long as
+/// `tools.timeouts.custom` lets it. Returns what it output, and how it exited if it failed.
+async fn execute(name: &str, command: &str) -> TokioResult<String> {
+    let config = &CONFIGURATION.tools;
+    let mut sh = Command::new("sh");
+    sh.arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if !config.workdir.as_os_str().is_empty() {
+        sh.current_dir(&config.workdir);
+    }
+    let mut child = sh
+        .spawn()
+        .map_err(|e| tr!("run-failed", program = "sh", error = e.to_string()))?;
+    let (Some(mut stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        unreachable!("both are piped")
+    };
+    // Both at once, as a terminal would show them, and only as much as is kept, while it runs.
+    let run = async move {
+        let mut output = Capped::default();
+        let (mut out, mut err) = ([0; 8192], [0; 8192]);
+        let (mut out_open, mut err_open) = (true, true);
+        while out_open || err_open {
+            tokio::select! {
+                read = stdout.read(&mut out), if out_open => match read? {
+                    0 => out_open = false,
+                    n => output.push(&out[..n]),
+                },
+                read = stderr.read(&mut err), if err_open => match read? {
+                    0 => err_open = false,
+                    n => output.push(&err[..n]),
+                },
+            }
+        }
+        Ok::<_, io::Error>((child.wait().await?, output))
+    };
+    let (status, output) = limited(name, run).await?;
+    let mut ret = output.text();
+    if !status.success() {
+        let status = status
+            .code()
+            .map_or_else(|| status.to_string(), |code| code.to_string());
+        ret = format!(
+            "{}\n{}",
+            ret.trim_end(),
+            tr!("tool-failed", status = status.as_str())
+        );
+    }
+    Ok(ret)
+}
+
+/// Make the request of `tool` to its API of `[tools.apis]`, with its headers, as `policy` says.
+/// Returns it, as its method, URL and body, and what the API answered.
+async fn request(
+    name: &str,
+    tool: &CustomTool,
+    arguments: &Map<String, Value>,
+) -> (String, String) {
+    let Some(api) = CONFIGURATION.tools.apis.get(&tool.api) else {
+        return (String::new(), tr!("tool-unknown", tool = name));
+    };
+    let path = fill(&tool.path, tool, arguments, encode);
+    let query = arguments
+        .iter()
+        .filter(|(key, _)| *key != "body" && !tool.path.contains(&format!("{{{key}}}")))
+        .map(|(key, value)| (key.as_str(), text(value)))
+        .collect::<Vec<_>>();
+    let method = Method::from_str(&tool.method.to_uppercase()).unwrap_or(Method::GET);
+    let url = format!("{}{path}", api.base_url.trim_end_matches('/'));
+    let mut builder = reqwest::Client::new()
+        .request(method.clone(), url)
+        .query(&query);
+    // Those left empty aren't filled in yet.
+    for (header, value) in api.headers.iter().filter(|(_, value)| !value.is_empty()) {
+        builder = builder.header(header, value);
+    }
+    let body = arguments.get("body");
+    if let Some(body) = body {
+        builder = builder.json(body);
+    }
+    let request = match builder.build() {
+        Ok(request) => request,
+        Err(e) => return (String::new(), e.to_string()),
+    };
+    let mut ran = format!("{method} {}", request.url());
+    if let Some(body) = body {
+        ran += &format!(
+            "\n{}",
+            serde_json::to_string_pretty(body).unwrap_or_default()
+        );
+    }
+    let question = tr!("policy-ask-command", tool = name, command = ran.as_str());
+    if !policy::permit(name, &ran, &question).await {
+        return (ran, tr!("tool-declined", tool = name));
+    }
+    let output = send(name, request).await.unwrap_or_else(|e| e.to_string());
+    (ran, output)
+}
+
+/// Send `request` of the tool `name`, for as long as `tools.timeouts.custom` lets it. Returns
+/// what the API answered, and its status if it's not a success.
+async fn send(name: &str, request: reqwest::Request) -> TokioResult<String> {
+    let mut response = limited(name, reqwest::Client::new().execute(request)).await?;
+    let status = response.status();
+    let mut body = Capped::default();
+    while let Some(chunk) = response.chunk().await? {
+        body.push(&chunk);
+    }
+    let text = body.text();
+    if status.is_success() {
+        return Ok(text);
+    }
+    Ok(format!(
+        "{}\n{}",
+        text.trim_end(),
+        tr!("tool-http-status", status = status.to_string())
+    ))
+}
+
+/// `run`, the tool `name` running, given up on after `tools.timeouts.custom`, if it's not 0.
+async fn limited<T, E>(name: &str, run: impl Future<Output = Result<T, E>>) -> TokioResult<T>
+where
+    E: Error + Send + Sync + 'static,
+{
+    match CONFIGURATION.tools.timeouts.of("custom") {
+        Some(limit) => Ok(timeout(limit, run)
+            .await
+            .map_err(|_| tr!("tool-timed-out", tool = name, secs = limit.as_secs()))??),
+        None => Ok(run.await?),
+    }
+}
+
+/// `s` as a segment of a URL's path.
+fn encode(s: &str) -> String {
+    utf8_percent_encode(s, NON_ALPHANUMERIC).to_string()
+}
+
+/// `s` as one word for `sh`.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
@@ -0,0 +1,146 @@
+//! The text of documents that aren't text, for `/attach`: PDFs, and Word's `.docx`. Each page
+//! starts with a marker like `[Page 3]`, for the model to say on which page something is. A
+//! `.docx` has no pages of its own, so its pages are where Word last broke them when it saved the
+//! file, and where it was told to. Of a web page, the text without its markup, see [`html`].
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use regex::Regex;
+use zip::ZipArchive;
+
+use std::io::{Cursor, Read as _};
+use std::panic;
+use std::path::Path;
+
+/// The text of `bytes`, read from `path`, if it's a document this can read. `None` if it isn't,
+/// to be read as text.
+pub fn text(path: &Path, bytes: &[u8]) -> Option<Result<String, String>> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "pdf" => Some(pdf(bytes)),
+        "docx" => Some(docx(bytes)),
+        _ => None,
+    }
+}
+
+fn pdf(bytes: &[u8]) -> Result<String, String> {
+    // It panics on some of what's out there rather than fail.
+    let pages = panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| String::from("not a PDF that can be read"))?
+        .map_err(|e| e.to_string())?;
+    Ok(with_markers(pages.iter().map(|page| page.trim())))
+}
+
+fn docx(bytes: &[u8]) -> Result<String, String> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|e| e.to_string())?
+        .read_to_string(&mut xml)
+        .map_err(|e| e.to_string())?;
+
+    let mut reader = Reader::from_str(&xml);
+    let mut pages = vec![String::new()];
+    let mut in_text = false;
+    loop {
+        let event = reader.read_event().map_err(|e| e.to_string())?;
+        let page = pages.last_mut().expect("there's always a page");
+        match event {
+            Event::Start(e) if e.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(e) if e.name().as_ref() == b"w:t" => in_text = false,
+            Event::Text(e) if in_text => page.push_str(&e.unescape().map_err(|e| e.to_string())?),
+            Event::End(e) if e.name().as_ref() == b"w:p" => page.push('\n'),
+            Event::Empty(e) => match e.name().as_ref() {
+                b"w:tab" => page.push('\t'),
+                b"w:br" | b"w:cr" if !is_page_break(&e) => page.push('\n'),
+                // Word marks where it broke a page after one it was told to break, too.
+                b"w:br" | b"w:lastRenderedPageBreak" if !page.trim().is_empty() => {
+                    pages.push(String::new())
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(with_markers(pages.iter().map(|page| page.trim())))
+}
+
+fn is_page_break(br: &BytesStart<'_>) -> bool {
+    br.try_get_attribute("w:type")
+        .ok()
+        .flatten()
+        .is_some_and(|t| t.value.as_ref() == b"page")
+}
+
+/// `pages`, each after its marker.
+fn with_markers<'a>(pages: impl Iterator<Item = &'a str>) -> String {
+    pages
+        .enumerate()
+        .map(|(i, page)| format!("{}\n{page}", tr!("extract-page", page = i + 1)))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// The text of the web page `html`, without its scripts, styles and tags, a paragraph where a
+/// block of it ends.
+pub fn html(html: &str) -> String {
+    lazy_static! {
+        static ref HIDDEN: Regex =
+            Regex::new(r"(?is)<(script|style|noscript|template|svg)\b.*?</\s*(script|style|noscript|template|svg)\s*>|<!--.*?-->").unwrap();
+        static ref BLOCK_END: Regex =
+            Regex::new(r"(?i)</\s*(p|div|section|article|header|footer|li|tr|h[1-6]|pre|blockquote|table|ul|ol)\s*>|<br\s*/?>").unwrap();
+        static ref TAG: Regex = Regex::new(r"(?s)<[^>]*>").unwrap();
+        static ref SPACES: Regex = Regex::new(r"[ \t\r\f]+").unwrap();
+        static ref PARAGRAPHS: Regex = Regex::new(r"\n\s*\n\s*").unwrap();
+        static ref ENTITY: Regex = Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap();
+    }
+    let text = HIDDEN.replace_all(html, "");
+    let text = BLOCK_END.replace_all(&text, "\n\n");
+    let text = TAG.replace_all(&text, "");
+    let text = ENTITY.replace_all(&text, |captures: &regex::Captures<'_>| {
+        let entity = &captures[1];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity
+                    .strip_prefix('#')
+                    .and_then(|n| n.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+        // Those it doesn't know are left as they are.
+        c.map_or_else(|| captures[0].to_string(), String::from)
+    });
+    let text = SPACES.replace_all(&text, " ");
+    let text = text.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+    PARAGRAPHS.replace_all(text.trim(), "\n\n").into_owned()
+}
@@ -0,0 +1,103 @@
+//! `ata2 --fim`: fill in the middle. Reads text with a `<CURSOR>` marker from stdin, sends what's
+//! before it as the prompt and what's after it as the suffix to a completions model, and streams
+//! only what goes in between to stdout, for editors to insert.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::CreateCompletionRequestArgs;
+
+use std::io::{self};
+
+use crate::cache;
+use crate::config::Config;
+use crate::openai;
+use crate::output;
+use crate::sink::{self, Event, Plain, Sinks};
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// Where the completion goes.
+const CURSOR: &str = "<CURSOR>";
+
+pub async fn run() -> TokioResult<()> {
+    let config: &Config = &CONFIGURATION;
+    let input = io::read_to_string(io::stdin())?;
+    let (prefix, suffix) = match input.split(CURSOR).collect::<Vec<_>>()[..] {
+        [prefix, suffix] => (prefix, Some(suffix)),
+        [prefix] => (prefix, config.suffix.as_deref()),
+        _ => return Err(tr!("fim-markers", marker = CURSOR).into()),
+    };
+    let mut sinks = Sinks::new(vec![Box::new(Plain::stdout())]);
+    complete(prefix, suffix, &mut sinks).await?;
+    end()
+}
+
+/// What goes between `prefix` and `suffix`, from `fim_model`, told to `sinks` as it comes.
+pub async fn complete(
+    prefix: &str,
+    suffix: Option<&str>,
+    sinks: &mut Sinks,
+) -> TokioResult<String> {
+    let config: &Config = &CONFIGURATION;
+    let mut request = CreateCompletionRequestArgs::default();
+    request
+        .model(&config.fim_model)
+        .prompt(prefix)
+        .n(1)
+        .max_tokens(config.max_tokens as u16)
+        .temperature(config.temperature as f32)
+        .top_p(config.top_p as f32)
+        .presence_penalty(config.presence_penalty as f32)
+        .frequency_penalty(config.frequency_penalty as f32);
+    if let Some(suffix) = suffix.filter(|suffix| !suffix.is_empty()) {
+        request.suffix(suffix);
+    }
+    if !config.stop.is_empty() {
+        request.stop(config.stop.clone());
+    }
+    if let Some(user_id) = &config.user_id {
+        request.user(user_id);
+    }
+
+    let request = request.build()?;
+    if let Some(middle) = cache::get("/completions", &request) {
+        sinks.push(Event::Text(&middle)).await;
+        sinks.push(Event::Finished).await;
+        return Ok(middle);
+    }
+    let stream = openai::completion_stream(request.clone()).await?;
+    let middle = sink::read_completion(stream, &config.fim_model, sinks).await?;
+    cache::put("/completions", &request, &middle);
+    let model = &config.fim_model;
+    usage::record_tokens(
+        model,
+        usage::count_tokens(model, prefix) + suffix.map_or(0, |s| usage::count_tokens(model, s)),
+        usage::count_tokens(model, &middle),
+    );
+    Ok(middle)
+}
+
+/// Nothing after the middle for an editor to strip, but the shell's prompt on a line of its own.
+fn end() -> TokioResult<()> {
+    if atty::is(atty::Stream::Stdout) {
+        output::print_and_flush("\n");
+    }
+    Ok(())
+}
@@ -0,0 +1,241 @@
+//! `ata2 search` and `/search`: the saved conversations that have all of some words, and with
+//! `storage = "sqlite"` the prompts of the history too. `/resume n` and `ata2 search … --resume n`
+//! continue conversation n of what was found.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use chrono::TimeZone as _;
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::commands;
+use crate::readline::{self, chat_completion_message_text, read_conversation};
+use crate::sessions;
+use crate::store::{self, Found};
+use crate::style::Style;
+use crate::width;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// How much of a conversation is shown where it matches, in columns.
+const EXCERPT_COLUMNS: usize = 100;
+/// How many characters of it come before the match.
+const EXCERPT_BEFORE: usize = 30;
+
+lazy_static! {
+    /// The conversations the last search found, as numbered.
+    static ref FOUND: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
+}
+
+/// The conversations, newest first, and prompts that have all of the words of `query`. Those in
+/// the database come first, best matches first.
+pub fn search(query: &[String]) -> TokioResult<Vec<Found>> {
+    let words = query
+        .iter()
+        .flat_map(|words| words.split_whitespace())
+        .collect::<Vec<_>>();
+    let mut ret = if store::enabled() {
+        store::search(&words)?
+    } else {
+        vec![]
+    };
+    // All of them without the database, and those saved before it otherwise.
+    for path in readline::saved_conversations_in(&config.ui.save_dir) {
+        if ret.iter().any(|found| found.path.as_ref() == Some(&path)) {
+            continue;
+        }
+        let Ok(conversation) = read_conversation(&path) else {
+            continue;
+        };
+        let text = conversation
+            .iter()
+            .filter_map(chat_completion_message_text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let Some(snippet) = excerpt(&text, &words) else {
+            continue;
+        };
+        let time = sessions::saved_at(&path).unwrap_or(0);
+        ret.push(Found {
+            path: Some(path),
+            profile: String::new(),
+            time,
+            snippet,
+        });
+    }
+    Ok(ret)
+}
+
+/// `ata2 search`.
+pub fn print(query: &[String]) -> TokioResult<()> {
+    let found = search(query)?;
+    if found.is_empty() {
+        eprintln!("{}", tr!("search-no-results"));
+    }
+    print!("{}", list(&found, atty::Stream::Stdout));
+    Ok(())
+}
+
+/// `ata2 search … --resume n`: continue conversation `n` of what `query` finds.
+pub async fn resume_found(query: &[String], n: usize) -> TokioResult<PathBuf> {
+    *FOUND.lock().unwrap() = search(query)?
+        .into_iter()
+        .filter_map(|found| found.path)
+        .collect();
+    resume_nth(&n.to_string()).await
+}
+
+/// `/search <words>`.
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    if args.is_empty() {
+        return Err(commands::usage("search"));
+    }
+    let found = search(&args)?;
+    if found.is_empty() {
+        eprintln!("{}", tr!("search-no-results"));
+    }
+    eprint!("{}", list(&found, atty::Stream::Stderr));
+    if found.iter().any(|found| found.path.is_some()) {
+        eprintln!("{}", tr!("search-resume-hint"));
+    }
+    Ok(())
+}
+
+/// `/resume [n]`: continue conversation `n` of the last search, or the newest one.
+pub async fn resume(args: Vec<String>) -> TokioResult<()> {
+    let path = match args.as_slice() {
+        [] => readline::resume_last().await?,
+        [n] => resume_nth(n).await?,
+        _ => return Err(commands::usage("resume")),
+    };
+    eprintln!(
+        "{}",
+        tr!("conversation-resumed", path = path.display().to_string())
+    );
+    Ok(())
+}
+
+async fn resume_nth(n: &str) -> TokioResult<PathBuf> {
+    let path = {
+        let found = FOUND.lock().unwrap();
+        n.parse::<usize>()
+            .ok()
+            .and_then(|n| found.get(n.checked_sub(1)?))
+            .cloned()
+            .ok_or_else(|| tr!("search-no-such", n = n, count = found.len()))?
+    };
+    readline::resume(&path).await?;
+    Ok(path)
+}
+
+/// `found` as shown, the conversations numbered for [`resume`], which it remembers them for.
+fn list(found: &[Found], stream: atty::Stream) -> String {
+    let bold = Style::parse("bold").unwrap_or_default();
+    let mut numbered = FOUND.lock().unwrap();
+    numbered.clear();
+    let mut ret = String::new();
+    for found in found {
+        let mut heading = match &found.path {
+            Some(path) => {
+                numbered.push(path.clone());
+                let id = path.file_stem().unwrap_or(path.as_os_str());
+                format!("{}. {}", numbered.len(), id.to_string_lossy())
+            }
+            None => tr!("search-prompt", profile = found.profile.as_str()),
+        };
+        // 0 for the prompts of a history file that didn't have times yet.
+        if let Some(time) = chrono::Local.timestamp_opt(found.time, 0).single() {
+            if found.time != 0 {
+                heading += &time.format(" · %Y-%m-%d %H:%M").to_string();
+            }
+        }
+        ret += &format!("{}\n", bold.paint_to(stream, &heading));
+        let snippet = found
+            .snippet
+            .split('\x01')
+            .enumerate()
+            .map(|(i, part)| match (i, part.split_once('\x02')) {
+                (0, _) | (_, None) => part.to_string(),
+                (_, Some((matched, rest))) => bold.paint_to(stream, matched) + rest,
+            })
+            .collect::<String>();
+        ret += &format!(
+            "    {}\n",
+            snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+    }
+    ret
+}
+
+/// If `text` has all of `words`, whatever their case, the first line with one of them, shortened
+/// around it, with the words marked like an FTS5 snippet.
+fn excerpt(text: &str, words: &[&str]) -> Option<String> {
+    let lowercase = text.to_lowercase();
+    let words = words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>();
+    if !words.iter().all(|word| lowercase.contains(word.as_str())) {
+        return None;
+    }
+    let (line, first) = text.lines().find_map(|line| {
+        let line = line.trim();
+        let first = line
+            .char_indices()
+            .find(|&(i, _)| words.iter().any(|word| match_at(line, i, word).is_some()))?;
+        Some((line, first.0))
+    })?;
+    let start = line[..first]
+        .char_indices()
+        .rev()
+        .nth(EXCERPT_BEFORE)
+        .map_or(0, |(i, _)| i);
+    let shown = width::truncate(&line[start..], EXCERPT_COLUMNS);
+    let mut ret = String::from(if start > 0 { "…" } else { "" });
+    let mut i = 0;
+    while i < shown.len() {
+        match words.iter().find_map(|word| match_at(shown, i, word)) {
+            Some(end) => {
+                ret += &format!("\x01{}\x02", &shown[i..end]);
+                i = end;
+            }
+            None => {
+                let c = shown[i..].chars().next().unwrap();
+                ret.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    if start + shown.len() < line.len() {
+        ret.push('…');
+    }
+    Some(ret)
+}
+
+/// Where `word`, lowercase, ends if `s` has it at `i`, whatever its case.
+fn match_at(s: &str, i: usize, word: &str) -> Option<usize> {
+    let mut lowercase = String::new();
+    let mut end = i;
+    for c in s[i..].chars() {
+        if lowercase.len() >= word.len() {
+            break;
+        }
+        lowercase.extend(c.to_lowercase());
+        end += c.len_utf8();
+    }
+    (!word.is_empty() && lowercase == word).then_some(end)
+}
@@ -0,0 +1,84 @@
+//! `ui.forecast`: what a prompt's request will be, estimated before it's sent, as in “this
+//! request ≈ 6.2k prompt tokens (~$0.03)”. Unlike `ui.input_tokens`, which counts what's typed,
+//! it counts all that's sent: the conversation before it, the system message, what's attached
+//! and the prompt, with the tokenizer and price of the model it goes to. Above
+//! `ui.confirm_above_cost`, the user's asked whether to send it.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::ChatCompletionRequestMessage;
+
+use crate::attach;
+use crate::language;
+use crate::overrides;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::router;
+use crate::usage;
+use crate::CONFIGURATION;
+
+/// A request, as estimated before it's sent.
+pub struct Forecast {
+    pub prompt_tokens: usize,
+    /// USD of the prompt tokens, for a model whose price is known.
+    pub cost: Option<f64>,
+}
+
+impl Forecast {
+    /// What `line`, sent after `conversation`, makes the request. `None` for a line with
+    /// overrides that won't be sent as they are.
+    pub fn of(line: &str, conversation: &[ChatCompletionRequestMessage]) -> Option<Self> {
+        let attached = attach::any();
+        let prompt = attach::peek(line.to_string());
+        let (overrides, text) = overrides::parse(&prompt).ok()?;
+        let model = router::model_for(&overrides, text, attached);
+        let mut messages = conversation.to_vec();
+        messages.push(string_to_chat_completion_request_user_message(
+            text.to_string(),
+        ));
+        let prompt_tokens = usage::conversation_tokens(&model, &language::instructed(&messages));
+        let cost = usage::model_info(&model)
+            .map(|info| prompt_tokens as f64 * info.input_price / 1_000_000.0);
+        Some(Self {
+            prompt_tokens,
+            cost,
+        })
+    }
+
+    /// “this request ≈ 6.2k prompt tokens (~$0.03)”
+    pub fn text(&self) -> String {
+        let tokens = match self.prompt_tokens {
+            n if n < 1000 => n.to_string(),
+            n => format!("{:.1}k", n as f64 / 1000.0),
+        };
+        match self.cost {
+            Some(cost) if cost < 0.01 => {
+                tr!("forecast", tokens = tokens, cost = format!("{cost:.4}"))
+            }
+            Some(cost) => tr!("forecast", tokens = tokens, cost = format!("{cost:.2}")),
+            None => tr!("forecast-unpriced", tokens = tokens),
+        }
+    }
+
+    /// Whether to ask before it's sent, as it's estimated to cost more than
+    /// `ui.confirm_above_cost`.
+    pub fn too_costly(&self) -> bool {
+        let limit = CONFIGURATION.ui.confirm_above_cost;
+        limit > 0.0 && self.cost.is_some_and(|cost| cost > limit)
+    }
+}
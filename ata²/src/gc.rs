@@ -0,0 +1,139 @@
+//! `ata2 gc`: delete the saved conversations and prompts of the history that `ui.retention_days`
+//! and `ui.max_sessions` don't keep, the expired responses of the cache, and the temporary files
+//! of saves, the cache and `open_editor` that were left behind by a crash. `--dry-run` only lists
+//! them.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::cache;
+use crate::history;
+use crate::readline::{self, read_conversation};
+use crate::sessions;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// How old a temporary file has to be to be taken for left behind, rather than still in use by
+/// another ata².
+const LEFTOVER_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub fn run(dry_run: bool) -> TokioResult<()> {
+    let cutoff = (config.ui.retention_days > 0)
+        .then(|| chrono::Utc::now().timestamp() - config.ui.retention_days as i64 * 24 * 60 * 60);
+    let mut any = false;
+
+    // Only what really is a conversation: with the default `save_dir`, that's the current
+    // directory, where other JSON could be.
+    let mut conversations = readline::saved_conversations()
+        .into_iter()
+        .filter(|path| read_conversation(path).is_ok())
+        .map(|path| (sessions::saved_at(&path).unwrap_or(0), path))
+        .collect::<Vec<_>>();
+    conversations.sort_unstable_by(|a, b| b.cmp(a));
+    for (i, (saved, path)) in conversations.iter().enumerate() {
+        let expired = cutoff.is_some_and(|cutoff| *saved < cutoff);
+        let excess = config.ui.max_sessions > 0 && i >= config.ui.max_sessions;
+        if expired || excess {
+            delete(path, dry_run, sessions::delete)?;
+            any = true;
+        }
+    }
+
+    // Prompts without a time, from before there were any, are kept.
+    if let Some(cutoff) = cutoff {
+        let mut entries = history::load(&config.ui.history_file)?;
+        let len = entries.len();
+        entries.retain(|entry| entry.time == 0 || entry.time >= cutoff);
+        let count = len - entries.len();
+        if count > 0 {
+            let path = history::location().display().to_string();
+            if dry_run {
+                println!("{}", tr!("gc-would-drop", count = count, path = path));
+            } else {
+                history::replace(&config.ui.history_file, &entries)?;
+                println!("{}", tr!("gc-history", count = count, path = path));
+            }
+            any = true;
+        }
+    }
+
+    for path in cache::expired_entries().into_iter().chain(leftovers()) {
+        delete(&path, dry_run, |path| Ok(fs::remove_file(path)?))?;
+        any = true;
+    }
+
+    if !any {
+        println!("{}", tr!("gc-nothing"));
+    }
+    Ok(())
+}
+
+fn delete(
+    path: &Path,
+    dry_run: bool,
+    remove: impl FnOnce(&Path) -> TokioResult<()>,
+) -> TokioResult<()> {
+    let shown = path.display().to_string();
+    if dry_run {
+        println!("{}", tr!("gc-would-delete", path = shown));
+    } else {
+        remove(path)?;
+        println!("{}", tr!("gc-deleted", path = shown));
+    }
+    Ok(())
+}
+
+/// The `.json.tmp` files of saves in `ui.save_dir`, the `.tmp` files of `cache.dir` and the
+/// `ata2-<pid>.md` files of `open_editor` in the temporary directory, older than [`LEFTOVER_AGE`].
+fn leftovers() -> Vec<PathBuf> {
+    let mut ret = old_files(&config.ui.save_dir, |name| name.ends_with(".json.tmp"));
+    ret.extend(old_files(&config.cache.dir, |name| name.ends_with(".tmp")));
+    ret.extend(old_files(&env::temp_dir(), |name| {
+        name.strip_prefix("ata2-")
+            .and_then(|name| name.strip_suffix(".md"))
+            .is_some_and(|pid| pid.parse::<u32>().is_ok())
+    }));
+    ret
+}
+
+/// The files in `dir` older than [`LEFTOVER_AGE`] with a name that `matches`.
+fn old_files(dir: &Path, matches: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut ret = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| matches(&entry.file_name().to_string_lossy()))
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age > LEFTOVER_AGE)
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    ret.sort_unstable();
+    ret
+}
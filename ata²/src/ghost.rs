@@ -0,0 +1,173 @@
+//! Ghost text: model-powered suggestions for the rest of the prompt being typed, shown dimmed after
+//! the cursor like a shell's autosuggestions (`ui.ghost_text`).
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    CreateChatCompletionRequestArgs, Role,
+};
+use rustyline::hint::Hinter;
+use rustyline::{Cmd, ConditionalEventHandler, Context, EventContext, RepeatCount};
+use terminal_size::{terminal_size, Width};
+use tokio::task::JoinHandle;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::openai;
+use crate::output::print_and_flush;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::style;
+use crate::width;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+const INSTRUCTIONS: &str = "You autocomplete what the user is typing into a chat with an AI \
+    assistant. Reply with only the text that should follow what they typed so far, without \
+    repeating any of it, and nothing else. Keep it short: finish the sentence at most.";
+const MAX_TOKENS: u16 = 32;
+
+/// Part of [`crate::helper::ReplHelper`].
+#[derive(Default)]
+pub struct GhostText {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The line as last seen, which a suggestion on its way is for.
+    line: String,
+    /// A line and the suggestion for it. Typing what was suggested keeps the rest of it.
+    suggestion: Option<(String, String)>,
+    /// Waiting for the typing pause to end, then for the model.
+    pending: Option<JoinHandle<()>>,
+}
+
+impl Hinter for GhostText {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if !CONFIGURATION.ui.ghost_text {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some((base, suggestion)) = &state.suggestion {
+            let full = format!("{base}{suggestion}");
+            if pos == line.len() && line.starts_with(base.as_str()) && full.starts_with(line) {
+                let rest = &full[line.len()..];
+                return (!rest.is_empty()).then(|| rest.to_string());
+            }
+        }
+        if line == state.line {
+            return None;
+        }
+        state.line = line.to_string();
+        state.suggestion = None;
+        if let Some(pending) = state.pending.take() {
+            pending.abort();
+        }
+        if pos == line.len() && !line.trim().is_empty() {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                let state_ref = self.state.clone();
+                let line = line.to_string();
+                state.pending = Some(runtime.spawn(suggest(state_ref, line)));
+            }
+        }
+        None
+    }
+}
+
+/// Once the user stopped typing for `ui.ghost_text_delay` milliseconds, ask for a suggestion.
+async fn suggest(state: Arc<Mutex<State>>, line: String) {
+    tokio::time::sleep(Duration::from_millis(CONFIGURATION.ui.ghost_text_delay)).await;
+    let suggestion = match complete(&line).await {
+        Ok(suggestion) if !suggestion.is_empty() => suggestion,
+        Ok(_) => return,
+        Err(e) => {
+            debug!("No ghost text: {e}");
+            return;
+        }
+    };
+    let mut state = state.lock().unwrap();
+    if state.line != line {
+        return;
+    }
+    // rustyline only asks for hints when it redraws, which it does on the next key press. Until
+    // then, draw the suggestion after the cursor ourselves.
+    draw(&line, &suggestion);
+    state.suggestion = Some((line, suggestion));
+    state.pending = None;
+}
+
+async fn complete(line: &str) -> TokioResult<String> {
+    let config: &Config = &CONFIGURATION;
+    let messages = vec![
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            role: Role::System,
+            content: Some(INSTRUCTIONS.to_string()),
+        }),
+        string_to_chat_completion_request_user_message(line.to_string()),
+    ];
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(&config.ui.ghost_text_model)
+        .messages(messages)
+        .max_tokens(MAX_TOKENS)
+        .temperature(0.0)
+        .build()?;
+    let response = openai::chat(request).await?;
+    let suggestion = response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+    // Only the first line: the rest would be drawn over whatever is below the prompt.
+    Ok(suggestion.lines().next().unwrap_or_default().to_string())
+}
+
+/// Draw `suggestion` after `line`, which the cursor is at the end of, without moving the cursor.
+fn draw(line: &str, suggestion: &str) {
+    let Some((Width(cols), _)) = terminal_size() else {
+        return;
+    };
+    let cols = cols as usize;
+    let (_, column) = width::position(line, cols, 0);
+    let shown = width::truncate(suggestion, cols.saturating_sub(column + 1));
+    if !shown.is_empty() {
+        // Over the token count, if that's shown.
+        print_and_flush(&format!("\x1b7\x1b[K{}\x1b8", style::paint("dim", shown)));
+    }
+}
+
+/// Tab takes the suggestion, like Right does, when there is one.
+pub struct AcceptHandler;
+
+impl ConditionalEventHandler for AcceptHandler {
+    fn handle(
+        &self,
+        _event: &rustyline::Event,
+        _n: RepeatCount,
+        _positive: bool,
+        ctx: &EventContext,
+    ) -> Option<Cmd> {
+        ctx.has_hint().then_some(Cmd::CompleteHint)
+    }
+}
@@ -0,0 +1,268 @@
+//! `--grammar`: responses that follow a grammar, from a local server that constrains what it
+//! generates to one, like llama.cpp's. The grammar is in its GBNF, or derived from a JSON schema
+//! in a `.json` file: objects with their properties, arrays, strings, numbers, booleans, null,
+//! `enum`, `const` and `anyOf`. With a schema, it's also sent as the `response_format`, which is
+//! what Ollama goes by.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use serde_json::{json, Value};
+
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::TokioResult;
+use crate::FLAGS;
+
+/// The grammar of `--grammar`, read at the start.
+static GRAMMAR: OnceLock<Grammar> = OnceLock::new();
+
+/// The rules every grammar derived from a schema has, for the values in it.
+const VALUES: &str = r#"ws ::= [ \t\n]*
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] ) )* "\""
+integer ::= "-"? ( "0" | [1-9] [0-9]* )
+number ::= integer ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )?
+boolean ::= "true" | "false"
+null ::= "null"
+value ::= object | array | string | number | boolean | null
+object ::= "{" ws ( string ws ":" ws value ws ( "," ws string ws ":" ws value ws )* )? "}"
+array ::= "[" ws ( value ws ( "," ws value ws )* )? "]""#;
+
+pub struct Grammar {
+    pub gbnf: String,
+    /// The JSON schema it was derived from.
+    pub schema: Option<Value>,
+}
+
+/// Read the grammar of `--grammar`, if it's given.
+pub fn load() -> TokioResult<()> {
+    let Some(path) = &FLAGS.grammar else {
+        return Ok(());
+    };
+    let contents = fs::read_to_string(path).map_err(|e| {
+        tr!(
+            "grammar-unreadable",
+            path = path.display().to_string(),
+            error = e.to_string()
+        )
+    })?;
+    let grammar = if is_schema(path) {
+        let schema: Value = serde_json::from_str(&contents).map_err(|e| {
+            tr!(
+                "grammar-unreadable",
+                path = path.display().to_string(),
+                error = e.to_string()
+            )
+        })?;
+        Grammar {
+            gbnf: from_schema(&schema)?,
+            schema: Some(schema),
+        }
+    } else {
+        if !contents
+            .lines()
+            .any(|line| line.trim_start().starts_with("root"))
+        {
+            return Err(tr!("grammar-no-root", path = path.display().to_string()).into());
+        }
+        Grammar {
+            gbnf: contents,
+            schema: None,
+        }
+    };
+    let _ = GRAMMAR.set(grammar);
+    Ok(())
+}
+
+fn is_schema(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+/// Add the grammar of `--grammar` to the `body` of a request for a chat completion.
+pub fn apply(body: &mut Value) {
+    let Some(grammar) = GRAMMAR.get() else {
+        return;
+    };
+    body["grammar"] = grammar.gbnf.clone().into();
+    if let Some(schema) = &grammar.schema {
+        body["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": { "name": "response", "schema": schema },
+        });
+    }
+}
+
+/// The GBNF of `--grammar`, if it's given.
+#[cfg(feature = "local")]
+pub fn gbnf() -> Option<&'static str> {
+    GRAMMAR.get().map(|grammar| grammar.gbnf.as_str())
+}
+
+/// The GBNF of what `schema` allows.
+fn from_schema(schema: &Value) -> Result<String, String> {
+    let mut rules = vec![];
+    let root = rule(schema, &mut rules)?;
+    let mut ret = format!("root ::= {root}\n");
+    for (i, rule) in rules.iter().enumerate() {
+        ret += &format!("r{i} ::= {rule}\n");
+    }
+    ret += VALUES;
+    ret.push('\n');
+    Ok(ret)
+}
+
+/// What `schema` allows, as an expression of GBNF, with the rules it needs added to `rules`.
+fn rule(schema: &Value, rules: &mut Vec<String>) -> Result<String, String> {
+    let Some(schema) = schema.as_object() else {
+        // `true`, or a schema of anything.
+        return Ok(String::from("value"));
+    };
+    if let Some(keyword) = ["$ref", "allOf", "not", "if", "patternProperties"]
+        .into_iter()
+        .find(|keyword| schema.contains_key(*keyword))
+    {
+        return Err(tr!("grammar-unsupported", keyword = keyword));
+    }
+    if let Some(value) = schema.get("const") {
+        return Ok(literal(value));
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return Ok(group(values.iter().map(literal)));
+    }
+    if let Some(schemas) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        let alternatives = schemas
+            .iter()
+            .map(|schema| rule(schema, rules))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(group(alternatives.into_iter()));
+    }
+    match schema.get("type") {
+        Some(Value::Array(types)) => {
+            let alternatives = types
+                .iter()
+                .map(|t| {
+                    let mut schema = schema.clone();
+                    schema.insert(String::from("type"), t.clone());
+                    rule(&Value::Object(schema), rules)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(group(alternatives.into_iter()))
+        }
+        Some(Value::String(t)) => match t.as_str() {
+            "object" => object(schema, rules),
+            "array" => {
+                let item = match schema.get("items") {
+                    Some(items) => rule(items, rules)?,
+                    None => String::from("value"),
+                };
+                Ok(add(
+                    rules,
+                    format!(r#""[" ws ( {item} ws ( "," ws {item} ws )* )? "]""#),
+                ))
+            }
+            "string" | "number" | "integer" | "boolean" | "null" => Ok(t.clone()),
+            _ => Err(tr!("grammar-unsupported", keyword = format!("type: {t}"))),
+        },
+        _ => Ok(String::from("value")),
+    }
+}
+
+/// An object of `schema`: its required properties, and then any of the others, each in the order
+/// of their names.
+fn object(
+    schema: &serde_json::Map<String, Value>,
+    rules: &mut Vec<String>,
+) -> Result<String, String> {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Ok(String::from("object"));
+    };
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut members = vec![];
+    let mut optional = vec![];
+    for (name, property) in properties {
+        let member = format!(
+            r#"{} ws ":" ws {} ws"#,
+            literal(&Value::String(name.clone())),
+            rule(property, rules)?
+        );
+        if required.contains(&name.as_str()) {
+            members.push(member);
+        } else {
+            optional.push(member);
+        }
+    }
+    // `after[i]`: any of the optional ones from the i-th on, in order, each after a comma, with a
+    // space before it.
+    let mut after = vec![String::new(); optional.len() + 1];
+    for i in (0..optional.len()).rev() {
+        let alternatives = (i..optional.len())
+            .map(|j| format!(r#""," ws {}{}"#, optional[j], after[j + 1]))
+            .collect::<Vec<_>>();
+        after[i] = format!(
+            " {}",
+            add(rules, format!("( {} )?", alternatives.join(" | ")))
+        );
+    }
+    let body = if members.is_empty() && !optional.is_empty() {
+        let first = (0..optional.len())
+            .map(|j| format!("{}{}", optional[j], after[j + 1]))
+            .collect::<Vec<_>>();
+        format!("( {} )?", first.join(" | "))
+    } else {
+        format!("{}{}", members.join(r#" "," ws "#), after[0])
+    };
+    Ok(add(rules, format!(r#""{{" ws {body} "}}""#)))
+}
+
+/// The name of a new rule of `rules` that's `expression`.
+fn add(rules: &mut Vec<String>, expression: String) -> String {
+    rules.push(expression);
+    format!("r{}", rules.len() - 1)
+}
+
+/// `alternatives`, any one of them.
+fn group(alternatives: impl Iterator<Item = String>) -> String {
+    format!("( {} )", alternatives.collect::<Vec<_>>().join(" | "))
+}
+
+/// `value` as JSON, a literal of GBNF.
+fn literal(value: &Value) -> String {
+    let json = value.to_string();
+    format!(
+        "\"{}\"",
+        json.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
@@ -1,4 +1,5 @@
-//! Help messages for the command-line interface.
+//! Help messages for the command-line interface. `/help` and `--print-shortcuts` are made from
+//! [`COMMANDS`] and the keys as the configuration binds them, so they never leave any out.
 //!
 //! # ata²
 //!
@@ -20,17 +21,74 @@
 
 use rustyline::Editor;
 
-use crate::config;
+use crate::commands::COMMANDS;
+use crate::config::{self, Config, UiConfig};
+use crate::i18n;
+use crate::keys;
+use crate::width;
 use config::DEFAULT_CONFIG_FILENAME;
 use std::fs::{self, File};
 use std::io::Write as _;
 use std::process::exit;
 
-pub fn commands() {
-    println!(include_str!("help/keybindings.txt"));
+/// Where the descriptions of keys start, and where they wrap.
+const KEY_COLUMN: usize = 20;
+const COLUMNS: usize = 80;
+
+/// `--print-shortcuts`.
+pub fn print_shortcuts(config: &Config) -> ! {
+    print!("{}", text(&config.ui));
     exit(0);
 }
 
+/// The slash commands, the keys `ui` binds, and those of rustyline.
+pub fn text(ui: &UiConfig) -> String {
+    let mut ret = format!("{}\n", tr!("commands-heading"));
+    for command in COMMANDS {
+        let usage = format!("/{} {}", command.name, command.usage);
+        ret += &format!("{}\n", usage.trim_end());
+        let description = tr!(&format!("command-{}", command.name));
+        ret += &wrap(&description, 4, &" ".repeat(4));
+    }
+    ret += &format!("\n{}\n", tr!("shortcuts-heading"));
+    for (chord, id) in keys::bindings(ui) {
+        let first = format!("{chord:<KEY_COLUMN$}");
+        if width::width(&first) > KEY_COLUMN {
+            ret += &format!("{chord}\n");
+            ret += &wrap(&tr!(id), KEY_COLUMN, &" ".repeat(KEY_COLUMN));
+        } else {
+            ret += &wrap(&tr!(id), KEY_COLUMN, &first);
+        }
+    }
+    ret += "\n";
+    ret += include_str!("help/rustyline.txt");
+    ret
+}
+
+/// `text` on lines of at most [`COLUMNS`], the first starting with `first`, the others indented by
+/// `indent`.
+fn wrap(text: &str, indent: usize, first: &str) -> String {
+    let mut ret = first.to_string();
+    let mut column = width::width(first);
+    let mut start = true;
+    for word in text.split_whitespace() {
+        let word_width = width::width(word);
+        if !start && column + 1 + word_width > COLUMNS {
+            ret += &format!("\n{}", " ".repeat(indent));
+            column = indent;
+            start = true;
+        }
+        if !start {
+            ret.push(' ');
+            column += 1;
+        }
+        ret += word;
+        column += word_width;
+        start = false;
+    }
+    ret + "\n"
+}
+
 const EXAMPLE_TOML: &str = r#"api_key = "<YOUR SECRET API KEY>"
 model = "gpt-3.5-turbo"
 max_tokens = 2048
@@ -38,44 +96,21 @@ temperature = 0.8"#;
 
 pub fn missing_toml() {
     let default_path = config::default_path::<1>(None);
+    let path = default_path.display().to_string();
     eprintln!(
-        r#"
-Could not find the file `{1}`. To fix this, create {0}.
-
-For example, use the following content (the text between the ```):
-
-```
-{EXAMPLE_TOML}
-```
-
-Here, replace `<YOUR SECRET API KEY>` with your API key, which you can request via https://beta.openai.com/account/api-keys.
-
-The `max_tokens` sets the maximum amount of tokens that the server can answer with.
-Longer answers will be truncated.
-
-The `temperature` sets the `sampling temperature`. From the OpenAI API docs: "What sampling temperature to use. Higher values means the model will take more risks. Try 0.9 for more creative applications, and 0 (argmax sampling) for ones with a well-defined answer." According to Stephen Wolfram [1], setting it to a higher value such as 0.8 will likely work best in practice.
-
-
-[1]: https://writings.stephenwolfram.com/2023/02/what-is-chatgpt-doing-and-why-does-it-work/
-
-    "#,
-        (&default_path).display(),
-        DEFAULT_CONFIG_FILENAME.to_string_lossy()
+        "\n{}\n",
+        tr!(
+            "config-missing",
+            path = path.as_str(),
+            filename = DEFAULT_CONFIG_FILENAME.to_string_lossy(),
+            example = EXAMPLE_TOML,
+        )
     );
     let mut rl = Editor::<()>::new().unwrap();
-    eprintln!(
-        "Do you want me to write this example file to {0} for you to edit?",
-        (&default_path).display()
-    );
-    let readline = rl.readline("[y/N] ");
+    eprintln!("{}", tr!("config-write-example", path = path.as_str()));
+    let readline = rl.readline(&format!("{} ", tr!("yes-no")));
     if let Ok(msg) = readline {
-        if msg
-            .trim()
-            .chars()
-            .nth(0)
-            .map(|c| c.to_lowercase().collect::<String>() == "y")
-            .unwrap_or(false)
-        {
+        if i18n::is_yes(&msg) {
             if !default_path.exists() && !default_path.parent().unwrap().is_dir() {
                 fs::create_dir_all(&default_path).expect("Could not make configuration directory");
             }
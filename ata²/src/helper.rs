@@ -0,0 +1,295 @@
+//! The rustyline helper of the REPL: Tab completion of slash commands and their arguments, hints
+//! (see also [`crate::ghost`]) and highlighting.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{
+    Cmd, ConditionalEventHandler, Context, EditMode, EventContext, Helper, InputMode, KeyCode,
+    KeyEvent, Modifiers, RepeatCount,
+};
+use terminal_size::{terminal_size, Width};
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::commands::{self, Completion, COMMANDS};
+use crate::ghost::GhostText;
+use crate::markdown;
+use crate::models;
+use crate::output::print_and_flush;
+use crate::paste;
+use crate::style::{self, theme};
+use crate::usage;
+use crate::width;
+use crate::CONFIGURATION as config;
+
+const INSERT_INDICATOR: &str = "[I] ";
+const NORMAL_INDICATOR: &str = "[N] ";
+
+/// Is vi in normal mode? See [`ViModeHandler`].
+static VI_NORMAL_MODE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+pub struct ReplHelper {
+    ghost_text: GhostText,
+    filenames: FilenameCompleter,
+    /// Is a hint shown after the input? Then the token count isn't.
+    hinted: AtomicBool,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let Some(typed) = line[..pos].strip_prefix('/') else {
+            return Ok((pos, vec![]));
+        };
+        let Some((name, args)) = typed.split_once(char::is_whitespace) else {
+            let candidates = COMMANDS
+                .iter()
+                .filter(|c| c.name.starts_with(typed))
+                .map(|c| Pair {
+                    display: format!("/{}", c.name),
+                    replacement: format!("/{} ", c.name),
+                })
+                .collect();
+            return Ok((0, candidates));
+        };
+        let Some(command) = COMMANDS.iter().find(|c| c.name == name) else {
+            return Ok((pos, vec![]));
+        };
+        // Where the argument under the cursor starts, and how many come before it.
+        let word_start = line[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[word_start..pos];
+        let first = args.split_whitespace().count() <= usize::from(!word.is_empty());
+        match command.completion {
+            Completion::Words(words) if first => {
+                let candidates = words
+                    .iter()
+                    .filter(|w| w.starts_with(word))
+                    .map(|w| Pair {
+                        display: w.to_string(),
+                        replacement: format!("{w} "),
+                    })
+                    .collect();
+                Ok((word_start, candidates))
+            }
+            Completion::Models if first => {
+                let candidates = models::completions(&config.cache.dir, word)
+                    .into_iter()
+                    .map(|id| Pair {
+                        replacement: format!("{id} "),
+                        display: id,
+                    })
+                    .collect();
+                Ok((word_start, candidates))
+            }
+            Completion::Path => self.filenames.complete_path(line, pos),
+            _ => Ok((pos, vec![])),
+        }
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    /// The rest of the command's name once it's unambiguous, otherwise ghost text.
+    fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<String> {
+        paste::observe(line, pos);
+        let hint = self.command_hint(line, pos, ctx);
+        self.hinted.store(hint.is_some(), Ordering::Relaxed);
+        hint
+    }
+}
+
+impl ReplHelper {
+    fn command_hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<String> {
+        if let Some(typed) = line.strip_prefix('/') {
+            if pos < line.len() || typed.is_empty() || typed.contains(char::is_whitespace) {
+                return None;
+            }
+            let mut matching = COMMANDS.iter().filter(|c| c.name.starts_with(typed));
+            return match (matching.next(), matching.next()) {
+                (Some(command), None) if command.name != typed => {
+                    Some(command.name[typed.len()..].to_string())
+                }
+                _ => None,
+            };
+        }
+        self.ghost_text.hint(line, pos, ctx)
+    }
+}
+
+impl Highlighter for ReplHelper {
+    /// The prompt is empty, or the vi mode indicator from [`prompt`].
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
+        &'s self,
+        prompt: &'p str,
+        _default: bool,
+    ) -> Cow<'b, str> {
+        if prompt.is_empty() {
+            return Cow::Borrowed(prompt);
+        }
+        Cow::Owned(styled_indicator())
+    }
+
+    /// Show the name of a known command in bold. A prompt is in `ui.theme.user`, gets its
+    /// Markdown code colored, and the token count after it.
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if commands::is_command(line) {
+            let end = line.find(char::is_whitespace).unwrap_or(line.len());
+            return Cow::Owned(format!(
+                "{}{}",
+                style::paint("bold", &line[..end]),
+                &line[end..]
+            ));
+        }
+        let highlighted = if config.ui.highlight_input {
+            markdown::highlight(line)
+        } else {
+            line.to_string()
+        };
+        let mut highlighted = theme().user.paint(&highlighted);
+        if !self.hinted.load(Ordering::Relaxed) {
+            if let Some(count) = token_count(line) {
+                highlighted.push_str(&style::paint("dim", &count));
+            }
+        }
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(style::paint("dim", hint))
+    }
+
+    /// The token count changes with every key, and so may what's code.
+    fn highlight_char(&self, line: &str, _pos: usize) -> bool {
+        config.ui.input_tokens
+            || style::enabled(atty::Stream::Stderr)
+                && (line.starts_with('/')
+                    || config.ui.highlight_input
+                    || !config.ui.theme.user.trim().is_empty())
+    }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// The prompt of the line editor. Empty, so that nothing looks ready for input while a response
+/// is being printed, unless the vi mode is to be shown.
+pub fn prompt() -> &'static str {
+    VI_NORMAL_MODE.store(false, Ordering::Relaxed);
+    if vi_mode_indicator() {
+        INSERT_INDICATOR
+    } else {
+        ""
+    }
+}
+
+/// `ui.input_tokens`: how many tokens `line` is, to show after it. rustyline places the cursor
+/// by the input alone, which works as long as the count doesn't wrap to another row.
+fn token_count(line: &str) -> Option<String> {
+    if !config.ui.input_tokens || line.trim().is_empty() {
+        return None;
+    }
+    let (Width(cols), _) = terminal_size()?;
+    let cols = usize::from(cols).max(1);
+    let indent = if vi_mode_indicator() {
+        width::width(INSERT_INDICATOR)
+    } else {
+        0
+    };
+    let (_, column) = width::position(line, cols, indent);
+    // At the end of a full row, rustyline is about to start the next one.
+    if column == 0 && !line.ends_with('\n') {
+        return None;
+    }
+    let count = format!("  ~{}", usage::tokens_text(usage::input_tokens(line)));
+    (column + width::width(&count) < cols).then_some(count)
+}
+
+pub fn vi_mode_indicator() -> bool {
+    config.ui.vi_mode_indicator && crate::readline::edit_mode() == EditMode::Vi
+}
+
+fn styled_indicator() -> String {
+    let indicator = if VI_NORMAL_MODE.load(Ordering::Relaxed) {
+        NORMAL_INDICATOR
+    } else {
+        INSERT_INDICATOR
+    };
+    style::paint("dim", indicator)
+}
+
+/// Sees every key not bound to anything else, to keep track of vi's mode. rustyline has no way to
+/// tell when it changes, nor does it redraw the prompt when it does, so the indicator is redrawn
+/// here, before the key is handled.
+pub struct ViModeHandler;
+
+impl ConditionalEventHandler for ViModeHandler {
+    fn handle(
+        &self,
+        event: &rustyline::Event,
+        _n: RepeatCount,
+        _positive: bool,
+        ctx: &EventContext,
+    ) -> Option<Cmd> {
+        let KeyEvent(key, modifiers) = *event.get(0)?;
+        let normal = match (ctx.input_mode(), key) {
+            (InputMode::Command, KeyCode::Char('i' | 'I' | 'a' | 'A' | 's' | 'S' | 'c' | 'C'))
+                if !modifiers.contains(Modifiers::CTRL) =>
+            {
+                false
+            }
+            (InputMode::Command, _) => true,
+            (_, KeyCode::Esc) => true,
+            _ => false,
+        };
+        if VI_NORMAL_MODE.swap(normal, Ordering::Relaxed) != normal {
+            redraw_indicator(&ctx.line()[..ctx.pos()]);
+        }
+        None
+    }
+}
+
+/// Redraw the indicator at the start of the input, `before_cursor` being the input up to the
+/// cursor.
+fn redraw_indicator(before_cursor: &str) {
+    let Some((Width(cols), _)) = terminal_size() else {
+        return;
+    };
+    let cols = usize::from(cols).max(1);
+    let (rows, _) = width::position(before_cursor, cols, width::width(INSERT_INDICATOR));
+    let up = if rows > 0 {
+        format!("\x1b[{rows}A")
+    } else {
+        String::new()
+    };
+    print_and_flush(&format!("\x1b7{up}\r{}\x1b8", styled_indicator()));
+}
@@ -0,0 +1,391 @@
+//! The prompt history in `ui.history_file`: a JSON object per line, each prompt with the time it
+//! was sent. Files rustyline wrote, as ata² did before, are read too, their prompts without a time.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::lock;
+use crate::store;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+use crate::FLAGS;
+
+/// The first line of a file rustyline wrote with newlines escaped.
+const RUSTYLINE_V2: &str = "#V2";
+
+/// How much of the history file is read at a time, from its end, for its newest prompts.
+const BLOCK: u64 = 64 * 1024;
+
+/// Set when the history couldn't be loaded or its directory can't be written to, see [`keep`].
+static UNSAVED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Entry {
+    /// When the prompt was sent, in seconds since the Unix epoch. 0 if unknown.
+    #[serde(default)]
+    pub time: i64,
+    pub text: String,
+}
+
+/// `ui.history_file` for `profile`. The default profile's history was `history` before there was
+/// one per profile, which is renamed to its new name, as long as nothing has that name yet.
+///
+/// Called while the configuration is loaded, so not using it.
+pub fn file(template: &Path, profile: &str) -> PathBuf {
+    let ret = PathBuf::from(template.to_string_lossy().replace("{profile}", profile));
+    let old = ret.with_file_name("history");
+    if profile == "default"
+        && template
+            .file_name()
+            .is_some_and(|name| name == "history-{profile}")
+        && !ret.exists()
+        && old.is_file()
+    {
+        if let Err(e) = fs::rename(&old, &ret) {
+            warn!(
+                "{}",
+                tr!(
+                    "history-move-failed",
+                    from = old.display().to_string(),
+                    to = ret.display().to_string(),
+                    error = e.to_string()
+                )
+            );
+        }
+    }
+    ret
+}
+
+/// Don't save the history when ata² exits, as it couldn't be loaded, and what's there might be
+/// lost, or can't be saved anyway.
+pub fn give_up() {
+    UNSAVED.store(true, Ordering::Relaxed);
+}
+
+/// Whether the REPL saves its history when it exits: unless [`give_up`] was called.
+pub fn keep() -> bool {
+    !UNSAVED.load(Ordering::Relaxed)
+}
+
+/// Where the history is kept: `ui.history_file`, or the database.
+pub fn location() -> &'static Path {
+    if store::enabled() {
+        &config.ui.database_file
+    } else {
+        &config.ui.history_file
+    }
+}
+
+/// The history in `path`, or the database, oldest first. There's none yet if the file doesn't
+/// exist.
+pub fn load(path: &Path) -> TokioResult<Vec<Entry>> {
+    load_newest(path, 0)
+}
+
+/// The newest `max` prompts of the history in `path`, or the database, oldest first, or all of
+/// them if `max` is 0. The file is read from its end, only as far back as those prompts go.
+pub fn load_newest(path: &Path, max: usize) -> TokioResult<Vec<Entry>> {
+    if store::enabled() {
+        let entries = store::history(&FLAGS.config.profile(), max)?;
+        // Until it's first saved to the database, the history is still in the file.
+        if !entries.is_empty() {
+            return Ok(entries);
+        }
+    }
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let len = file.metadata()?.len();
+    Ok(read_newest(&mut file, len, max)?)
+}
+
+/// The newest `max` prompts of the history `file` of `len` bytes, or all of them if `max` is 0,
+/// read a block at a time from its end.
+fn read_newest(file: &mut (impl Read + Seek), len: u64, max: usize) -> io::Result<Vec<Entry>> {
+    let mut start = len;
+    let mut tail = vec![];
+    let mut newlines = 0;
+    // A block at a time from the end, until there's a line more than `max`, as the first is likely
+    // cut short.
+    while start > 0 && (max == 0 || newlines <= max) {
+        let from = start.saturating_sub(BLOCK);
+        let mut block = vec![0; (start - from) as usize];
+        file.seek(SeekFrom::Start(from))?;
+        file.read_exact(&mut block)?;
+        newlines += block.iter().filter(|&&b| b == b'\n').count();
+        block.append(&mut tail);
+        tail = block;
+        start = from;
+    }
+    let tail = String::from_utf8_lossy(&tail);
+    let mut lines = tail.lines().peekable();
+    let escaped = if start == 0 {
+        lines.next_if_eq(&RUSTYLINE_V2).is_some()
+    } else {
+        lines.next();
+        let mut first = [0; RUSTYLINE_V2.len() + 1];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut first)?;
+        first.starts_with(RUSTYLINE_V2.as_bytes()) && matches!(first.last(), Some(b'\n' | b'\r'))
+    };
+    let mut entries = lines
+        .filter(|line| !line.is_empty())
+        .map(|line| match serde_json::from_str::<Entry>(line) {
+            Ok(entry) => entry,
+            Err(_) if escaped => Entry {
+                time: 0,
+                text: unescape(line),
+            },
+            Err(_) => Entry {
+                time: 0,
+                text: line.to_string(),
+            },
+        })
+        .collect::<Vec<_>>();
+    if max > 0 {
+        entries.drain(..entries.len().saturating_sub(max));
+    }
+    Ok(entries)
+}
+
+/// Write `entries` to `path`, or the database, with what another ata² added to it since this one
+/// loaded it, the newest `ui.history_max_entries` of them.
+pub fn save(path: &Path, entries: &[Entry]) -> TokioResult<()> {
+    lock::with(&lock_name(path), || {
+        let entries = merged(load(path)?, entries);
+        write(path, &entries)
+    })
+}
+
+/// Write `entries` to `path`, or the database, instead of what's there, as when prompts were
+/// deleted from it.
+pub fn replace(path: &Path, entries: &[Entry]) -> TokioResult<()> {
+    lock::with(&lock_name(path), || write(path, entries))
+}
+
+/// The lock on the history in `path`, see [`crate::lock`].
+fn lock_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || String::from("history"),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// `entries`, and those of `saved` that aren't among them, as another ata² added them, in the
+/// order they were sent, with only the newest copy of a prompt per `ui.history_dedup`.
+fn merged(saved: Vec<Entry>, entries: &[Entry]) -> Vec<Entry> {
+    let ours = entries
+        .iter()
+        .map(|entry| (entry.time, entry.text.as_str()))
+        .collect::<HashSet<_>>();
+    let mut ret = saved
+        .into_iter()
+        .filter(|entry| !ours.contains(&(entry.time, entry.text.as_str())))
+        .chain(entries.iter().cloned())
+        .collect::<Vec<_>>();
+    // Stable, so that prompts without a time stay first, as they were.
+    ret.sort_by_key(|entry| entry.time);
+    if config.ui.history_dedup {
+        let mut seen = HashSet::new();
+        ret.reverse();
+        ret.retain(|entry| seen.insert(entry.text.clone()));
+        ret.reverse();
+    }
+    ret
+}
+
+/// Write the newest `ui.history_max_entries` of `entries` to `path`, or the database.
+fn write(path: &Path, entries: &[Entry]) -> TokioResult<()> {
+    let skip = entries.len().saturating_sub(config.ui.history_max_entries);
+    if store::enabled() {
+        return store::save_history(&FLAGS.config.profile(), &entries[skip..]);
+    }
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    for entry in &entries[skip..] {
+        serde_json::to_writer(&mut file, entry)?;
+        writeln!(file)?;
+    }
+    Ok(file.flush()?)
+}
+
+/// Add `text`, sent now, to `entries`. Returns whether an earlier copy of it was dropped, per
+/// `ui.history_dedup`.
+pub fn add(entries: &mut Vec<Entry>, text: &str) -> bool {
+    let len = entries.len();
+    if config.ui.history_dedup {
+        entries.retain(|entry| entry.text != text);
+    }
+    let deduped = entries.len() != len;
+    entries.push(Entry {
+        time: chrono::Utc::now().timestamp(),
+        text: text.to_string(),
+    });
+    let excess = entries.len().saturating_sub(config.ui.history_max_entries);
+    entries.drain(..excess);
+    deduped
+}
+
+/// A line of rustyline's: `\n` is a newline and `\\` a backslash.
+fn unescape(line: &str) -> String {
+    let mut ret = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                ret.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                ret.push('\\');
+                chars.next();
+            }
+            (c, _) => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+
+    /// A file that counts how much of it is read.
+    struct Counted {
+        file: Cursor<Vec<u8>>,
+        read: u64,
+    }
+
+    impl Read for Counted {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.file.read(buf)?;
+            self.read += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl Seek for Counted {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.file.seek(pos)
+        }
+    }
+
+    fn history(prompts: usize) -> Counted {
+        let mut file = vec![];
+        for i in 0..prompts {
+            let entry = Entry {
+                time: i as i64,
+                text: format!("prompt {i}"),
+            };
+            serde_json::to_writer(&mut file, &entry).unwrap();
+            file.push(b'\n');
+        }
+        Counted {
+            file: Cursor::new(file),
+            read: 0,
+        }
+    }
+
+    #[test]
+    fn history_file_of_a_profile() {
+        let template = Path::new("/nowhere/ata2/history-{profile}");
+        assert_eq!(
+            file(template, "work"),
+            Path::new("/nowhere/ata2/history-work")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn history_file_of_a_profile_on_windows() {
+        for (template, expected) in [
+            (
+                r"C:\Users\Jane Doe\AppData\Roaming\ata2\history-{profile}",
+                r"C:\Users\Jane Doe\AppData\Roaming\ata2\history-work",
+            ),
+            (
+                "C:/Users/Jane Doe/ata2/history-{profile}",
+                "C:/Users/Jane Doe/ata2/history-work",
+            ),
+            (
+                r"\\server\share\ata2\history-{profile}",
+                r"\\server\share\ata2\history-work",
+            ),
+            (
+                r"\\?\C:\ata2\history-{profile}",
+                r"\\?\C:\ata2\history-work",
+            ),
+        ] {
+            let template = Path::new(template);
+            let file = file(template, "work");
+            assert_eq!(file, Path::new(expected));
+            assert!(file.is_absolute());
+            // Where the queue, and the history from before profiles, are looked for.
+            assert_eq!(file.parent(), template.parent());
+        }
+    }
+
+    #[test]
+    fn newest_of_a_long_history() {
+        let mut file = history(100_000);
+        let len = file.file.get_ref().len() as u64;
+        let started = Instant::now();
+        let entries = read_newest(&mut file, len, 1000).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(entries.len(), 1000);
+        assert_eq!(entries[0].text, "prompt 99000");
+        assert_eq!(entries[999].text, "prompt 99999");
+        // The blocks its last prompts are in, and the start, for whether it's rustyline's.
+        assert!(file.read <= 2 * BLOCK + RUSTYLINE_V2.len() as u64 + 1);
+        assert!(file.read < len / 10);
+    }
+
+    #[test]
+    fn whole_history() {
+        let mut file = history(1000);
+        let len = file.file.get_ref().len() as u64;
+        let entries = read_newest(&mut file, len, 0).unwrap();
+        assert_eq!(entries.len(), 1000);
+        assert_eq!(entries[0].text, "prompt 0");
+        assert_eq!(file.read, len);
+    }
+
+    #[test]
+    fn escaped_history() {
+        let history = b"#V2\nfirst\\nline\nsecond\n";
+        let len = history.len() as u64;
+        let mut file = Cursor::new(history.to_vec());
+        let entries = read_newest(&mut file, len, 1).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].text, "second");
+        let entries = read_newest(&mut file, len, 0).unwrap();
+        assert_eq!(entries[0].text, "first\nline");
+    }
+}
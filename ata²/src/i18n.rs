@@ -0,0 +1,217 @@
+//! ata²'s own messages, in the language of the locale (`LC_ALL`, `LC_MESSAGES` or `LANG`, with
+//! `LANGUAGE` listing more to try first). The messages are [Fluent](https://projectfluent.org)
+//! files: those in `locales/` are built in, and a `<locale>.ftl` in the `locales` directory next
+//! to the configuration file adds a language or overrides messages of one. Whatever a translation
+//! lacks is in English.
+//!
+//! The locale is read from the environment alone, as messages are needed before the configuration
+//! is, to tell about it missing.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config;
+
+const FALLBACK: &str = "en-US";
+
+/// Translations built into the binary, by locale.
+const BUILTIN: &[(&str, &str)] = &[
+    ("en-US", include_str!("../locales/en-US/ata2.ftl")),
+    ("de", include_str!("../locales/de/ata2.ftl")),
+];
+
+lazy_static! {
+    /// The translations to look messages up in, most preferred first and ending with English.
+    static ref BUNDLES: Vec<(LanguageIdentifier, FluentBundle<FluentResource>)> = bundles();
+}
+
+/// The message `$id` in the user's language, with `$name = value` arguments filled in.
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::tr($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = ::fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::tr($id, Some(&args))
+    }};
+}
+
+/// Message `id`, see [`tr!`]. A message missing from every translation is shown as its ID.
+pub fn tr(id: &str, args: Option<&FluentArgs>) -> String {
+    BUNDLES
+        .iter()
+        .find_map(|(_, bundle)| format(bundle, id, args))
+        .unwrap_or_else(|| {
+            warn!("No message {id}");
+            id.to_string()
+        })
+}
+
+/// The language messages are looked up in first, like `de`.
+pub fn language() -> String {
+    BUNDLES.first().map_or_else(
+        || FALLBACK.to_string(),
+        |(language, _)| language.to_string(),
+    )
+}
+
+/// Does `answer` to a `[y/N]` question start with yes, in English or the user's language?
+pub fn is_yes(answer: &str) -> bool {
+    let first = |s: &str| {
+        s.trim()
+            .chars()
+            .next()
+            .map(|c| c.to_lowercase().to_string())
+    };
+    first(answer).is_some_and(|answer| answer == "y" || Some(answer) == first(&tr!("answer-yes")))
+}
+
+fn format(
+    bundle: &FluentBundle<FluentResource>,
+    id: &str,
+    args: Option<&FluentArgs>,
+) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = vec![];
+    let text = bundle.format_pattern(pattern, args, &mut errors);
+    if !errors.is_empty() {
+        // Most likely a translation using an argument the message doesn't have.
+        warn!("Message {id}: {errors:?}");
+        return None;
+    }
+    Some(text.into_owned())
+}
+
+fn bundles() -> Vec<(LanguageIdentifier, FluentBundle<FluentResource>)> {
+    let fallback: LanguageIdentifier = FALLBACK.parse().unwrap();
+    let mut available: Vec<LanguageIdentifier> = BUILTIN
+        .iter()
+        .filter_map(|(locale, _)| locale.parse().ok())
+        .collect();
+    if let Ok(entries) = fs::read_dir(user_dir()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "ftl") {
+                let locale = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str()?.parse().ok());
+                if let Some(locale) = locale.filter(|locale| !available.contains(locale)) {
+                    available.push(locale);
+                }
+            }
+        }
+    }
+    let mut locales = negotiate(&requested(), &available);
+    locales.retain(|locale| *locale != fallback);
+    locales.push(fallback);
+    locales
+        .into_iter()
+        .map(|locale| {
+            let bundle = bundle(&locale);
+            (locale, bundle)
+        })
+        .collect()
+}
+
+/// The builtin translation to `locale`, if any, overridden by the user's.
+fn bundle(locale: &LanguageIdentifier) -> FluentBundle<FluentResource> {
+    let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+    // Unicode isolation marks around arguments only show up as junk in a terminal.
+    bundle.set_use_isolating(false);
+    let name = locale.to_string();
+    let builtin = BUILTIN
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, source)| source.to_string());
+    let path = user_dir().join(format!("{name}.ftl"));
+    let user = fs::read_to_string(&path).ok();
+    for source in builtin.into_iter().chain(user) {
+        let resource = FluentResource::try_new(source).unwrap_or_else(|(resource, errors)| {
+            // The messages that did parse are still good.
+            warn!("Errors in the {name} translation: {errors:?}");
+            resource
+        });
+        bundle.add_resource_overriding(resource);
+    }
+    bundle
+}
+
+/// Where translations of the user's own go.
+fn user_dir() -> PathBuf {
+    config::get_config_dir::<2>().join("locales")
+}
+
+/// The locales the environment asks for, most preferred first. Like gettext, `LANGUAGE` only
+/// counts if the locale isn't `C`.
+pub fn requested() -> Vec<LanguageIdentifier> {
+    let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    let Some(locale) = var("LC_ALL")
+        .or_else(|| var("LC_MESSAGES"))
+        .or_else(|| var("LANG"))
+    else {
+        return vec![];
+    };
+    if locale == "C" || locale == "POSIX" || locale.starts_with("C.") {
+        return vec![];
+    }
+    var("LANGUAGE")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|language| !language.is_empty())
+        .chain([locale.as_str()])
+        .filter_map(parse_locale)
+        .collect()
+}
+
+/// `de_AT.UTF-8@euro` as `de-AT`.
+fn parse_locale(locale: &str) -> Option<LanguageIdentifier> {
+    let locale = locale.split(['.', '@']).next()?.replace('_', "-");
+    locale.parse().ok()
+}
+
+/// The locales of `available` to use for `requested`, in order: the same one if available, else
+/// another of the same language.
+fn negotiate(
+    requested: &[LanguageIdentifier],
+    available: &[LanguageIdentifier],
+) -> Vec<LanguageIdentifier> {
+    let mut ret: Vec<LanguageIdentifier> = vec![];
+    for wanted in requested {
+        let exact = available.iter().find(|locale| *locale == wanted);
+        let language = || {
+            available
+                .iter()
+                .find(|locale| locale.language == wanted.language)
+        };
+        if let Some(locale) = exact.or_else(language) {
+            if !ret.contains(locale) {
+                ret.push(locale.clone());
+            }
+        }
+    }
+    ret
+}
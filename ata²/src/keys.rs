@@ -0,0 +1,314 @@
+//! Key chords as written in `[ui.keybindings]`, and the actions they can be bound to.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::Role;
+use futures_util::FutureExt as _;
+use rustyline::{
+    Cmd, ConditionalEventHandler, Event, EventContext, KeyCode, KeyEvent, Modifiers, Movement,
+    RepeatCount,
+};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::Ordering;
+
+use crate::ask;
+use crate::clipboard;
+use crate::config::UiConfig;
+use crate::prompt::CONVERSATION;
+use crate::readline::{chat_completion_message_role, chat_completion_message_text};
+use crate::CANCEL;
+use crate::IS_RUNNING;
+
+/// Parse a chord such as `ctrl-d`, `alt-enter`, `f2` or, for a sequence of keys, `ctrl-x ctrl-e`.
+/// An empty chord binds nothing.
+pub fn parse(chord: &str) -> Result<Option<Event>, String> {
+    let keys = chord
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((!keys.is_empty()).then_some(Event::KeySeq(keys)))
+}
+
+fn parse_key(key: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = Modifiers::NONE;
+    let mut rest = key;
+    // Split off modifiers, but not the `-` of e.g. `ctrl--`.
+    while let Some((modifier, name)) = rest.split_once('-').filter(|(_, name)| !name.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "c" => Modifiers::CTRL,
+            "alt" | "meta" | "m" => Modifiers::ALT,
+            "shift" | "s" => Modifiers::SHIFT,
+            _ => return Err(tr!("config-key-modifier", modifier = modifier, key = key)),
+        };
+        rest = name;
+    }
+    let code = match rest.to_lowercase().as_str() {
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "esc" | "escape" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "space" => KeyCode::Char(' '),
+        name => match name.strip_prefix('f').map(str::parse::<u8>) {
+            Some(Ok(n)) if (1..=24).contains(&n) => KeyCode::F(n),
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(tr!("config-key", key = key)),
+                }
+            }
+        },
+    };
+    Ok(KeyEvent(code, modifiers))
+}
+
+/// A chord the way help shows it, like `Ctrl-X Ctrl-E` for `ctrl-x ctrl-e`.
+pub fn describe(chord: &str) -> String {
+    chord
+        .split_whitespace()
+        .map(|key| parse_key(key).map_or_else(|_| key.to_string(), describe_key))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn describe_key(KeyEvent(code, modifiers): KeyEvent) -> String {
+    let mut ret = String::new();
+    for (modifier, name) in [
+        (Modifiers::CTRL, "Ctrl-"),
+        (Modifiers::ALT, "Alt-"),
+        (Modifiers::SHIFT, "Shift-"),
+    ] {
+        if modifiers.contains(modifier) {
+            ret.push_str(name);
+        }
+    }
+    match code {
+        KeyCode::Char(' ') => ret.push_str("Space"),
+        KeyCode::Char(c) => ret.extend(c.to_uppercase()),
+        KeyCode::F(n) => ret.push_str(&format!("F{n}")),
+        KeyCode::Esc => ret.push_str("Esc"),
+        code => ret.push_str(&format!("{code:?}")),
+    }
+    ret
+}
+
+/// The keys of the prompt as `ui` binds them, with the message that describes each. Takes the
+/// configuration rather than reading [`crate::CONFIGURATION`] so `--print-shortcuts` can show it
+/// while it's being loaded.
+pub fn bindings(ui: &UiConfig) -> Vec<(String, &'static str)> {
+    let keys = &ui.keybindings;
+    let mut ret = vec![];
+    if ui.multiline_insertions {
+        ret.push((keys.accept_line.as_str(), "key-accept-line"));
+        ret.push((keys.newline.as_str(), "key-newline"));
+    }
+    ret.extend([
+        (keys.save_conversation.as_str(), "key-save-conversation"),
+        (keys.cancel_generation.as_str(), "key-cancel-generation"),
+        (keys.copy_response.as_str(), "key-copy-response"),
+        (keys.open_editor.as_str(), "key-open-editor"),
+        ("ctrl-o", "key-skip-pacing"),
+        ("ctrl-r", "key-search"),
+    ]);
+    if ui.ghost_text {
+        ret.push(("tab", "key-accept-suggestion"));
+    }
+    ret.push((
+        "ctrl-c",
+        if ui.double_ctrlc {
+            "key-exit-twice"
+        } else {
+            "key-exit"
+        },
+    ));
+    ret.into_iter()
+        .filter(|(chord, _)| !chord.trim().is_empty())
+        .map(|(chord, id)| (describe(chord), id))
+        .collect()
+}
+
+/// Start a new line, or send the line if it's the answer to a question, see [`crate::ask`].
+pub struct NewlineHandler;
+
+impl ConditionalEventHandler for NewlineHandler {
+    fn handle(
+        &self,
+        _event: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        _: &EventContext,
+    ) -> Option<Cmd> {
+        Some(if ask::pending() {
+            Cmd::AcceptLine
+        } else {
+            Cmd::Newline
+        })
+    }
+}
+
+/// Stop the response that is streaming in, keeping what arrived so far.
+pub struct CancelHandler;
+
+impl ConditionalEventHandler for CancelHandler {
+    fn handle(
+        &self,
+        _event: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        _: &EventContext,
+    ) -> Option<Cmd> {
+        if IS_RUNNING.load(Ordering::SeqCst) {
+            CANCEL.store(true, Ordering::Relaxed);
+        }
+        Some(Cmd::Noop)
+    }
+}
+
+/// Copy the last response to the clipboard.
+pub struct CopyResponseHandler;
+
+impl ConditionalEventHandler for CopyResponseHandler {
+    fn handle(
+        &self,
+        _event: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        _: &EventContext,
+    ) -> Option<Cmd> {
+        let conversation = CONVERSATION.lock().now_or_never()?;
+        let response = conversation
+            .iter()
+            .rev()
+            .find(|m| chat_completion_message_role(m) == Role::Assistant)
+            .and_then(chat_completion_message_text);
+        drop(conversation);
+        if let Some(response) = response {
+            clipboard::copy(&response);
+        }
+        Some(Cmd::Noop)
+    }
+}
+
+/// Edit the input in `$VISUAL` or `$EDITOR`.
+pub struct OpenEditorHandler;
+
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Through the shell, since $EDITOR may have arguments, like `code --wait`.
+#[cfg(not(windows))]
+fn editor_command(editor: &str, path: &Path) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(path);
+    command
+}
+
+#[cfg(windows)]
+fn editor_command(editor: &str, path: &Path) -> Command {
+    use std::os::windows::process::CommandExt as _;
+    let mut command = Command::new("cmd");
+    // cmd has quoting rules of its own, which Rust's can't know about.
+    command.arg("/S").arg("/C").raw_arg(cmd_line(editor, path));
+    command
+}
+
+/// What `cmd /S /C` runs to edit `path` with `editor`. Quoted as a whole, since with more than
+/// one pair of quotes in it, like a quoted editor in `Program Files` and a temporary folder in a
+/// user name with a space, cmd takes off the first and the last.
+#[cfg(any(windows, test))]
+fn cmd_line(editor: &str, path: &Path) -> String {
+    format!("\"{editor} \"{}\"\"", path.display())
+}
+
+impl ConditionalEventHandler for OpenEditorHandler {
+    fn handle(
+        &self,
+        _event: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        ctx: &EventContext,
+    ) -> Option<Cmd> {
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+        let path = env::temp_dir().join(format!("ata2-{}.md", std::process::id()));
+        fs::write(&path, ctx.line()).ok()?;
+        let status = editor_command(&editor, &path).status();
+        // Notepad and the like save with CRLF.
+        let edited = fs::read_to_string(&path).map(|edited| edited.replace("\r\n", "\n"));
+        let _ = fs::remove_file(&path);
+        match (status, edited) {
+            (Ok(status), Ok(edited)) if status.success() => Some(Cmd::Replace(
+                Movement::WholeBuffer,
+                Some(edited.trim_end_matches('\n').to_string()),
+            )),
+            // Still replace, as the redraw that comes with it cleans up after the editor.
+            _ => Some(Cmd::Replace(
+                Movement::WholeBuffer,
+                Some(ctx.line().to_string()),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editor_command_lines() {
+        let temp = Path::new(r"C:\Users\Jane Doe\AppData\Local\Temp\ata2-42.md");
+        assert_eq!(
+            cmd_line("notepad", temp),
+            r#""notepad "C:\Users\Jane Doe\AppData\Local\Temp\ata2-42.md"""#
+        );
+        assert_eq!(
+            cmd_line(
+                r#""C:\Program Files\Microsoft VS Code\bin\code.cmd" --wait"#,
+                temp
+            ),
+            r#"""C:\Program Files\Microsoft VS Code\bin\code.cmd" --wait "C:\Users\Jane Doe\AppData\Local\Temp\ata2-42.md"""#
+        );
+        let unc = Path::new(r"\\server\share\Temp\ata2-42.md");
+        assert_eq!(
+            cmd_line("notepad", unc),
+            r#""notepad "\\server\share\Temp\ata2-42.md"""#
+        );
+    }
+}
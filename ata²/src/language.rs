@@ -0,0 +1,80 @@
+//! `reply_language`: the model is told to reply in a language, so that it needn't be asked every
+//! time. With `"auto"`, the language the prompt is written in, or of the locale when that can't
+//! be told, like for a prompt of a few words or of code. The instruction is added to the system
+//! message of each request, and not kept in the conversation.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, Role};
+
+use crate::i18n;
+use crate::readline::{chat_completion_message_role, chat_completion_message_text};
+use crate::CONFIGURATION as config;
+
+/// The language of the prompt, detected from it.
+const AUTO: &str = "auto";
+
+/// The language to reply to `messages` in, if there's one to.
+fn language(messages: &[ChatCompletionRequestMessage]) -> Option<String> {
+    match config.reply_language.trim() {
+        "" => None,
+        AUTO => {
+            let prompt = messages
+                .iter()
+                .rev()
+                .find(|m| chat_completion_message_role(m) == Role::User)
+                .and_then(chat_completion_message_text)
+                .unwrap_or_default();
+            whatlang::detect(&prompt)
+                .filter(|info| info.is_reliable())
+                .map(|info| info.lang().eng_name().to_string())
+                .or_else(|| {
+                    let locale = i18n::requested().into_iter().next()?;
+                    Some(format!("the language of the locale {locale}"))
+                })
+        }
+        language => Some(language.to_string()),
+    }
+}
+
+/// `messages` with the system message telling the model to reply in `reply_language`, if it's
+/// set.
+pub fn instructed(messages: &[ChatCompletionRequestMessage]) -> Vec<ChatCompletionRequestMessage> {
+    let mut ret = messages.to_vec();
+    let Some(language) = language(messages) else {
+        return ret;
+    };
+    let instruction = format!("Always reply in {language}, whatever language you're asked in.");
+    let system = match ret.first() {
+        Some(first) if chat_completion_message_role(first) == Role::System => {
+            let content = chat_completion_message_text(first).unwrap_or_default();
+            ret.remove(0);
+            format!("{}\n\n{instruction}", content.trim_end())
+        }
+        _ => instruction,
+    };
+    ret.insert(
+        0,
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            role: Role::System,
+            content: Some(system),
+        }),
+    );
+    ret
+}
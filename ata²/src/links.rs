@@ -0,0 +1,210 @@
+//! Links in responses. They're made clickable with OSC 8 escapes on terminals known to support
+//! them (`ui.hyperlinks`), and `/links` lists those of the last response and opens them.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::Role;
+use tokio::process::Command;
+
+use std::env;
+use std::process::Stdio;
+
+use crate::commands;
+use crate::markdown;
+use crate::prompt::CONVERSATION;
+use crate::readline::{chat_completion_message_role, chat_completion_message_text};
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+const SCHEMES: &[&str] = &["https://", "http://"];
+
+#[cfg(target_os = "macos")]
+const OPENER: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const OPENER: &str = "xdg-open";
+
+lazy_static! {
+    static ref ENABLED: bool =
+        config.ui.hyperlinks && atty::is(atty::Stream::Stdout) && terminal_supports_hyperlinks();
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    /// The text of a Markdown link, `None` for a bare URL.
+    pub text: Option<String>,
+    pub url: String,
+}
+
+/// Are links printed as hyperlinks?
+pub fn enabled() -> bool {
+    *ENABLED
+}
+
+/// `word` with the URLs in it made clickable, if [`enabled`].
+pub fn hyperlink(word: &str) -> String {
+    if !enabled() {
+        return word.to_string();
+    }
+    let mut ret = String::with_capacity(word.len());
+    let mut rest = word;
+    while let Some((start, end)) = next_url(rest) {
+        let url = &rest[start..end];
+        ret.push_str(&rest[..start]);
+        ret.push_str(&format!("\x1b]8;;{url}\x1b\\{url}\x1b]8;;\x1b\\"));
+        rest = &rest[end..];
+    }
+    ret.push_str(rest);
+    ret
+}
+
+/// The links in `text` outside code blocks, in order and each only once.
+pub fn find(text: &str) -> Vec<Link> {
+    let mut ret: Vec<Link> = vec![];
+    let mut fence: Option<String> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match &fence {
+            Some(opening) => {
+                if trimmed.starts_with(opening.as_str()) {
+                    fence = None;
+                }
+                continue;
+            }
+            None => {
+                fence = markdown::opening_fence(trimmed);
+                if fence.is_some() {
+                    continue;
+                }
+            }
+        }
+        let mut offset = 0;
+        while let Some((start, end)) = next_url(&line[offset..]) {
+            let (start, end) = (offset + start, offset + end);
+            let url = line[start..end].to_string();
+            // `[text](url)`
+            let text = line[..start]
+                .strip_suffix("](")
+                .and_then(|before| before.rfind('[').map(|i| before[i + 1..].to_string()));
+            if !ret.iter().any(|link| link.url == url) {
+                ret.push(Link { text, url });
+            }
+            offset = end;
+        }
+    }
+    ret
+}
+
+/// Where the first URL in `s` starts and ends. It ends at whitespace, or a bracket or quote that
+/// is probably around it, and punctuation at its end is taken to be the sentence's.
+fn next_url(s: &str) -> Option<(usize, usize)> {
+    let start = SCHEMES.iter().filter_map(|scheme| s.find(scheme)).min()?;
+    let len = s[start..]
+        .find(|c: char| c.is_whitespace() || "<>()[]\"'`".contains(c))
+        .unwrap_or(s.len() - start);
+    let url = s[start..start + len].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    if SCHEMES.contains(&url) {
+        // Nothing after the scheme; look past it.
+        let after = start + url.len();
+        return next_url(&s[after..]).map(|(a, b)| (after + a, after + b));
+    }
+    Some((start, start + url.len()))
+}
+
+/// Going by what terminals announce about themselves, as there's no asking them.
+fn terminal_supports_hyperlinks() -> bool {
+    if let Ok(force) = env::var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    let vte_version: u32 = var("VTE_VERSION").parse().unwrap_or_default();
+    matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper" | "Tabby" | "rio"
+    ) || vte_version >= 5000
+        || [
+            "KITTY_WINDOW_ID",
+            "WT_SESSION",
+            "KONSOLE_VERSION",
+            "ALACRITTY_WINDOW_ID",
+        ]
+        .iter()
+        .any(|name| env::var_os(name).is_some())
+        || ["kitty", "foot", "alacritty", "wezterm", "ghostty"]
+            .iter()
+            .any(|name| var("TERM").contains(name))
+}
+
+/// `/links [n]`
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    let response = CONVERSATION
+        .lock()
+        .await
+        .iter()
+        .rev()
+        .find(|m| chat_completion_message_role(m) == Role::Assistant)
+        .and_then(chat_completion_message_text)
+        .ok_or_else(|| tr!("links-no-response"))?;
+    let links = find(&response);
+    match args.as_slice() {
+        [] => {
+            if links.is_empty() {
+                eprintln!("{}", tr!("links-none"));
+            }
+            for (i, link) in links.iter().enumerate() {
+                let url = hyperlink(&link.url);
+                match &link.text {
+                    Some(text) => eprintln!("{n}. {text} {url}", n = i + 1),
+                    None => eprintln!("{n}. {url}", n = i + 1),
+                }
+            }
+            Ok(())
+        }
+        [n] => {
+            let link = n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| links.get(n.checked_sub(1)?))
+                .ok_or_else(|| tr!("links-no-such", n = n.as_str(), count = links.len()))?;
+            open(&link.url).await
+        }
+        _ => Err(commands::usage("links")),
+    }
+}
+
+async fn open(url: &str) -> TokioResult<()> {
+    let status = Command::new(OPENER)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .map_err(|e| tr!("run-failed", program = OPENER, error = e.to_string()))?;
+    if !status.success() {
+        let program = format!("{OPENER} {url}");
+        return Err(tr!(
+            "program-failed",
+            program = program,
+            error = status.to_string()
+        )
+        .into());
+    }
+    info!("{}", tr!("links-opened", url = url));
+    Ok(())
+}
@@ -0,0 +1,265 @@
+//! `api = "local"`: a GGUF model run by ata² itself, without a server, for ata² built with the
+//! `local` feature, which embeds llama.cpp. The model is loaded from `[provider.local]` the first
+//! time it's asked something and kept for the rest of the session, and each response is generated
+//! on a thread of its own, in a context that's made for it, and streamed as if it came from the
+//! API. The prompt is in the model's own chat template, and `--grammar` constrains the response.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use crate::config::Api;
+use crate::CONFIGURATION;
+
+/// Whether conversations go to the model of `[provider.local]`.
+pub fn enabled() -> bool {
+    CONFIGURATION.api == Api::Local
+}
+
+#[cfg(not(feature = "local"))]
+pub async fn chat_stream(
+    _request: async_openai::types::CreateChatCompletionRequest,
+) -> crate::TokioResult<async_openai::types::ChatCompletionResponseStream> {
+    Err(tr!("local-not-built").into())
+}
+
+#[cfg(feature = "local")]
+pub use engine::chat_stream;
+
+#[cfg(feature = "local")]
+mod engine {
+    use async_openai::error::OpenAIError;
+    use async_openai::types::{
+        ChatCompletionResponseStream, ChatCompletionResponseStreamMessage,
+        ChatCompletionStreamResponseDelta, CreateChatCompletionRequest,
+        CreateChatCompletionStreamResponse, FinishReason, Role,
+    };
+    use llama_cpp_2::context::params::LlamaContextParams;
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::llama_batch::LlamaBatch;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{LlamaChatMessage, LlamaModel};
+    use llama_cpp_2::sampling::LlamaSampler;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    use std::num::NonZeroU32;
+    use std::sync::OnceLock;
+    use std::thread;
+
+    use crate::config::Config;
+    use crate::grammar;
+    use crate::readline::{chat_completion_message_role, chat_completion_message_text};
+    use crate::TokioResult;
+    use crate::CONFIGURATION;
+
+    /// The model of `[provider.local]`, once it's loaded, or why it couldn't be.
+    static MODEL: OnceLock<Result<(LlamaBackend, LlamaModel), String>> = OnceLock::new();
+
+    /// How many tokens of the prompt are decoded at once.
+    const BATCH: usize = 512;
+
+    type Chunk = Result<CreateChatCompletionStreamResponse, OpenAIError>;
+
+    fn model() -> Result<&'static (LlamaBackend, LlamaModel), String> {
+        MODEL
+            .get_or_init(|| {
+                let local = &CONFIGURATION.provider.local;
+                let path = local.model.display().to_string();
+                info!("{}", tr!("local-loading", path = path.as_str()));
+                let mut backend = LlamaBackend::init().map_err(|e| e.to_string())?;
+                backend.void_logs();
+                let params = LlamaModelParams::default().with_n_gpu_layers(local.gpu_layers);
+                let model =
+                    LlamaModel::load_from_file(&backend, &local.model, &params).map_err(|e| {
+                        tr!(
+                            "local-load-failed",
+                            path = path.as_str(),
+                            error = e.to_string()
+                        )
+                    })?;
+                Ok((backend, model))
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// The response of the model of `[provider.local]` to the messages of `request`, streamed as
+    /// the API streams one.
+    pub async fn chat_stream(
+        request: CreateChatCompletionRequest,
+    ) -> TokioResult<ChatCompletionResponseStream> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let prompt = tokio::task::spawn_blocking(move || prompt(&request)).await??;
+        thread::spawn(move || {
+            if let Err(e) = generate(&prompt, &tx) {
+                let _ = tx.send(Err(OpenAIError::StreamError(e)));
+            }
+        });
+        Ok(Box::pin(
+            tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+        ))
+    }
+
+    /// The messages of `request` in the chat template of the model, up to where it replies.
+    fn prompt(request: &CreateChatCompletionRequest) -> Result<String, String> {
+        let (_, model) = model()?;
+        let messages = request
+            .messages
+            .iter()
+            .filter_map(|message| {
+                let role = match chat_completion_message_role(message) {
+                    Role::System => "system",
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::Tool | Role::Function => return None,
+                };
+                let text = chat_completion_message_text(message)?;
+                Some(LlamaChatMessage::new(role.to_string(), text).map_err(|e| e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let template = model
+            .chat_template(None)
+            .map_err(|e| tr!("local-no-template", error = e.to_string()))?;
+        model
+            .apply_chat_template(&template, &messages, true)
+            .map_err(|e| tr!("local-no-template", error = e.to_string()))
+    }
+
+    /// Generate the response to `prompt`, sending it to `tx` as it comes, until the model's done,
+    /// `max_tokens` are generated, the context is full or nothing reads it anymore.
+    fn generate(prompt: &str, tx: &UnboundedSender<Chunk>) -> Result<(), String> {
+        let config: &Config = &CONFIGURATION;
+        let local = &config.provider.local;
+        let (backend, model) = model()?;
+        let threads = i32::try_from(local.threads).unwrap_or(i32::MAX);
+        let mut params =
+            LlamaContextParams::default().with_n_ctx(NonZeroU32::new(local.context_size));
+        if threads > 0 {
+            params = params.with_n_threads(threads).with_n_threads_batch(threads);
+        }
+        let mut ctx = model
+            .new_context(backend, params)
+            .map_err(|e| e.to_string())?;
+        let vocab = model.vocab();
+
+        let tokens = vocab.tokenize(prompt.as_bytes(), false, true);
+        let n_ctx = ctx.n_ctx() as usize;
+        if tokens.len() >= n_ctx {
+            return Err(tr!(
+                "local-too-long",
+                tokens = tokens.len(),
+                context = n_ctx
+            ));
+        }
+        let mut batch = LlamaBatch::new(BATCH, 1);
+        let mut pos = 0;
+        for part in tokens.chunks(BATCH) {
+            batch.clear();
+            for (i, token) in part.iter().enumerate() {
+                let last = pos as usize + i + 1 == tokens.len();
+                batch
+                    .add(*token, pos + i as i32, &[0], last)
+                    .map_err(|e| e.to_string())?;
+            }
+            ctx.decode(&mut batch).map_err(|e| e.to_string())?;
+            pos += part.len() as i32;
+        }
+
+        let mut sampler = sampler(model, config)?;
+        let max_tokens = usize::try_from(config.max_tokens).unwrap_or(0);
+        // Bytes of a character that a token ends in the middle of, until the next one finishes it.
+        let mut pending = vec![];
+        let mut generated = 0;
+        let finish_reason = loop {
+            if generated == max_tokens || pos as usize >= n_ctx {
+                break FinishReason::Length;
+            }
+            let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+            sampler.accept(token);
+            if vocab.is_eog(token) {
+                break FinishReason::Stop;
+            }
+            pending.extend(vocab.token_to_piece(token, false, None));
+            let complete = match std::str::from_utf8(&pending) {
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                _ => pending.len(),
+            };
+            let text = String::from_utf8_lossy(&pending[..complete]).into_owned();
+            pending.drain(..complete);
+            if !text.is_empty() && tx.send(Ok(chunk(Some(text), None))).is_err() {
+                return Ok(());
+            }
+            generated += 1;
+            batch.clear();
+            batch
+                .add(token, pos, &[0], true)
+                .map_err(|e| e.to_string())?;
+            ctx.decode(&mut batch).map_err(|e| e.to_string())?;
+            pos += 1;
+        };
+        let _ = tx.send(Ok(chunk(None, Some(finish_reason))));
+        Ok(())
+    }
+
+    /// How the next token is picked: by `temperature` and `top_p`, or the likeliest one at a
+    /// temperature of 0, of those that `--grammar` allows.
+    fn sampler(model: &LlamaModel, config: &Config) -> Result<LlamaSampler, String> {
+        let mut samplers = vec![];
+        if let Some(gbnf) = grammar::gbnf() {
+            samplers.push(LlamaSampler::grammar(model, gbnf, "root").map_err(|e| e.to_string())?);
+        }
+        if config.temperature > 0.0 {
+            samplers.push(LlamaSampler::top_p(config.top_p as f32, 1));
+            samplers.push(LlamaSampler::temp(config.temperature as f32));
+            samplers.push(LlamaSampler::dist(seed()));
+        } else {
+            samplers.push(LlamaSampler::greedy());
+        }
+        Ok(LlamaSampler::chain_simple(samplers))
+    }
+
+    fn seed() -> u32 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos())
+    }
+
+    fn chunk(
+        content: Option<String>,
+        finish_reason: Option<FinishReason>,
+    ) -> CreateChatCompletionStreamResponse {
+        #[allow(deprecated)]
+        let delta = ChatCompletionStreamResponseDelta {
+            content,
+            function_call: None,
+            tool_calls: None,
+            role: None,
+        };
+        CreateChatCompletionStreamResponse {
+            id: String::new(),
+            choices: vec![ChatCompletionResponseStreamMessage {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+            created: 0,
+            model: CONFIGURATION.model.clone(),
+            system_fingerprint: None,
+            object: String::from("chat.completion.chunk"),
+        }
+    }
+}
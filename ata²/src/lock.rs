@@ -0,0 +1,43 @@
+//! Locks on the files that more than one ata² may write at once, as from panes of tmux side by
+//! side. Each is a file of its own in ata²'s state directory, `{name}.lock`, locked while what
+//! it's for is read and written, for one ata² not to write over what another just wrote, or read
+//! it half written. Nothing's left locked by an ata² that's killed, as the system lets go of it.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use fd_lock::RwLock;
+
+use std::fs::{self, OpenOptions};
+
+use crate::config;
+use crate::TokioResult;
+
+/// Run `f` while no other ata² runs something for `name`, waiting for one that does to finish.
+pub fn with<T>(name: &str, f: impl FnOnce() -> TokioResult<T>) -> TokioResult<T> {
+    let dir = config::get_state_dir();
+    fs::create_dir_all(&dir)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(format!("{name}.lock")))?;
+    let mut lock = RwLock::new(file);
+    let _guard = lock.write()?;
+    f()
+}
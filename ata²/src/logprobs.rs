@@ -0,0 +1,160 @@
+//! `--logprobs`: how sure the model was of each token of its responses, for debugging prompts
+//! and seeing how calibrated a model is. The likelihood of each token is asked for with the
+//! request, and the response is shown again below it, shaded by it: the less sure the model was of
+//! a token, the redder its background. With a `top_k`, the tokens it was unsure of are followed by
+//! the alternatives it weighed, in brackets. Without styles, the likelihood is bracketed instead.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use serde_json::Value;
+
+use std::sync::Mutex;
+
+use crate::style::{self, Style};
+use crate::FLAGS;
+
+/// The most alternatives the API gives for each token.
+pub const MAX_TOP_K: u8 = 20;
+
+/// Tokens less likely than this are unsure, and shown with their alternatives.
+const SURE: f64 = 0.9;
+
+/// The backgrounds of tokens at least as likely as each bound, of the 256-color palette.
+const SHADES: &[(f64, &str)] = &[(0.5, "on 58"), (0.2, "on 94"), (0.0, "on 88")];
+
+/// The tokens of the response that's coming, as they come.
+static TOKENS: Mutex<Vec<Token>> = Mutex::new(vec![]);
+
+struct Token {
+    text: String,
+    probability: f64,
+    /// What else the model weighed, and how likely each was.
+    alternatives: Vec<(String, f64)>,
+}
+
+/// Whether to ask for, and show, the likelihood of tokens.
+pub fn enabled() -> bool {
+    FLAGS.logprobs.is_some()
+}
+
+/// Ask for the likelihood of each token in the `body` of a chat completions request.
+pub fn apply(body: &mut Value) {
+    let Some(top_k) = FLAGS.logprobs else {
+        return;
+    };
+    body["logprobs"] = true.into();
+    if top_k > 0 {
+        body["top_logprobs"] = top_k.into();
+    }
+}
+
+/// Keep the tokens of a streamed `chunk`, as JSON, for [`render`].
+pub fn record(chunk: &Value) {
+    if !enabled() {
+        return;
+    }
+    let Some(choices) = chunk["choices"].as_array() else {
+        return;
+    };
+    let tokens = choices
+        .iter()
+        .filter_map(|choice| choice["logprobs"]["content"].as_array())
+        .flatten()
+        .filter_map(|token| {
+            let text = token["token"].as_str()?.to_string();
+            let probability = token["logprob"].as_f64()?.exp();
+            let alternatives = token["top_logprobs"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|alternative| {
+                    let text = alternative["token"].as_str()?;
+                    let probability = alternative["logprob"].as_f64()?.exp();
+                    Some((text.to_string(), probability))
+                })
+                .filter(|(alternative, _)| *alternative != text)
+                .collect();
+            Some(Token {
+                text,
+                probability,
+                alternatives,
+            })
+        });
+    TOKENS.lock().unwrap().extend(tokens);
+}
+
+/// The response that came with the tokens recorded since the last time, shaded by how sure the
+/// model was of each, and a legend. `None` if the API didn't say.
+pub fn render() -> Option<String> {
+    let tokens = std::mem::take(&mut *TOKENS.lock().unwrap());
+    if tokens.is_empty() {
+        return None;
+    }
+    let styled = style::enabled(atty::Stream::Stderr);
+    let dim = Style::parse("dim").unwrap_or_default();
+    let mut ret = String::new();
+    for token in &tokens {
+        let shade = SHADES
+            .iter()
+            .find(|(bound, _)| token.probability >= *bound)
+            .filter(|_| token.probability < SURE)
+            .map(|(_, shade)| Style::parse(shade).unwrap_or_default());
+        // What's said of it goes before the line it ends, if it does.
+        let text = token.text.trim_end_matches('\n');
+        let newlines = &token.text[text.len()..];
+        // Not across lines, where the background would run to the edge of the terminal.
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                ret.push('\n');
+            }
+            ret += &match &shade {
+                Some(shade) => shade.paint(line),
+                None => line.to_string(),
+            };
+        }
+        if token.probability >= SURE {
+            ret += newlines;
+            continue;
+        }
+        let mut notes = vec![];
+        if !styled {
+            notes.push(percent(token.probability));
+        }
+        notes.extend(
+            token
+                .alternatives
+                .iter()
+                .map(|(text, probability)| format!("{text:?} {}", percent(*probability))),
+        );
+        if !notes.is_empty() {
+            ret += &dim.paint(&format!("[{}]", notes.join(" | ")));
+        }
+        ret += newlines;
+    }
+    let legend = if styled {
+        tr!("logprobs-legend")
+    } else {
+        tr!("logprobs-legend-plain")
+    };
+    Some(format!("{}\n{}", ret.trim_end(), dim.paint(&legend)))
+}
+
+fn percent(probability: f64) -> String {
+    format!("{:.0}%", probability * 100.0)
+}
@@ -0,0 +1,280 @@
+//! `ata2 lsp`: a server for editors' plugins, of Neovim or Helix, say, that keep it running and
+//! send it requests, rather than starting ata² and reading its configuration for each. They're of
+//! JSON-RPC on stdin and stdout, framed as the Language Server Protocol frames its messages, with
+//! a `Content-Length` header, and answered as they're done, so a completion doesn't wait for a
+//! chat:
+//!
+//! * `complete`, `{prefix, suffix?}`: what goes between them, from `fim_model`, as with `--fim`.
+//! * `edit-selection`, `{text, instruction, language?}`: the text as the instruction has it.
+//! * `chat`, `{prompt, conversation?}`: the response to the prompt, which goes on from those
+//!   before it with the same `conversation`, whatever the plugin calls it.
+//!
+//! Each returns `{text}`. `initialize`, `shutdown` and `exit` are answered as a language server
+//! answers them, for clients of those.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    CreateChatCompletionRequestArgs, Role,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _, BufReader, Stdin};
+
+use std::collections::BTreeMap;
+use std::io::{self, Write as _};
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::fim;
+use crate::language;
+use crate::markdown;
+use crate::openai;
+use crate::paste;
+use crate::prompt;
+use crate::readline::{
+    string_to_chat_completion_assistant_message, string_to_chat_completion_request_user_message,
+};
+use crate::sink::{self, Sinks};
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// The codes of JSON-RPC's errors.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Of a request that failed, for the API's error, say.
+const FAILED: i64 = -32603;
+
+const EDIT: &str = "The user sends text selected in an editor and what to do with it. Reply with \
+                    the text to put in its place alone, without fencing it or saying anything \
+                    about it.";
+
+/// The conversations of `chat`, by the names the plugin gives them.
+static CONVERSATIONS: Mutex<BTreeMap<String, Vec<ChatCompletionRequestMessage>>> =
+    Mutex::new(BTreeMap::new());
+
+#[derive(Deserialize)]
+struct Complete {
+    prefix: String,
+    suffix: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EditSelection {
+    text: String,
+    instruction: String,
+    /// What it's written in, like `rust`.
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    prompt: String,
+    conversation: Option<String>,
+}
+
+pub async fn run() -> TokioResult<()> {
+    let mut stdin = BufReader::new(tokio::io::stdin());
+    while let Some(body) = read(&mut stdin).await? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                send(Value::Null, Err((PARSE_ERROR, e.to_string())));
+                continue;
+            }
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message["method"].as_str() else {
+            if let Some(id) = id {
+                send(id, Err((INVALID_REQUEST, tr!("lsp-no-method"))));
+            }
+            continue;
+        };
+        match (method, id) {
+            ("exit", _) => break,
+            // Notifications, like `initialized` and `$/cancelRequest`, which aren't answered.
+            (_, None) => {}
+            ("initialize", Some(id)) => send(
+                id,
+                Ok(json!({
+                    "capabilities": {},
+                    "serverInfo": { "name": "ata2", "version": env!("CARGO_PKG_VERSION") },
+                })),
+            ),
+            ("shutdown", Some(id)) => send(id, Ok(Value::Null)),
+            (method, Some(id)) => {
+                let method = method.to_string();
+                let params = message["params"].clone();
+                tokio::spawn(async move {
+                    let text = answer(&method, params).await;
+                    send(id, text.map(|text| json!({ "text": text })));
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The body of the next message on stdin, or `None` at its end.
+async fn read(stdin: &mut BufReader<Stdin>) -> TokioResult<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if stdin.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() && length.is_some() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                let value = value.trim();
+                length = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|e| format!("Content-Length: {value}: {e}"))?,
+                );
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    stdin.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+/// Send the response to request `id`: its result, or the code and message of its error.
+fn send(id: Value, result: Result<Value, (i64, String)>) {
+    let message = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    };
+    let json = message.to_string();
+    let mut stdout = io::stdout().lock();
+    // There's no one to tell if the editor's gone.
+    let _ = write!(stdout, "Content-Length: {}\r\n\r\n{json}", json.len())
+        .and_then(|()| stdout.flush());
+}
+
+/// The text that answers a request for `method` with `params`.
+async fn answer(method: &str, params: Value) -> Result<String, (i64, String)> {
+    let failed = |e: Box<dyn std::error::Error + Send + Sync>| (FAILED, e.to_string());
+    match method {
+        "complete" => {
+            let params: Complete = parse(params)?;
+            fim::complete(
+                &params.prefix,
+                params.suffix.as_deref(),
+                &mut Sinks::default(),
+            )
+            .await
+            .map_err(failed)
+        }
+        "edit-selection" => edit(parse(params)?).await.map_err(failed),
+        "chat" => chat(parse(params)?).await.map_err(failed),
+        _ => Err((METHOD_NOT_FOUND, tr!("lsp-unknown-method", method = method))),
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+async fn edit(params: EditSelection) -> TokioResult<String> {
+    let fenced = paste::fenced(&params.text);
+    let (fence, code) = fenced.split_once('\n').unwrap_or_default();
+    let language = params.language.unwrap_or_default();
+    let messages = vec![
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            role: Role::System,
+            content: Some(EDIT.to_string()),
+        }),
+        string_to_chat_completion_request_user_message(format!(
+            "{}\n\n{fence}{language}\n{code}",
+            params.instruction
+        )),
+    ];
+    let mut text = unfenced(&ask(&messages).await?);
+    // For the line after the selection to stay on a line of its own.
+    if params.text.ends_with('\n') && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// `text` without the code fence around it, if a model put it in one all the same.
+fn unfenced(text: &str) -> String {
+    let lines = text.trim().lines().collect::<Vec<_>>();
+    match (lines.first(), lines.last()) {
+        (Some(first), Some(last))
+            if lines.len() >= 2
+                && markdown::opening_fence(first).is_some_and(|fence| last.trim_end() == fence) =>
+        {
+            lines[1..lines.len() - 1].join("\n")
+        }
+        _ => text.trim_matches('\n').to_string(),
+    }
+}
+
+async fn chat(params: Chat) -> TokioResult<String> {
+    let mut messages = match &params.conversation {
+        Some(name) => CONVERSATIONS
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_default(),
+        None => vec![],
+    };
+    if messages.is_empty() {
+        prompt::set_system(&mut messages);
+    }
+    messages.push(string_to_chat_completion_request_user_message(
+        params.prompt,
+    ));
+    let response = ask(&language::instructed(&messages)).await?;
+    if let Some(name) = params.conversation {
+        messages.push(string_to_chat_completion_assistant_message(
+            response.clone(),
+        ));
+        CONVERSATIONS.lock().unwrap().insert(name, messages);
+    }
+    Ok(response)
+}
+
+/// The response of the model of the configuration to `messages`.
+async fn ask(messages: &[ChatCompletionRequestMessage]) -> TokioResult<String> {
+    let config: &Config = &CONFIGURATION;
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    let report = usage::Report::default();
+    let stream = openai::chat_stream(request.messages(messages.to_vec()).build()?, &report).await?;
+    // Not a word of it goes to stdout, which is the client's.
+    let response = sink::read(stream, &config.model, &mut Sinks::default()).await?;
+    usage::record(&config.model, messages, &response.text, &report);
+    Ok(response.text)
+}
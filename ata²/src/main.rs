@@ -15,29 +15,115 @@
 //!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 //!  See the License for the specific language governing permissions and
 //!  limitations under the License.
+#![allow(clippy::tabs_in_doc_comments)]
+
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
+#[macro_use]
+mod i18n;
+mod api_error;
 mod args;
+mod ask;
+mod attach;
 pub use crate::args::Ata2;
+mod bench;
+mod cache;
+mod chunk;
+mod clipboard;
+mod clipwatch;
+mod commands;
+mod completions;
 mod config;
+mod continuation;
+mod credentials;
+mod daemon;
+mod diff;
+mod doctor;
+mod draft;
+mod environment;
+mod eval;
+mod external;
+mod extract;
+mod fim;
+mod find;
+mod forecast;
+mod gc;
 pub use crate::config::Config;
+mod ghost;
+mod grammar;
 mod help;
+mod helper;
+mod history;
+mod keys;
+mod language;
+mod links;
+mod local;
+mod lock;
+mod logprobs;
+mod lsp;
+mod markdown;
+mod models;
+mod notify;
+mod openai;
+mod openapi;
+mod output;
+mod overrides;
+mod pacing;
+mod paste;
+mod pin;
+mod piped;
+mod policy;
+mod progress;
 mod prompt;
+mod queue;
+use crate::api_error::ApiError;
+use crate::args::{ColorChoice, Command, ConfigCommand, StdinFormat, ToolsCommand};
+use crate::piped::Input;
 use crate::prompt::load_conversation;
 mod readline;
+mod regen;
+mod remote;
+mod report;
+mod responses;
+mod rotation;
+mod router;
+mod sanitize;
+mod scheduler;
+mod scrollback;
+mod search;
+mod sessions;
+mod share;
+mod shutdown;
+mod sink;
+mod sources;
+mod spinner;
 mod state;
+mod store;
+mod style;
+mod summarize;
+mod sweep;
+mod tabular;
 pub use crate::state::*;
+mod template;
+mod tmux;
+mod tools;
+mod transcribe;
+mod translate;
+mod tui;
+mod usage;
+mod watch;
+mod width;
+mod workflow;
+mod wrap;
 
-use ansi_colors::ColouredStr;
 use futures_util::future::FutureExt as _;
 use futures_util::task::Context;
 use futures_util::task::Poll;
 
 use std::error::Error;
-use std::fs::File;
 
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
@@ -47,61 +133,170 @@ use std::time::Duration;
 pub type TokioResult<S = dyn Send + Sync, E = Box<dyn Error + Send + Sync>> = Result<S, E>;
 #[tokio::main]
 pub async fn main() -> TokioResult<()> {
+    completions::complete();
     if EXIT.load(Ordering::Acquire) {
         std::process::exit(0);
     } else {
+        // Before anything is printed, for the Windows console to take escape sequences.
+        style::ansi_supported();
         init_logger();
     }
+    grammar::load()?;
+    match &FLAGS.command {
+        Some(Command::Search {
+            query,
+            resume: Some(n),
+        }) => {
+            let path = find::resume_found(query, *n).await?;
+            info!(
+                "{}",
+                tr!("conversation-resumed", path = path.display().to_string())
+            );
+        }
+        Some(Command::Search {
+            query,
+            resume: None,
+        }) => return find::print(query),
+        Some(Command::Sessions { command }) => return sessions::run(command),
+        Some(Command::Share {
+            session,
+            output,
+            format,
+            gist,
+        }) => return share::run(session, output.as_deref(), *format, *gist).await,
+        Some(Command::Eval { suite, repeat }) => return eval::run(suite, *repeat).await,
+        Some(Command::Bench {
+            models,
+            prompt,
+            runs,
+        }) => return bench::run(models, prompt, *runs).await,
+        Some(Command::Sweep {
+            params,
+            prompt_file,
+            output,
+            format,
+        }) => return sweep::run(params, prompt_file, output.as_deref(), *format).await,
+        Some(Command::Flush) => return queue::run().await,
+        Some(Command::Keys) => return rotation::run(),
+        Some(Command::Models) => return models::run().await,
+        Some(Command::Doctor) => return doctor::run().await,
+        Some(Command::Gc { dry_run }) => return gc::run(*dry_run),
+        Some(Command::Watch { file, prompt }) => return watch::run(file, prompt).await,
+        Some(Command::Clipwatch { template }) => return clipwatch::run(template).await,
+        Some(Command::Translate { to }) => return translate::run(to).await,
+        Some(Command::Summarize { input, length }) => return summarize::run(input, *length).await,
+        Some(Command::Config {
+            command: ConfigCommand::Explain { key },
+        }) => return sources::explain(key),
+        // Goes on as any conversation, started as the workflow says.
+        Some(Command::New { .. }) => {}
+        Some(Command::Run {
+            workflow,
+            output,
+            retries,
+            timeout,
+        }) => return report::run(workflow, output.as_deref(), *retries, *timeout).await,
+        Some(Command::Lsp) => return lsp::run().await,
+        Some(Command::Daemon) => return daemon::run().await,
+        Some(Command::Ask { prompt }) => return daemon::ask(prompt).await,
+        Some(Command::Tools {
+            command: ToolsCommand::Import { spec, filter, api },
+        }) => return openapi::import(spec, filter, api.as_deref()),
+        Some(Command::Completions { shell }) => return completions::script(*shell),
+        Some(Command::Manpage) => return completions::manpage(),
+        None => {}
+    }
+    if FLAGS.dump_config {
+        return sources::dump();
+    }
+    if FLAGS.fim {
+        return fim::run().await;
+    }
+    if FLAGS.stdin_format == StdinFormat::Conversation {
+        return piped::conversation().await;
+    }
+    if FLAGS.save_key {
+        return credentials::save_key(&FLAGS.config.profile());
+    }
     if FLAGS.load.is_some() {
         load_conversation(FLAGS.load.as_ref().unwrap()).await?;
     }
+    if FLAGS.resume_last {
+        let path = readline::resume_last().await?;
+        info!(
+            "{}",
+            tr!("conversation-resumed", path = path.display().to_string())
+        );
+    }
+    prompt::set_system(&mut *prompt::CONVERSATION.lock().await);
+    attach::from_flags().await?;
+    workflow::start().await?;
     let mut rl = readline::Readline::new();
     let config = CONFIGURATION.clone();
     config.validate().unwrap_or_else(|e| {
-        error!("Config error!: {e}. Dying.");
+        error!("{}", tr!("config-error", error = e));
         panic!()
     });
+    shutdown::install();
 
-    let mut header = ColouredStr::new("Ask the Terminal Anything²\n\n");
-    header.bold();
+    if FLAGS.tui && config.ui.screen_reader {
+        warn!("{}", tr!("tui-screen-reader"));
+    } else if FLAGS.tui {
+        return tui::run().await;
+    }
 
     if atty::is(atty::Stream::Stderr) {
-        eprint!("{}", header);
+        eprint!("{}\n\n", style::paint("bold", "Ask the Terminal Anything²"));
     }
 
     if !FLAGS.hide_config && !config.ui.hide_config && atty::is(atty::Stream::Stderr) {
         eprintln!("{config}");
     }
     if atty::is(atty::Stream::Stdin) && config.ui.save_history {
-        if rl.load_history().await.is_err() {
-            warn!("No history file found. Creating a new one.");
-            File::create(&config.ui.history_file).unwrap_or_else(|e| {
-                error!("Could not create history file: {e}");
-                warn!("Using /dev/null as history file.");
-                File::open("/dev/null").unwrap()
-            });
+        if let Err(e) = rl.load_history().await {
+            history::give_up();
+            error!(
+                "{}",
+                tr!(
+                    "history-load-failed",
+                    path = history::location().display().to_string(),
+                    error = e.to_string()
+                )
+            );
         }
     }
-    rl.enable_multiline().await;
-    rl.enable_request_save().await;
+    rl.enable_skip_pacing().await;
+    rl.enable_ghost_text().await;
+    rl.enable_fuzzy_search().await;
+    rl.enable_vi_mode_indicator().await;
     // use tokio asynchronous message queue
-    let (tx, mut rx): (tokio::sync::mpsc::Sender<Option<String>>, _) =
-        tokio::sync::mpsc::channel(1);
+    let (tx, mut rx): (tokio::sync::mpsc::Sender<Option<Input>>, _) = tokio::sync::mpsc::channel(1);
 
-    let handle = tokio::spawn(async move {
+    let mut handle = tokio::spawn(async move {
         let n_pending_debug_log_notices = Arc::new(AtomicUsize::new(0));
         loop {
             let msg = Box::pin(rx.recv()).poll_unpin(&mut Context::from_waker(
                 futures_util::task::noop_waker_ref(),
             ));
             match msg {
-                Poll::Ready(Some(Some(line))) => {
+                Poll::Ready(Some(Some(Input::Message(message)))) => {
+                    prompt::CONVERSATION.lock().await.push(message);
+                }
+                Poll::Ready(Some(Some(Input::Line(line)))) if commands::is_command(&line) => {
+                    // Those that stream a response, which the readline loop leaves to this one.
+                    if let Err(e) = commands::dispatch(&line).await {
+                        error!("{e}");
+                        prompt::print_prompt();
+                    }
+                    n_pending_debug_log_notices.store(0, Ordering::SeqCst);
+                }
+                Poll::Ready(Some(Some(Input::Line(line)))) => {
                     let result = prompt::request(line.to_string(), 0).await;
                     match result {
                         Ok(_) => {}
-                        Err(e) => {
-                            error!("failed to request: {e}");
-                        }
+                        // Those of the API say what to do about them.
+                        Err(e) if e.is::<ApiError>() => error!("{e}"),
+                        Err(e) => error!("{}", tr!("request-failed", error = e.to_string())),
                     }
                     n_pending_debug_log_notices.store(0, Ordering::SeqCst);
                 }
@@ -153,31 +348,44 @@ pub async fn main() -> TokioResult<()> {
     });
 
     let readline_handle = rl.handle(tx).await;
+    // Only saving the history needs it, which isn't worth holding up the prompt for.
+    if atty::is(atty::Stream::Stdin) && config.ui.save_history {
+        tokio::task::spawn_blocking(|| {
+            if let Err(e) = CONFIGURATION.ui.check_history_dir() {
+                history::give_up();
+                error!("{}", tr!("history-unusable", error = e));
+            }
+        });
+    }
 
     tokio::select! {
         _ = readline_handle => {
             info!("Readline died");
+            // What was piped in is read at once, and answered one prompt after the other.
+            if !atty::is(atty::Stream::Stdin) {
+                let _ = (&mut handle).await;
+            }
         }
-        _ = handle => {
+        _ = &mut handle => {
             info!("API request loop died");
         }
     }
 
-    if atty::is(atty::Stream::Stdin) && config.ui.save_history {
-        rl.save_history().await?;
-        info!(
-            "Saved history to {history_file}. Number of entries: {entries}",
-            history_file = config.ui.history_file.to_string_lossy(),
-            entries = rl.history_len().await
-        );
-    }
-
-    Ok(())
+    shutdown::run(&mut rl, handle).await
 }
 
 fn init_logger() {
     let env = env_logger::Env::default().default_filter_or("info");
+    // Not by style::enabled, which reads the config, warning about it before there's a logger.
+    // env_logger's own `auto` already minds NO_COLOR.
+    let write_style = match FLAGS.color {
+        ColorChoice::Always => env_logger::WriteStyle::Always,
+        ColorChoice::Never => env_logger::WriteStyle::Never,
+        ColorChoice::Auto if FLAGS.a11y => env_logger::WriteStyle::Never,
+        ColorChoice::Auto => env_logger::WriteStyle::Auto,
+    };
     env_logger::Builder::from_env(env)
         .format_timestamp(None)
+        .write_style(write_style)
         .init();
 }
@@ -0,0 +1,252 @@
+//! Minimal Markdown helpers for model responses and the input being typed.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use crate::style::{theme, Style};
+
+/// A fenced code block (```` ``` ````) found in a Markdown document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// The info string after the opening fence, e.g. `rust` in ```` ```rust ````.
+    pub lang: Option<String>,
+    pub code: String,
+}
+
+/// Prose between code blocks, by line and without blank lines around it, or a code block.
+pub(crate) enum Part<'a> {
+    Prose(Vec<&'a str>),
+    Code(CodeBlock),
+}
+
+/// All fenced code blocks in `text`, in order. An unterminated fence runs to the end of the text,
+/// which is what you want for a response that got cut off.
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    parts(text)
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Code(block) => Some(block),
+            Part::Prose(_) => None,
+        })
+        .collect()
+}
+
+/// `text` split into prose and fenced code blocks, in order, as [`code_blocks`] finds them.
+pub(crate) fn parts(text: &str) -> Vec<Part<'_>> {
+    let mut ret = vec![];
+    let mut prose = vec![];
+    let mut current: Option<(String, CodeBlock)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match current.take() {
+            None => match opening_fence(trimmed) {
+                Some(fence) => {
+                    push_prose(&mut ret, &mut prose);
+                    let info = trimmed[fence.len()..].trim();
+                    let lang = info.split_whitespace().next().map(str::to_string);
+                    current = Some((
+                        fence,
+                        CodeBlock {
+                            lang,
+                            code: String::new(),
+                        },
+                    ));
+                }
+                None => prose.push(line),
+            },
+            Some((fence, mut block)) => {
+                if trimmed.starts_with(&fence) && trimmed[fence.len()..].trim().is_empty() {
+                    ret.push(Part::Code(block));
+                } else {
+                    block.code.push_str(line);
+                    block.code.push('\n');
+                    current = Some((fence, block));
+                }
+            }
+        }
+    }
+    if let Some((_, block)) = current {
+        ret.push(Part::Code(block));
+    }
+    push_prose(&mut ret, &mut prose);
+    ret
+}
+
+fn push_prose<'a>(parts: &mut Vec<Part<'a>>, prose: &mut Vec<&'a str>) {
+    let blank = |line: &&str| line.trim().is_empty();
+    let start = prose.iter().position(|line| !blank(line));
+    let end = prose.iter().rposition(|line| !blank(line));
+    if let (Some(start), Some(end)) = (start, end) {
+        parts.push(Part::Prose(prose[start..=end].to_vec()));
+    }
+    prose.clear();
+}
+
+/// `text` with fenced code blocks and inline code colored with ANSI escapes, the code in
+/// `ui.theme.code` and fences dim. Only escapes are added, so what the text takes up on screen
+/// stays the same.
+pub fn highlight(text: &str) -> String {
+    let fence_style = Style::parse("dim").unwrap_or_default();
+    let code_style = &theme().code;
+    let mut ret = String::with_capacity(text.len());
+    let mut fence: Option<String> = None;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            ret.push('\n');
+        }
+        let trimmed = line.trim_start();
+        match &fence {
+            None => match opening_fence(trimmed) {
+                Some(opening) => {
+                    fence = Some(opening);
+                    styled(&mut ret, &fence_style, line);
+                }
+                None => highlight_inline_code(&mut ret, code_style, line),
+            },
+            Some(opening) => {
+                if trimmed.starts_with(opening.as_str())
+                    && trimmed[opening.len()..].trim().is_empty()
+                {
+                    fence = None;
+                    styled(&mut ret, &fence_style, line);
+                } else {
+                    styled(&mut ret, code_style, line);
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Color spans between runs of as many backticks, like `` `this` `` or ``` ``that`` ```.
+fn highlight_inline_code(ret: &mut String, code_style: &Style, line: &str) {
+    for (piece, code) in inline_code(line) {
+        if code {
+            styled(ret, code_style, piece);
+        } else {
+            ret.push_str(piece);
+        }
+    }
+}
+
+/// `line` in pieces, each with whether it's inline code.
+pub(crate) fn inline_code(line: &str) -> Vec<(&str, bool)> {
+    let mut ret = vec![];
+    let mut rest = line;
+    let mut text = 0;
+    while let Some(start) = rest[text..].find('`').map(|i| text + i) {
+        let after = start + backticks(&rest[start..]);
+        match closing(&rest[after..], after - start) {
+            Some(i) => {
+                let end = after + i + (after - start);
+                if start > 0 {
+                    ret.push((&rest[..start], false));
+                }
+                ret.push((&rest[start..end], true));
+                rest = &rest[end..];
+                text = 0;
+            }
+            None => text = after,
+        }
+    }
+    if !rest.is_empty() {
+        ret.push((rest, false));
+    }
+    ret
+}
+
+/// How many backticks `text` starts with.
+pub(crate) fn backticks(text: &str) -> usize {
+    text.len() - text.trim_start_matches('`').len()
+}
+
+/// Where in `text` a run of exactly `run` backticks starts, not part of a longer one. Only ever
+/// cut at backticks, so whatever else is in the line.
+fn closing(text: &str, run: usize) -> Option<usize> {
+    let mut from = 0;
+    while let Some(i) = text[from..].find('`') {
+        let start = from + i;
+        let len = backticks(&text[start..]);
+        if len == run {
+            return Some(start);
+        }
+        from = start + len;
+    }
+    None
+}
+
+fn styled(ret: &mut String, style: &Style, text: &str) {
+    ret.push_str(&style.paint(text));
+}
+
+/// The fence that `line` opens a code block with, if it does.
+pub fn opening_fence(line: &str) -> Option<String> {
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let fence: String = line.chars().take_while(|c2| *c2 == c).collect();
+    (fence.len() >= 3).then_some(fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(line: &str) -> Vec<&str> {
+        inline_code(line)
+            .into_iter()
+            .filter_map(|(piece, code)| code.then_some(piece))
+            .collect()
+    }
+
+    #[test]
+    fn inline_code_spans() {
+        assert_eq!(code("a `b` c ``d`e`` f"), ["`b`", "``d`e``"]);
+        assert_eq!(code("``not closed` by one"), Vec::<&str>::new());
+        assert_eq!(code("```"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn inline_code_beside_non_ascii() {
+        assert_eq!(code("café `é` naïve"), ["`é`"]);
+        assert_eq!(code("``é`ü``"), ["``é`ü``"]);
+        for line in ["`é", "é`", "``é`", "`é``ü`", "日本`語`", "naïve ``"] {
+            let pieces = inline_code(line);
+            assert_eq!(
+                pieces.iter().map(|(piece, _)| *piece).collect::<String>(),
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn prose_between_code_blocks() {
+        let parts = parts("\nSee:\n\n```rust\nfn main() {}\n```\n\nThen\n~~~\nunterminated\n");
+        match &parts[..] {
+            [Part::Prose(before), Part::Code(first), Part::Prose(after), Part::Code(last)] => {
+                assert_eq!(before, &["See:"]);
+                assert_eq!(first.lang.as_deref(), Some("rust"));
+                assert_eq!(first.code, "fn main() {}\n");
+                assert_eq!(after, &["Then"]);
+                assert_eq!(
+                    (last.lang.as_deref(), last.code.as_str()),
+                    (None, "unterminated\n")
+                );
+            }
+            _ => panic!("split wrongly"),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! The models the API offers, for `ata2 models` and `/models`, and `/model`, which switches to one: each with its context size and what
+//! it can do, where either is known. The list is kept in `models.json` under `cache.dir`, to be
+//! shown when the API can't be reached and for the shell to complete model names from.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::api_error::ApiError;
+use crate::commands;
+use crate::openai;
+use crate::overrides;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// What models of an ID prefix can do, as far as we know. The longest matching prefix wins, as in
+/// [`usage::model_info`].
+static CAPABILITIES: &[(&str, &[&str])] = &[
+    ("gpt-3.5-turbo", &["chat", "tools"]),
+    ("gpt-3.5-turbo-instruct", &["completions"]),
+    ("gpt-4", &["chat", "tools"]),
+    ("gpt-4-turbo", &["chat", "tools", "vision"]),
+    ("gpt-4o", &["chat", "tools", "vision"]),
+    ("gpt-4o-audio", &["chat", "audio"]),
+    ("gpt-4o-realtime", &["audio"]),
+    ("gpt-4o-transcribe", &["transcription"]),
+    ("gpt-4o-mini-transcribe", &["transcription"]),
+    ("gpt-4o-mini-tts", &["speech"]),
+    ("o1", &["chat", "tools", "vision", "reasoning"]),
+    ("o1-mini", &["chat", "reasoning"]),
+    ("o3", &["chat", "tools", "vision", "reasoning"]),
+    ("o3-mini", &["chat", "tools", "reasoning"]),
+    ("o4-mini", &["chat", "tools", "vision", "reasoning"]),
+    ("text-embedding", &["embeddings"]),
+    ("dall-e", &["images"]),
+    ("gpt-image", &["images"]),
+    ("whisper", &["transcription"]),
+    ("tts", &["speech"]),
+    ("omni-moderation", &["moderation"]),
+    ("text-moderation", &["moderation"]),
+    ("babbage", &["completions"]),
+    ("davinci", &["completions"]),
+];
+
+/// A model of the listing.
+#[derive(Deserialize, Serialize)]
+pub struct Model {
+    pub id: String,
+    /// As the API says, for those that do, or as we know it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<usize>,
+}
+
+impl Model {
+    /// The model `entry` of the listing describes. OpenAI's don't say their context size, but the
+    /// listings of OpenRouter and some local servers do.
+    fn of(entry: &Value) -> Option<Self> {
+        let id = entry["id"].as_str()?.to_string();
+        let context = ["context_length", "context_window", "max_context_length"]
+            .iter()
+            .find_map(|field| entry[field].as_u64())
+            .map(|context| context as usize)
+            .or_else(|| usage::model_info(&id).map(|info| info.context));
+        Some(Self { id, context })
+    }
+
+    fn capabilities(&self) -> &'static [&'static str] {
+        CAPABILITIES
+            .iter()
+            .filter(|(prefix, _)| self.id.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(&[], |(_, capabilities)| capabilities)
+    }
+}
+
+/// Where the listing is kept under the cache directory `dir`.
+fn path(dir: &Path) -> PathBuf {
+    dir.join("models.json")
+}
+
+/// The listing last kept under the cache directory `dir`. None if there's none.
+pub fn cached(dir: &Path) -> Vec<Model> {
+    fs::read_to_string(path(dir))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// The IDs of the models of the listing last kept under the cache directory `dir` that start with
+/// `typed`, for the shell and the REPL to complete.
+pub fn completions(dir: &Path, typed: &str) -> Vec<String> {
+    cached(dir)
+        .into_iter()
+        .map(|model| model.id)
+        .filter(|id| id.starts_with(typed))
+        .collect()
+}
+
+/// Keep `models` for [`cached`].
+fn keep(models: &[Model]) {
+    let path = path(&config.cache.dir);
+    // Through a temporary file, for a completion never to read half of it.
+    let tmp = path.with_extension("tmp");
+    let result = serde_json::to_string(models)
+        .map_err(Into::into)
+        .and_then(|json| fs::create_dir_all(&config.cache.dir).and_then(|()| fs::write(&tmp, json)))
+        .and_then(|()| fs::rename(&tmp, &path));
+    if let Err(e) = result {
+        error!(
+            "{}",
+            tr!(
+                "models-cache-failed",
+                path = path.display().to_string(),
+                error = e.to_string()
+            )
+        );
+    }
+}
+
+/// The models the API offers, sorted by ID.
+pub async fn fetch() -> TokioResult<Vec<Model>> {
+    let response = openai::get("/models")?.send().await?;
+    if !response.status().is_success() {
+        return Err(ApiError::of(response, &config.model).await.into());
+    }
+    let json = response.json::<Value>().await?;
+    let mut models = json["data"]
+        .as_array()
+        .ok_or_else(|| tr!("models-unexpected"))?
+        .iter()
+        .filter_map(Model::of)
+        .collect::<Vec<_>>();
+    models.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
+
+/// The models the API offers, kept for next time, or those kept last time if it can't be reached.
+async fn list() -> TokioResult<Vec<Model>> {
+    match fetch().await {
+        Ok(models) => {
+            keep(&models);
+            Ok(models)
+        }
+        Err(e) if e.is::<reqwest::Error>() => {
+            let models = cached(&config.cache.dir);
+            if models.is_empty() {
+                return Err(e);
+            }
+            warn!("{}", tr!("models-offline", error = e.to_string()));
+            Ok(models)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Print `models`, the one prompts are sent to marked.
+fn print(models: &[Model]) {
+    let current = overrides::default();
+    let width = models.iter().map(|model| model.id.len()).max().unwrap_or(0);
+    for model in models {
+        let context = model
+            .context
+            .map(|context| tr!("models-context", tokens = context))
+            .unwrap_or_default();
+        let capabilities = model
+            .capabilities()
+            .iter()
+            .map(|capability| tr!("models-capability", capability = *capability))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mark = if model.id == current { '*' } else { ' ' };
+        let line = format!("{mark} {:width$}  {context:>16}  {capabilities}", model.id);
+        println!("{}", line.trim_end());
+    }
+}
+
+/// `ata2 models`: list the models the API offers.
+pub async fn run() -> TokioResult<()> {
+    print(&list().await?);
+    Ok(())
+}
+
+/// `/models`.
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    if !args.is_empty() {
+        return Err(commands::usage("models"));
+    }
+    print(&list().await?);
+    Ok(())
+}
+
+/// `/model [model]`: print the model prompts are sent to, or send them to `model` from now on.
+pub async fn switch(args: Vec<String>) -> TokioResult<()> {
+    match args.as_slice() {
+        [] => println!("{}", overrides::default()),
+        [model] => {
+            overrides::switch(model.clone());
+            info!("{}", tr!("model-switched", model = model.as_str()));
+        }
+        _ => return Err(commands::usage("model")),
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! Bell and desktop notifications for responses which finish while the terminal doesn't have
+//! focus.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use notify_rust::Notification;
+
+use std::env;
+use std::io::Write as _;
+use std::process::Command;
+
+use crate::config::Notify;
+use crate::CONFIGURATION as config;
+
+const MAX_BODY_CHARS: usize = 120;
+
+/// Called when a response has been printed in full.
+pub fn response_complete(response: &str) {
+    let how = config.ui.notify_on_complete;
+    if how == Notify::Off {
+        return;
+    }
+    let body = summarize(response);
+    // Asking tmux and X11 about focus means running processes, so keep it off the reactor.
+    tokio::task::spawn_blocking(move || {
+        // When we can't tell, notify anyway: the user asked for it.
+        if terminal_has_focus() == Some(true) {
+            return;
+        }
+        if matches!(how, Notify::Bell | Notify::Both) && atty::is(atty::Stream::Stderr) {
+            eprint!("\x07");
+            let _ = std::io::stderr().flush();
+        }
+        if matches!(how, Notify::Desktop | Notify::Both) {
+            if let Err(e) = Notification::new()
+                .appname("ata2")
+                .summary(&tr!("notification-summary"))
+                .body(&body)
+                .show()
+            {
+                warn!("{}", tr!("notification-failed", error = e.to_string()));
+            }
+        }
+    });
+}
+
+/// First line of the response, shortened to fit in a notification bubble.
+fn summarize(response: &str) -> String {
+    let first_line = response
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default();
+    if first_line.chars().count() > MAX_BODY_CHARS {
+        let mut short: String = first_line.chars().take(MAX_BODY_CHARS - 1).collect();
+        short.push('…');
+        short
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// Does the terminal we're running in have focus? `None` if we can't tell.
+fn terminal_has_focus() -> Option<bool> {
+    // A tmux pane in a background window, or in a detached session, can't have focus whatever the
+    // window manager says.
+    if let Ok(pane) = env::var("TMUX_PANE") {
+        let visible = output(
+            "tmux",
+            &[
+                "display-message",
+                "-p",
+                "-t",
+                &pane,
+                "#{pane_active}#{window_active}#{?session_attached,1,0}",
+            ],
+        )?;
+        if visible != "111" {
+            return Some(false);
+        }
+    }
+    // X11 terminal emulators export their window's ID as $WINDOWID.
+    let window: u64 = env::var("WINDOWID").ok()?.parse().ok()?;
+    let active: u64 = output("xdotool", &["getactivewindow"])?.parse().ok()?;
+    Some(window == active)
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,312 @@
+//! Requests to OpenAI's API, made here and not by async-openai so that `api = "local"`, `auth` and
+//! the rotation of API keys apply to all of them: streamed chat completions that report their
+//! usage and route the conversation to where its prefix is cached, those that aren't streamed,
+//! completions for FIM, and what [`crate::responses`] needs.
+//!
+//! OpenAI caches the prefixes of prompts on its own, and a conversation only ever grows at the
+//! end, so each request of it starts with the last one. The usage of a response says how much of
+//! the prompt was cached, which is billed at a discount.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::config::{Config as _, OpenAIConfig};
+use async_openai::error::OpenAIError;
+use async_openai::types::{
+    ChatChoice, ChatCompletionRequestMessage, ChatCompletionResponseMessage,
+    ChatCompletionResponseStream, CompletionResponseStream, CreateChatCompletionRequest,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse, CreateCompletionRequest,
+    CreateCompletionResponse, Role,
+};
+use eventsource_stream::Eventsource as _;
+use futures_util::StreamExt as _;
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
+
+use sha2::{Digest as _, Sha256};
+
+use std::sync::OnceLock;
+
+use crate::api_error::ApiError;
+use crate::config::{Auth, Config};
+use crate::grammar;
+use crate::local;
+use crate::logprobs;
+use crate::readline::chat_completion_message_text;
+use crate::rotation;
+use crate::usage::{self, Reported};
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// The client of every request to the API, for the connections it keeps open to be used again.
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// A POST request to `path` of the API, as configured, with the next API key of [`rotation`].
+pub fn post(path: &str) -> TokioResult<RequestBuilder> {
+    request(Method::POST, path)
+}
+
+/// A GET request to `path` of the API, like [`post`].
+pub fn get(path: &str) -> TokioResult<RequestBuilder> {
+    request(Method::GET, path)
+}
+
+fn request(method: Method, path: &str) -> TokioResult<RequestBuilder> {
+    let config: &Config = &CONFIGURATION;
+    let key = match config.auth {
+        Auth::Key => rotation::next(config).ok_or_else(|| tr!("keys-spent-all"))?,
+        Auth::None => String::new(),
+    };
+    let openai = OpenAIConfig::new()
+        .with_api_base(config.api_base.trim_end_matches('/'))
+        .with_api_key(key);
+    let mut headers = openai.headers();
+    // async-openai's, for the Assistants API it was written for.
+    headers.remove("OpenAI-Beta");
+    if config.auth == Auth::None {
+        headers.remove(AUTHORIZATION);
+    }
+    Ok(CLIENT
+        .get_or_init(reqwest::Client::new)
+        .request(method, openai.url(path))
+        .headers(headers))
+}
+
+/// POST `body` to `path` of the API. A request that's rate limited is sent again with the next API
+/// key, as long as there's one it wasn't sent with.
+pub async fn send(path: &str, body: &Value) -> TokioResult<Response> {
+    let mut tries = rotation::available(&CONFIGURATION);
+    loop {
+        let response = post(path)?.json(body).send().await?;
+        if response.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(response);
+        }
+        rotation::rate_limited();
+        tries = tries.saturating_sub(1);
+        if tries == 0 {
+            return Ok(response);
+        }
+    }
+}
+
+/// Open a connection to the API, or keep the one that's open from being closed for being idle, for
+/// the next request not to wait for it. Whether the API answers doesn't matter.
+pub async fn warm() {
+    if local::enabled() {
+        return;
+    }
+    match get("/models") {
+        Ok(request) => match request.send().await {
+            Ok(response) => debug!("Warmed the connection: {}", response.status()),
+            Err(e) => debug!("Could not warm the connection: {e}"),
+        },
+        Err(e) => debug!("Could not warm the connection: {e}"),
+    }
+}
+
+/// Whether requests go to OpenAI's own API, and not to a compatible server, which may refuse what
+/// only OpenAI's knows, like `stream_options` and `prompt_cache_key`.
+pub fn official() -> bool {
+    reqwest::Url::parse(&CONFIGURATION.api_base)
+        .is_ok_and(|url| url.host_str() == Some("api.openai.com"))
+}
+
+/// The `prompt_cache_key` of the conversation of `messages`, after its first message, so that its
+/// requests go where its prefix is likely cached. The same from one run of ata² to the next.
+pub fn cache_key(messages: &[ChatCompletionRequestMessage]) -> Option<String> {
+    let first = chat_completion_message_text(messages.first()?)?;
+    let hash = format!("{:x}", Sha256::digest(first));
+    Some(format!("ata2-{}", &hash[..16]))
+}
+
+/// Stream the response to `request`, as async-openai's `create_stream` does, and report its usage
+/// to `report` before the last chunk. With `api = "local"`, it's generated by [`local`] instead.
+pub async fn chat_stream(
+    request: CreateChatCompletionRequest,
+    report: &usage::Report,
+) -> TokioResult<ChatCompletionResponseStream> {
+    if local::enabled() {
+        return local::chat_stream(request).await;
+    }
+    let mut body = serde_json::to_value(&request)?;
+    body["stream"] = true.into();
+    if official() {
+        body["stream_options"] = json!({ "include_usage": true });
+        if let Some(key) = cache_key(&request.messages) {
+            body["prompt_cache_key"] = key.into();
+        }
+    }
+    grammar::apply(&mut body);
+    logprobs::apply(&mut body);
+    let response = send("/chat/completions", &body).await?;
+    if !response.status().is_success() {
+        return Err(ApiError::of(response, &request.model).await.into());
+    }
+
+    let mut events = response.bytes_stream().eventsource();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let report = report.clone();
+    tokio::spawn(async move {
+        // The chunk that finishes the response comes before the usage, and the response is
+        // accounted for as soon as it's read, so it's held back until the usage is in.
+        let mut last = None;
+        while let Some(event) = events.next().await {
+            let data = match event {
+                Ok(event) if event.data == "[DONE]" => break,
+                Ok(event) => event.data,
+                Err(e) => {
+                    let _ = tx.send(Err(OpenAIError::StreamError(e.to_string())));
+                    return;
+                }
+            };
+            let Ok(json) = serde_json::from_str::<Value>(&data) else {
+                continue;
+            };
+            if let Some(reported) = reported(&json["usage"]) {
+                report.set(reported);
+            }
+            logprobs::record(&json);
+            let chunk = match serde_json::from_value::<CreateChatCompletionStreamResponse>(json) {
+                Ok(chunk) if chunk.choices.is_empty() => continue,
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    let _ = tx.send(Err(OpenAIError::JSONDeserialize(e)));
+                    return;
+                }
+            };
+            if chunk.choices.iter().any(|c| c.finish_reason.is_some()) {
+                last = Some(chunk);
+            } else if tx.send(Ok(chunk)).is_err() {
+                return;
+            }
+        }
+        if let Some(chunk) = last {
+            let _ = tx.send(Ok(chunk));
+        }
+    });
+    Ok(Box::pin(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+    ))
+}
+
+/// The response to `request`, not streamed, as async-openai's `create` does, but [`send`] with
+/// the next API key if it's rate limited. With `api = "local"`, it's the response [`local`]
+/// streams, put together.
+pub async fn chat(
+    request: CreateChatCompletionRequest,
+) -> TokioResult<CreateChatCompletionResponse> {
+    if local::enabled() {
+        return chat_local(request).await;
+    }
+    let mut body = serde_json::to_value(&request)?;
+    body["stream"] = false.into();
+    let response = send("/chat/completions", &body).await?;
+    if !response.status().is_success() {
+        return Err(ApiError::of(response, &request.model).await.into());
+    }
+    Ok(response.json().await?)
+}
+
+async fn chat_local(
+    request: CreateChatCompletionRequest,
+) -> TokioResult<CreateChatCompletionResponse> {
+    let model = request.model.clone();
+    let mut stream = local::chat_stream(request).await?;
+    let mut content = String::new();
+    let mut finish_reason = None;
+    while let Some(chunk) = stream.next().await {
+        for choice in chunk?.choices {
+            content += &choice.delta.content.unwrap_or_default();
+            finish_reason = choice.finish_reason.or(finish_reason);
+        }
+    }
+    #[allow(deprecated)]
+    let message = ChatCompletionResponseMessage {
+        content: Some(content),
+        tool_calls: None,
+        role: Role::Assistant,
+        function_call: None,
+    };
+    Ok(CreateChatCompletionResponse {
+        id: String::new(),
+        choices: vec![ChatChoice {
+            index: 0,
+            message,
+            finish_reason,
+        }],
+        created: 0,
+        model,
+        system_fingerprint: None,
+        object: "chat.completion".to_string(),
+        usage: None,
+    })
+}
+
+/// Stream the completion of `request`, a prompt and not a conversation, as async-openai's
+/// `create_stream` does. A model of `api = "local"` only chats, so it's an error.
+pub async fn completion_stream(
+    request: CreateCompletionRequest,
+) -> TokioResult<CompletionResponseStream> {
+    if local::enabled() {
+        return Err(tr!("local-no-completions").into());
+    }
+    let mut body = serde_json::to_value(&request)?;
+    body["stream"] = true.into();
+    let response = send("/completions", &body).await?;
+    if !response.status().is_success() {
+        return Err(ApiError::of(response, &request.model).await.into());
+    }
+
+    let mut events = response.bytes_stream().eventsource();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let chunk = match event {
+                Ok(event) if event.data == "[DONE]" => break,
+                Ok(event) => serde_json::from_str::<CreateCompletionResponse>(&event.data)
+                    .map_err(OpenAIError::JSONDeserialize),
+                Err(e) => Err(OpenAIError::StreamError(e.to_string())),
+            };
+            let failed = chunk.is_err();
+            if tx.send(chunk).is_err() || failed {
+                return;
+            }
+        }
+    });
+    Ok(Box::pin(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+    ))
+}
+
+/// The usage of chat completions, or of responses, which name it differently.
+pub fn reported(usage: &Value) -> Option<Reported> {
+    let count = |keys: [&str; 2]| {
+        keys.iter()
+            .find_map(|key| usage[key].as_u64())
+            .map(|n| n as usize)
+    };
+    Some(Reported {
+        prompt_tokens: count(["prompt_tokens", "input_tokens"])?,
+        completion_tokens: count(["completion_tokens", "output_tokens"])?,
+        cached_tokens: ["prompt_tokens_details", "input_tokens_details"]
+            .iter()
+            .find_map(|key| usage[key]["cached_tokens"].as_u64())
+            .unwrap_or_default() as usize,
+    })
+}
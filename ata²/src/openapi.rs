@@ -0,0 +1,285 @@
+//! `ata2 tools import`: tools of `[tools.custom]` made of the operations of an OpenAPI spec, each
+//! a request to the API, for the model to use an API that nothing's been written for. They're
+//! printed for the configuration with the API of `[tools.apis]` they make requests to, and a
+//! header for each way the spec says it's authenticated, to be filled in.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::TokioResult;
+
+/// The methods of the operations of a path, as the spec has them.
+const METHODS: &[&str] = &["get", "put", "post", "delete", "patch", "head", "options"];
+
+/// Print the tools of the operations of the paths of `spec` that match any of `filters`, or all
+/// of them, with the API they make requests to, named `api` or after the spec.
+pub fn import(spec: &Path, filters: &[String], api: Option<&str>) -> TokioResult<()> {
+    let text = fs::read_to_string(spec).map_err(|e| format!("{}: {e}", spec.display()))?;
+    // YAML has JSON in it, but its errors are of YAML.
+    let spec: Value = match serde_json::from_str(&text) {
+        Ok(spec) => spec,
+        Err(_) => serde_yaml::from_str(&text).map_err(|e| format!("{}: {e}", spec.display()))?,
+    };
+    let filters = filters.iter().map(|f| pattern(f)).collect::<Vec<_>>();
+    let api = match api {
+        Some(api) => name(api),
+        None => name(spec["info"]["title"].as_str().unwrap_or("api")).to_lowercase(),
+    };
+
+    let mut tools = String::new();
+    let mut count = 0;
+    for (path, item) in spec["paths"].as_object().into_iter().flatten() {
+        if !filters.is_empty() && !filters.iter().any(|filter| filter.is_match(path)) {
+            continue;
+        }
+        for method in METHODS {
+            let Some(operation) = item.get(*method) else {
+                continue;
+            };
+            let id = operation["operationId"]
+                .as_str()
+                .map_or_else(|| format!("{method}_{path}"), str::to_string);
+            writeln!(tools, "\n[tools.custom.{}]", name(&id))?;
+            let description = operation["summary"]
+                .as_str()
+                .or(operation["description"].as_str())
+                .unwrap_or_default()
+                .trim();
+            if !description.is_empty() {
+                writeln!(tools, "description = {}", string(description))?;
+            }
+            writeln!(tools, "api = {}", string(&api))?;
+            writeln!(tools, "method = {}", string(&method.to_uppercase()))?;
+            writeln!(tools, "path = {}", string(path))?;
+            let parameters = parameters(&spec, item, operation).to_string();
+            writeln!(tools, "parameters = {}", literal(&parameters))?;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return Err(tr!("openapi-none").into());
+    }
+
+    let base_url = base_url(&spec);
+    println!("[tools.apis.{api}]");
+    println!("base_url = {}", string(&base_url));
+    println!("headers = {{ {} }}", headers(&spec).join(", "));
+    print!("{tools}");
+
+    info!(
+        "{}",
+        tr!("openapi-imported", count = count, api = api.as_str())
+    );
+    if reqwest::Url::parse(&base_url).is_err() {
+        warn!("{}", tr!("openapi-no-base-url", api = api.as_str()));
+    }
+    Ok(())
+}
+
+/// `s` as a TOML string.
+fn string(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
+
+/// `s` as a TOML string, a literal one if it can be, for JSON to be read as it is.
+fn literal(s: &str) -> String {
+    if s.contains(['\'', '\n', '\r']) {
+        string(s)
+    } else {
+        format!("'{s}'")
+    }
+}
+
+/// `filter` as a regex, where `*` is anything.
+fn pattern(filter: &str) -> Regex {
+    let regex = filter
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+    Regex::new(&format!("^{regex}$")).expect("escaped")
+}
+
+/// `s` as the name of a tool, which is up to 64 letters, digits, `_` or `-`.
+fn name(s: &str) -> String {
+    let ret = s
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    let ret = ret.trim_matches('_');
+    match ret.char_indices().nth(64) {
+        Some((end, _)) => ret[..end].to_string(),
+        None if ret.is_empty() => String::from("api"),
+        None => ret.to_string(),
+    }
+}
+
+/// The JSON schema of what the operation takes, of the parameters of its path and its query, and
+/// of what it's sent, as `body`. Headers and cookies are left to `[tools.apis]`.
+fn parameters(spec: &Value, item: &Value, operation: &Value) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    let given = item["parameters"]
+        .as_array()
+        .into_iter()
+        .chain(operation["parameters"].as_array())
+        .flatten();
+    for parameter in given {
+        let parameter = resolve(spec, parameter, &mut vec![]);
+        let Some(name) = parameter["name"].as_str() else {
+            continue;
+        };
+        let (name, mut schema) = match parameter["in"].as_str() {
+            Some("path" | "query") => {
+                let schema = match &parameter["schema"] {
+                    Value::Null => match &parameter["type"] {
+                        // Of Swagger 2.0, which has it in the parameter.
+                        Value::Null => json!({ "type": "string" }),
+                        kind => json!({ "type": kind }),
+                    },
+                    schema => schema.clone(),
+                };
+                (name, schema)
+            }
+            Some("body") => ("body", parameter["schema"].clone()),
+            _ => continue,
+        };
+        if let (Some(description), Some(schema)) =
+            (parameter["description"].as_str(), schema.as_object_mut())
+        {
+            schema.insert("description".into(), description.into());
+        }
+        properties.insert(name.to_string(), schema);
+        if parameter["in"] == "path" || parameter["required"] == true {
+            required.push(name.to_string());
+        }
+    }
+    let body = resolve(spec, &operation["requestBody"], &mut vec![]);
+    let schema = &body["content"]["application/json"]["schema"];
+    if !schema.is_null() {
+        properties.insert("body".into(), schema.clone());
+        if body["required"] == true {
+            required.push("body".into());
+        }
+    }
+    let mut ret = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        ret["required"] = required.into();
+    }
+    ret
+}
+
+/// `value` with what each `$ref` in it refers to in place of it, but those of `seen`, which it's
+/// in already. What refers to itself, or to other files, is left open.
+fn resolve<'a>(spec: &'a Value, value: &'a Value, seen: &mut Vec<&'a str>) -> Value {
+    match value {
+        Value::Object(object) => {
+            if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+                let target = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| spec.pointer(pointer));
+                return match target {
+                    Some(target) if !seen.contains(&reference) => {
+                        seen.push(reference);
+                        let ret = resolve(spec, target, seen);
+                        seen.pop();
+                        ret
+                    }
+                    _ => json!({}),
+                };
+            }
+            Value::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), resolve(spec, value, seen)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| resolve(spec, value, seen))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Where the API is: its first server, with the defaults of its variables, or of Swagger 2.0,
+/// its host and base path.
+fn base_url(spec: &Value) -> String {
+    let server = &spec["servers"][0];
+    if let Some(url) = server["url"].as_str() {
+        let mut url = url.to_string();
+        for (name, variable) in server["variables"].as_object().into_iter().flatten() {
+            if let Some(default) = variable["default"].as_str() {
+                url = url.replace(&format!("{{{name}}}"), default);
+            }
+        }
+        return url;
+    }
+    let Some(host) = spec["host"].as_str() else {
+        return String::new();
+    };
+    let scheme = spec["schemes"][0].as_str().unwrap_or("https");
+    let base_path = spec["basePath"].as_str().unwrap_or_default();
+    format!("{scheme}://{host}{base_path}")
+}
+
+/// A header for each way the spec says the API is authenticated, that's sent with each request,
+/// with what comes before the credentials, if anything, as TOML.
+fn headers(spec: &Value) -> Vec<String> {
+    let schemes = spec["components"]["securitySchemes"]
+        .as_object()
+        .or(spec["securityDefinitions"].as_object());
+    let mut ret = vec![];
+    for (_, scheme) in schemes.into_iter().flatten() {
+        let scheme = resolve(spec, scheme, &mut vec![]);
+        let (header, value) = match (scheme["type"].as_str(), scheme["in"].as_str()) {
+            (Some("apiKey"), Some("header")) => match scheme["name"].as_str() {
+                Some(name) => (name, ""),
+                None => continue,
+            },
+            (Some("http"), _) if scheme["scheme"].as_str() == Some("basic") => {
+                ("Authorization", "Basic ")
+            }
+            (Some("basic"), _) => ("Authorization", "Basic "),
+            (Some("http" | "oauth2" | "openIdConnect"), _) => ("Authorization", "Bearer "),
+            _ => continue,
+        };
+        let header = if name(header) == header {
+            header.to_string()
+        } else {
+            string(header)
+        };
+        let header = format!("{header} = {}", string(value));
+        if !ret.contains(&header) {
+            ret.push(header);
+        }
+    }
+    ret
+}
@@ -0,0 +1,282 @@
+//! Stdout, written by a thread of its own from a bounded queue, for a slow reader, like a pager or
+//! a pipe over the network, not to hold up the worker that streams a response in: it waits for
+//! room in the queue without blocking, and stops waiting for Ctrl-C or the cancel key.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::time::sleep;
+
+use std::io;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TryRecvError, TrySendError};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::ABORT;
+use crate::CANCEL;
+
+/// How many writes can wait in the queue, which are the chunks of a response, mostly.
+const CAPACITY: usize = 256;
+
+/// How often stdout is written to at most while a response streams in, about as often as a
+/// terminal is redrawn, 60 times a second.
+const FRAME: Duration = Duration::from_micros(16_667);
+
+/// How often a full queue is looked at again, and whether the response was stopped meanwhile.
+const POLL: Duration = Duration::from_millis(10);
+
+static QUEUE: OnceLock<SyncSender<Write>> = OnceLock::new();
+
+enum Write {
+    Text(String),
+    /// Say so once everything before it is written.
+    Flush(mpsc::Sender<()>),
+}
+
+fn queue() -> &'static SyncSender<Write> {
+    QUEUE.get_or_init(|| {
+        let (tx, rx) = mpsc::sync_channel(CAPACITY);
+        thread::spawn(move || writer(rx, io::stdout()));
+        tx
+    })
+}
+
+/// Write what's queued a frame at a time: text that comes in within [`FRAME`] of the last write
+/// is held back and written with it, in one write and one flush, rather than a flush per chunk of
+/// a response. What a reader that's gone doesn't read is lost.
+fn writer(rx: Receiver<Write>, mut stdout: impl io::Write) {
+    let mut frame = String::new();
+    let mut drawn = Instant::now();
+    loop {
+        let write = if frame.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout((drawn + FRAME).saturating_duration_since(Instant::now()))
+        };
+        match write {
+            Ok(Write::Text(text)) => {
+                frame.push_str(&text);
+                if drawn.elapsed() < FRAME {
+                    continue;
+                }
+            }
+            Ok(Write::Flush(done)) => {
+                draw(&mut stdout, &mut frame);
+                let _ = done.send(());
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                draw(&mut stdout, &mut frame);
+                return;
+            }
+        }
+        draw(&mut stdout, &mut frame);
+        drawn = Instant::now();
+    }
+}
+
+fn draw(stdout: &mut impl io::Write, frame: &mut String) {
+    let _ = stdout.write_all(frame.as_bytes());
+    let _ = stdout.flush();
+    frame.clear();
+}
+
+fn stopped() -> bool {
+    ABORT.load(Ordering::Relaxed) || CANCEL.load(Ordering::Relaxed)
+}
+
+/// Queue `write`, waiting for room. Returns whether it was queued, which it isn't if the response
+/// is stopped first.
+async fn queued(write: Write) -> bool {
+    queued_to(queue(), write).await
+}
+
+async fn queued_to(queue: &SyncSender<Write>, mut write: Write) -> bool {
+    loop {
+        match queue.try_send(write) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(full)) if !stopped() => write = full,
+            Err(_) => return false,
+        }
+        sleep(POLL).await;
+    }
+}
+
+/// Queue `text`, waiting for room, unless the response is stopped first.
+pub async fn write(text: &str) {
+    queued(Write::Text(text.to_string())).await;
+}
+
+/// Wait for what's queued to be written, unless the response is stopped first, before what's
+/// printed to stderr after it.
+pub async fn flush() {
+    let (tx, rx) = mpsc::channel();
+    if !queued(Write::Flush(tx)).await {
+        return;
+    }
+    while let Err(TryRecvError::Empty) = rx.try_recv() {
+        if stopped() {
+            return;
+        }
+        sleep(POLL).await;
+    }
+}
+
+/// Queue `text`, blocking, if the queue's full, on a thread of its own rather than a worker of the
+/// runtime.
+pub fn print(text: &str) {
+    let write = Write::Text(text.to_string());
+    if let Err(TrySendError::Full(write)) = queue().try_send(write) {
+        let _ = blocking(|| queue().send(write));
+    }
+}
+
+/// Write `text` and wait for it to be written, as `print!` and a flush of stdout do.
+pub fn print_and_flush(text: &str) {
+    print(text);
+    let (tx, rx) = mpsc::channel();
+    let _ = blocking(|| {
+        queue()
+            .send(Write::Flush(tx))
+            .ok()
+            .and_then(|()| rx.recv().ok())
+    });
+}
+
+/// Run `f`, which blocks, with `block_in_place` on a worker of a multi-threaded runtime, for its
+/// tasks to move to the others, or just run it where that would panic, on a runtime of one thread
+/// like that [`crate::remote`] downloads the configuration with.
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::CurrentThread => f(),
+        _ => tokio::task::block_in_place(f),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    /// A reader that takes its time with each write, like a pager or a pipe over the network, or
+    /// that doesn't read at all until `gate` is dropped.
+    struct Reader {
+        written: Arc<Mutex<Vec<String>>>,
+        delay: Duration,
+        gate: Option<Receiver<()>>,
+    }
+
+    impl io::Write for Reader {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(gate) = &self.gate {
+                let _ = gate.recv();
+            }
+            thread::sleep(self.delay);
+            self.written
+                .lock()
+                .unwrap()
+                .push(String::from_utf8_lossy(buf).into_owned());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn chunks(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("{i} ")).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slow_reader_does_not_hold_up_the_stream() {
+        let written = Arc::new(Mutex::new(vec![]));
+        let (tx, rx) = mpsc::sync_channel(CAPACITY);
+        let reader = Reader {
+            written: written.clone(),
+            delay: Duration::from_millis(50),
+            gate: None,
+        };
+        let writer = thread::spawn(move || writer(rx, reader));
+
+        // A token a millisecond, with the reader taking 50 for each write.
+        let chunks = chunks(200);
+        let started = Instant::now();
+        for chunk in &chunks {
+            assert!(queued_to(&tx, Write::Text(chunk.clone())).await);
+            sleep(Duration::from_millis(1)).await;
+        }
+        assert!(started.elapsed() < Duration::from_secs(2));
+        drop(tx);
+        writer.join().unwrap();
+
+        let written = written.lock().unwrap();
+        assert_eq!(written.concat(), chunks.concat());
+        // A write a frame, not a write a chunk.
+        assert!(
+            written.len() < chunks.len() / 16,
+            "{} writes",
+            written.len()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn full_queue_waits_without_blocking_the_runtime() {
+        let written = Arc::new(Mutex::new(vec![]));
+        let (tx, rx) = mpsc::sync_channel(CAPACITY);
+        let (open, gate) = mpsc::channel();
+        let reader = Reader {
+            written: written.clone(),
+            delay: Duration::ZERO,
+            gate: Some(gate),
+        };
+        let writer = thread::spawn(move || writer(rx, reader));
+
+        // The writer takes the first chunk and is stuck writing it.
+        assert!(queued_to(&tx, Write::Text("first ".to_string())).await);
+        sleep(FRAME * 3).await;
+        let chunks = chunks(CAPACITY + 16);
+        let producer = tokio::spawn({
+            let (tx, chunks) = (tx.clone(), chunks.clone());
+            async move {
+                for chunk in chunks {
+                    assert!(queued_to(&tx, Write::Text(chunk)).await);
+                }
+            }
+        });
+        sleep(POLL * 10).await;
+        assert!(!producer.is_finished(), "queued more than the queue holds");
+
+        drop(open);
+        tokio::time::timeout(Duration::from_secs(2), producer)
+            .await
+            .expect("still waiting for room")
+            .unwrap();
+        drop(tx);
+        writer.join().unwrap();
+        assert_eq!(
+            written.lock().unwrap().concat(),
+            format!("first {}", chunks.concat())
+        );
+    }
+}
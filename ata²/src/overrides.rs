@@ -0,0 +1,219 @@
+//! Parameters of a single prompt, given before it: `@t=0.1 @m=gpt-4o explain this stack trace`
+//! sends “explain this stack trace” to `gpt-4o` at temperature 0.1, and the next prompt as
+//! configured again. Each is `@name=value`, with the name of the setting or its short name, and
+//! they end at the first word that isn't one.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::CreateChatCompletionRequestArgs;
+use serde_json::Value;
+
+use std::sync::Mutex;
+
+use crate::CONFIGURATION;
+
+/// The parameters that can be overridden, each with its short name.
+const PARAMS: [(&str, &str); 6] = [
+    ("model", "m"),
+    ("temperature", "t"),
+    ("top_p", "p"),
+    ("max_tokens", "mt"),
+    ("presence_penalty", "pp"),
+    ("frequency_penalty", "fp"),
+];
+
+/// The overrides of the last prompt, which go for continuing its response too.
+static LAST: Mutex<Overrides> = Mutex::new(Overrides::NONE);
+
+/// The model `/model` switched to, which prompts are sent to unless they say otherwise.
+static SWITCHED: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Clone, Debug)]
+pub struct Overrides {
+    model: Option<String>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_tokens: Option<u16>,
+    presence_penalty: Option<f32>,
+    frequency_penalty: Option<f32>,
+}
+
+impl Overrides {
+    const NONE: Self = Self {
+        model: None,
+        temperature: None,
+        top_p: None,
+        max_tokens: None,
+        presence_penalty: None,
+        frequency_penalty: None,
+    };
+
+    /// Set parameter `name`, or its short name, to `value`.
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let Some((param, _)) = PARAMS
+            .iter()
+            .find(|(long, short)| name == *long || name == *short)
+        else {
+            let params = PARAMS
+                .iter()
+                .map(|(long, short)| format!("{short} ({long})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(tr!("override-unknown", name = name, params = params));
+        };
+        let not_valid = || tr!("override-not-valid", name = name, value = value);
+        let number = || {
+            value
+                .parse::<f32>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .ok_or_else(not_valid)
+        };
+        match *param {
+            "model" if !value.is_empty() => self.model = Some(value.to_string()),
+            "model" => return Err(not_valid()),
+            "temperature" => self.temperature = Some(number()?),
+            "top_p" => self.top_p = Some(number()?),
+            "max_tokens" => {
+                self.max_tokens = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|&max_tokens| max_tokens >= 1)
+                        .ok_or_else(not_valid)?,
+                );
+            }
+            "presence_penalty" => self.presence_penalty = Some(number()?),
+            _ => self.frequency_penalty = Some(number()?),
+        }
+        Ok(())
+    }
+
+    /// The model they say to send to.
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Send to `model`, unless they say which model to send to.
+    pub fn or_model(&mut self, model: String) {
+        self.model.get_or_insert(model);
+    }
+
+    /// Them, but to send to `model`.
+    pub fn with_model(self, model: &str) -> Self {
+        Self {
+            model: Some(model.to_string()),
+            ..self
+        }
+    }
+
+    /// Apply them to a chat completions `request`.
+    pub fn chat(&self, request: &mut CreateChatCompletionRequestArgs) {
+        if let Some(model) = &self.model {
+            request.model(model);
+        }
+        if let Some(temperature) = self.temperature {
+            request.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            request.top_p(top_p);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            request.max_tokens(max_tokens);
+        }
+        if let Some(presence_penalty) = self.presence_penalty {
+            request.presence_penalty(presence_penalty);
+        }
+        if let Some(frequency_penalty) = self.frequency_penalty {
+            request.frequency_penalty(frequency_penalty);
+        }
+    }
+
+    /// Apply them to the `body` of a responses request, which has no penalties.
+    pub fn responses(&self, body: &mut Value) {
+        if let Some(model) = &self.model {
+            body["model"] = model.as_str().into();
+        }
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = temperature.into();
+        }
+        if let Some(top_p) = self.top_p {
+            body["top_p"] = top_p.into();
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            body["max_output_tokens"] = max_tokens.into();
+        }
+    }
+}
+
+/// The overrides `prompt` begins with, and the rest of it. Fails on one that's unknown or of a
+/// value that isn't valid, rather than send the prompt as it shouldn't be, and on a prompt that's
+/// nothing but overrides.
+pub fn parse(prompt: &str) -> Result<(Overrides, &str), String> {
+    let mut overrides = Overrides {
+        model: SWITCHED.lock().unwrap().clone(),
+        ..Overrides::NONE
+    };
+    let mut rest = prompt.trim_start();
+    let mut any = false;
+    while let Some(word) = rest.split_whitespace().next() {
+        let Some((name, value)) = word.strip_prefix('@').and_then(|word| word.split_once('='))
+        else {
+            break;
+        };
+        overrides.set(name, value)?;
+        any = true;
+        rest = rest[word.len()..].trim_start();
+    }
+    match (any, rest.is_empty()) {
+        (false, _) => Ok((overrides, prompt)),
+        (true, true) => Err(tr!("override-no-prompt")),
+        (true, false) => Ok((overrides, rest)),
+    }
+}
+
+/// Send the requests of the next prompt with `overrides`.
+pub fn set(overrides: Overrides) {
+    *LAST.lock().unwrap() = overrides;
+}
+
+/// The overrides of the last prompt.
+pub fn last() -> Overrides {
+    LAST.lock().unwrap().clone()
+}
+
+/// The model the last prompt was sent to.
+pub fn model() -> String {
+    LAST.lock().unwrap().model.clone().unwrap_or_else(default)
+}
+
+/// Send the prompts after to `model`, unless they say which model to send to.
+pub fn switch(model: String) {
+    *SWITCHED.lock().unwrap() = Some(model);
+}
+
+/// The model prompts are sent to unless they say otherwise: the one `/model` switched to, or the
+/// configured one.
+pub fn default() -> String {
+    SWITCHED
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| CONFIGURATION.model.clone())
+}
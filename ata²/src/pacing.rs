@@ -0,0 +1,98 @@
+//! Pacing of streamed responses, so fast models don't print walls of text faster than anyone can
+//! read them.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use tokio::time::{sleep_until, Instant};
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use crate::config::StreamPacing;
+use crate::output;
+use crate::ABORT;
+use crate::CANCEL;
+use crate::CONFIGURATION as config;
+use crate::SKIP_PACING;
+
+/// Prints the chunks of one response according to `ui.stream_pacing`.
+pub struct Pacer {
+    /// Text received but not printed yet.
+    pending: String,
+    /// When the typewriter started, and how many characters it has typed since.
+    typed: Option<(Instant, u32)>,
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Self {
+            pending: String::new(),
+            typed: None,
+        }
+    }
+
+    pub async fn push(&mut self, text: &str) {
+        if SKIP_PACING.load(Ordering::Relaxed) {
+            self.pending.push_str(text);
+            self.finish();
+            return;
+        }
+        match config.ui.stream_pacing {
+            StreamPacing::Instant => output::write(text).await,
+            StreamPacing::Words => {
+                self.pending.push_str(text);
+                if let Some((i, c)) = self
+                    .pending
+                    .char_indices()
+                    .rev()
+                    .find(|(_, c)| c.is_whitespace())
+                {
+                    let words: String = self.pending.drain(..i + c.len_utf8()).collect();
+                    output::write(&words).await;
+                }
+            }
+            StreamPacing::Typewriter => {
+                let delay = Duration::from_secs_f64(1.0 / config.ui.typewriter_cps as f64);
+                let (start, typed) = self.typed.get_or_insert_with(|| (Instant::now(), 0));
+                for (i, c) in text.char_indices() {
+                    if SKIP_PACING.load(Ordering::Relaxed)
+                        || ABORT.load(Ordering::Relaxed)
+                        || CANCEL.load(Ordering::Relaxed)
+                    {
+                        output::write(&text[i..]).await;
+                        break;
+                    }
+                    output::write(c.encode_utf8(&mut [0; 4])).await;
+                    *typed += 1;
+                    // Sleeping until a deadline rather than for `delay` keeps the rate steady,
+                    // however long printing took.
+                    sleep_until(*start + delay * *typed).await;
+                }
+            }
+        }
+    }
+
+    /// Print whatever is left, e.g. the last word in [`StreamPacing::Words`] mode.
+    pub fn finish(&mut self) {
+        if !self.pending.is_empty() {
+            output::print(&self.pending);
+            self.pending.clear();
+        }
+    }
+}
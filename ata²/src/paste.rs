@@ -0,0 +1,145 @@
+//! Large pastes (`ui.large_paste`). The terminal brackets what is pasted, so rustyline inserts
+//! it whole, newlines and all, instead of taking a newline in it for Enter. This keeps track of
+//! what was pasted, so that a big paste can be sent fenced or as an attachment.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, RepeatCount};
+
+use std::io::{self, Write as _};
+use std::sync::Mutex;
+
+use crate::config::LargePaste;
+use crate::CONFIGURATION as config;
+
+/// Where the cursor was when a paste started, until [`observe`] sees it inserted.
+static PASTE_START: Mutex<Option<usize>> = Mutex::new(None);
+/// Large pastes into the input being edited.
+static PASTES: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+/// Bound to the start of a bracketed paste. Notes where the cursor is, then lets rustyline read
+/// and insert the paste.
+pub struct PasteStartHandler;
+
+impl ConditionalEventHandler for PasteStartHandler {
+    fn handle(
+        &self,
+        _event: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        ctx: &EventContext,
+    ) -> Option<Cmd> {
+        *PASTE_START.lock().unwrap() = Some(ctx.pos());
+        None
+    }
+}
+
+/// Called with the input whenever it changes, from [`crate::helper::ReplHelper`]'s hinter. The
+/// paste ends at the cursor once inserted.
+pub fn observe(line: &str, pos: usize) {
+    let Some(start) = PASTE_START.lock().unwrap().take() else {
+        return;
+    };
+    if let Some(pasted) = line.get(start..pos).filter(|pasted| is_large(pasted)) {
+        PASTES.lock().unwrap().push(pasted.to_string());
+    }
+}
+
+pub fn is_large(text: &str) -> bool {
+    config.ui.large_paste_size > 0 && text.chars().count() >= config.ui.large_paste_size
+}
+
+/// The large pastes that are still in `line`, which is being sent. Forgets them all.
+pub fn take(line: &str) -> Vec<String> {
+    let mut pastes = std::mem::take(&mut *PASTES.lock().unwrap());
+    pastes.retain(|paste| line.contains(paste.as_str()));
+    pastes
+}
+
+/// What to do with `pastes`: `ui.large_paste`, or if that's `ask`, what the user answers. `None`
+/// if they'd rather not send the input after all.
+pub fn choose(pastes: &[String]) -> Option<LargePaste> {
+    if config.ui.large_paste != LargePaste::Ask {
+        return Some(config.ui.large_paste);
+    }
+    let lines = pastes
+        .iter()
+        .map(|paste| paste.lines().count())
+        .sum::<usize>();
+    let chars = pastes
+        .iter()
+        .map(|paste| paste.chars().count())
+        .sum::<usize>();
+    loop {
+        eprint!("{} ", tr!("paste-choose", lines = lines, chars = chars));
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+        // rustyline is done with the terminal, so it's back to reading whole lines.
+        match io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => {}
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "" | "f" => return Some(LargePaste::Fence),
+            "a" => return Some(LargePaste::Attach),
+            "i" => return Some(LargePaste::Inline),
+            _ => {}
+        }
+    }
+}
+
+/// `line` with `pastes` in it fenced, or moved to the end as attachments named `paste-1.txt` and
+/// so on, each leaving its name behind.
+pub fn apply(mut line: String, pastes: &[String], how: LargePaste) -> String {
+    let mut attachments = vec![];
+    for paste in pastes {
+        match how {
+            LargePaste::Ask | LargePaste::Inline => {}
+            LargePaste::Fence => {
+                line = line.replacen(paste.as_str(), &format!("\n{}\n", fenced(paste)), 1)
+            }
+            LargePaste::Attach => {
+                let name = format!("paste-{}.txt", attachments.len() + 1);
+                line = line.replacen(paste.as_str(), &format!("[{name}]"), 1);
+                attachments.push(format!("{name}:\n{}", fenced(paste)));
+            }
+        }
+    }
+    let line = line.trim().to_string();
+    if attachments.is_empty() {
+        return line;
+    }
+    format!("{line}\n\n{}", attachments.join("\n\n"))
+}
+
+/// In a code fence longer than any run of backticks in `text`.
+pub fn fenced(text: &str) -> String {
+    fenced_as(text, "")
+}
+
+/// In a code fence as [`fenced`] puts it, with `language` as its info string.
+pub fn fenced_as(text: &str, language: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{}\n{fence}", text.trim_matches('\n'))
+}
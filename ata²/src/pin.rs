@@ -0,0 +1,141 @@
+//! `/pin`: messages that are always sent, however long the conversation gets. A request that
+//! wouldn't fit in the context of the model, with room for the response, is sent without the
+//! oldest messages, as many as it takes, but for the system message, the prompt and those that
+//! are pinned, such as the one with the requirements that everything after depends on. The
+//! conversation itself keeps them all. Pins are saved with the conversation, like its tags.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, Role};
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use crate::commands;
+use crate::overrides;
+use crate::prompt::CONVERSATION;
+use crate::readline::{self, chat_completion_message_role};
+use crate::sessions;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// The pinned messages of the conversation, by their number, counting from 1.
+static PINS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+/// The pinned messages, as for saving them.
+pub fn all() -> Vec<usize> {
+    PINS.lock().unwrap().iter().copied().collect()
+}
+
+/// Pin `pins` instead, as when a conversation is loaded.
+pub fn set(pins: Vec<usize>) {
+    *PINS.lock().unwrap() = pins.into_iter().collect();
+}
+
+/// Whether message `n`, counting from 1, is pinned.
+pub fn is_pinned(n: usize) -> bool {
+    PINS.lock().unwrap().contains(&n)
+}
+
+/// `/pin [n | -n]`: pin message n of the conversation, as `/show` numbers them, or the last
+/// prompt. `-n` unpins it.
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    let len = CONVERSATION.lock().await.len();
+    let (n, pin) = match args.as_slice() {
+        [] => {
+            let conversation = CONVERSATION.lock().await;
+            let last = conversation
+                .iter()
+                .rposition(|m| chat_completion_message_role(m) == Role::User)
+                .ok_or_else(|| tr!("conversation-empty"))?;
+            (last + 1, true)
+        }
+        [arg] => match arg.strip_prefix('-') {
+            Some(n) => (n.parse().map_err(|_| commands::usage("pin"))?, false),
+            None => (arg.parse().map_err(|_| commands::usage("pin"))?, true),
+        },
+        _ => return Err(commands::usage("pin")),
+    };
+    if n == 0 || n > len {
+        return Err(tr!("pin-no-such", n = n, count = len).into());
+    }
+    {
+        let mut pins = PINS.lock().unwrap();
+        if pin {
+            pins.insert(n);
+        } else {
+            pins.remove(&n);
+        }
+    }
+    if let Some(path) = readline::autosave_path() {
+        sessions::set_pins(&path, &all())?;
+    }
+    if pin {
+        info!("{}", tr!("pinned", n = n));
+    } else {
+        info!("{}", tr!("unpinned", n = n));
+    }
+    Ok(())
+}
+
+/// `messages`, the conversation with the prompt, without as many of the oldest of them as it takes
+/// for the request to fit in the context of the model it goes to, with room for `max_tokens` of
+/// response. The system message, the prompt and pinned messages stay. As they are for a model
+/// whose context isn't known.
+pub fn fit(messages: &[ChatCompletionRequestMessage]) -> Vec<ChatCompletionRequestMessage> {
+    let model = overrides::model();
+    let Some(info) = usage::model_info(&model) else {
+        return messages.to_vec();
+    };
+    let budget = info
+        .context
+        .saturating_sub(usize::try_from(config.max_tokens).unwrap_or(0));
+    let mut tokens = usage::conversation_tokens(&model, messages);
+    if tokens <= budget {
+        return messages.to_vec();
+    }
+    let pins = PINS.lock().unwrap().clone();
+    let mut left_out = BTreeSet::new();
+    for (i, message) in messages
+        .iter()
+        .enumerate()
+        .take(messages.len().saturating_sub(1))
+    {
+        if tokens <= budget {
+            break;
+        }
+        if chat_completion_message_role(message) == Role::System || pins.contains(&(i + 1)) {
+            continue;
+        }
+        tokens -= usage::conversation_tokens(&model, std::slice::from_ref(message))
+            .saturating_sub(usage::conversation_tokens(&model, &[]));
+        left_out.insert(i);
+    }
+    info!(
+        "{}",
+        tr!("context-trimmed", count = left_out.len(), tokens = tokens)
+    );
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !left_out.contains(i))
+        .map(|(_, message)| message.clone())
+        .collect()
+}
@@ -0,0 +1,189 @@
+//! Prompts piped to stdin: all of it as one prompt, the parts between lines of `--delimiter` as
+//! one each, or with `--stdin-format jsonl` a message a line, like
+//! `{"role": "user", "content": "…"}`. With `--stdin-format conversation`, a whole conversation,
+//! which is sent and printed to stdout with the response, for scripts to take it from there.
+//! Prompts that are just text are sent in `stdin_wrapper`, if it's set.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, Role};
+use serde::Deserialize;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Read as _};
+
+use crate::args::StdinFormat;
+use crate::overrides;
+use crate::prompt::{self, eprint_and_flush};
+use crate::readline::{
+    chat_completion_message_role, chat_completion_message_text,
+    string_to_chat_completion_assistant_message, string_to_chat_completion_request_user_message,
+};
+use crate::sanitize;
+use crate::sink::{self, Plain, Sinks};
+use crate::template;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+use crate::FLAGS;
+
+/// What the prompt loop is sent, from the line editor or stdin.
+#[derive(Debug)]
+pub enum Input {
+    /// A prompt or a slash command, as typed.
+    Line(String),
+    /// A message of `--stdin-format jsonl` that isn't the user's, added to the conversation as it is.
+    Message(ChatCompletionRequestMessage),
+}
+
+/// A line of `--stdin-format jsonl`.
+#[derive(Deserialize)]
+struct Line {
+    role: Role,
+    content: String,
+}
+
+/// `stdin`, all of it, as what it has for the prompt loop, in order, each prompt in the template
+/// of `--template`.
+pub fn split(stdin: &str) -> TokioResult<VecDeque<Input>> {
+    let mut ret = inputs(stdin)?;
+    if let Some(name) = &FLAGS.template {
+        let template = CONFIGURATION.template(name)?;
+        for input in &mut ret {
+            if let Input::Line(line) = input {
+                *line = template::prompt(template, line);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+fn inputs(stdin: &str) -> TokioResult<VecDeque<Input>> {
+    let stdin = stdin.replace("\r\n", "\n");
+    if FLAGS.stdin_format == StdinFormat::Jsonl {
+        return jsonl(&stdin);
+    }
+    let Some(delimiter) = &FLAGS.delimiter else {
+        return Ok(VecDeque::from([Input::Line(wrap(&stdin))]));
+    };
+    let mut ret = VecDeque::new();
+    let mut prompt = String::new();
+    for line in stdin.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            ret.extend(part(&prompt));
+            prompt.clear();
+        } else {
+            prompt += line;
+        }
+    }
+    ret.extend(part(&prompt));
+    Ok(ret)
+}
+
+/// `prompt` without the blank lines around it, if there's more to it than those.
+fn part(prompt: &str) -> Option<Input> {
+    let prompt = prompt.trim_matches('\n');
+    (!prompt.trim().is_empty()).then(|| Input::Line(wrap(prompt)))
+}
+
+/// `prompt` in `stdin_wrapper`.
+fn wrap(prompt: &str) -> String {
+    let wrapper = &CONFIGURATION.stdin_wrapper;
+    if wrapper.is_empty() {
+        return prompt.to_string();
+    }
+    let filename = file_name().unwrap_or_else(|| String::from("stdin"));
+    template::render(
+        wrapper,
+        &[
+            ("input", prompt.trim_end_matches('\n').to_string()),
+            ("filename", filename),
+        ],
+        false,
+    )
+}
+
+/// The name of the file stdin is, as in `ata2 < notes.txt`. Only known on Linux, and not for a
+/// pipe.
+fn file_name() -> Option<String> {
+    let path = fs::read_link("/proc/self/fd/0").ok()?;
+    path.is_file()
+        .then(|| path.file_name())
+        .flatten()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+fn jsonl(stdin: &str) -> TokioResult<VecDeque<Input>> {
+    let mut ret = VecDeque::new();
+    for (i, line) in stdin.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Line { role, content } = serde_json::from_str(line)
+            .map_err(|e| tr!("jsonl-invalid", line = i + 1, error = e.to_string()))?;
+        ret.push_back(match role {
+            Role::User => Input::Line(content),
+            Role::Assistant => Input::Message(string_to_chat_completion_assistant_message(content)),
+            Role::System => Input::Message(ChatCompletionRequestMessage::System(
+                ChatCompletionRequestSystemMessage {
+                    role: Role::System,
+                    content: Some(content),
+                },
+            )),
+            _ => return Err(tr!("jsonl-role", line = i + 1).into()),
+        });
+    }
+    Ok(ret)
+}
+
+/// `--stdin-format conversation`: send the conversation on stdin, in the JSON of saved ones, print
+/// the response to stderr as it streams in, and the conversation with the response to stdout.
+pub async fn conversation() -> TokioResult<()> {
+    let mut json = vec![];
+    io::stdin().read_to_end(&mut json)?;
+    let json = sanitize::bytes(&json);
+    let mut messages: Vec<ChatCompletionRequestMessage> = serde_json::from_str(&json)
+        .map_err(|e| tr!("stdin-conversation-invalid", error = e.to_string()))?;
+    if messages.last().map(chat_completion_message_role) != Some(Role::User) {
+        return Err(tr!("stdin-conversation-no-prompt").into());
+    }
+    if let Some(name) = &FLAGS.template {
+        let template = CONFIGURATION.template(name)?;
+        let prompt = messages
+            .pop()
+            .as_ref()
+            .and_then(chat_completion_message_text);
+        messages.push(string_to_chat_completion_request_user_message(
+            template::prompt(template, &prompt.unwrap_or_default()),
+        ));
+    }
+    prompt::set_system(&mut messages);
+    let model = overrides::model();
+    let report = usage::Report::default();
+    let stream = prompt::stream(&messages, &report).await?;
+    // Stdout is for the conversation.
+    let mut sinks = Sinks::new(vec![Box::new(Plain::stderr())]);
+    let response = sink::read(stream, &model, &mut sinks).await?;
+    eprint_and_flush("\n");
+    usage::record(&model, &messages, &response.text, &report);
+    messages.push(string_to_chat_completion_assistant_message(response.text));
+    println!("{}", serde_json::to_string(&messages)?);
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! Live status line (elapsed time, tokens so far, tokens per second) shown on the bottom row of the
+//! terminal while a response streams in.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use terminal_size::{terminal_size, Height, Width};
+use tokio::task::JoinHandle;
+
+use std::io::{self, Write as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::shutdown;
+use crate::style;
+use crate::width;
+use crate::CONFIGURATION as config;
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The status line of one response. It's cleared, and the terminal's scrolling region restored,
+/// on [`Progress::finish`] or when dropped.
+pub struct Progress {
+    inner: Option<Inner>,
+}
+
+struct Inner {
+    tokens: Arc<AtomicU64>,
+    /// `None` once finished. Draws happen with this locked, so that a redraw the ticker started
+    /// just before being cancelled can't undo [`Progress::finish`].
+    rows: Arc<Mutex<Option<u16>>>,
+    ticker: JoinHandle<()>,
+}
+
+impl Progress {
+    pub fn start() -> Self {
+        if !config.ui.show_progress || !atty::is(atty::Stream::Stderr) {
+            return Self { inner: None };
+        }
+        let Some((Width(cols), Height(rows))) = terminal_size() else {
+            return Self { inner: None };
+        };
+        if rows < 3 {
+            return Self { inner: None };
+        }
+        // Make sure there's a line below the cursor, then keep the last row out of the scrolling
+        // region so the response can't scroll over the status line.
+        write_stderr(&format!("\n\x1b[1A\x1b7\x1b[1;{top}r\x1b8", top = rows - 1));
+        shutdown::SCROLL_REGION.store(true, Ordering::Relaxed);
+        let tokens = Arc::new(AtomicU64::new(0));
+        let rows = Arc::new(Mutex::new(Some(rows)));
+        let start = Instant::now();
+        let ticker = tokio::spawn({
+            let tokens = tokens.clone();
+            let rows = rows.clone();
+            async move {
+                loop {
+                    if let Some(rows) = *rows.lock().unwrap() {
+                        let status = status(start.elapsed(), tokens.load(Ordering::Relaxed));
+                        let status = width::truncate(&status, cols as usize);
+                        write_stderr(&format!(
+                            "\x1b7\x1b[{rows};1H\x1b[2K{}\x1b8",
+                            style::paint("dim", status)
+                        ));
+                    }
+                    tokio::time::sleep(REDRAW_INTERVAL).await;
+                }
+            }
+        });
+        Self {
+            inner: Some(Inner {
+                tokens,
+                rows,
+                ticker,
+            }),
+        }
+    }
+
+    /// Count one streamed chunk. OpenAI sends one token per chunk.
+    pub fn add_token(&self) {
+        if let Some(inner) = &self.inner {
+            inner.tokens.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        let Some(inner) = self.inner.take() else {
+            return;
+        };
+        inner.ticker.abort();
+        let rows = inner.rows.lock().unwrap().take();
+        if let Some(rows) = rows {
+            write_stderr(&format!("\x1b7\x1b[r\x1b8\x1b7\x1b[{rows};1H\x1b[2K\x1b8"));
+            shutdown::SCROLL_REGION.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+fn status(elapsed: Duration, tokens: u64) -> String {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 {
+        tokens as f64 / secs
+    } else {
+        0.0
+    };
+    format!("{secs:.1}s · {tokens} tokens · {rate:.1} tokens/s")
+}
+
+/// Escape sequences have to reach the terminal in one piece, hence one `write_all`.
+pub fn write_stderr(s: &str) {
+    let mut stderr = io::stderr().lock();
+    let _ = stderr.write_all(s.as_bytes());
+    let _ = stderr.flush();
+}
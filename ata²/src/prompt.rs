@@ -18,74 +18,102 @@
 //!  See the License for the specific language governing permissions and
 //!  limitations under the License.
 
-use ansi_colors::ColouredStr;
-use async_openai::{
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestMessage, ChatCompletionResponseStreamMessage,
-        CreateChatCompletionRequestArgs, FinishReason,
-    },
-    Client,
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, ChatCompletionResponseStream,
+    ChatCompletionResponseStreamMessage, CreateChatCompletionRequestArgs, FinishReason, Role,
 };
-use atty;
 use log::debug;
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt as _;
 
-use std::io::{self, Stderr, Stdout};
-use std::io::{Read as _, Write as _};
+use std::io::Write as _;
+use std::io::{self, Stderr};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use crate::api_error::{self, ApiError};
+use crate::attach;
+use crate::commands;
+use crate::config::{Config, ContinueTruncated};
+use crate::continuation::{self, Stitcher};
+use crate::draft;
+use crate::external;
+use crate::language;
+use crate::logprobs;
+use crate::notify;
+use crate::openai;
+use crate::overrides;
+use crate::pin;
+use crate::queue;
 use crate::readline::{
+    self, autosave, autosave_to, chat_completion_message_role, chat_completion_message_text,
     string_to_chat_completion_assistant_message, string_to_chat_completion_request_user_message,
 };
+use crate::responses;
+use crate::router;
+use crate::sessions;
+use crate::sink::{Event, Sinks, Terminal};
+use crate::spinner::Spinner;
+use crate::style::{self, theme};
+use crate::template;
+use crate::tools;
+use crate::usage;
+use crate::workflow;
 use crate::TokioResult;
 use crate::ABORT;
+use crate::CANCEL;
 use crate::CONFIGURATION;
+use crate::FLAGS;
 use crate::IS_RUNNING;
+use crate::SKIP_PACING;
 
 lazy_static! {
-    static ref STDOUT: Stdout = io::stdout();
     static ref STDERR: Stderr = io::stderr();
     pub static ref CONVERSATION: Mutex<Vec<ChatCompletionRequestMessage>> = Mutex::new(vec![]);
 }
 
 pub async fn load_conversation<P: AsRef<std::path::Path>>(path: P) -> TokioResult<()> {
-    let mut file = std::fs::File::open(path)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    let lines = contents.split("\n").collect::<Vec<_>>();
+    let loaded_conversation = readline::read_conversation(path.as_ref())?;
     let mut conversation = CONVERSATION.lock().await;
-    let loaded_conversation = serde_json::from_str::<Vec<ChatCompletionRequestMessage>>(
-        &lines
-            .into_iter()
-            .filter(|o| !o.is_empty())
-            .collect::<Vec<_>>()
-            .join("\n"),
-    )?;
     conversation.clear();
     conversation.extend(loaded_conversation);
+    responses::set_thread(sessions::thread_of(path.as_ref())?);
+    pin::set(sessions::pins_of(path.as_ref())?);
+    continuation::set_truncated(false);
+    // Don't autosave over the conversation that was there before.
+    autosave_to(None);
     Ok(())
 }
 
-fn print_and_flush(text: &str) {
-    print!("{text}");
-    (&*STDOUT).flush().unwrap();
+/// Start `conversation` with the system message of `--system`, or of the workflow of `ata2 new`,
+/// if there's one, instead of the one it starts with.
+pub fn set_system(conversation: &mut Vec<ChatCompletionRequestMessage>) {
+    let workflow = workflow::get().and_then(|workflow| workflow.system.as_ref());
+    let Some(system) = FLAGS.system.as_ref().or(workflow) else {
+        return;
+    };
+    if conversation.first().map(chat_completion_message_role) == Some(Role::System) {
+        conversation.remove(0);
+    }
+    conversation.insert(
+        0,
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            role: Role::System,
+            content: Some(system.clone()),
+        }),
+    );
 }
 
-fn eprint_and_flush(text: &str) {
+pub fn eprint_and_flush(text: &str) {
     eprint!("{text}");
     (&*STDERR).flush().unwrap();
 }
 
-fn eprint_bold(msg: &str) {
+pub fn eprint_dim(msg: &str) {
     if atty::is(atty::Stream::Stderr) {
-        let mut bold = ColouredStr::new(msg);
-        bold.bold();
-        let bold = bold.to_string();
-        eprint_and_flush(&bold.as_str());
+        eprint_and_flush(&style::paint("dim", msg));
     } else {
         eprint_and_flush(msg);
     }
@@ -93,13 +121,61 @@ fn eprint_bold(msg: &str) {
 
 pub fn print_prompt() {
     if atty::is(atty::Stream::Stderr) {
-        eprint_bold("\nPrompt:\n");
+        if CONFIGURATION.ui.status_line {
+            eprint_dim(&format!("\n{}", status_line()));
+        }
+        let banner = banner(&CONFIGURATION.ui.prompt_format);
+        eprint_and_flush(&format!("\n{}\n", theme().prompt.paint(&banner)));
+    }
+}
+
+/// Model, profile, context usage, how much of the prompts was cached and session cost.
+pub fn status_line() -> String {
+    let model = &CONFIGURATION.model;
+    let usage = usage::session();
+    let mut context = match usage::model_info(model) {
+        Some(info) => format!("{}/{} tokens", usage.context_tokens, info.context),
+        None => format!("{} tokens", usage.context_tokens),
+    };
+    if usage.cached_tokens > 0 {
+        let cached = usage.cached_tokens * 100 / usage.prompt_tokens.max(1);
+        context += &format!(" · {cached}% cached");
     }
+    format!(
+        "{model} · {profile} · {context} · {cost}",
+        profile = FLAGS.config.profile(),
+        cost = cost(&usage),
+    )
 }
 
-fn print_response_prompt() {
+fn cost(usage: &usage::SessionUsage) -> String {
+    match (usage.cost, usage::model_info(&CONFIGURATION.model)) {
+        (Some(cost), _) => format!("${cost:.4}"),
+        (None, Some(_)) => format!("${:.4}", 0.0),
+        (None, None) => String::from("cost unknown"),
+    }
+}
+
+/// Placeholders of `ui.prompt_format` and `ui.response_format`.
+pub const BANNER_PLACEHOLDERS: &[&str] = &["model", "profile", "time", "turn", "cost"];
+
+/// The line above a prompt or response, from `ui.prompt_format` or `ui.response_format`.
+fn banner(format: &str) -> String {
+    let usage = usage::session();
+    let values = [
+        ("model", CONFIGURATION.model.clone()),
+        ("profile", FLAGS.config.profile()),
+        ("time", chrono::Local::now().format("%H:%M:%S").to_string()),
+        ("turn", (usage.exchanges + 1).to_string()),
+        ("cost", cost(&usage)),
+    ];
+    template::render(format, &values, style::enabled(atty::Stream::Stderr))
+}
+
+pub fn print_response_prompt() {
     if atty::is(atty::Stream::Stderr) {
-        eprint_bold("\nResponse:\n");
+        let banner = banner(&CONFIGURATION.ui.response_format);
+        eprint_and_flush(&format!("\n{}\n", theme().response.paint(&banner)));
     }
 }
 
@@ -109,122 +185,388 @@ fn finish_prompt() {
 }
 
 fn print_error(msg: &str) {
-    error!("{msg}");
+    if atty::is(atty::Stream::Stderr) {
+        error!("{}", theme().error.paint(msg));
+    } else {
+        error!("{msg}");
+    }
     finish_prompt()
 }
 
-fn store_and_do_nothing(print_buffer: &mut Vec<String>, text: &str) -> String {
-    print_buffer.push(text.to_string());
-    "".to_string()
+/// Append `prompt` to the conversation as a user message and send the conversation to the model.
+/// Returns the messages sent and where its usage is reported, to be passed on to
+/// [`finish_exchange`], and the response stream.
+///
+/// This, and [`finish_exchange`], is what the REPL and the TUI have in common.
+pub async fn send(
+    prompt: String,
+) -> TokioResult<(
+    Vec<ChatCompletionRequestMessage>,
+    ChatCompletionResponseStream,
+    usage::Report,
+)> {
+    let (prompt, messages) = prepare(prompt).await?;
+    let report = usage::Report::default();
+    match stream(&messages, &report).await {
+        Ok(stream) => Ok((messages, stream, report)),
+        Err(e) if queue::enabled() && queue::offline(&*e) => {
+            // Without a response, it's not part of the conversation until `ata2 flush` sends it,
+            // overrides and all.
+            CONVERSATION.lock().await.pop();
+            let mut queued = messages;
+            queued.pop();
+            queued.push(string_to_chat_completion_request_user_message(prompt));
+            let count = queue::push(&queued)?;
+            Err(tr!("queue-queued", count = count).into())
+        }
+        Err(e) => Err(e),
+    }
 }
 
-fn join_and_clear(print_buffer: &mut Vec<String>, text: &str) -> String {
-    let from_buffer = print_buffer.join("");
-    print_buffer.clear();
-    let joined = format!("{from_buffer}{text}");
-    joined.replace("\\n", "\n")
+/// Append `prompt`, with what's attached to it, to the conversation, and send the requests of
+/// the next prompt with its overrides. Returns it with what's attached, as it's queued, and the
+/// conversation.
+async fn prepare(prompt: String) -> TokioResult<(String, Vec<ChatCompletionRequestMessage>)> {
+    // Of a response that didn't finish.
+    tools::clear();
+    let attached = attach::any();
+    let prompt = attach::take(prompt);
+    let (mut overrides, text) = overrides::parse(&prompt)?;
+    router::route(&mut overrides, text, attached);
+    overrides::set(overrides);
+    let messages = {
+        let mut conversation = CONVERSATION.lock().await;
+        conversation.push(string_to_chat_completion_request_user_message(
+            text.to_string(),
+        ));
+        conversation.clone()
+    };
+    Ok((prompt, messages))
 }
 
-// Fixes cases where the model returns ["\", "n"] instead of ["\n"],
-// which is interpreted as a newline in the OpenAI playground.
-fn fix_newlines(print_buffer: &mut Vec<String>, text: &str) -> String {
-    let single_backslash = r#"\"#;
-    if text.ends_with(single_backslash) {
-        return store_and_do_nothing(print_buffer, text);
+/// The response of the model to `messages`, with its usage reported to `report`.
+pub async fn stream(
+    messages: &[ChatCompletionRequestMessage],
+    report: &usage::Report,
+) -> TokioResult<ChatCompletionResponseStream> {
+    let messages = language::instructed(&pin::fit(messages));
+    if responses::enabled() {
+        return responses::send(&messages, report).await;
     }
-    if !print_buffer.is_empty() {
-        return join_and_clear(print_buffer, text);
+    let config: &Config = &CONFIGURATION;
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    overrides::last().chat(&mut request);
+    if external::enabled() {
+        request.tools(external::tools());
     }
-    text.to_string()
+    let messages = tools::without(&messages);
+    openai::chat_stream(request.messages(messages).build()?, report).await
+}
+
+/// What `ui.show_meta` shows below a response.
+struct Meta<'a> {
+    /// As the API says, which may be more exact than what was asked for.
+    model: &'a str,
+    finish_reason: Option<FinishReason>,
+    usage: usage::Reported,
+    /// How long it took for the response to start, and to finish.
+    first_token: Duration,
+    total: Duration,
+    fingerprint: Option<&'a str>,
 }
 
-fn post_process(print_buffer: &mut Vec<String>, text: &str) -> String {
-    fix_newlines(print_buffer, text)
+impl Meta<'_> {
+    /// “gpt-4o-2024-08-06 · stop · 1234 + 56 tokens · 0.8 s to the first token, 4.2 s in all ·
+    /// fp_f85bea6784”
+    fn text(&self) -> String {
+        let finish_reason = match self.finish_reason {
+            Some(FinishReason::Stop) => "stop",
+            Some(FinishReason::Length) => "length",
+            Some(FinishReason::ToolCalls) => "tool_calls",
+            Some(FinishReason::ContentFilter) => "content_filter",
+            Some(FinishReason::FunctionCall) => "function_call",
+            None => "-",
+        };
+        let mut ret = tr!(
+            "meta",
+            model = self.model,
+            finish_reason = finish_reason,
+            prompt_tokens = self.usage.prompt_tokens,
+            completion_tokens = self.usage.completion_tokens,
+            first_token = format!("{:.1}", self.first_token.as_secs_f64()),
+            total = format!("{:.1}", self.total.as_secs_f64())
+        );
+        if let Some(fingerprint) = self.fingerprint {
+            ret += &format!(" · {fingerprint}");
+        }
+        ret
+    }
+}
+
+/// Add the model's `response` to `messages` to the conversation. Returns its usage, as reported
+/// to `report`.
+pub async fn finish_exchange(
+    messages: &[ChatCompletionRequestMessage],
+    response: String,
+    report: &usage::Report,
+) -> usage::Reported {
+    notify::response_complete(&response);
+    let usage = usage::record(&overrides::model(), messages, &response, report);
+    {
+        let mut conversation = CONVERSATION.lock().await;
+        conversation.extend(tools::take());
+        conversation.push(string_to_chat_completion_assistant_message(response));
+    }
+    autosave().await;
+    usage
 }
 
 pub async fn request(
     prompt: String,
     _count: i64,
 ) -> TokioResult<Vec<ChatCompletionResponseStreamMessage>> {
-    let mut print_buffer: Vec<String> = Vec::new();
-    let config = &*CONFIGURATION.to_owned();
-    let oconfig: OpenAIConfig = config.into();
-    let openai = Client::with_config(oconfig);
-    let completions = openai.chat();
-    let messages = {
-        CONVERSATION
-            .lock()
-            .await
-            .push(string_to_chat_completion_request_user_message(
-                prompt.clone(),
-            ));
-        CONVERSATION
-            .lock()
-            .await
-            .clone()
-            .into_iter()
-            .collect::<Vec<_>>()
+    let spinner = Spinner::start();
+    if draft::enabled() {
+        return request_draft(spinner, prompt).await;
+    }
+    let (messages, stream, report) = send(prompt).await?;
+    respond(spinner, messages, stream, report, None).await
+}
+
+/// Print the response to `prompt` of the model of `[draft]`, while the model it's for answers in
+/// the background, and then offer the answer of that one instead, see [`draft`].
+async fn request_draft(
+    spinner: Spinner,
+    prompt: String,
+) -> TokioResult<Vec<ChatCompletionResponseStreamMessage>> {
+    let (_, messages) = prepare(prompt).await?;
+    let report = usage::Report::default();
+    if overrides::model() == draft::model() {
+        let stream = stream(&messages, &report).await?;
+        return respond(spinner, messages, stream, report, None).await;
+    }
+    let refining = draft::refine(&messages)?;
+    let overrides = overrides::last();
+    overrides::set(overrides.clone().with_model(draft::model()));
+    let drafted = match stream(&messages, &report).await {
+        Ok(stream) => respond(spinner, messages, stream, report, None).await,
+        Err(e) => Err(e),
     };
-    let mut request: CreateChatCompletionRequestArgs = config.into();
-    let mut stream = completions
-        .create_stream(request.messages(messages).build()?)
-        .await?;
+    // The draft is replaced, or it's continued from, with the model it's for.
+    overrides::set(overrides);
+    match drafted {
+        Ok(drafted) if !drafted.is_empty() => {
+            draft::offer(refining).await?;
+            print_prompt();
+            Ok(drafted)
+        }
+        drafted => {
+            refining.abort();
+            drafted
+        }
+    }
+}
+
+/// `/continue`: ask for the rest of the last response, which was cut off at `max_tokens`, and
+/// stitch it on.
+pub async fn continue_response(args: Vec<String>) -> TokioResult<()> {
+    if !args.is_empty() {
+        return Err(commands::usage("continue"));
+    }
+    let last = {
+        let conversation = CONVERSATION.lock().await;
+        conversation
+            .split_last()
+            .filter(|(last, _)| chat_completion_message_role(last) == Role::Assistant)
+            .map(|(last, before)| (before.to_vec(), chat_completion_message_text(last)))
+    };
+    let Some((messages, Some(partial))) = last.filter(|_| continuation::truncated()) else {
+        return Err(tr!("continue-nothing").into());
+    };
+    let spinner = Spinner::start();
+    let report = usage::Report::default();
+    let stream = stream(&continuation::messages(&messages, &partial), &report).await?;
+    respond(spinner, messages, stream, report, Some(partial)).await?;
+    Ok(())
+}
+
+/// Print the response in `stream` to `messages`, the conversation up to and including the
+/// prompt, and add it to the conversation, with the usage reported to `report`. If it continues
+/// `resumed`, the last message of the conversation, that's replaced with both, stitched together.
+async fn respond(
+    spinner: Spinner,
+    messages: Vec<ChatCompletionRequestMessage>,
+    mut stream: ChatCompletionResponseStream,
+    mut report: usage::Report,
+    resumed: Option<String>,
+) -> TokioResult<Vec<ChatCompletionResponseStreamMessage>> {
     IS_RUNNING.store(true, Ordering::SeqCst);
+    SKIP_PACING.store(false, Ordering::Relaxed);
+    CANCEL.store(false, Ordering::Relaxed);
+    // What's resumed may have stopped in the middle of a block.
+    let terminal = Terminal::new(spinner, if resumed.is_some() { &[] } else { &messages });
+    let mut sinks = Sinks::new(vec![Box::new(terminal)]);
 
     let got_first_success: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
     let mut ret = vec![];
+    // As printed, without what continuations repeated.
+    let mut response = resumed.clone().unwrap_or_default();
+    let mut stitcher = resumed.as_deref().map(Stitcher::new);
+    let mut continued = 0;
+    let mut truncated = false;
+    // The tools of `[tools.custom]` the model called, and what they output, for it to go on from.
+    let mut exchanged = vec![];
+    let mut rounds = 0;
+    // For `ui.show_meta`, as the API says.
+    let started = Instant::now();
+    let mut first_token = Duration::ZERO;
+    let mut finish_reason = None;
+    let mut model = None;
+    let mut fingerprint = None;
 
     'abort: while !ABORT.load(Ordering::Relaxed) {
-        while let Some(c) = stream.next().await {
+        // This part of the response, for the usage of each to be recorded.
+        let mut part = String::new();
+        let mut calls = external::Calls::default();
+        'stream: while let Some(c) = stream.next().await {
             match c {
                 Ok(completion) => {
                     let completion = Arc::new(completion);
                     ret.push(completion.clone());
+                    if !completion.model.is_empty() {
+                        model = Some(completion.model.clone());
+                    }
+                    if let Some(id) = &completion.system_fingerprint {
+                        fingerprint = Some(id.clone());
+                    }
                     if !got_first_success.load(Ordering::SeqCst) {
                         got_first_success.store(true, Ordering::SeqCst);
-                        print_response_prompt();
+                        first_token = started.elapsed();
+                        sinks.push(Event::Started).await;
                     }
                     for choice in &completion.choices {
-                        if ABORT.load(Ordering::Relaxed) {
+                        if ABORT.load(Ordering::Relaxed) || CANCEL.load(Ordering::Relaxed) {
                             break 'abort;
                         }
-                        match choice.delta.content {
-                            Some(ref text) => {
-                                let newline_fixed = post_process(&mut print_buffer, &text);
-                                print_and_flush(&newline_fixed);
+                        if let Some(ref text) = choice.delta.content {
+                            sinks.push(Event::Token).await;
+                            part += text;
+                            let text = match &mut stitcher {
+                                Some(stitcher) => stitcher.push(text),
+                                None => text.clone(),
+                            };
+                            response += &text;
+                            sinks.push(Event::Text(&text)).await;
+                        }
+                        if let Some(chunks) = &choice.delta.tool_calls {
+                            if external::enabled() {
+                                calls.push(chunks);
                             }
-                            None => {}
+                            // What the model says after them is a paragraph of its own.
+                            let paragraph = !response.is_empty() && !response.ends_with('\n');
+                            if paragraph {
+                                response += "\n\n";
+                            }
+                            let blocks = chunks
+                                .iter()
+                                .filter_map(|call| call.id.as_deref())
+                                .map(|id| tools::block(id).unwrap_or_default())
+                                .collect::<Vec<_>>();
+                            sinks
+                                .push(Event::Tools {
+                                    blocks: &blocks,
+                                    paragraph,
+                                })
+                                .await;
+                        }
+                        if choice.finish_reason.is_some() {
+                            finish_reason = choice.finish_reason;
                         }
                         match choice.finish_reason {
                             Some(FinishReason::Stop) => {
                                 debug!("Got stop from API, returning to REPL");
-                                IS_RUNNING.store(false, Ordering::SeqCst);
-                                break 'abort;
+                                break 'stream;
+                            }
+                            Some(FinishReason::Length) => {
+                                debug!("Got length from API, the response was cut off");
+                                truncated = true;
+                                break 'stream;
+                            }
+                            Some(FinishReason::ToolCalls) if !calls.is_empty() => {
+                                debug!("Got tool calls from API, running them");
+                                break 'stream;
                             }
                             Some(reason) => {
                                 let msg = format!("OpenAI API error: {reason:?}");
                                 print_error(&msg);
-                                continue 'abort;
+                                break 'stream;
                             }
                             None => {}
                         }
                     }
                 }
                 Err(e) => {
-                    let msg = format!("OpenAI API error: {e}");
+                    let msg = match api_error::from_openai(e, &overrides::model()) {
+                        e if e.is::<ApiError>() => e.to_string(),
+                        e => format!("OpenAI API error: {e}"),
+                    };
                     print_error(&msg);
                     break 'abort;
                 }
             }
         }
+        if let Some(stitcher) = &mut stitcher {
+            let text = stitcher.finish();
+            response += &text;
+            sinks.push(Event::Text(&text)).await;
+        }
+        if !calls.is_empty() {
+            if rounds == external::MAX_ROUNDS {
+                print_error(&tr!("tool-too-many-rounds", count = rounds));
+                break 'abort;
+            }
+            let sent = [messages.clone(), exchanged.clone()].concat();
+            usage::record(&overrides::model(), &sent, &part, &report);
+            exchanged.extend(external::run(&part, calls, &mut sinks).await);
+            report = usage::Report::default();
+            let sent = [messages.clone(), exchanged.clone()].concat();
+            stream = match self::stream(&sent, &report).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    print_error(&format!("OpenAI API error: {e}"));
+                    break 'abort;
+                }
+            };
+            rounds += 1;
+            continue;
+        }
         debug!("Got end of stream, returning to REPL");
-        IS_RUNNING.store(false, Ordering::SeqCst);
-        break 'abort;
+        if !truncated
+            || CONFIGURATION.ui.continue_truncated != ContinueTruncated::Auto
+            || continued == continuation::MAX_AUTO
+        {
+            break 'abort;
+        }
+        // Printed on as if it were the same response.
+        let sent = continuation::messages(&messages, &response);
+        usage::record(&overrides::model(), &sent[..sent.len() - 2], &part, &report);
+        report = usage::Report::default();
+        stream = match self::stream(&sent, &report).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                print_error(&format!("OpenAI API error: {e}"));
+                break 'abort;
+            }
+        };
+        stitcher = Some(Stitcher::new(&response));
+        continued += 1;
+        truncated = false;
     }
-    eprint_and_flush("\n");
+    IS_RUNNING.store(false, Ordering::SeqCst);
+    sinks.push(Event::Finished).await;
 
     if !got_first_success.load(Ordering::SeqCst) {
-        let msg = format!("Empty prompt, aborting.");
+        let msg = tr!("empty-prompt");
         print_error(&msg);
         return Ok(vec![]);
     }
@@ -234,27 +576,38 @@ pub async fn request(
         .map(|o| Arc::new(o.choices.clone().into_iter().collect::<Vec<_>>()))
         .collect::<Vec<_>>()
         .drain(..)
-        .map(|choice: Arc<Vec<ChatCompletionResponseStreamMessage>>| {
-            choice
-                .iter()
-                .map(|choice| choice.clone())
-                .collect::<Vec<_>>()
+        .flat_map(|choice: Arc<Vec<ChatCompletionResponseStreamMessage>>| {
+            choice.iter().cloned().collect::<Vec<_>>()
         })
-        .flatten()
         .collect::<Vec<_>>();
 
-    let complete_message = result.iter().map(|o| o.delta.clone()).collect::<Vec<_>>();
-
-    let assistant_msg = string_to_chat_completion_assistant_message(
-        complete_message
-            .into_iter()
-            .map(|o| o.content.unwrap_or_else(String::new))
-            .collect::<Vec<_>>()
-            .join(""),
-    );
-    (*CONVERSATION).lock().await.push(assistant_msg);
+    if resumed.is_some() {
+        CONVERSATION.lock().await.pop();
+    }
+    let usage = finish_exchange(&messages, response, &report).await;
+    if logprobs::enabled() {
+        match logprobs::render() {
+            Some(rendered) => eprint_and_flush(&format!("{rendered}\n")),
+            None => eprint_dim(&format!("{}\n", tr!("logprobs-none"))),
+        }
+    }
+    if CONFIGURATION.ui.show_meta {
+        let model = model.unwrap_or_else(overrides::model);
+        let meta = Meta {
+            model: &model,
+            finish_reason,
+            usage,
+            first_token,
+            total: started.elapsed(),
+            fingerprint: fingerprint.as_deref(),
+        };
+        eprint_dim(&format!("{}\n", meta.text()));
+    }
+    continuation::set_truncated(truncated);
+    if truncated {
+        eprint_dim(&format!("{}\n", tr!("continue-offer")));
+    }
 
-    IS_RUNNING.store(false, Ordering::SeqCst);
     finish_prompt();
     Ok(result)
 }
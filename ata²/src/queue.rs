@@ -0,0 +1,196 @@
+//! `--queue`: a prompt that can't be sent because there's no connection is queued on disk, with the
+//! conversation before it, instead of failing, and `ata2 flush` sends the queued prompts later,
+//! printing each exchange and saving each conversation. Each profile has its own queue,
+//! `queue-{profile}` next to its history file, or in the database.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::error::OpenAIError;
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::{Deserialize, Serialize};
+
+use std::error::Error;
+use std::fs;
+use std::io::{self};
+use std::path::PathBuf;
+
+use crate::output;
+use crate::pin;
+use crate::prompt::{self, CONVERSATION};
+use crate::readline::{
+    autosave_path, autosave_to, chat_completion_message_text, save_conversation,
+};
+use crate::responses;
+use crate::sink::{self, Plain, Sinks};
+use crate::store;
+use crate::style::Style;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+use crate::FLAGS;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Queued {
+    /// When the prompt was queued, in seconds since the Unix epoch.
+    pub time: i64,
+    /// The conversation, ending with the prompt.
+    pub messages: Vec<ChatCompletionRequestMessage>,
+}
+
+/// Queue prompts that can't be sent? Not while `ata2 flush` sends them.
+pub fn enabled() -> bool {
+    FLAGS.queue && FLAGS.command.is_none()
+}
+
+/// Did sending a prompt fail with `e` because there's no connection, rather than because of the
+/// API?
+pub fn offline(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+    let e = match e.downcast_ref::<OpenAIError>() {
+        Some(OpenAIError::Reqwest(e)) => Some(e),
+        _ => e.downcast_ref::<reqwest::Error>(),
+    };
+    e.is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+/// Queue `messages`, a conversation ending with a prompt. Returns how many prompts are queued.
+pub fn push(messages: &[ChatCompletionRequestMessage]) -> TokioResult<usize> {
+    let mut queued = load()?;
+    queued.push(Queued {
+        time: chrono::Utc::now().timestamp(),
+        messages: messages.to_vec(),
+    });
+    save(&queued)?;
+    Ok(queued.len())
+}
+
+/// `ata2 flush`: send the queued prompts, oldest first, and print and save each exchange. Stops at
+/// the first that still can't be sent for lack of a connection. Prompts the API fails on stay
+/// queued too.
+pub async fn run() -> TokioResult<()> {
+    let mut queued = load()?;
+    if queued.is_empty() {
+        println!("{}", tr!("flush-nothing"));
+        return Ok(());
+    }
+    let dim = Style::parse("dim").unwrap_or_default();
+    let mut kept = vec![];
+    while !queued.is_empty() {
+        let entry = queued.remove(0);
+        let Some((prompt, before)) = entry.messages.split_last() else {
+            continue;
+        };
+        let prompt = chat_completion_message_text(prompt).unwrap_or_default();
+        *CONVERSATION.lock().await = before.to_vec();
+        responses::set_thread(None);
+        pin::set(vec![]);
+        autosave_to(None);
+        for line in prompt.trim_end().lines() {
+            output::write(&format!("{}\n", format!("> {line}").trim_end())).await;
+        }
+        output::write("\n").await;
+        match exchange(prompt).await {
+            Ok(path) => eprintln!(
+                "{}",
+                dim.paint_to(
+                    atty::Stream::Stderr,
+                    &tr!("flush-saved", path = path.display().to_string())
+                )
+            ),
+            Err(e) if offline(&*e) => {
+                kept.push(entry);
+                kept.append(&mut queued);
+                save(&kept)?;
+                return Err(tr!("flush-offline", count = kept.len()).into());
+            }
+            Err(e) => {
+                error!("{e}");
+                kept.push(entry);
+            }
+        }
+        // After each, for what's been sent not to be sent again if ata² is stopped halfway.
+        save(&[&kept[..], &queued[..]].concat())?;
+    }
+    if !kept.is_empty() {
+        return Err(tr!("flush-kept", count = kept.len()).into());
+    }
+    Ok(())
+}
+
+/// Send `prompt` after the conversation, print the response and save the conversation. Returns
+/// where it's saved.
+async fn exchange(prompt: String) -> TokioResult<PathBuf> {
+    let (messages, stream, report) = prompt::send(prompt).await?;
+    let mut sinks = Sinks::new(vec![Box::new(Plain::stdout())]);
+    let (response, _, report) = sink::exchange(&messages, stream, report, &mut sinks).await?;
+    output::write("\n\n").await;
+    output::flush().await;
+    prompt::finish_exchange(&messages, response, &report).await;
+    // With `ui.autosave`, it's been saved already.
+    match autosave_path() {
+        Some(path) => Ok(path),
+        None => save_conversation().await,
+    }
+}
+
+/// `queue-{profile}`, next to `ui.history_file`.
+fn file() -> PathBuf {
+    config
+        .ui
+        .history_file
+        .with_file_name(format!("queue-{}", FLAGS.config.profile()))
+}
+
+/// The queued prompts, oldest first.
+fn load() -> TokioResult<Vec<Queued>> {
+    if store::enabled() {
+        return store::queue(&FLAGS.config.profile());
+    }
+    let contents = match fs::read_to_string(file()) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(contents
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?)
+}
+
+/// Replace the queue with `queued`, one JSON object a line. The file goes when it's empty.
+fn save(queued: &[Queued]) -> TokioResult<()> {
+    if store::enabled() {
+        return store::save_queue(&FLAGS.config.profile(), queued);
+    }
+    let path = file();
+    if queued.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+    let mut lines = String::new();
+    for entry in queued {
+        lines += &serde_json::to_string(entry)?;
+        lines.push('\n');
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, lines)?;
+    Ok(())
+}
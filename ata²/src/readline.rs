@@ -22,7 +22,8 @@
 
 use async_openai::types::{
     ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
-    ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageContent, Role,
+    ChatCompletionRequestMessageContentPart, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageContent, Role,
 };
 use futures_util::lock::Mutex;
 use rustyline::error::ReadlineError;
@@ -30,21 +31,46 @@ use rustyline::{
     Cmd, ConditionalEventHandler, Editor, EventContext, EventHandler, KeyCode, KeyEvent, Modifiers,
     RepeatCount,
 };
-use std::future::IntoFuture;
 use std::io::Read as _;
 use std::io::Write as _;
-use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc::Sender;
 use tokio::task::JoinHandle;
 
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use crate::ask;
+use crate::commands;
+use crate::config::EditMode;
+use crate::forecast::Forecast;
+use crate::ghost;
+use crate::helper::{self, ReplHelper};
+use crate::history;
+use crate::i18n;
+use crate::keys;
+use crate::lock;
+use crate::paste;
+use crate::pin;
+use crate::piped::{self, Input};
+use crate::policy;
 use crate::prompt::{self, CONVERSATION};
+use crate::responses;
+use crate::sanitize;
+use crate::search;
+use crate::sessions;
+use crate::shutdown;
+use crate::store;
+use crate::template;
+use crate::usage;
 use crate::TokioResult;
 use crate::ABORT;
 use crate::CONFIGURATION as config;
-use crate::HAD_FIRST_INTERRUPT;
+use crate::FIRST_INTERRUPT;
+use crate::IS_RUNNING;
+use crate::SKIP_PACING;
 
 pub fn string_to_chat_completion_request_user_message(
     string: String,
@@ -52,7 +78,6 @@ pub fn string_to_chat_completion_request_user_message(
     ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
         role: Role::User,
         content: Some(ChatCompletionRequestUserMessageContent::Text(string)),
-        ..Default::default()
     })
 }
 
@@ -64,21 +89,138 @@ pub fn string_to_chat_completion_assistant_message(string: String) -> ChatComple
     })
 }
 
+/// The role of `message`. Don't match on the variant instead: the enum is untagged, so every
+/// message of a conversation loaded from JSON deserializes as
+/// [`ChatCompletionRequestMessage::System`].
+pub fn chat_completion_message_role(message: &ChatCompletionRequestMessage) -> Role {
+    match message {
+        ChatCompletionRequestMessage::System(m) => m.role,
+        ChatCompletionRequestMessage::User(m) => m.role,
+        ChatCompletionRequestMessage::Assistant(m) => m.role,
+        ChatCompletionRequestMessage::Tool(m) => m.role,
+        ChatCompletionRequestMessage::Function(m) => m.role,
+    }
+}
+
+/// The text content of `message`, if it has any.
+pub fn chat_completion_message_text(message: &ChatCompletionRequestMessage) -> Option<String> {
+    match message {
+        ChatCompletionRequestMessage::System(m) => m.content.clone(),
+        ChatCompletionRequestMessage::User(m) => match &m.content {
+            Some(ChatCompletionRequestUserMessageContent::Text(text)) => Some(text.clone()),
+            Some(ChatCompletionRequestUserMessageContent::Array(parts)) => Some(
+                parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ChatCompletionRequestMessageContentPart::Text(t) => Some(t.text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            None => None,
+        },
+        ChatCompletionRequestMessage::Assistant(m) => m.content.clone(),
+        ChatCompletionRequestMessage::Tool(m) => m.content.clone(),
+        ChatCompletionRequestMessage::Function(m) => m.content.clone(),
+    }
+}
+
 pub struct Readline {
-    pub rl: Arc<Mutex<Editor<()>>>,
+    pub rl: Arc<Mutex<Editor<ReplHelper>>>,
+    /// rustyline's history with the time of each prompt, which is what's saved, and what
+    /// [`search::SearchHandler`] searches.
+    history: Arc<std::sync::Mutex<Vec<history::Entry>>>,
 }
 
 impl Readline {
     pub fn new() -> Self {
-        let rl = Editor::<()>::new().unwrap();
+        // With bracketed paste, a newline in what is pasted doesn't send the input.
+        let rl_config = rustyline::Config::builder()
+            .edit_mode(edit_mode())
+            .bracketed_paste(true)
+            .max_history_size(config.ui.history_max_entries)
+            .history_ignore_dups(false)
+            .build();
+        let mut rl = Editor::<ReplHelper>::with_config(rl_config).unwrap();
+        rl.set_helper(Some(ReplHelper::default()));
+        if atty::is(atty::Stream::Stdin) {
+            bind_keys(&mut rl);
+        }
         Self {
             rl: Arc::new(Mutex::new(rl)),
+            history: Default::default(),
         }
     }
 }
 
-use futures_util::FutureExt as _;
+/// `ui.edit_mode`, with `auto` resolved.
+pub fn edit_mode() -> rustyline::EditMode {
+    match config.ui.edit_mode {
+        EditMode::Emacs => rustyline::EditMode::Emacs,
+        EditMode::Vi => rustyline::EditMode::Vi,
+        EditMode::Auto => match inputrc_editing_mode().as_deref() {
+            Some("vi") => rustyline::EditMode::Vi,
+            _ => rustyline::EditMode::Emacs,
+        },
+    }
+}
+
+/// The value of the last `set editing-mode` in the user's inputrc.
+fn inputrc_editing_mode() -> Option<String> {
+    let path = match std::env::var_os("INPUTRC") {
+        Some(path) => std::path::PathBuf::from(path),
+        None => directories::BaseDirs::new()?.home_dir().join(".inputrc"),
+    };
+    let inputrc = std::fs::read_to_string(path).ok()?;
+    inputrc.lines().rev().find_map(|line| {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("set"), Some("editing-mode"), Some(mode)) => Some(mode.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// Apply `[ui.keybindings]`. The chords were checked by [`Config::validate`](crate::Config).
+fn bind_keys(rl: &mut Editor<ReplHelper>) {
+    let keys = &config.ui.keybindings;
+    let mut bind = |chord: &str, handler: EventHandler| {
+        if let Ok(Some(event)) = keys::parse(chord) {
+            rl.bind_sequence(event, handler);
+        }
+    };
+    if config.ui.multiline_insertions {
+        bind(
+            &keys.newline,
+            EventHandler::Conditional(Box::new(keys::NewlineHandler)),
+        );
+        bind(&keys.accept_line, Cmd::AcceptLine.into());
+    }
+    bind(
+        &keys.save_conversation,
+        EventHandler::Conditional(Box::new(RequestSaveHandler)),
+    );
+    bind(
+        &keys.cancel_generation,
+        EventHandler::Conditional(Box::new(keys::CancelHandler)),
+    );
+    bind(
+        &keys.copy_response,
+        EventHandler::Conditional(Box::new(keys::CopyResponseHandler)),
+    );
+    bind(
+        &keys.open_editor,
+        EventHandler::Conditional(Box::new(keys::OpenEditorHandler)),
+    );
+    rl.bind_sequence(
+        KeyEvent(KeyCode::BracketedPasteStart, Modifiers::NONE),
+        EventHandler::Conditional(Box::new(paste::PasteStartHandler)),
+    );
+}
 
+/// Saves the conversation on a thread of its own, since a response may still be streaming into it,
+/// and the runtime's workers may all be busy, one of them with this very line editor.
 struct RequestSaveHandler;
 impl ConditionalEventHandler for RequestSaveHandler {
     fn handle(
@@ -88,31 +230,333 @@ impl ConditionalEventHandler for RequestSaveHandler {
         _positive: bool,
         _: &EventContext,
     ) -> Option<Cmd> {
-        let convo = CONVERSATION.lock().into_future();
-        let convo = convo.now_or_never().unwrap();
-        let convo = convo.clone();
-        let convo_json = serde_json::to_string(&convo).unwrap();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        // as unix secs
-        let filename = format!("conversation-{}.json", now);
-        let _ = std::fs::remove_file(&filename);
-        let convo_file = std::fs::File::create(&filename).unwrap();
-        let mut convo_file = std::io::BufWriter::new(convo_file);
-        convo_file.write_all(convo_json.as_bytes()).unwrap();
-        info!("Saved conversation to {filename}");
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return Some(Cmd::Noop);
+        };
+        std::thread::spawn(move || match runtime.block_on(save_conversation()) {
+            Ok(path) => info!(
+                "{}",
+                tr!("conversation-saved", path = path.display().to_string())
+            ),
+            Err(e) => error!("{}", tr!("conversation-save-failed", error = e.to_string())),
+        });
+        Some(Cmd::Noop)
+    }
+}
+
+/// Where [`autosave`] writes the conversation, once it has picked a name.
+static AUTOSAVE_PATH: std::sync::Mutex<Option<PathBuf>> = std::sync::Mutex::new(None);
+
+/// Save the conversation to `ui.save_dir`, named after `ui.save_filename`.
+pub async fn save_conversation() -> TokioResult<PathBuf> {
+    let conversation = CONVERSATION.lock().await.clone();
+    save_to(None, &conversation)
+}
+
+/// With `ui.autosave`, save the conversation, always to the same file for the whole session.
+pub async fn autosave() {
+    if !config.ui.autosave {
+        return;
+    }
+    autosave_conversation(&CONVERSATION.lock().await.clone());
+}
+
+/// [`autosave`] `conversation`, for when there's no waiting for the lock on it.
+pub fn autosave_conversation(conversation: &[ChatCompletionRequestMessage]) {
+    if !config.ui.autosave || conversation.is_empty() {
+        return;
+    }
+    let mut autosave_path = AUTOSAVE_PATH.lock().unwrap();
+    match save_to(autosave_path.clone(), conversation) {
+        Ok(path) => {
+            debug!("Autosaved conversation to {}", path.display());
+            *autosave_path = Some(path);
+        }
+        Err(e) => error!(
+            "{}",
+            tr!("conversation-autosave-failed", error = e.to_string())
+        ),
+    }
+}
+
+/// Write `conversation` to `path`, or to a new file named after `ui.save_filename`, while no
+/// other ata² picks a name or saves, for two not to pick the same one.
+pub fn save_to(
+    path: Option<PathBuf>,
+    conversation: &[ChatCompletionRequestMessage],
+) -> TokioResult<PathBuf> {
+    lock::with("conversations", || {
+        let path = match path {
+            Some(path) => path,
+            None => new_save_path(conversation)?,
+        };
+        write_conversation(&path, conversation).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(path)
+    })
+}
+
+/// Load the newest saved conversation, and keep autosaving to it.
+pub async fn resume_last() -> TokioResult<PathBuf> {
+    let Some(path) = saved_conversations().into_iter().next() else {
+        return Err(format!(
+            "No saved conversation in {}",
+            config.ui.save_dir.display()
+        ))?;
+    };
+    resume(&path).await?;
+    Ok(path)
+}
+
+/// Load the conversation saved as `path`, and keep autosaving to it.
+pub async fn resume(path: &std::path::Path) -> TokioResult<()> {
+    prompt::load_conversation(path).await?;
+    autosave_to(Some(path.to_path_buf()));
+    Ok(())
+}
+
+/// The file the conversation is autosaved to, once it's been saved or resumed.
+pub fn autosave_path() -> Option<PathBuf> {
+    AUTOSAVE_PATH.lock().unwrap().clone()
+}
+
+/// Where [`autosave`] writes from now on. `None` to pick a new name, as for a new conversation.
+pub fn autosave_to(path: Option<PathBuf>) {
+    *AUTOSAVE_PATH.lock().unwrap() = path;
+}
+
+/// A file in `ui.save_dir` named after `ui.save_filename` that doesn't exist yet.
+fn new_save_path(conversation: &[ChatCompletionRequestMessage]) -> std::io::Result<PathBuf> {
+    let title = conversation
+        .iter()
+        .find(|m| chat_completion_message_role(m) == Role::User)
+        .and_then(chat_completion_message_text)
+        .map(|text| slug(text.lines().next().unwrap_or_default()))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| "untitled".to_string());
+    let date = chrono::Local::now().format("%Y-%m-%d-%H%M%S").to_string();
+    let filename = template::render(
+        &config.ui.save_filename,
+        &[("date", date), ("title", title)],
+        false,
+    );
+    if !store::enabled() {
+        std::fs::create_dir_all(&config.ui.save_dir)?;
+    }
+    let mut path = config.ui.save_dir.join(&filename);
+    // Don't overwrite a conversation saved earlier in the same second, or with no placeholders.
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{extension}")),
+        None => (filename.as_str(), String::new()),
+    };
+    let saved = if store::enabled() {
+        store::conversations().unwrap_or_default()
+    } else {
+        vec![]
+    };
+    for n in 2.. {
+        if !path.exists() && !saved.contains(&path) {
+            break;
+        }
+        path = config.ui.save_dir.join(format!("{stem}-{n}{extension}"));
+    }
+    Ok(path)
+}
+
+/// Write to a temporary file next to `path` first, then rename it, so that a crash halfway leaves
+/// the previous save intact. With `storage = "sqlite"`, `path` only names it in the database.
+fn write_conversation(
+    path: &std::path::Path,
+    conversation: &[ChatCompletionRequestMessage],
+) -> TokioResult<()> {
+    if let Some(id) = responses::thread() {
+        sessions::set_thread(path, Some(&id))?;
+    }
+    let pins = pin::all();
+    if !pins.is_empty() {
+        sessions::set_pins(path, &pins)?;
+    }
+    if store::enabled() {
+        return store::save_conversation(path, conversation);
+    }
+    let json = serde_json::to_string(conversation)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Lowercase words joined by dashes, at most 40 characters long.
+fn slug(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .fold(String::new(), |mut slug, word| {
+            if slug.chars().count() + word.chars().count() < 40 {
+                if !slug.is_empty() {
+                    slug.push('-');
+                }
+                slug.push_str(&word);
+            }
+            slug
+        })
+}
+
+/// The conversation saved as `path`, in the database or a file.
+pub fn read_conversation(path: &std::path::Path) -> TokioResult<Vec<ChatCompletionRequestMessage>> {
+    if store::enabled() {
+        if let Some(conversation) = store::conversation(path)? {
+            return Ok(conversation);
+        }
+    }
+    let json = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Conversations saved in `ui.save_dir`, newest first. With `storage = "sqlite"`, those in the
+/// database come first.
+pub fn saved_conversations() -> Vec<PathBuf> {
+    let mut ret = if store::enabled() {
+        store::conversations().unwrap_or_else(|e| {
+            error!("{e}");
+            vec![]
+        })
+    } else {
+        vec![]
+    };
+    for path in saved_conversations_in(&config.ui.save_dir) {
+        if !ret.contains(&path) {
+            ret.push(path);
+        }
+    }
+    ret
+}
+
+/// Conversations saved in `dir`, newest first. Any JSON array there is taken to be one.
+pub fn saved_conversations_in(dir: &std::path::Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut ret = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.path().extension().is_some_and(|e| e == "json") && is_json_array(&entry.path())
+        })
+        .map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            (modified, entry.path())
+        })
+        .collect::<Vec<_>>();
+    ret.sort_unstable_by(|a, b| b.cmp(a));
+    ret.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Does the file start like a JSON array? Only reads that far, so `package.json` and the like are
+/// told apart cheaply.
+fn is_json_array(path: &std::path::Path) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    std::io::BufReader::new(file)
+        .bytes()
+        .map_while(Result::ok)
+        .find(|b| !b.is_ascii_whitespace())
+        == Some(b'[')
+}
+
+/// Above `ui.confirm_above_tokens`, ask whether to send `line` after all.
+fn confirm_long_prompt(line: &str) -> bool {
+    let limit = config.ui.confirm_above_tokens;
+    if limit == 0 || !atty::is(atty::Stream::Stdin) {
+        return true;
+    }
+    let tokens = usage::input_tokens(line);
+    if tokens <= limit {
+        return true;
+    }
+    eprint!(
+        "{} {} ",
+        tr!("confirm-tokens", tokens = tokens),
+        tr!("yes-no")
+    );
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    // rustyline is done with the terminal, so it's back to reading whole lines.
+    std::io::stdin().read_line(&mut answer).is_ok() && i18n::is_yes(&answer)
+}
+
+/// With `ui.forecast`, print what sending `line` makes the request, and above
+/// `ui.confirm_above_cost`, ask whether to send it after all.
+async fn confirm_forecast(line: &str) -> bool {
+    if !config.ui.forecast && config.ui.confirm_above_cost <= 0.0 {
+        return true;
+    }
+    let conversation = CONVERSATION.lock().await.clone();
+    let Some(forecast) = Forecast::of(line, &conversation) else {
+        return true;
+    };
+    if !forecast.too_costly() || !atty::is(atty::Stream::Stdin) {
+        if config.ui.forecast {
+            prompt::eprint_dim(&format!("{}\n", forecast.text()));
+        }
+        return true;
+    }
+    eprint!(
+        "{} {} ",
+        tr!("confirm-forecast", forecast = forecast.text()),
+        tr!("yes-no")
+    );
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    // rustyline is done with the terminal, so it's back to reading whole lines.
+    std::io::stdin().read_line(&mut answer).is_ok() && i18n::is_yes(&answer)
+}
+
+/// Is this ^C the second, within `ui.double_ctrlc_window_ms` of the first?
+fn second_interrupt() -> bool {
+    let window = config.ui.double_ctrlc_window_ms;
+    FIRST_INTERRUPT
+        .lock()
+        .unwrap()
+        .is_some_and(|first| window == 0 || first.elapsed() < Duration::from_millis(window))
+}
+
+/// The hint after the first ^C, with how long there is for the second.
+fn press_ctrl_c_again() -> String {
+    match config.ui.double_ctrlc_window_ms {
+        0 => tr!("press-ctrl-c-again"),
+        window => tr!(
+            "press-ctrl-c-again-within",
+            seconds = (window as f64 / 1000.0).to_string()
+        ),
+    }
+}
+
+struct SkipPacingHandler;
+impl ConditionalEventHandler for SkipPacingHandler {
+    fn handle(
+        &self,
+        _event: &rustyline::Event,
+        _n: RepeatCount,
+        _positive: bool,
+        _: &EventContext,
+    ) -> Option<Cmd> {
+        if IS_RUNNING.load(Ordering::SeqCst) {
+            SKIP_PACING.store(true, Ordering::Relaxed);
+        }
         Some(Cmd::Noop)
     }
 }
 
 impl Readline {
-    pub async fn handle(&mut self, tx: Sender<Option<String>>) -> JoinHandle<TokioResult<()>> {
+    pub async fn handle(&mut self, tx: Sender<Option<Input>>) -> JoinHandle<TokioResult<()>> {
         let rl = self.rl.clone();
-        let readline_handle: JoinHandle<TokioResult<()>> = tokio::spawn(async move {
-            // If stdin is not a tty, we want to read once to the end of it and then exit.
+        let history = self.history.clone();
+        let readline_handle: JoinHandle<TokioResult<()>> = tokio::spawn(ask::reading(async move {
+            // If stdin is not a tty, we want to read once to the end of it, send what's in it and
+            // then exit.
             let mut already_read = false;
+            let mut piped = VecDeque::new();
             let mut stdin = std::io::stdin();
             prompt::print_prompt();
             while !ABORT.load(Ordering::Relaxed) {
@@ -123,28 +567,87 @@ impl Readline {
                 // Also, the current readline is cleared in some cases by rustyline,
                 // so being on a newline is the only way to avoid that.
                 let readline = if atty::is(atty::Stream::Stdin) {
-                    rl.readline("")
-                } else if !already_read {
-                    let mut buf = String::with_capacity(1024);
-                    stdin.read_to_string(&mut buf)?;
-                    already_read = true;
-                    Ok(buf)
+                    // Blocking, so off the worker, for what it would run next not to wait for the
+                    // line, like a question that the line before answered, see [`ask`].
+                    tokio::task::block_in_place(|| rl.readline(helper::prompt()))
                 } else {
-                    Err(ReadlineError::Eof)?
+                    if !already_read {
+                        let mut buf = Vec::with_capacity(1024);
+                        stdin.read_to_end(&mut buf)?;
+                        already_read = true;
+                        // Nothing is sent if any of it is wrong.
+                        piped = piped::split(&sanitize::bytes(&buf)).unwrap_or_else(|e| {
+                            error!("{e}");
+                            VecDeque::new()
+                        });
+                    }
+                    match piped.pop_front() {
+                        Some(Input::Line(line)) => Ok(line),
+                        Some(message) => {
+                            tx.send(Some(message)).await?;
+                            continue;
+                        }
+                        None => Err(ReadlineError::Eof),
+                    }
                 };
                 match readline {
                     Ok(line) => {
+                        // As pasted on Windows, or from a log with colors.
+                        let line = sanitize::text(&line);
+                        // An answer to a question asked mid-response isn't a prompt.
+                        let Some(line) = ask::answer(line) else {
+                            continue;
+                        };
                         if line.is_empty() {
                             continue;
                         }
-                        rl.add_history_entry(line.as_str());
-                        tx.send(Some(line)).await?;
-                        HAD_FIRST_INTERRUPT.store(false, Ordering::Relaxed);
+                        {
+                            let mut entries = history.lock().unwrap();
+                            if history::add(&mut entries, &line) {
+                                rl.clear_history();
+                                for entry in entries.iter() {
+                                    rl.add_history_entry(entry.text.as_str());
+                                }
+                            } else {
+                                rl.add_history_entry(line.as_str());
+                            }
+                        }
+                        let pastes = paste::take(&line);
+                        if commands::streams(&line) {
+                            tx.send(Some(Input::Line(line))).await?;
+                            continue;
+                        }
+                        if commands::is_command(&line) {
+                            if let Err(e) = commands::dispatch(&line).await {
+                                error!("{e}");
+                            }
+                            prompt::print_prompt();
+                            continue;
+                        }
+                        let line = if pastes.is_empty() {
+                            line
+                        } else {
+                            match paste::choose(&pastes) {
+                                Some(how) => paste::apply(line, &pastes, how),
+                                None => {
+                                    prompt::print_prompt();
+                                    continue;
+                                }
+                            }
+                        };
+                        if !confirm_long_prompt(&line) || !confirm_forecast(&line).await {
+                            eprintln!("{}", tr!("not-sent"));
+                            prompt::print_prompt();
+                            continue;
+                        }
+                        policy::ask_for_prompt(&line).await;
+                        tx.send(Some(Input::Line(line))).await?;
+                        *FIRST_INTERRUPT.lock().unwrap() = None;
                     }
                     Err(ReadlineError::Interrupted) => {
-                        if config.ui.double_ctrlc && !HAD_FIRST_INTERRUPT.load(Ordering::Relaxed) {
-                            HAD_FIRST_INTERRUPT.store(true, Ordering::Relaxed);
-                            eprint!("\nPress Ctrl-C again to exit.");
+                        if config.ui.double_ctrlc && !second_interrupt() {
+                            *FIRST_INTERRUPT.lock().unwrap() = Some(Instant::now());
+                            eprint!("\n{}", press_ctrl_c_again());
                             prompt::print_prompt();
                             continue;
                         } else {
@@ -154,7 +657,7 @@ impl Readline {
                         }
                     }
                     Err(ReadlineError::Eof) => {
-                        HAD_FIRST_INTERRUPT.store(false, Ordering::Relaxed);
+                        *FIRST_INTERRUPT.lock().unwrap() = None;
                         tx.send(None).await?;
                         break;
                     }
@@ -165,44 +668,67 @@ impl Readline {
                     }
                 }
             }
-            return Ok(());
-        });
+            Ok(())
+        }));
         readline_handle
     }
 
-    pub async fn enable_multiline(&mut self) {
+    pub async fn enable_skip_pacing(&mut self) {
         let mut rl = self.rl.lock().await;
-        if config.ui.multiline_insertions {
-            if atty::is(atty::Stream::Stdin) {
-                // Cmd::Newline inserts a newline, Cmd::AcceptLine accepts the line
-                rl.bind_sequence(KeyEvent(KeyCode::Enter, Modifiers::NONE), Cmd::Newline);
-                rl.bind_sequence(
-                    KeyEvent(KeyCode::Char('d'), Modifiers::CTRL),
-                    Cmd::AcceptLine,
-                );
-            }
+        if atty::is(atty::Stream::Stdin) {
+            rl.bind_sequence(
+                KeyEvent(KeyCode::Char('o'), Modifiers::CTRL),
+                EventHandler::Conditional(Box::new(SkipPacingHandler)),
+            );
         }
     }
 
-    pub async fn enable_request_save(&mut self) {
+    pub async fn enable_fuzzy_search(&mut self) {
         let mut rl = self.rl.lock().await;
         if atty::is(atty::Stream::Stdin) {
             rl.bind_sequence(
-                KeyEvent(KeyCode::F(2), Modifiers::NONE),
-                EventHandler::Conditional(Box::new(RequestSaveHandler)),
+                KeyEvent(KeyCode::Char('r'), Modifiers::CTRL),
+                EventHandler::Conditional(Box::new(search::SearchHandler {
+                    history: self.history.clone(),
+                })),
             );
         }
     }
 
-    pub async fn save_history(&mut self) -> TokioResult<()> {
+    pub async fn enable_vi_mode_indicator(&mut self) {
+        let mut rl = self.rl.lock().await;
+        if helper::vi_mode_indicator() && atty::is(atty::Stream::Stdin) {
+            rl.bind_sequence(
+                rustyline::Event::Any,
+                EventHandler::Conditional(Box::new(helper::ViModeHandler)),
+            );
+        }
+    }
+
+    pub async fn enable_ghost_text(&mut self) {
         let mut rl = self.rl.lock().await;
-        rl.save_history(&config.ui.history_file)?;
+        if config.ui.ghost_text && atty::is(atty::Stream::Stdin) {
+            rl.bind_sequence(
+                KeyEvent(KeyCode::Tab, Modifiers::NONE),
+                EventHandler::Conditional(Box::new(ghost::AcceptHandler)),
+            );
+        }
+    }
+
+    pub async fn save_history(&mut self) -> TokioResult<()> {
+        history::save(&config.ui.history_file, &self.history.lock().unwrap())?;
         Ok(())
     }
 
+    /// Load `ui.history_file`, if it exists yet, or its newest `ui.history_load_max` prompts.
     pub async fn load_history(&mut self) -> TokioResult<()> {
         let mut rl = self.rl.lock().await;
-        rl.load_history(&config.ui.history_file)?;
+        let entries = history::load_newest(&config.ui.history_file, config.ui.history_load_max)?;
+        for entry in &entries {
+            rl.add_history_entry(entry.text.as_str());
+        }
+        *self.history.lock().unwrap() = entries;
+        shutdown::keep_history(self.history.clone());
         Ok(())
     }
 
@@ -0,0 +1,178 @@
+//! `/regen [k]`: ask for the last response again, k times at once, and choose which of them, or the
+//! response there was, the conversation goes on from. The requests go through the
+//! [scheduler](crate::scheduler), with the parameters of the last prompt.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, CreateChatCompletionRequestArgs, FinishReason, Role,
+};
+use futures_util::StreamExt as _;
+
+use std::io::{self, Write as _};
+
+use crate::commands;
+use crate::config::Config;
+use crate::continuation;
+use crate::openai;
+use crate::output::print_and_flush;
+use crate::overrides;
+use crate::prompt::CONVERSATION;
+use crate::readline::{
+    autosave, chat_completion_message_role, string_to_chat_completion_assistant_message,
+};
+use crate::responses;
+use crate::scheduler;
+use crate::style::theme;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// How many responses `/regen` asks for without `k`.
+const DEFAULT_COUNT: usize = 3;
+
+/// A response asked for again: its text and whether it was cut off at `max_tokens`.
+type Candidate = TokioResult<(String, bool)>;
+
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    let count = match args.as_slice() {
+        [] => DEFAULT_COUNT,
+        [k] => k
+            .parse()
+            .ok()
+            .filter(|&k| k >= 1)
+            .ok_or_else(|| commands::usage("regen"))?,
+        _ => return Err(commands::usage("regen")),
+    };
+    // Responses continue from the last of them, and there'd be more than one last.
+    if responses::enabled() {
+        return Err(tr!("regen-responses").into());
+    }
+    let messages = {
+        let conversation = CONVERSATION.lock().await;
+        conversation
+            .split_last()
+            .filter(|(last, _)| chat_completion_message_role(last) == Role::Assistant)
+            .map(|(_, before)| before.to_vec())
+    };
+    let Some(messages) = messages else {
+        return Err(tr!("regen-nothing").into());
+    };
+
+    let model = overrides::model();
+    let jobs = (0..count)
+        .map(|_| (model.clone(), complete(&messages, &model)))
+        .collect();
+    let mut results = scheduler::run(jobs)?;
+    let mut candidates = vec![];
+    while let Some(candidate) = results.next().await {
+        candidates.push(candidate);
+    }
+    if candidates.iter().all(Result::is_err) {
+        return candidates.remove(0).map(|_| ());
+    }
+
+    print_candidates(&candidates);
+    let Some(n) = choose(&candidates) else {
+        return Ok(());
+    };
+    let Ok((text, truncated)) = candidates.swap_remove(n - 1) else {
+        unreachable!("only responses can be chosen")
+    };
+    {
+        let mut conversation = CONVERSATION.lock().await;
+        conversation.pop();
+        conversation.push(string_to_chat_completion_assistant_message(text));
+    }
+    continuation::set_truncated(truncated);
+    autosave().await;
+    info!("{}", tr!("regen-kept", n = n));
+    Ok(())
+}
+
+/// A response to `messages` from `model`, not streamed, as it's one of several.
+async fn complete(messages: &[ChatCompletionRequestMessage], model: &str) -> Candidate {
+    let config: &Config = &CONFIGURATION;
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    overrides::last().chat(&mut request);
+    request.n(1).stream(false).messages(messages.to_vec());
+    let response = openai::chat(request.build()?).await?;
+    let choice = response.choices.into_iter().next();
+    let truncated = choice
+        .as_ref()
+        .is_some_and(|choice| choice.finish_reason == Some(FinishReason::Length));
+    let text = choice
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+    let (prompt_tokens, completion_tokens) = match response.usage {
+        Some(usage) => (
+            usage.prompt_tokens as usize,
+            usage.completion_tokens as usize,
+        ),
+        None => (
+            usage::conversation_tokens(model, messages),
+            usage::count_tokens(model, &text),
+        ),
+    };
+    usage::record_tokens(model, prompt_tokens, completion_tokens);
+    Ok((text, truncated))
+}
+
+/// Print each of `candidates` under its number, or why it failed.
+fn print_candidates(candidates: &[Candidate]) {
+    for (i, candidate) in candidates.iter().enumerate() {
+        let n = i + 1;
+        match candidate {
+            Ok((text, _)) => {
+                let header = tr!("regen-candidate", n = n);
+                print_and_flush(&format!(
+                    "{}\n{}\n\n",
+                    theme().response.paint_to(atty::Stream::Stdout, &header),
+                    text.trim_end()
+                ));
+            }
+            Err(e) => error!("{}", tr!("regen-failed", n = n, error = e.to_string())),
+        }
+    }
+}
+
+/// The number of the candidate the user chooses, from 1. `None` to keep the response there was.
+fn choose(candidates: &[Candidate]) -> Option<usize> {
+    loop {
+        eprint!("{} ", tr!("regen-choose", count = candidates.len()));
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+        // rustyline is done with the terminal, so it's back to reading whole lines.
+        match io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => {}
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return None;
+        }
+        if let Some(n) = answer
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| candidates.get(n.wrapping_sub(1)).is_some_and(Result::is_ok))
+        {
+            return Some(n);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! `--config https://…`: a configuration shared by a team, downloaded over HTTPS and kept in the
+//! cache with its ETag, so it's only downloaded again when it changes, and used from there when the
+//! server can't be reached. What's in the local file of the same name, like `team.toml` for
+//! `https://intranet/ata2/team.toml`, is set over it: the API key, say, which a shared file
+//! shouldn't have. Where requests go and what tools may do it only sets if the local file trusts
+//! it with them in `trust_remote`.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use reqwest::header::{ETAG, IF_NONE_MATCH, USER_AGENT};
+use reqwest::StatusCode;
+use sha2::{Digest as _, Sha256};
+use toml::value::{Table, Value};
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+use crate::config;
+use crate::TokioResult;
+
+/// Where the configuration at `url` is cached, and its ETag beside it.
+fn cached(url: &str) -> (PathBuf, PathBuf) {
+    let hash = Sha256::digest(url);
+    let dir = config::get_cache_dir().join("config");
+    (
+        dir.join(format!("{hash:x}.toml")),
+        dir.join(format!("{hash:x}.etag")),
+    )
+}
+
+/// The configuration at `url` as it was last downloaded.
+pub fn cached_copy(url: &str) -> Option<String> {
+    fs::read_to_string(cached(url).0).ok()
+}
+
+/// The configuration at `url`, as the cache has it if it's unchanged or can't be downloaded.
+pub fn fetch(url: &str) -> TokioResult<String> {
+    let (path, etag_path) = cached(url);
+    let cached = cached_copy(url);
+    let etag = cached
+        .as_ref()
+        .and_then(|_| fs::read_to_string(&etag_path).ok());
+    // Its own runtime on its own thread, as the configuration is first read inside another.
+    let downloaded = {
+        let url = url.to_string();
+        thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?
+                .block_on(download(&url, etag))
+        })
+        .join()
+        .expect("downloading the configuration panicked")
+    };
+    match (downloaded, cached) {
+        (Ok(Some((contents, etag))), _) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, &contents)?;
+            match etag {
+                Some(etag) => fs::write(&etag_path, etag)?,
+                None => {
+                    let _ = fs::remove_file(&etag_path);
+                }
+            }
+            Ok(contents)
+        }
+        (Ok(None), Some(cached)) => Ok(cached),
+        (Err(e), Some(cached)) => {
+            warn!(
+                "{}",
+                tr!("config-remote-cached", url = url, error = e.to_string())
+            );
+            Ok(cached)
+        }
+        (Ok(None), None) => unreachable!("only asked if it changed with a cached copy"),
+        (Err(e), None) => Err(tr!("config-remote-failed", url = url, error = e.to_string()).into()),
+    }
+}
+
+/// What's at `url`, with its ETag, or `None` if it's still `etag`.
+async fn download(
+    url: &str,
+    etag: Option<String>,
+) -> TokioResult<Option<(String, Option<String>)>> {
+    let mut request = reqwest::Client::new()
+        .get(url)
+        .header(USER_AGENT, concat!("ata2/", env!("CARGO_PKG_VERSION")));
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag.trim());
+    }
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let response = response.error_for_status()?;
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string);
+    let contents = response.text().await?;
+    // Not cached if it isn't a configuration, like the login page of a proxy.
+    toml::from_str::<Table>(&contents)
+        .map_err(|e| tr!("config-remote-invalid", url = url, error = e.to_string()))?;
+    Ok(Some((contents, etag)))
+}
+
+/// What a downloaded configuration only sets if the local file lists it in `trust_remote`: where
+/// requests and the API key go, and what tools may run.
+const GUARDED: &[&str] = &[
+    "api_key",
+    "api_base",
+    "auth",
+    "provider",
+    "rotation",
+    "integrations",
+    "tools",
+];
+
+/// `remote` with what `local` sets over it, table by table, and what of [`GUARDED`] `remote` sets
+/// but was left out of it, as `local` doesn't trust it with that.
+pub fn with_overrides(
+    remote: &str,
+    local: &str,
+) -> Result<(String, Vec<&'static str>), toml::de::Error> {
+    let mut table: Table = toml::from_str(remote)?;
+    let local: Table = toml::from_str(local)?;
+    let trusted: Vec<&str> = local
+        .get("trust_remote")
+        .and_then(Value::as_array)
+        .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    // Only ever the local file's to say.
+    table.remove("trust_remote");
+    let left_out = GUARDED
+        .iter()
+        .copied()
+        .filter(|key| !trusted.contains(key) && table.remove(*key).is_some())
+        .collect();
+    merge(&mut table, local);
+    let merged = toml::to_string(&table).expect("a table read from TOML is TOML");
+    Ok((merged, left_out))
+}
+
+fn merge(table: &mut Table, over: Table) {
+    for (key, value) in over {
+        match (table.get_mut(&key), value) {
+            (Some(Value::Table(table)), Value::Table(over)) => merge(table, over),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REMOTE: &str = "model = \"gpt-4\"\napi_base = \"https://proxy.example/v1\"\ntrust_remote = [\"tools\"]\n\n[tools.policy]\ncode_interpreter = \"allow\"\n";
+
+    #[test]
+    fn guarded_settings_are_left_out() {
+        let (merged, left_out) = with_overrides(REMOTE, "temperature = 0.5\n").unwrap();
+        let merged: Table = toml::from_str(&merged).unwrap();
+        assert_eq!(left_out, ["api_base", "tools"]);
+        assert_eq!(merged["model"].as_str(), Some("gpt-4"));
+        assert_eq!(merged["temperature"].as_float(), Some(0.5));
+        assert!(!merged.contains_key("api_base"));
+        assert!(!merged.contains_key("tools"));
+    }
+
+    #[test]
+    fn trusted_settings_are_kept() {
+        let local = "trust_remote = [\"tools\"]\n\n[tools.policy]\nfile_search = \"deny\"\n";
+        let (merged, left_out) = with_overrides(REMOTE, local).unwrap();
+        let merged: Table = toml::from_str(&merged).unwrap();
+        assert_eq!(left_out, ["api_base"]);
+        let policy = &merged["tools"]["policy"];
+        assert_eq!(policy["code_interpreter"].as_str(), Some("allow"));
+        assert_eq!(policy["file_search"].as_str(), Some("deny"));
+        assert_eq!(merged["trust_remote"].as_array().map(Vec::len), Some(1));
+    }
+}
@@ -0,0 +1,182 @@
+//! `ata2 run workflow.toml --output report.md`: the prompt of a workflow sent once, with what it
+//! attaches, and the response written, for cron to make a daily summary of logs or digest of news.
+//! Nothing's asked, so tools that the policy asks about are refused, failed requests are sent
+//! again, and the exit code says what went wrong: 1 for the workflow, the configuration, what's
+//! attached or the output, 3 for requests that still failed, and 4 for taking too long.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::ChatCompletionRequestMessage;
+use tokio::time::{sleep, timeout};
+
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::Path;
+use std::process::exit;
+use std::time::Duration;
+
+use crate::api_error;
+use crate::attach;
+use crate::external;
+use crate::overrides;
+use crate::prompt;
+use crate::readline::string_to_chat_completion_request_user_message;
+
+use crate::sink::{self, Sinks};
+use crate::usage;
+use crate::workflow;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// What it exits with when the workflow, the configuration, what's attached or the output is wrong,
+/// which sending it again won't change.
+const FAILED: i32 = 1;
+/// When requests failed as many times as they're sent again.
+const REQUEST_FAILED: i32 = 3;
+/// When `--timeout` ran out.
+const TIMED_OUT: i32 = 4;
+
+/// The longest wait before a request is sent again, in seconds.
+const MAX_DELAY: u64 = 60;
+
+pub async fn run(
+    workflow: &Path,
+    output: Option<&Path>,
+    retries: u32,
+    seconds: u64,
+) -> TokioResult<()> {
+    let outcome = timeout(Duration::from_secs(seconds), async {
+        let messages = messages(workflow).await.map_err(|e| (FAILED, e))?;
+        respond(messages, retries)
+            .await
+            .map_err(|e| (REQUEST_FAILED, e))
+    })
+    .await;
+    let response = match outcome {
+        Ok(Ok(response)) => response,
+        Ok(Err((code, e))) => {
+            error!("{e}");
+            exit(code)
+        }
+        Err(_) => {
+            error!("{}", tr!("report-timed-out", seconds = seconds));
+            exit(TIMED_OUT)
+        }
+    };
+    if let Err(e) = write(output, response.trim()) {
+        error!("{e}");
+        exit(FAILED)
+    }
+    Ok(())
+}
+
+/// The conversation the workflow starts: its system message and its prompt, with what it
+/// attaches.
+async fn messages(path: &Path) -> TokioResult<Vec<ChatCompletionRequestMessage>> {
+    let Some(prompt) = workflow::get().and_then(|workflow| workflow.prompt.clone()) else {
+        let file = path.display().to_string();
+        return Err(tr!("report-no-prompt", file = file).into());
+    };
+    CONFIGURATION.validate()?;
+    workflow::start().await?;
+    let mut messages = vec![];
+    prompt::set_system(&mut messages);
+    messages.push(string_to_chat_completion_request_user_message(
+        attach::take(prompt),
+    ));
+    Ok(messages)
+}
+
+/// The response to `messages`, after the model's calls of tools, if it makes any.
+async fn respond(
+    mut messages: Vec<ChatCompletionRequestMessage>,
+    retries: u32,
+) -> TokioResult<String> {
+    let mut rounds = 0;
+    loop {
+        let (said, calls) = ask(&messages, retries).await?;
+        if calls.is_empty() {
+            return Ok(said);
+        }
+        if rounds == external::MAX_ROUNDS {
+            return Err(tr!("tool-too-many-rounds", count = rounds).into());
+        }
+        messages.extend(external::run(&said, calls, &mut Sinks::default()).await);
+        rounds += 1;
+    }
+}
+
+/// What the model says to `messages` and the tools it calls, with the request sent again up to
+/// `retries` times if it fails for what may not last, waiting twice as long each time.
+async fn ask(
+    messages: &[ChatCompletionRequestMessage],
+    retries: u32,
+) -> TokioResult<(String, external::Calls)> {
+    let mut attempt = 0;
+    loop {
+        let e = match once(messages).await {
+            Ok(ret) => return Ok(ret),
+            Err(e) => e,
+        };
+        if attempt == retries || !api_error::transient(&*e) {
+            return Err(e);
+        }
+        attempt += 1;
+        let delay = 2u64.saturating_pow(attempt).min(MAX_DELAY);
+        warn!(
+            "{}",
+            tr!(
+                "report-retrying",
+                error = e.to_string(),
+                seconds = delay,
+                attempt = attempt,
+                retries = retries
+            )
+        );
+        sleep(Duration::from_secs(delay)).await;
+    }
+}
+
+async fn once(messages: &[ChatCompletionRequestMessage]) -> TokioResult<(String, external::Calls)> {
+    let report = usage::Report::default();
+    let stream = prompt::stream(messages, &report).await?;
+    let model = overrides::model();
+    // Written once it's complete.
+    let said = sink::read(stream, &model, &mut Sinks::default()).await?;
+    usage::record(&model, messages, &said.text, &report);
+    Ok((said.text, said.calls))
+}
+
+/// Write `response` to `output`, whole or not at all, or to stdout.
+fn write(output: Option<&Path>, response: &str) -> TokioResult<()> {
+    let Some(path) = output else {
+        writeln!(io::stdout(), "{response}")?;
+        return Ok(());
+    };
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, format!("{response}\n"))
+        .and_then(|()| fs::rename(&tmp, path))
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    info!(
+        "{}",
+        tr!("report-written", file = path.display().to_string())
+    );
+    Ok(())
+}
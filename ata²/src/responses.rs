@@ -0,0 +1,323 @@
+//! `api = "responses"`: conversations through OpenAI's Responses API instead of chat completions.
+//! OpenAI keeps the conversation, so after the first exchange only the new prompt is sent, along
+//! with the ID of the last response, which is saved with the conversation. The model can use the
+//! built-in tools of `[responses]`, and files uploaded with `/upload`.
+//!
+//! The events of the response are turned into chat completion chunks, for the REPL and the TUI to
+//! take them as they take any other response.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::error::OpenAIError;
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionResponseStream,
+    ChatCompletionResponseStreamMessage, ChatCompletionStreamResponseDelta,
+    CreateChatCompletionStreamResponse, FinishReason, Role,
+};
+use eventsource_stream::Eventsource as _;
+use futures_util::StreamExt as _;
+use reqwest::multipart::{Form, Part};
+use serde_json::{json, Value};
+
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::api_error::ApiError;
+use crate::commands::usage;
+use crate::config::{Api, Config};
+use crate::openai::{self, post};
+use crate::overrides;
+use crate::policy;
+use crate::readline::{chat_completion_message_role, chat_completion_message_text};
+use crate::tools;
+use crate::usage::Report;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// The last response of the conversation, which the next continues. `None` to send all of it.
+static THREAD: Mutex<Option<String>> = Mutex::new(None);
+
+/// Files uploaded with `/upload` that go with the next prompt, and whether each is a PDF, which
+/// the model reads. Others are only for `code_interpreter`.
+static UPLOADS: Mutex<Vec<(String, bool)>> = Mutex::new(vec![]);
+
+pub fn enabled() -> bool {
+    CONFIGURATION.api == Api::Responses
+}
+
+/// The ID of the last response of the conversation, if it can be continued from there.
+pub fn thread() -> Option<String> {
+    THREAD.lock().unwrap().clone()
+}
+
+/// Continue from response `id` from now on, as when a conversation is loaded. `None` to send the
+/// whole conversation next time.
+pub fn set_thread(id: Option<String>) {
+    *THREAD.lock().unwrap() = id;
+}
+
+/// Send the last message of `messages`, the prompt, continuing the conversation from the last
+/// response. If there's none, as in a new conversation or one from chat completions, send all of
+/// them.
+pub async fn send(
+    messages: &[ChatCompletionRequestMessage],
+    report: &Report,
+) -> TokioResult<ChatCompletionResponseStream> {
+    let config: &Config = &CONFIGURATION;
+    // Taken, so that a response that doesn't complete leaves the whole conversation to be sent.
+    let previous = THREAD.lock().unwrap().take();
+    tools::clear();
+    let uploads = std::mem::take(&mut *UPLOADS.lock().unwrap());
+    let sent = match previous {
+        Some(_) => &messages[messages.len().saturating_sub(1)..],
+        None => messages,
+    };
+    let mut input = sent
+        .iter()
+        .filter_map(|message| {
+            let role = match chat_completion_message_role(message) {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool | Role::Function => return None,
+            };
+            let text = chat_completion_message_text(message)?;
+            Some(json!({ "role": role, "content": text }))
+        })
+        .collect::<Vec<_>>();
+    let pdfs = uploads
+        .iter()
+        .filter(|(_, pdf)| *pdf)
+        .map(|(id, _)| json!({ "type": "input_file", "file_id": id }))
+        .collect::<Vec<_>>();
+    if let Some(prompt) = input.last_mut().filter(|_| !pdfs.is_empty()) {
+        let mut content = vec![json!({ "type": "input_text", "text": prompt["content"] })];
+        content.extend(pdfs);
+        prompt["content"] = Value::Array(content);
+    }
+    let file_ids = uploads.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+    let prompt = sent
+        .last()
+        .and_then(chat_completion_message_text)
+        .unwrap_or_default();
+    let tools = policy::prompt_tools(&prompt)
+        .iter()
+        .map(|tool| match tool.as_str() {
+            "file_search" => json!({
+                "type": "file_search",
+                "vector_store_ids": config.responses.vector_store_ids,
+            }),
+            _ => json!({
+                "type": "code_interpreter",
+                "container": { "type": "auto", "file_ids": file_ids },
+            }),
+        })
+        .collect::<Vec<_>>();
+    let mut body = json!({
+        "model": config.model,
+        "input": input,
+        "stream": true,
+        "store": true,
+        "max_output_tokens": config.max_tokens,
+        "temperature": config.temperature,
+        "top_p": config.top_p,
+    });
+    if let Some(previous) = previous {
+        body["previous_response_id"] = previous.into();
+    }
+    if !tools.is_empty() {
+        body["tools"] = tools.into();
+        // What the tools found and output, to be shown with them.
+        body["include"] = json!(["code_interpreter_call.outputs", "file_search_call.results"]);
+    }
+    if let Some(user_id) = &config.user_id {
+        body["user"] = user_id.as_str().into();
+    }
+    if let Some(key) = openai::cache_key(messages).filter(|_| openai::official()) {
+        body["prompt_cache_key"] = key.into();
+    }
+    overrides::last().responses(&mut body);
+
+    let model = overrides::model();
+    let response = openai::send("/responses", &body).await?;
+    if !response.status().is_success() {
+        return Err(ApiError::of(response, &model).await.into());
+    }
+
+    let mut events = response.bytes_stream().eventsource();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let report = report.clone();
+    tokio::spawn(async move {
+        loop {
+            let event = match tools::deadline() {
+                Some((tool, deadline)) => {
+                    match tokio::time::timeout_at(deadline.into(), events.next()).await {
+                        Ok(event) => event,
+                        Err(_) => {
+                            let secs = CONFIGURATION
+                                .tools
+                                .timeouts
+                                .of(&tool)
+                                .unwrap_or_default()
+                                .as_secs();
+                            let message = tr!("tool-timed-out", tool = tool, secs = secs);
+                            let _ = tx.send(Err(OpenAIError::StreamError(message)));
+                            break;
+                        }
+                    }
+                }
+                None => events.next().await,
+            };
+            let Some(event) = event else {
+                break;
+            };
+            let chunk = match event {
+                Ok(event) => match chunk(&model, &event.data, &report) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                },
+                Err(e) => Err(OpenAIError::StreamError(e.to_string())),
+            };
+            let last = chunk.is_err()
+                || chunk.as_ref().is_ok_and(|chunk| {
+                    chunk
+                        .choices
+                        .iter()
+                        .any(|choice| choice.finish_reason.is_some())
+                });
+            if tx.send(chunk).is_err() || last {
+                break;
+            }
+        }
+    });
+    Ok(Box::pin(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+    ))
+}
+
+/// The chat completion chunk of the event `data`, if it's one with text, a tool that ran, or the
+/// end. Its usage, at the end, is reported to `report`.
+fn chunk(
+    model: &str,
+    data: &str,
+    report: &Report,
+) -> Option<Result<CreateChatCompletionStreamResponse, OpenAIError>> {
+    let event = serde_json::from_str::<Value>(data).ok()?;
+    let mut tool_calls = None;
+    let (content, finish_reason) = match event["type"].as_str()? {
+        "response.output_text.delta" => (Some(event["delta"].as_str()?.to_string()), None),
+        "response.output_item.added" => {
+            tools::started(&event["item"]);
+            return None;
+        }
+        "response.output_item.done" => {
+            tool_calls = Some(vec![tools::finished(&event["item"])?]);
+            (None, None)
+        }
+        "response.completed" => {
+            if let Some(reported) = openai::reported(&event["response"]["usage"]) {
+                report.set(reported);
+            }
+            set_thread(event["response"]["id"].as_str().map(str::to_string));
+            (None, Some(FinishReason::Stop))
+        }
+        "response.incomplete" => {
+            set_thread(event["response"]["id"].as_str().map(str::to_string));
+            (None, Some(FinishReason::Length))
+        }
+        "response.failed" => {
+            let message = event["response"]["error"]["message"]
+                .as_str()
+                .unwrap_or("failed");
+            return Some(Err(OpenAIError::StreamError(message.to_string())));
+        }
+        "error" => {
+            let message = event["message"].as_str().unwrap_or("error");
+            return Some(Err(OpenAIError::StreamError(message.to_string())));
+        }
+        _ => return None,
+    };
+    #[allow(deprecated)]
+    let delta = ChatCompletionStreamResponseDelta {
+        content,
+        function_call: None,
+        tool_calls,
+        role: None,
+    };
+    Some(Ok(CreateChatCompletionStreamResponse {
+        id: String::new(),
+        choices: vec![ChatCompletionResponseStreamMessage {
+            index: 0,
+            delta,
+            finish_reason,
+        }],
+        created: 0,
+        model: model.to_string(),
+        system_fingerprint: None,
+        object: String::from("chat.completion.chunk"),
+    }))
+}
+
+/// `/upload <file>`: upload a file for the next prompt, a PDF for the model to read, and any file
+/// for the `code_interpreter` tool to run code on.
+pub async fn upload(args: Vec<String>) -> TokioResult<()> {
+    let [path] = args.as_slice() else {
+        return Err(usage("upload"));
+    };
+    if !enabled() {
+        return Err(tr!("upload-chat-api").into());
+    }
+    if !Path::new(path).is_file() {
+        return Err(tr!("upload-no-such", path = path.as_str()).into());
+    }
+    tools::confine(Path::new(path))?;
+    let pdf = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+    let interpreter = CONFIGURATION
+        .responses
+        .tools
+        .iter()
+        .any(|tool| tool == "code_interpreter");
+    if !pdf && !interpreter {
+        return Err(tr!("upload-not-pdf").into());
+    }
+    let name = Path::new(path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let form = Form::new()
+        .text("purpose", "user_data")
+        .part("file", Part::bytes(fs::read(path)?).file_name(name));
+    let response = post("/files")?.multipart(form).send().await?;
+    if !response.status().is_success() {
+        return Err(ApiError::of(response, &CONFIGURATION.model).await.into());
+    }
+    let file = response.json::<Value>().await?;
+    let id = file["id"].as_str().unwrap_or_default().to_string();
+    info!(
+        "{}",
+        tr!("upload-done", path = path.as_str(), id = id.as_str())
+    );
+    UPLOADS.lock().unwrap().push((id, pdf));
+    Ok(())
+}
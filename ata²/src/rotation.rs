@@ -0,0 +1,161 @@
+//! Several API keys that share the requests between them, as `[rotation]` configures: with the
+//! `failover` strategy each request is sent with the same key until it's rate limited, and with
+//! `round-robin` with the next one every time. A request that's rate limited is sent again with
+//! the next key, as long as there's one it wasn't sent with. With `storage = "sqlite"` the usage
+//! ledger says what each key spent, and a key that spent `monthly_limit` this month is passed over.
+//!
+//! Keys are told apart in the ledger and in `ata2 keys` by their last four characters, for the
+//! keys themselves not to be written anywhere.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use chrono::{Datelike as _, TimeZone as _};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::config::{Config, Strategy};
+use crate::store;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// Index of the key the next request is sent with, in [`keys`].
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// The key the last request was sent with.
+static LAST: Mutex<Option<String>> = Mutex::new(None);
+
+/// `api_key` and then `rotation.keys`, each once.
+pub fn keys(config: &Config) -> Vec<String> {
+    let mut ret = Vec::<String>::new();
+    for key in config.api_key.iter().chain(&config.rotation.keys) {
+        if !key.is_empty() && !ret.contains(key) {
+            ret.push(key.clone());
+        }
+    }
+    ret
+}
+
+/// How `key` is told apart from the others where it's shown or recorded: “…” and its last four
+/// characters, as OpenAI's dashboard shows it.
+pub fn fingerprint(key: &str) -> String {
+    let start = key.char_indices().rev().nth(3).map_or(0, |(i, _)| i);
+    format!("…{}", &key[start..])
+}
+
+/// The start of this calendar month, UTC as OpenAI bills, in seconds since the Unix epoch.
+fn month_start() -> i64 {
+    let now = chrono::Utc::now();
+    chrono::Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .unwrap()
+        .timestamp()
+}
+
+/// What `key` spent this month, if the usage ledger keeps track.
+fn spent(key: &str) -> Option<f64> {
+    if !store::enabled() {
+        return None;
+    }
+    store::spent(&fingerprint(key), month_start())
+        .map_err(|e| error!("{}", tr!("keys-ledger-read-failed", error = e.to_string())))
+        .ok()
+}
+
+/// Has `key` spent less than `rotation.monthly_limit` this month?
+fn within_limit(config: &Config, key: &str) -> bool {
+    let limit = config.rotation.monthly_limit;
+    limit <= 0.0 || spent(key).is_none_or(|spent| spent < limit)
+}
+
+/// How many of the keys haven't spent their `rotation.monthly_limit` this month.
+pub fn available(config: &Config) -> usize {
+    keys(config)
+        .iter()
+        .filter(|key| within_limit(config, key))
+        .count()
+}
+
+/// The key to send the next request with, as `rotation.strategy` says. `None` if there's none, or
+/// if every key spent its `rotation.monthly_limit`.
+pub fn next(config: &Config) -> Option<String> {
+    let keys = keys(config);
+    if keys.is_empty() {
+        return None;
+    }
+    let start = match config.rotation.strategy {
+        Strategy::Failover => NEXT.load(Ordering::SeqCst),
+        Strategy::RoundRobin => NEXT.fetch_add(1, Ordering::SeqCst),
+    };
+    let i = (start..start + keys.len())
+        .map(|i| i % keys.len())
+        .find(|&i| within_limit(config, &keys[i]))?;
+    if config.rotation.strategy == Strategy::Failover && i != start % keys.len() {
+        NEXT.store(i, Ordering::SeqCst);
+    }
+    let key = keys[i].clone();
+    *LAST.lock().unwrap() = Some(key.clone());
+    Some(key)
+}
+
+/// The last request was rate limited, so with `failover` the next goes out with the next key.
+pub fn rate_limited() {
+    let keys = keys(&CONFIGURATION);
+    let Some(last) = LAST.lock().unwrap().clone() else {
+        return;
+    };
+    if keys.len() > 1 {
+        warn!("{}", tr!("keys-rate-limited", key = fingerprint(&last)));
+    }
+    if CONFIGURATION.rotation.strategy == Strategy::Failover {
+        let current = NEXT.load(Ordering::SeqCst);
+        if keys.get(current % keys.len().max(1)) == Some(&last) {
+            NEXT.store(current + 1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// The [`fingerprint`] of the key the last request was sent with, for the usage ledger.
+pub fn last() -> Option<String> {
+    LAST.lock().unwrap().as_deref().map(fingerprint)
+}
+
+/// `ata2 keys`: list the API keys with what each spent this month.
+pub fn run() -> TokioResult<()> {
+    let keys = keys(&CONFIGURATION);
+    if keys.is_empty() {
+        return Err(tr!("keys-none").into());
+    }
+    let limit = CONFIGURATION.rotation.monthly_limit;
+    for key in &keys {
+        let key_shown = fingerprint(key);
+        let line = match spent(key) {
+            Some(spent) if limit > 0.0 => tr!(
+                "keys-spent-limit",
+                key = key_shown,
+                spent = format!("{spent:.2}"),
+                limit = format!("{limit:.2}")
+            ),
+            Some(spent) => tr!("keys-spent", key = key_shown, spent = format!("{spent:.2}")),
+            None => tr!("keys-untracked", key = key_shown),
+        };
+        println!("{line}");
+    }
+    Ok(())
+}
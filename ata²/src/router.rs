@@ -0,0 +1,108 @@
+//! `[router]`: each prompt sent to a cheap model or a strong one, by what it's like, for the
+//! questions that a cheap model answers as well not to be paid for at the price of a strong one.
+//! A prompt goes to the strong model when it asks the model to think hard, has files attached,
+//! has code in it, or is long, each as `[router]` says, and to the cheap one otherwise. A model
+//! that the prompt names with `@model=` goes before either.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use std::sync::Mutex;
+
+use crate::overrides::Overrides;
+use crate::usage::count_tokens;
+use crate::CONFIGURATION;
+
+/// Where the last prompt was sent and why, if it was routed.
+static LAST: Mutex<Option<String>> = Mutex::new(None);
+
+/// Send `prompt`, with something attached to it or not, to the model `[router]` picks for it,
+/// unless `overrides` say which model it goes to.
+pub fn route(overrides: &mut Overrides, prompt: &str, attached: bool) {
+    let routed = (CONFIGURATION.router.enabled && overrides.model().is_none()).then(|| {
+        let (model, reason) = pick(prompt, attached);
+        overrides.or_model(model.to_string());
+        tr!("router-routed", model = model, reason = reason)
+    });
+    *LAST.lock().unwrap() = routed;
+}
+
+/// The model `prompt`, with something attached to it or not, would be sent to: the one
+/// `overrides` say, or else the one `[router]` picks, or else the configured one.
+pub fn model_for(overrides: &Overrides, prompt: &str, attached: bool) -> String {
+    match overrides.model() {
+        Some(model) => model.to_string(),
+        None if CONFIGURATION.router.enabled => pick(prompt, attached).0.to_string(),
+        None => CONFIGURATION.model.clone(),
+    }
+}
+
+/// Where the last prompt was sent and why, if it was routed.
+pub fn last() -> Option<String> {
+    LAST.lock().unwrap().clone()
+}
+
+/// The model for `prompt`, and why it's that one.
+fn pick(prompt: &str, attached: bool) -> (&'static str, String) {
+    let router = &CONFIGURATION.router;
+    let lowercase = prompt.to_lowercase();
+    if let Some(marker) = router
+        .markers
+        .iter()
+        .find(|marker| lowercase.contains(&marker.to_lowercase()))
+    {
+        return (
+            &router.strong,
+            tr!("router-marker", marker = marker.as_str()),
+        );
+    }
+    if router.attachments && attached {
+        return (&router.strong, tr!("router-attachments"));
+    }
+    if router.code && is_code(prompt) {
+        return (&router.strong, tr!("router-code"));
+    }
+    let tokens = count_tokens(&router.cheap, prompt);
+    if router.max_cheap_tokens > 0 && tokens > router.max_cheap_tokens {
+        return (&router.strong, tr!("router-long", tokens = tokens));
+    }
+    (&router.cheap, tr!("router-short", tokens = tokens))
+}
+
+/// Whether `text` has a code block in it, or is mostly lines that look like code: ending in
+/// brackets or a semicolon, or indented.
+fn is_code(text: &str) -> bool {
+    if text.contains("```") {
+        return true;
+    }
+    let lines = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let code = lines
+        .iter()
+        .filter(|line| {
+            line.starts_with("    ")
+                || line.starts_with('\t')
+                || line
+                    .trim_end()
+                    .ends_with([';', '{', '}', '(', ')', '[', ']'])
+        })
+        .count();
+    lines.len() >= 3 && code * 2 >= lines.len()
+}
@@ -0,0 +1,107 @@
+//! Text from stdin or pasted in, without what shouldn't go in a conversation: bytes that aren't
+//! UTF-8, the escape sequences of terminals, like the colors of a log, and the other control
+//! characters but newlines and tabs. Sent on, they'd garble the terminal when the prompt is shown
+//! again and end up in the saved conversation.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+/// The one-character CSI of C1.
+const CSI: char = '\u{9b}';
+
+/// `bytes` as clean text, warning of what was stripped if anything was.
+pub fn bytes(bytes: &[u8]) -> String {
+    let mut ret = String::with_capacity(bytes.len());
+    let mut stripped = 0;
+    for chunk in bytes.utf8_chunks() {
+        ret += chunk.valid();
+        stripped += chunk.invalid().len();
+    }
+    let (ret, more) = strip(&ret);
+    warn_stripped(stripped + more);
+    ret
+}
+
+/// `text` without escape sequences and control characters, warning of them if it had any.
+pub fn text(text: &str) -> String {
+    let (ret, stripped) = strip(text);
+    warn_stripped(stripped);
+    ret
+}
+
+fn warn_stripped(bytes: usize) {
+    if bytes > 0 {
+        warn!("{}", tr!("input-stripped", bytes = bytes));
+    }
+}
+
+/// `text` without escape sequences and control characters, and how many bytes they were. `\r\n`
+/// is a newline, and a `\r` on its own, as a progress bar redraws its line with, is stripped.
+fn strip(text: &str) -> (String, usize) {
+    let mut ret = String::with_capacity(text.len());
+    let mut stripped = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let end = match c {
+            '\n' | '\t' => {
+                ret.push(c);
+                continue;
+            }
+            '\r' if chars.peek().is_some_and(|(_, c)| *c == '\n') => continue,
+            // CSI, like `ESC [ 1 ; 3 1 m`, ends at a character from `@` to `~`.
+            ESC if chars.next_if(|(_, c)| *c == '[').is_some() => csi_end(&mut chars),
+            CSI => csi_end(&mut chars),
+            // OSC, like the title of a window or a hyperlink, ends at BEL or `ESC \`.
+            ESC if chars.next_if(|(_, c)| *c == ']').is_some() => {
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    if c == BEL {
+                        end = Some(i + 1);
+                        break;
+                    }
+                    if c == ESC && chars.next_if(|(_, c)| *c == '\\').is_some() {
+                        end = Some(i + 2);
+                        break;
+                    }
+                }
+                end
+            }
+            // The rest are ESC and one character.
+            ESC => chars.next().map(|(i, c)| i + c.len_utf8()),
+            _ if c.is_control() => Some(start + c.len_utf8()),
+            _ => {
+                ret.push(c);
+                continue;
+            }
+        };
+        stripped += end.unwrap_or(text.len()) - start;
+    }
+    (ret, stripped)
+}
+
+/// Past the character that ends a CSI sequence.
+fn csi_end(chars: &mut Peekable<CharIndices<'_>>) -> Option<usize> {
+    chars
+        .find(|(_, c)| ('@'..='~').contains(c))
+        .map(|(i, c)| i + c.len_utf8())
+}
@@ -0,0 +1,138 @@
+//! Requests sent at once, as many as `[scheduler]` allows, for `ata2 sweep`, `ata2 eval` and the
+//! chunks of [`crate::chunk`]: at most `concurrency` of them, and at most `per_model` of those to
+//! a model that has a limit of its own there. The results come in the order of the jobs, each as
+//! soon as it and those before it are done, and meanwhile a status line on stderr says how far
+//! along they are.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use futures_util::stream::{self, Stream, StreamExt as _};
+use tokio::sync::Semaphore;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+
+use crate::progress::write_stderr;
+use crate::style;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// How far along the jobs are, for the status line.
+struct Status {
+    total: usize,
+    done: AtomicUsize,
+    running: AtomicUsize,
+    /// Is the status line on the screen?
+    shown: AtomicBool,
+    /// Is there a status line at all? Not when stderr isn't a terminal, nor with
+    /// `ui.show_progress = false`.
+    enabled: bool,
+}
+
+impl Status {
+    fn draw(&self) {
+        if !self.enabled {
+            return;
+        }
+        let status = tr!(
+            "scheduler-progress",
+            done = self.done.load(Ordering::SeqCst),
+            total = self.total,
+            running = self.running.load(Ordering::SeqCst)
+        );
+        write_stderr(&format!("\r\x1b[2K{}", style::paint("dim", &status)));
+        self.shown.store(true, Ordering::SeqCst);
+    }
+
+    /// Clear the status line, for what's printed about a result to take its place.
+    fn clear(&self) {
+        if self.shown.swap(false, Ordering::SeqCst) {
+            write_stderr("\r\x1b[2K");
+        }
+    }
+}
+
+/// Run `jobs`, each the model it sends a request to and what does that, at once as `[scheduler]`
+/// allows, and yield what they return in their order. Nothing may be printed while they run but by
+/// whoever takes the results, for the status line. A limit of 0, which none would get past, is an
+/// error, as the subcommands that run jobs don't validate the configuration first.
+pub fn run<'a, T, F>(jobs: Vec<(String, F)>) -> TokioResult<impl Stream<Item = T> + Unpin + 'a>
+where
+    F: Future<Output = T> + 'a,
+    T: 'a,
+{
+    let scheduler = &config.scheduler;
+    if scheduler.concurrency < 1 || scheduler.per_model.values().any(|n| *n < 1) {
+        return Err(tr!("scheduler-zero").into());
+    }
+    let all = Arc::new(Semaphore::new(config.scheduler.concurrency));
+    let per_model = config
+        .scheduler
+        .per_model
+        .iter()
+        .map(|(model, limit)| (model.clone(), Arc::new(Semaphore::new(*limit))))
+        .collect::<HashMap<_, _>>();
+    let status = Arc::new(Status {
+        total: jobs.len(),
+        done: AtomicUsize::new(0),
+        running: AtomicUsize::new(0),
+        shown: AtomicBool::new(false),
+        enabled: config.ui.show_progress && atty::is(atty::Stream::Stderr),
+    });
+
+    let len = jobs.len().max(1);
+    let mut results = stream::iter(jobs)
+        .map({
+            let status = status.clone();
+            move |(model, job)| {
+                let all = all.clone();
+                let model_limit = per_model.get(&model).cloned();
+                let status = status.clone();
+                async move {
+                    // The model's place first, not to take up one of the others' while waiting.
+                    let _model_permit = match model_limit {
+                        Some(limit) => limit.acquire_owned().await.ok(),
+                        None => None,
+                    };
+                    let _permit = all.acquire_owned().await.ok();
+                    status.running.fetch_add(1, Ordering::SeqCst);
+                    status.draw();
+                    let result = job.await;
+                    status.running.fetch_sub(1, Ordering::SeqCst);
+                    status.done.fetch_add(1, Ordering::SeqCst);
+                    status.draw();
+                    result
+                }
+            }
+        })
+        // All of them polled, for the semaphores to decide which go ahead.
+        .buffered(len);
+    Ok(stream::poll_fn(move |cx| {
+        let poll = results.poll_next_unpin(cx);
+        match poll {
+            Poll::Ready(_) => status.clear(),
+            Poll::Pending if !status.shown.load(Ordering::SeqCst) => status.draw(),
+            Poll::Pending => {}
+        }
+        poll
+    }))
+}
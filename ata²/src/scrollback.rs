@@ -0,0 +1,151 @@
+//! Re-printing the conversation (`/show`), since terminal scrollback doesn't survive clearing the
+//! screen or editing long multiline prompts.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, Role};
+use tokio::io::AsyncWriteExt as _;
+use tokio::process::Command;
+
+use std::env;
+use std::ops::Range;
+use std::process::Stdio;
+
+use crate::commands;
+use crate::output::print_and_flush;
+use crate::pin;
+use crate::prompt::CONVERSATION;
+use crate::readline::{chat_completion_message_role, chat_completion_message_text};
+use crate::style::{self, theme, Style};
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+pub async fn show(args: Vec<String>) -> TokioResult<()> {
+    let n = match args.as_slice() {
+        [] => 1,
+        [n] => n.parse().map_err(|_| commands::usage("show"))?,
+        _ => return Err(commands::usage("show")),
+    };
+    let conversation = CONVERSATION.lock().await.clone();
+    let exchanges = exchanges(&conversation);
+    if exchanges.is_empty() {
+        return Err(tr!("conversation-empty").into());
+    }
+    let styled = style::enabled(atty::Stream::Stdout);
+    let text = exchanges[exchanges.len().saturating_sub(n)..]
+        .iter()
+        .map(|exchange| render(&conversation, exchange.clone(), styled))
+        .collect::<String>();
+    page(&text).await
+}
+
+/// Split `conversation` before each of the user's messages, so that each part is one prompt and the
+/// response to it. Whatever precedes the first prompt, such as a system message, is a part of its
+/// own.
+fn exchanges(conversation: &[ChatCompletionRequestMessage]) -> Vec<Range<usize>> {
+    let mut ret = vec![];
+    let mut start = 0;
+    for (i, message) in conversation.iter().enumerate() {
+        if i > start && chat_completion_message_role(message) == Role::User {
+            ret.push(start..i);
+            start = i;
+        }
+    }
+    if start < conversation.len() {
+        ret.push(start..conversation.len());
+    }
+    ret
+}
+
+/// Print the messages of `conversation` in `range` the way the REPL did, each with its number for
+/// `/pin`, and whether it's pinned.
+fn render(
+    conversation: &[ChatCompletionRequestMessage],
+    range: Range<usize>,
+    styled: bool,
+) -> String {
+    let mut ret = String::new();
+    for (i, message) in conversation[range.clone()].iter().enumerate() {
+        // The model's calls of tools, which say nothing.
+        let Some(text) = chat_completion_message_text(message) else {
+            continue;
+        };
+        let n = range.start + i + 1;
+        let role = chat_completion_message_role(message);
+        let header = match role {
+            Role::User => "Prompt:",
+            Role::Assistant => "Response:",
+            Role::System => "System:",
+            _ => "Tool:",
+        };
+        if styled {
+            let style = match role {
+                Role::User => theme().prompt.clone(),
+                Role::Assistant => theme().response.clone(),
+                _ => Style::parse("bold").unwrap_or_default(),
+            };
+            ret.push_str(&style.paint_to(atty::Stream::Stdout, header));
+        } else {
+            ret.push_str(header);
+        }
+        let number = if pin::is_pinned(n) {
+            tr!("show-pinned", n = n)
+        } else {
+            format!("#{n}")
+        };
+        if styled {
+            ret += &format!(
+                " {}",
+                Style::parse("dim")
+                    .unwrap_or_default()
+                    .paint_to(atty::Stream::Stdout, &number)
+            );
+        } else {
+            ret += &format!(" {number}");
+        }
+        ret.push('\n');
+        ret.push_str(text.trim_end());
+        ret.push_str("\n\n");
+    }
+    ret
+}
+
+/// Show `text` with `ui.pager`, or just print it if stdout isn't a TTY or there's no pager.
+pub async fn page(text: &str) -> TokioResult<()> {
+    let pager = config.ui.pager.trim();
+    if pager.is_empty() || !atty::is(atty::Stream::Stdout) {
+        print_and_flush(text);
+        return Ok(());
+    }
+    let mut command = Command::new("sh");
+    command.args(["-c", pager]).stdin(Stdio::piped());
+    // Like git, have a plain `less` show the styles rather than their escapes.
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "R");
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| tr!("run-failed", program = pager, error = e.to_string()))?;
+    let mut stdin = child.stdin.take().unwrap();
+    // The pager closes its end of the pipe if it's quit before reading everything, which is fine.
+    let _ = stdin.write_all(text.as_bytes()).await;
+    drop(stdin);
+    child.wait().await?;
+    Ok(())
+}
@@ -0,0 +1,329 @@
+//! Fuzzy search over the prompt history and saved conversations (Ctrl-R).
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::Role;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use futures_util::FutureExt as _;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher as _;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout, Position};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListDirection, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+use rustyline::{Cmd, ConditionalEventHandler, EventContext, Movement, RepeatCount};
+
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use crate::history;
+use crate::prompt::load_conversation;
+use crate::readline::{
+    chat_completion_message_role, chat_completion_message_text, read_conversation,
+    saved_conversations,
+};
+use crate::shutdown;
+use crate::style;
+use crate::width;
+
+/// How much of a conversation is searched. The matcher is quadratic, and the start of a
+/// conversation is what people remember anyway.
+const MAX_SEARCHED: usize = 16 * 1024;
+
+enum Item {
+    History(String),
+    Conversation {
+        path: PathBuf,
+        /// The first line of the first prompt.
+        title: String,
+        text: String,
+    },
+}
+
+impl Item {
+    fn searched(&self) -> &str {
+        match self {
+            Item::History(entry) => entry,
+            Item::Conversation { text, .. } => text,
+        }
+    }
+
+    fn render(&self) -> Line<'_> {
+        match self {
+            Item::History(entry) => Line::from(entry.lines().next().unwrap_or_default()),
+            Item::Conversation { path, title, .. } => Line::from(vec![
+                Span::styled(
+                    "conversation ",
+                    Style::default().fg(style::tui_color(Color::Green)),
+                ),
+                Span::raw(title.as_str()),
+                Span::styled(
+                    format!("  {}", path.display()),
+                    Style::default().add_modifier(Modifier::DIM),
+                ),
+            ]),
+        }
+    }
+}
+
+/// What the user picked.
+enum Picked {
+    History(String),
+    Conversation(PathBuf),
+}
+
+/// Bound to Ctrl-R instead of rustyline's incremental search.
+pub struct SearchHandler {
+    /// Prompt history, oldest first. rustyline's own can't be read from a key handler.
+    pub history: Arc<Mutex<Vec<history::Entry>>>,
+}
+
+impl ConditionalEventHandler for SearchHandler {
+    fn handle(
+        &self,
+        _event: &rustyline::Event,
+        _n: RepeatCount,
+        _positive: bool,
+        ctx: &EventContext,
+    ) -> Option<Cmd> {
+        let history = self
+            .history
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.text.clone())
+            .collect();
+        let picked = match pick(history, ctx.line()) {
+            Ok(picked) => picked,
+            Err(e) => {
+                error!("{}", tr!("search-failed", error = e.to_string()));
+                return Some(Cmd::Noop);
+            }
+        };
+        match picked {
+            Some(Picked::History(entry)) => Some(Cmd::Replace(Movement::WholeBuffer, Some(entry))),
+            Some(Picked::Conversation(path)) => {
+                // This runs while the REPL waits for input, so the conversation is normally free.
+                let message = match load_conversation(&path).now_or_never() {
+                    Some(Ok(())) => tr!("conversation-resumed", path = path.display().to_string()),
+                    Some(Err(e)) => tr!(
+                        "conversation-resume-failed",
+                        path = path.display().to_string(),
+                        error = e.to_string(),
+                    ),
+                    None => tr!("conversation-busy"),
+                };
+                // The line rustyline redraws the prompt on is the one we're on, so move on.
+                print!("\r\x1b[2K{message}\r\n");
+                let _ = io::stdout().flush();
+                Some(Cmd::Replace(Movement::WholeBuffer, Some(String::new())))
+            }
+            None => Some(Cmd::Noop),
+        }
+    }
+}
+
+fn items(history: Vec<String>) -> Vec<Item> {
+    let mut items = history
+        .into_iter()
+        .rev()
+        .map(Item::History)
+        .collect::<Vec<_>>();
+    for path in saved_conversations() {
+        let Ok(messages) = read_conversation(&path) else {
+            continue;
+        };
+        let texts = messages
+            .iter()
+            .filter_map(chat_completion_message_text)
+            .collect::<Vec<_>>();
+        let title = messages
+            .iter()
+            .find(|m| chat_completion_message_role(m) == Role::User)
+            .and_then(chat_completion_message_text)
+            .and_then(|text| text.lines().next().map(str::to_string))
+            .unwrap_or_default();
+        let mut text = format!("{title}\n{}", texts.join("\n"));
+        if let Some((i, _)) = text.char_indices().nth(MAX_SEARCHED) {
+            text.truncate(i);
+        }
+        items.push(Item::Conversation { path, title, text });
+    }
+    items
+}
+
+/// Run the picker on the alternate screen, so the REPL's screen comes back untouched.
+fn pick(history: Vec<String>, query: &str) -> io::Result<Option<Picked>> {
+    let items = items(history);
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    shutdown::ALTERNATE_SCREEN.store(true, Ordering::Relaxed);
+    let result = Terminal::new(CrosstermBackend::new(io::stdout()))
+        .and_then(|mut terminal| Picker::new(&items, query).run(&mut terminal));
+    execute!(stdout, LeaveAlternateScreen)?;
+    shutdown::ALTERNATE_SCREEN.store(false, Ordering::Relaxed);
+    result
+}
+
+struct Picker<'a> {
+    items: &'a [Item],
+    query: String,
+    /// Indices into `items` of those matching `query`, best first.
+    matches: Vec<usize>,
+    selected: ListState,
+    /// Asking whether to resume this conversation.
+    confirm: Option<&'a PathBuf>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(items: &'a [Item], query: &str) -> Self {
+        let mut picker = Self {
+            items,
+            query: query.to_string(),
+            matches: vec![],
+            selected: ListState::default(),
+            confirm: None,
+        };
+        picker.filter();
+        picker
+    }
+
+    fn filter(&mut self) {
+        let matcher = SkimMatcherV2::default();
+        let mut scored = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                if self.query.is_empty() {
+                    return Some((0, i));
+                }
+                matcher
+                    .fuzzy_match(item.searched(), &self.query)
+                    .map(|score| (score, i))
+            })
+            .collect::<Vec<_>>();
+        // Stable, so equally good matches stay newest first.
+        scored.sort_by_key(|(score, _)| -score);
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected
+            .select((!self.matches.is_empty()).then_some(0));
+    }
+
+    fn run<W: io::Write>(
+        mut self,
+        terminal: &mut Terminal<CrosstermBackend<W>>,
+    ) -> io::Result<Option<Picked>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(path) = self.confirm {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {
+                        return Ok(Some(Picked::Conversation(path.clone())))
+                    }
+                    _ => self.confirm = None,
+                }
+                continue;
+            }
+            if let Some(picked) = self.handle_key(key) {
+                return Ok(picked);
+            }
+        }
+    }
+
+    /// `Some` once the picker is done.
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Option<Picked>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let selected = self.selected.selected();
+        match key.code {
+            KeyCode::Esc => return Some(None),
+            KeyCode::Char('c' | 'g') if ctrl => return Some(None),
+            KeyCode::Enter => match selected.map(|i| &self.items[self.matches[i]]) {
+                Some(Item::History(entry)) => return Some(Some(Picked::History(entry.clone()))),
+                Some(Item::Conversation { path, .. }) => self.confirm = Some(path),
+                None => return Some(None),
+            },
+            // The best match is at the bottom, next to the query, so up is towards worse ones.
+            KeyCode::Up => self.selected.select_next(),
+            KeyCode::Char('p' | 'r') if ctrl => self.selected.select_next(),
+            KeyCode::Down => self.selected.select_previous(),
+            KeyCode::Char('n' | 's') if ctrl => self.selected.select_previous(),
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.filter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.filter();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, status_area, query_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let list = List::new(
+            self.matches
+                .iter()
+                .map(|&i| ListItem::new(self.items[i].render())),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .direction(ListDirection::BottomToTop);
+        frame.render_stateful_widget(list, list_area, &mut self.selected);
+
+        let status = match self.confirm {
+            Some(path) => tr!("search-confirm-resume", path = path.display().to_string()),
+            None => tr!(
+                "search-status",
+                matches = self.matches.len(),
+                total = self.items.len()
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().add_modifier(Modifier::DIM)),
+            status_area,
+        );
+        let query = format!("> {}", self.query);
+        let cursor = Position::new(query_area.x + width::width(&query) as u16, query_area.y);
+        frame.render_widget(Paragraph::new(query), query_area);
+        frame.set_cursor_position(cursor);
+    }
+}
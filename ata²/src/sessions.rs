@@ -0,0 +1,371 @@
+//! Tags of saved conversations: `/tag` and `ata2 sessions list --tag …`. With file storage they
+//! are kept in `tags.json` in `ui.save_dir`, by the name of the conversation's file, so the
+//! conversations themselves stay as they are.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, Role};
+use chrono::TimeZone as _;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::args::SessionsCommand;
+use crate::commands;
+use crate::lock;
+use crate::prompt::CONVERSATION;
+use crate::readline::{
+    self, chat_completion_message_role, chat_completion_message_text, read_conversation,
+    string_to_chat_completion_request_user_message,
+};
+use crate::store;
+use crate::style::Style;
+use crate::summarize;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+const TAGS_FILE: &str = "tags.json";
+/// What the summary of a conversation that `/append` adds starts with, for the model.
+const SUMMARIZED: &str = "A summary of an earlier conversation, which what follows may refer to:";
+/// The last response of each conversation with `api = "responses"`, which the next continues.
+const THREADS_FILE: &str = "threads.json";
+/// The pinned messages of each conversation, see [`crate::pin`].
+const PINS_FILE: &str = "pins.json";
+
+pub fn run(command: &SessionsCommand) -> TokioResult<()> {
+    match command {
+        SessionsCommand::List { tag } => list(tag),
+        SessionsCommand::Merge { sessions, output } => merge(sessions, output),
+    }
+}
+
+/// `ata2 sessions list`: the saved conversations, newest first, those with all of `tags` if any.
+fn list(tags: &[String]) -> TokioResult<()> {
+    let bold = Style::parse("bold").unwrap_or_default();
+    for path in readline::saved_conversations() {
+        let has = tags_of(&path)?;
+        if !tags.iter().all(|tag| has.contains(tag)) {
+            continue;
+        }
+        let mut heading = path
+            .file_stem()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        if let Some(time) = saved_at(&path).and_then(|t| chrono::Local.timestamp_opt(t, 0).single())
+        {
+            heading += &time.format(" · %Y-%m-%d %H:%M").to_string();
+        }
+        if !has.is_empty() {
+            heading += &format!(" · {}", has.join(", "));
+        }
+        println!("{}", bold.paint_to(atty::Stream::Stdout, &heading));
+        let title = read_conversation(&path)
+            .map(|conversation| title(&conversation))
+            .unwrap_or_default();
+        println!("    {path}", path = path.display());
+        if !title.is_empty() {
+            println!("    {title}");
+        }
+    }
+    Ok(())
+}
+
+/// `ata2 sessions merge`: the conversations `names` one after the other, with the system message
+/// of the first alone, saved as `output`, in `ui.save_dir` if it's only a name.
+fn merge(names: &[String], output: &Path) -> TokioResult<()> {
+    let mut merged = vec![];
+    for name in names {
+        let conversation = read_conversation(&find(name)?)?;
+        let first = merged.is_empty();
+        merged.extend(
+            conversation
+                .into_iter()
+                .filter(|m| first || chat_completion_message_role(m) != Role::System),
+        );
+    }
+    let mut output = match output.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => output.to_path_buf(),
+        _ => config.ui.save_dir.join(output),
+    };
+    if output.extension().is_none() {
+        output.set_extension("json");
+    }
+    let path = readline::save_to(Some(output), &merged)?;
+    info!(
+        "{}",
+        tr!(
+            "sessions-merged",
+            count = names.len(),
+            path = path.display().to_string()
+        )
+    );
+    Ok(())
+}
+
+/// `/append <conversation>`: add a saved conversation to the end of this one, but for its system
+/// message, for the next prompt to go on from both. If they wouldn't fit in the context of the
+/// model together, with room for the response, a summary of it is added instead.
+pub async fn append(args: Vec<String>) -> TokioResult<()> {
+    let [name] = args.as_slice() else {
+        return Err(commands::usage("append"));
+    };
+    let earlier = read_conversation(&find(name)?)?
+        .into_iter()
+        .filter(|m| chat_completion_message_role(m) != Role::System)
+        .collect::<Vec<_>>();
+    if earlier.is_empty() {
+        return Err(tr!("conversation-empty").into());
+    }
+    let model = &config.model;
+    let budget = usage::model_info(model).map(|info| {
+        info.context
+            .saturating_sub(usize::try_from(config.max_tokens).unwrap_or(0))
+    });
+    let tokens = {
+        let conversation = CONVERSATION.lock().await;
+        let both = conversation
+            .iter()
+            .chain(&earlier)
+            .cloned()
+            .collect::<Vec<_>>();
+        usage::conversation_tokens(model, &both)
+    };
+    if budget.is_some_and(|budget| tokens > budget) {
+        info!(
+            "{}",
+            tr!("append-summarizing", name = name.as_str(), tokens = tokens)
+        );
+        let summary = summarize::conversation(&earlier).await?;
+        CONVERSATION
+            .lock()
+            .await
+            .push(string_to_chat_completion_request_user_message(format!(
+                "{SUMMARIZED}\n\n{}",
+                summary.trim()
+            )));
+        info!("{}", tr!("appended-summary", name = name.as_str()));
+    } else {
+        CONVERSATION.lock().await.extend(earlier.iter().cloned());
+        info!(
+            "{}",
+            tr!("appended", name = name.as_str(), count = earlier.len())
+        );
+    }
+    readline::autosave().await;
+    Ok(())
+}
+
+/// The first line of the first prompt of `conversation`, or nothing.
+pub fn title(conversation: &[ChatCompletionRequestMessage]) -> String {
+    conversation
+        .iter()
+        .find(|m| chat_completion_message_role(m) == Role::User)
+        .and_then(chat_completion_message_text)
+        .and_then(|text| text.lines().next().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The saved conversation `name` is: its path, its file name or that without `.json`. Any other
+/// conversation file by its path.
+pub fn find(name: &str) -> TokioResult<PathBuf> {
+    let saved = readline::saved_conversations().into_iter().find(|path| {
+        path.as_os_str() == name
+            || path
+                .strip_prefix(".")
+                .is_ok_and(|path| path.as_os_str() == name)
+            || path.file_name().is_some_and(|file| file == name)
+            || path.file_stem().is_some_and(|stem| stem == name)
+    });
+    match saved {
+        Some(path) => Ok(path),
+        None if Path::new(name).is_file() => Ok(PathBuf::from(name)),
+        None => Err(tr!("sessions-no-such", name = name).into()),
+    }
+}
+
+/// `/tag [tag…]`: add tags to the conversation, or with a `-` in front, remove them. The
+/// conversation is saved first if it isn't yet. Without any, show its tags.
+pub async fn tag(args: Vec<String>) -> TokioResult<()> {
+    let path = match readline::autosave_path() {
+        Some(path) => path,
+        None if args.is_empty() => return Err(tr!("tags-unsaved").into()),
+        None => {
+            if CONVERSATION.lock().await.is_empty() {
+                return Err(tr!("conversation-empty").into());
+            }
+            let path = readline::save_conversation().await?;
+            readline::autosave_to(Some(path.clone()));
+            info!(
+                "{}",
+                tr!("conversation-saved", path = path.display().to_string())
+            );
+            path
+        }
+    };
+    let mut tags = tags_of(&path)?;
+    for arg in &args {
+        match arg.strip_prefix('-') {
+            Some(tag) => tags.retain(|t| t != tag),
+            None if !tags.contains(arg) => tags.push(arg.clone()),
+            None => {}
+        }
+    }
+    tags.sort_unstable();
+    if !args.is_empty() {
+        set_tags(&path, &tags)?;
+    }
+    if tags.is_empty() {
+        eprintln!("{}", tr!("tags-none"));
+    } else {
+        eprintln!("{}", tr!("tags", tags = tags.join(", ")));
+    }
+    Ok(())
+}
+
+/// Delete the conversation saved as `path`, and its tags.
+pub fn delete(path: &Path) -> TokioResult<()> {
+    if store::enabled() && store::saved(path)?.is_some() {
+        return store::delete_conversation(path);
+    }
+    fs::remove_file(path)?;
+    if !tags_of(path)?.is_empty() {
+        set_tags(path, &[])?;
+    }
+    if thread_of(path)?.is_some() {
+        set_thread(path, None)?;
+    }
+    if !pins_of(path)?.is_empty() {
+        set_pins(path, &[])?;
+    }
+    Ok(())
+}
+
+/// The tags of the conversation saved as `path`, sorted.
+pub fn tags_of(path: &Path) -> TokioResult<Vec<String>> {
+    if store::enabled() {
+        return store::tags(path);
+    }
+    Ok(read_file::<Vec<String>>(TAGS_FILE)?
+        .remove(&key(path))
+        .unwrap_or_default())
+}
+
+fn set_tags(path: &Path, tags: &[String]) -> TokioResult<()> {
+    if store::enabled() {
+        return store::set_tags(path, tags);
+    }
+    lock::with(TAGS_FILE, || {
+        let mut all = read_file(TAGS_FILE)?;
+        if tags.is_empty() {
+            all.remove(&key(path));
+        } else {
+            all.insert(key(path), tags.to_vec());
+        }
+        write_file(TAGS_FILE, &all)
+    })
+}
+
+/// The last response of the conversation saved as `path`, with `api = "responses"`.
+pub fn thread_of(path: &Path) -> TokioResult<Option<String>> {
+    if store::enabled() {
+        return store::thread(path);
+    }
+    Ok(read_file::<String>(THREADS_FILE)?.remove(&key(path)))
+}
+
+pub fn set_thread(path: &Path, id: Option<&str>) -> TokioResult<()> {
+    if store::enabled() {
+        return store::set_thread(path, id);
+    }
+    lock::with(THREADS_FILE, || {
+        let mut all = read_file(THREADS_FILE)?;
+        match id {
+            Some(id) => all.insert(key(path), id.to_string()),
+            None => all.remove(&key(path)),
+        };
+        write_file(THREADS_FILE, &all)
+    })
+}
+
+/// The pinned messages of the conversation saved as `path`, by their number.
+pub fn pins_of(path: &Path) -> TokioResult<Vec<usize>> {
+    if store::enabled() {
+        return store::pins(path);
+    }
+    Ok(read_file::<Vec<usize>>(PINS_FILE)?
+        .remove(&key(path))
+        .unwrap_or_default())
+}
+
+pub fn set_pins(path: &Path, pins: &[usize]) -> TokioResult<()> {
+    if store::enabled() {
+        return store::set_pins(path, pins);
+    }
+    lock::with(PINS_FILE, || {
+        let mut all = read_file(PINS_FILE)?;
+        if pins.is_empty() {
+            all.remove(&key(path));
+        } else {
+            all.insert(key(path), pins.to_vec());
+        }
+        write_file(PINS_FILE, &all)
+    })
+}
+
+/// A file in `ui.save_dir` of something about each conversation.
+fn read_file<T: DeserializeOwned>(name: &str) -> TokioResult<BTreeMap<String, T>> {
+    match fs::read_to_string(config.ui.save_dir.join(name)) {
+        Ok(json) => Ok(serde_json::from_str(&json)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_file<T: Serialize>(name: &str, all: &BTreeMap<String, T>) -> TokioResult<()> {
+    let path = config.ui.save_dir.join(name);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(all)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// A conversation in `ui.save_dir` by its file name, any other by its path.
+fn key(path: &Path) -> String {
+    path.strip_prefix(&config.ui.save_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// When the conversation `path` was saved, in seconds since the Unix epoch.
+pub fn saved_at(path: &Path) -> Option<i64> {
+    if store::enabled() {
+        if let Ok(Some(saved)) = store::saved(path) {
+            return Some(saved);
+        }
+    }
+    let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
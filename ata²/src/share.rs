@@ -0,0 +1,539 @@
+//! `ata2 share`: a saved conversation as one HTML file that needs nothing else to be read, a
+//! Jupyter notebook or an Org document, and with `--gist` uploaded as a secret gist.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, Role};
+use chrono::{DateTime, Local, TimeZone as _};
+use serde_json::{json, Value};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::args::ShareFormat;
+
+use crate::i18n;
+use crate::markdown::{self, CodeBlock, Part};
+use crate::readline::{
+    chat_completion_message_role, chat_completion_message_text, read_conversation,
+};
+use crate::sessions;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+const GISTS_URL: &str = "https://api.github.com/gists";
+const STYLE: &str = include_str!("share/style.css");
+
+/// Languages whose comments start with `#` or `--`. Those of all others with `//`.
+const HASH_COMMENTS: &[&str] = &[
+    "bash",
+    "conf",
+    "dockerfile",
+    "fish",
+    "make",
+    "makefile",
+    "perl",
+    "py",
+    "python",
+    "r",
+    "rb",
+    "ruby",
+    "sh",
+    "shell",
+    "toml",
+    "yaml",
+    "yml",
+    "zsh",
+];
+const DASH_COMMENTS: &[&str] = &["haskell", "hs", "lua", "sql"];
+/// Languages that aren't code, so have nothing to highlight or run.
+const PLAIN: &[&str] = &["markdown", "md", "plain", "text", "txt"];
+
+/// Write conversation `session` as `format` to `output`, stdout if it's `-`, or
+/// `<name>.<format>`. With `gist`, upload it and print the gist's URL instead, writing it too only
+/// if there's `output`.
+pub async fn run(
+    session: &str,
+    output: Option<&Path>,
+    format: ShareFormat,
+    gist: bool,
+) -> TokioResult<()> {
+    let path = sessions::find(session)?;
+    let conversation = read_conversation(&path)?;
+    let name = path
+        .file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned();
+    let mut title = sessions::title(&conversation)
+        .trim_start_matches('#')
+        .trim()
+        .to_string();
+    if title.is_empty() {
+        title = name.clone();
+    }
+    let saved = sessions::saved_at(&path);
+    let (document, extension) = match format {
+        ShareFormat::Html => (html(&title, saved, &conversation), "html"),
+        ShareFormat::Ipynb => (notebook(&title, saved, &conversation)?, "ipynb"),
+        ShareFormat::Org => (org(&title, saved, &conversation), "org"),
+    };
+    let file = format!("{name}.{extension}");
+    let output = match output {
+        Some(output) => Some(output.to_path_buf()),
+        None if !gist => Some(PathBuf::from(&file)),
+        None => None,
+    };
+    match output {
+        Some(output) if output == Path::new("-") => print!("{document}"),
+        Some(output) => {
+            fs::write(&output, &document)?;
+            eprintln!(
+                "{}",
+                tr!("share-written", path = output.display().to_string())
+            );
+        }
+        None => {}
+    }
+    if gist {
+        println!("{}", upload(&file, &title, &document).await?);
+    }
+    Ok(())
+}
+
+/// Create a secret gist of `content` as `file`, returning its URL.
+async fn upload(file: &str, description: &str, content: &str) -> TokioResult<String> {
+    let token = config
+        .integrations
+        .github
+        .token
+        .as_deref()
+        .ok_or_else(|| tr!("share-no-token"))?;
+    let mut files = serde_json::Map::new();
+    files.insert(file.to_string(), json!({ "content": content }));
+    let response = reqwest::Client::new()
+        .post(GISTS_URL)
+        .bearer_auth(token)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("ata2/", env!("CARGO_PKG_VERSION")),
+        )
+        .json(&json!({
+            "description": description,
+            "public": false,
+            "files": files,
+        }))
+        .send()
+        .await
+        .map_err(|e| tr!("share-gist-failed", error = e.to_string()))?;
+    let status = response.status();
+    let body = response.json::<Value>().await.unwrap_or_default();
+    if !status.is_success() {
+        let error = match body["message"].as_str() {
+            Some(message) => format!("{status}: {message}"),
+            None => status.to_string(),
+        };
+        return Err(tr!("share-gist-failed", error = error).into());
+    }
+    match body["html_url"].as_str() {
+        Some(url) => Ok(url.to_string()),
+        None => Err(tr!("share-gist-failed", error = body.to_string()).into()),
+    }
+}
+
+/// The messages with text: the class of their role, its name and the text.
+fn messages(conversation: &[ChatCompletionRequestMessage]) -> Vec<(&'static str, String, String)> {
+    conversation
+        .iter()
+        .filter_map(|message| {
+            let text = chat_completion_message_text(message)?;
+            let (class, label) = match chat_completion_message_role(message) {
+                Role::User => ("user", tr!("tui-you")),
+                Role::Assistant => ("assistant", tr!("tui-assistant")),
+                Role::System => ("system", tr!("tui-system")),
+                Role::Tool | Role::Function => ("tool", tr!("tui-tool")),
+            };
+            Some((class, label, text))
+        })
+        .collect()
+}
+
+/// A tool message's summary, its first line, and the rest, which is folded away under it.
+fn folded(text: &str) -> (&str, &str) {
+    text.split_once('\n').unwrap_or((text, ""))
+}
+
+fn local_time(saved: Option<i64>) -> Option<DateTime<Local>> {
+    saved.and_then(|t| Local.timestamp_opt(t, 0).single())
+}
+
+/// The parts of `text`, with the languages of code blocks lowercased.
+fn blocks(text: &str) -> Vec<Part<'_>> {
+    markdown::parts(text)
+        .into_iter()
+        .map(|part| match part {
+            Part::Code(CodeBlock { lang, code }) => Part::Code(CodeBlock {
+                lang: lang.map(|lang| lang.to_lowercase()),
+                code,
+            }),
+            prose => prose,
+        })
+        .collect()
+}
+
+/// Is `lang` that of code, to highlight or run?
+fn is_code(lang: &Option<String>) -> bool {
+    lang.as_deref().is_some_and(|lang| !PLAIN.contains(&lang))
+}
+
+/// `text` with `plain` applied to it, but `code` to the spans between runs of as many backticks,
+/// like `` `this` `` or ``` ``that`` ```, without them.
+fn inline_code(
+    text: &str,
+    plain: impl Fn(&str) -> String,
+    code: impl Fn(&str) -> String,
+) -> String {
+    markdown::inline_code(text)
+        .into_iter()
+        .map(|(piece, is_code)| {
+            if is_code {
+                let run = markdown::backticks(piece);
+                code(piece[run..piece.len() - run].trim())
+            } else {
+                plain(piece)
+            }
+        })
+        .collect()
+}
+
+/// The page: `title`, when it was `saved` and each message with text.
+fn html(title: &str, saved: Option<i64>, conversation: &[ChatCompletionRequestMessage]) -> String {
+    let mut ret = format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n<main>\n\
+         <h1>{title}</h1>\n",
+        lang = i18n::language(),
+        title = escape(title),
+    );
+    if let Some(time) = local_time(saved) {
+        ret += &format!("<p class=\"meta\">{}</p>\n", time.format("%Y-%m-%d %H:%M"));
+    }
+    for (class, label, text) in messages(conversation) {
+        ret += &format!("<section class=\"{class}\">\n");
+        let text = if class == "tool" {
+            let (summary, rest) = folded(&text);
+            ret += &format!(
+                "<details>\n<summary>{}: {}</summary>\n",
+                escape(&label),
+                escape(summary)
+            );
+            rest
+        } else {
+            ret += &format!("<h2>{}</h2>\n", escape(&label));
+            &text
+        };
+        for block in blocks(text) {
+            match block {
+                // Paragraphs, the rest of Markdown left as it is, its line breaks kept.
+                Part::Prose(lines) => {
+                    for paragraph in lines.split(|line| line.trim().is_empty()) {
+                        if !paragraph.is_empty() {
+                            ret += &format!(
+                                "<p>{}</p>\n",
+                                inline_code(&paragraph.join("\n"), escape, |code| {
+                                    format!("<code>{}</code>", escape(code))
+                                })
+                            );
+                        }
+                    }
+                }
+                Part::Code(CodeBlock { lang, code }) => ret += &code_block(lang.as_deref(), &code),
+            }
+        }
+        if class == "tool" {
+            ret += "</details>\n";
+        }
+        ret += "</section>\n";
+    }
+    ret += &format!(
+        "<footer>{}</footer>\n</main>\n</body>\n</html>\n",
+        escape(&tr!("share-footer"))
+    );
+    ret
+}
+
+fn code_block(lang: Option<&str>, code: &str) -> String {
+    match lang {
+        Some(lang) => format!(
+            "<pre><code class=\"language-{}\">{}</code></pre>\n",
+            escape(lang),
+            highlight(lang, code)
+        ),
+        None => format!("<pre><code>{}</code></pre>\n", escape(code)),
+    }
+}
+
+/// `code` escaped, with double-quoted strings, line comments and numbers in spans. Only what looks
+/// alike in most languages, so no keywords.
+fn highlight(lang: &str, code: &str) -> String {
+    if PLAIN.contains(&lang) {
+        return escape(code);
+    }
+    let comment = if HASH_COMMENTS.contains(&lang) {
+        "#"
+    } else if DASH_COMMENTS.contains(&lang) {
+        "--"
+    } else {
+        "//"
+    };
+    let mut ret = String::with_capacity(code.len());
+    for line in code.split_inclusive('\n') {
+        let line_end = line.trim_end_matches('\n').len();
+        let mut i = 0;
+        while i < line_end {
+            let rest = &line[i..line_end];
+            let previous = line[..i].chars().next_back();
+            if rest.starts_with(comment) && previous.is_none_or(char::is_whitespace) {
+                ret += &span("comment", rest);
+                i = line_end;
+            } else if rest.starts_with('"') {
+                let end = string_end(rest);
+                ret += &span("string", &rest[..end]);
+                i += end;
+            } else if rest.starts_with(|c: char| c.is_ascii_digit())
+                && !previous.is_some_and(|c| c.is_alphanumeric() || c == '_')
+            {
+                let end = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                ret += &span("number", &rest[..end]);
+                i += end;
+            } else {
+                let c = rest.chars().next().unwrap();
+                ret += &escape(&rest[..c.len_utf8()]);
+                i += c.len_utf8();
+            }
+        }
+        ret += &line[line_end..];
+    }
+    ret
+}
+
+/// Where the string `s` starts with ends: after its closing quote, or at the end of the line.
+fn string_end(s: &str) -> usize {
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+fn span(class: &str, text: &str) -> String {
+    format!("<span class=\"{class}\">{}</span>", escape(text))
+}
+
+fn escape(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => ret += "&amp;",
+            '<' => ret += "&lt;",
+            '>' => ret += "&gt;",
+            '"' => ret += "&quot;",
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+/// A Jupyter notebook: the prose in Markdown cells under the name of who wrote it, and code blocks
+/// in code cells, with their language. The notebook is in the language most of them are.
+fn notebook(
+    title: &str,
+    saved: Option<i64>,
+    conversation: &[ChatCompletionRequestMessage],
+) -> TokioResult<String> {
+    let mut heading = format!("# {title}");
+    if let Some(time) = local_time(saved) {
+        heading += &time.format("\n\n%Y-%m-%d %H:%M").to_string();
+    }
+    let mut cells = vec![markdown_cell(&heading)];
+    let mut languages: Vec<(String, usize)> = vec![];
+    for (class, label, text) in messages(conversation) {
+        if class == "tool" {
+            let (summary, rest) = folded(&text);
+            cells.push(markdown_cell(&format!(
+                "### {label}\n\n<details>\n<summary>{summary}</summary>\n\n{}\n\n</details>",
+                rest.trim()
+            )));
+            continue;
+        }
+        let mut heading = Some(format!("### {label}"));
+        for block in blocks(&text) {
+            let markdown = match block {
+                Part::Code(CodeBlock { lang, code }) if is_code(&lang) => {
+                    let lang = lang.unwrap_or_default();
+                    if let Some(heading) = heading.take() {
+                        cells.push(markdown_cell(&heading));
+                    }
+                    match languages.iter_mut().find(|(l, _)| *l == lang) {
+                        Some((_, count)) => *count += 1,
+                        None => languages.push((lang.clone(), 1)),
+                    }
+                    cells.push(json!({
+                        "cell_type": "code",
+                        "execution_count": null,
+                        "metadata": { "language": lang },
+                        "outputs": [],
+                        "source": source(&code),
+                    }));
+                    continue;
+                }
+                Part::Code(CodeBlock { lang, code }) => {
+                    format!("```{}\n{code}```", lang.unwrap_or_default())
+                }
+                Part::Prose(lines) => lines.join("\n"),
+            };
+            cells.push(markdown_cell(&match heading.take() {
+                Some(heading) => format!("{heading}\n\n{markdown}"),
+                None => markdown,
+            }));
+        }
+    }
+    let mut metadata = json!({});
+    // The first of those with the most cells.
+    if let Some((language, _)) = languages.iter().rev().max_by_key(|(_, count)| *count) {
+        if ["py", "python", "python3"].contains(&language.as_str()) {
+            metadata = json!({
+                "kernelspec": { "name": "python3", "display_name": "Python 3", "language": "python" },
+                "language_info": { "name": "python" },
+            });
+        } else {
+            metadata["language_info"] = json!({ "name": language });
+        }
+    }
+    let notebook = json!({
+        "cells": cells,
+        "metadata": metadata,
+        "nbformat": 4,
+        "nbformat_minor": 4,
+    });
+    Ok(serde_json::to_string_pretty(&notebook)? + "\n")
+}
+
+fn markdown_cell(text: &str) -> Value {
+    json!({ "cell_type": "markdown", "metadata": {}, "source": source(text) })
+}
+
+/// `text` as the lines of a cell, each with its newline but the last.
+fn source(text: &str) -> Vec<&str> {
+    text.trim_end_matches('\n').split_inclusive('\n').collect()
+}
+
+/// An Org document: a heading for each message, and code blocks as source blocks that Babel can
+/// run.
+fn org(title: &str, saved: Option<i64>, conversation: &[ChatCompletionRequestMessage]) -> String {
+    let mut ret = format!("#+TITLE: {title}\n");
+    if let Some(time) = local_time(saved) {
+        ret += &time.format("#+DATE: [%Y-%m-%d %a %H:%M]\n").to_string();
+    }
+    for (class, label, text) in messages(conversation) {
+        let text = if class == "tool" {
+            let (summary, rest) = folded(&text);
+            ret += &format!("\n* {label}: {summary}\n:PROPERTIES:\n:VISIBILITY: folded\n:END:\n");
+            rest.trim_start_matches('\n')
+        } else {
+            ret += &format!("\n* {label}\n");
+            &text
+        };
+        for (i, block) in blocks(text).into_iter().enumerate() {
+            if i > 0 {
+                ret.push('\n');
+            }
+            match block {
+                Part::Prose(lines) => {
+                    for line in lines {
+                        ret += &org_line(line);
+                        ret.push('\n');
+                    }
+                }
+                Part::Code(CodeBlock { lang, code }) if is_code(&lang) => {
+                    ret += &format!(
+                        "#+begin_src {}\n{}#+end_src\n",
+                        lang.unwrap_or_default(),
+                        org_escape(&code)
+                    );
+                }
+                Part::Code(CodeBlock { code, .. }) => {
+                    ret += &format!("#+begin_example\n{}#+end_example\n", org_escape(&code));
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// A line of Markdown prose as Org: headings under the message's, `*` bullets as `-` ones, and
+/// inline code and bold text as Org has them.
+fn org_line(line: &str) -> String {
+    let level = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+        return format!("{} {}", "*".repeat(level + 1), line[level..].trim());
+    }
+    let line = match line.strip_prefix("* ") {
+        Some(rest) => format!("- {rest}"),
+        None => line.to_string(),
+    };
+    inline_code(
+        &line,
+        |text| text.replace("**", "*"),
+        |code| {
+            let marker = if code.contains('~') { '=' } else { '~' };
+            format!("{marker}{code}{marker}")
+        },
+    )
+}
+
+/// `code` with a comma in front of the lines Org would take for its own, those starting with `*`
+/// or `#+`.
+fn org_escape(code: &str) -> String {
+    let mut ret = String::with_capacity(code.len());
+    for line in code.split_inclusive('\n') {
+        let indented = line.trim_start();
+        let rest = indented.strip_prefix(',').unwrap_or(indented);
+        if line.starts_with('*') || line.starts_with(",*") {
+            ret.push(',');
+        } else if rest.starts_with("#+") {
+            ret += &line[..line.len() - indented.len()];
+            ret.push(',');
+            ret += indented;
+            continue;
+        }
+        ret += line;
+    }
+    ret
+}
@@ -0,0 +1,126 @@
+//! Leaving the REPL cleanly, whether by Ctrl-C, EOF or a panic: the response streaming in is
+//! stopped, the history and the autosave are written out, and the terminal is put back the way it
+//! was, with no raw mode, alternate screen or scrolling region left behind and the cursor shown.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+use std::io::{self, Write as _};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::history;
+use crate::prompt::CONVERSATION;
+use crate::readline::{self, Readline};
+use crate::style;
+use crate::ABORT;
+use crate::CONFIGURATION as config;
+
+/// How long the response streaming in gets to stop, and be added to the conversation, before it's
+/// dropped.
+const GRACE: Duration = Duration::from_secs(2);
+
+/// Set while the search picker has the alternate screen.
+pub static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// Set while the status line of a response is kept out of the scrolling region.
+pub static SCROLL_REGION: AtomicBool = AtomicBool::new(false);
+
+/// The history of the REPL, once it's loaded, for a panic not to lose it.
+static HISTORY: OnceLock<Arc<Mutex<Vec<history::Entry>>>> = OnceLock::new();
+
+/// Save the history and the conversation, and restore the terminal, when ata² panics. Only once
+/// the configuration is loaded, which saving them reads.
+pub fn install() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        save_history();
+        // Whoever holds it might be halfway through changing it.
+        if let Ok(conversation) = CONVERSATION.try_lock() {
+            readline::autosave_conversation(&conversation);
+        }
+        previous(info);
+    }));
+}
+
+/// Keep `history` to be saved on a panic.
+pub fn keep_history(history: Arc<Mutex<Vec<history::Entry>>>) {
+    let _ = HISTORY.set(history);
+}
+
+/// Stop the API request loop of `handle`, waiting [`GRACE`] for the response coming in to end,
+/// then save what's left to save and restore the terminal.
+pub async fn run(rl: &mut Readline, mut handle: JoinHandle<()>) -> crate::TokioResult<()> {
+    if !handle.is_finished() {
+        ABORT.store(true, Ordering::Relaxed);
+        if timeout(GRACE, &mut handle).await.is_err() {
+            handle.abort();
+            let _ = handle.await;
+        }
+    }
+    restore_terminal();
+    if atty::is(atty::Stream::Stdin) && config.ui.save_history && history::keep() {
+        rl.save_history().await?;
+        info!(
+            "{}",
+            tr!(
+                "history-saved",
+                path = history::location().display().to_string(),
+                entries = rl.history_len().await
+            )
+        );
+    }
+    readline::autosave().await;
+    Ok(())
+}
+
+fn save_history() {
+    let Some(history) = HISTORY.get().filter(|_| history::keep()) else {
+        return;
+    };
+    if let Ok(entries) = history.try_lock() {
+        if let Err(e) = history::save(&config.ui.history_file, &entries) {
+            eprintln!("{}", tr!("history-save-failed", error = e.to_string()));
+        }
+    }
+}
+
+/// Undo whatever ata² did to the terminal. Safe to call when it's as it was.
+pub fn restore_terminal() {
+    let _ = crossterm::terminal::disable_raw_mode();
+    if !atty::is(atty::Stream::Stderr) {
+        return;
+    }
+    let mut sequences = String::new();
+    if SCROLL_REGION.swap(false, Ordering::Relaxed) {
+        sequences += "\x1b7\x1b[r\x1b8";
+    }
+    if ALTERNATE_SCREEN.swap(false, Ordering::Relaxed) {
+        sequences += "\x1b[?1049l";
+    }
+    sequences += style::RESET;
+    sequences += "\x1b[?25h";
+    let mut stderr = io::stderr().lock();
+    let _ = stderr.write_all(sequences.as_bytes());
+    let _ = stderr.flush();
+}
@@ -0,0 +1,395 @@
+//! Where a response goes as it streams in. [`read`] reads the stream once and tells each [`Sink`]
+//! of [`Sinks`] what came of it, as [`Event`]s, for a transcript or a log, say, to take the
+//! response from there rather than handling the chunks of the stream again. [`Terminal`] prints
+//! it: under its banner, paced, wrapped, with the changes to code blocks marked and a line of
+//! progress below. [`Plain`] writes it as it is, to a pipe, and [`Channel`] sends it on, to a
+//! client of the daemon. Without any sink, it's only what [`read`] returns.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionResponseStream, CompletionResponseStream,
+    FinishReason,
+};
+use futures_util::future::{BoxFuture, FutureExt as _};
+use futures_util::StreamExt as _;
+use tokio::sync::mpsc::UnboundedSender;
+
+use std::sync::atomic::Ordering;
+
+use crate::api_error;
+
+use crate::diff::Differ;
+use crate::external::{self, Calls};
+use crate::output::{self, print_and_flush};
+use crate::overrides;
+use crate::pacing::Pacer;
+use crate::progress::Progress;
+use crate::prompt::{self, eprint_and_flush, eprint_dim, print_response_prompt};
+use crate::router;
+use crate::spinner::Spinner;
+use crate::tools;
+use crate::usage;
+use crate::wrap::Wrapper;
+use crate::TokioResult;
+use crate::ABORT;
+
+/// What came of a response.
+pub enum Event<'a> {
+    /// Its first chunk came in.
+    Started,
+    /// A token of it came in, which may not be shown yet, or at all, as with the start of a
+    /// continuation that repeats the end of what it continues.
+    Token,
+    /// The next of its text.
+    Text(&'a str),
+    /// The model called tools after what it said so far: the blocks of their runs, and whether what
+    /// it says after them is a paragraph of its own.
+    Tools {
+        blocks: &'a [String],
+        paragraph: bool,
+    },
+    /// There's no more of it, whether it's complete or not.
+    Finished,
+}
+
+pub trait Sink: Send {
+    fn push<'a>(&'a mut self, event: &'a Event<'a>) -> BoxFuture<'a, ()>;
+}
+
+/// The sinks a response goes to, each told of each event in turn.
+#[derive(Default)]
+pub struct Sinks(Vec<Box<dyn Sink>>);
+
+impl Sinks {
+    pub fn new(sinks: Vec<Box<dyn Sink>>) -> Self {
+        Self(sinks)
+    }
+
+    pub async fn push(&mut self, event: Event<'_>) {
+        for sink in &mut self.0 {
+            sink.push(&event).await;
+        }
+    }
+}
+
+/// What [`read`] read of a response.
+#[derive(Default)]
+pub struct Read {
+    /// What the model said, for its usage and the tools it called to go by.
+    pub text: String,
+    /// It as the sinks were told of it, with a paragraph of its own after the tools it called.
+    pub shown: String,
+    /// The tools of `[tools.custom]` it called.
+    pub calls: Calls,
+    /// Was it cut off at `max_tokens`?
+    pub cut_off: bool,
+}
+
+/// Read `stream`, the response of `model`, telling `sinks` what comes of it, until there's no
+/// more of it or ata² is stopped.
+pub async fn read(
+    mut stream: ChatCompletionResponseStream,
+    model: &str,
+    sinks: &mut Sinks,
+) -> TokioResult<Read> {
+    let mut ret = Read::default();
+    let mut started = false;
+    while let Some(chunk) = stream.next().await {
+        if ABORT.load(Ordering::Relaxed) {
+            break;
+        }
+        let chunk = chunk.map_err(|e| api_error::from_openai(e, model))?;
+        if !started {
+            started = true;
+            sinks.push(Event::Started).await;
+        }
+        for choice in chunk.choices {
+            if let Some(text) = &choice.delta.content {
+                sinks.push(Event::Token).await;
+                ret.text += text;
+                ret.shown += text;
+                sinks.push(Event::Text(text)).await;
+            }
+            if let Some(chunks) = &choice.delta.tool_calls {
+                if external::enabled() {
+                    ret.calls.push(chunks);
+                }
+                // What the model says after them is a paragraph of its own.
+                let paragraph = !ret.shown.is_empty() && !ret.shown.ends_with('\n');
+                if paragraph {
+                    ret.shown += "\n\n";
+                }
+                let blocks = chunks
+                    .iter()
+                    .filter_map(|call| call.id.as_deref())
+                    .map(|id| tools::block(id).unwrap_or_default())
+                    .collect::<Vec<_>>();
+                sinks
+                    .push(Event::Tools {
+                        blocks: &blocks,
+                        paragraph,
+                    })
+                    .await;
+            }
+            ret.cut_off |= choice.finish_reason == Some(FinishReason::Length);
+        }
+    }
+    sinks.push(Event::Finished).await;
+    Ok(ret)
+}
+
+/// [`read`] of a completion, rather than a chat completion, as of `ata2 --fim`. Returns its text.
+pub async fn read_completion(
+    mut stream: CompletionResponseStream,
+    model: &str,
+    sinks: &mut Sinks,
+) -> TokioResult<String> {
+    let mut ret = String::new();
+    let mut started = false;
+    while let Some(chunk) = stream.next().await {
+        if ABORT.load(Ordering::Relaxed) {
+            break;
+        }
+        let chunk = chunk.map_err(|e| api_error::from_openai(e, model))?;
+        if !started {
+            started = true;
+            sinks.push(Event::Started).await;
+        }
+        for choice in chunk.choices {
+            sinks.push(Event::Token).await;
+            ret += &choice.text;
+            sinks.push(Event::Text(&choice.text)).await;
+        }
+    }
+    sinks.push(Event::Finished).await;
+    Ok(ret)
+}
+
+/// The response to `messages`, of which `stream` is the start, read to `sinks`, with the tools of
+/// `[tools.custom]` the model calls run and what it says to what they output read on from there,
+/// for up to [`external::MAX_ROUNDS`] rounds. The usage of each round but the last is recorded;
+/// returns the response as it's shown, the calls and what came of them, and where the usage of
+/// the last is reported.
+pub async fn exchange(
+    messages: &[ChatCompletionRequestMessage],
+    mut stream: ChatCompletionResponseStream,
+    mut report: usage::Report,
+    sinks: &mut Sinks,
+) -> TokioResult<(String, Vec<ChatCompletionRequestMessage>, usage::Report)> {
+    let mut response = String::new();
+    let mut exchanged = vec![];
+    let mut rounds = 0;
+    loop {
+        let model = overrides::model();
+        let read = read(stream, &model, sinks).await?;
+        response += &read.shown;
+        if read.calls.is_empty() || ABORT.load(Ordering::Relaxed) {
+            return Ok((response, exchanged, report));
+        }
+        if rounds == external::MAX_ROUNDS {
+            return Err(tr!("tool-too-many-rounds", count = rounds).into());
+        }
+        let sent = [messages, &exchanged].concat();
+        usage::record(&model, &sent, &read.text, &report);
+        exchanged.extend(external::run(&read.text, read.calls, sinks).await);
+        report = usage::Report::default();
+        let sent = [messages, &exchanged].concat();
+        stream = prompt::stream(&sent, &report).await?;
+        rounds += 1;
+    }
+}
+
+/// The response as it is, without formatting, for a pipe or a file: to stdout, through
+/// [`output`], or to stderr.
+pub struct Plain {
+    stderr: bool,
+}
+
+impl Plain {
+    pub fn stdout() -> Self {
+        Self { stderr: false }
+    }
+
+    pub fn stderr() -> Self {
+        Self { stderr: true }
+    }
+
+    async fn write(&self, text: &str) {
+        if self.stderr {
+            eprint_and_flush(text);
+        } else {
+            output::write(text).await;
+        }
+    }
+}
+
+impl Sink for Plain {
+    fn push<'a>(&'a mut self, event: &'a Event<'a>) -> BoxFuture<'a, ()> {
+        async move {
+            match event {
+                Event::Started | Event::Token => {}
+                Event::Text(text) => self.write(text).await,
+                Event::Tools { blocks, paragraph } => {
+                    if *paragraph {
+                        self.write("\n").await;
+                    }
+                    for block in *blocks {
+                        self.write(block).await;
+                    }
+                }
+                Event::Finished if self.stderr => {}
+                Event::Finished => output::flush().await,
+            }
+        }
+        .boxed()
+    }
+}
+
+/// The text of the response sent on as it comes, as to a client of the daemon. A receiver that's
+/// gone isn't sent it, though the response is paid for all the same.
+pub struct Channel(pub UnboundedSender<String>);
+
+impl Sink for Channel {
+    fn push<'a>(&'a mut self, event: &'a Event<'a>) -> BoxFuture<'a, ()> {
+        async move {
+            match event {
+                Event::Text(text) => {
+                    let _ = self.0.send(text.to_string());
+                }
+                Event::Tools {
+                    paragraph: true, ..
+                } => {
+                    let _ = self.0.send("\n\n".to_string());
+                }
+                _ => {}
+            }
+        }
+        .boxed()
+    }
+}
+
+/// The response printed to the terminal, as it's read.
+pub struct Terminal {
+    spinner: Spinner,
+    pacer: Pacer,
+    wrapper: Wrapper,
+    differ: Differ,
+    progress: Progress,
+    /// Of a backslash at the end of a chunk, which may be of a `\n` split in two.
+    print_buffer: Vec<String>,
+}
+
+impl Terminal {
+    /// Stop `spinner` as the response starts, and mark the changes to the code blocks of
+    /// `messages`, the conversation it's to.
+    pub fn new(spinner: Spinner, messages: &[ChatCompletionRequestMessage]) -> Self {
+        Self {
+            spinner,
+            pacer: Pacer::new(),
+            wrapper: Wrapper::new(),
+            differ: Differ::new(messages),
+            progress: Progress::start(),
+            print_buffer: vec![],
+        }
+    }
+
+    /// Print what's held back to be wrapped or diffed, as there's no more of it for now, and wait
+    /// for stdout to take it before anything goes to stderr.
+    async fn flush(&mut self) {
+        let held = self.differ.finish();
+        self.pacer
+            .push(&format!(
+                "{}{}",
+                self.wrapper.push(&held),
+                self.wrapper.finish()
+            ))
+            .await;
+        self.pacer.finish();
+        output::flush().await;
+    }
+}
+
+impl Sink for Terminal {
+    fn push<'a>(&'a mut self, event: &'a Event<'a>) -> BoxFuture<'a, ()> {
+        async move {
+            match event {
+                Event::Started => {
+                    self.spinner.stop();
+                    print_response_prompt();
+                    if let Some(route) = router::last() {
+                        eprint_dim(&format!("{route}\n"));
+                    }
+                }
+                Event::Token => self.progress.add_token(),
+                Event::Text(text) => {
+                    let newline_fixed = post_process(&mut self.print_buffer, text);
+                    let text = self.wrapper.push(&self.differ.push(&newline_fixed));
+                    self.pacer.push(&text).await;
+                }
+                Event::Tools { blocks, paragraph } => {
+                    // The response so far, up to where the tools ran.
+                    self.flush().await;
+                    if *paragraph {
+                        print_and_flush("\n");
+                    }
+                    for block in *blocks {
+                        print_and_flush(block);
+                    }
+                }
+                Event::Finished => {
+                    self.spinner.stop();
+                    self.flush().await;
+                    self.progress.finish();
+                    eprint_and_flush("\n");
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+fn store_and_do_nothing(print_buffer: &mut Vec<String>, text: &str) -> String {
+    print_buffer.push(text.to_string());
+    "".to_string()
+}
+
+fn join_and_clear(print_buffer: &mut Vec<String>, text: &str) -> String {
+    let from_buffer = print_buffer.join("");
+    print_buffer.clear();
+    let joined = format!("{from_buffer}{text}");
+    joined.replace("\\n", "\n")
+}
+
+// Fixes cases where the model returns ["\", "n"] instead of ["\n"],
+// which is interpreted as a newline in the OpenAI playground.
+fn fix_newlines(print_buffer: &mut Vec<String>, text: &str) -> String {
+    let single_backslash = r#"\"#;
+    if text.ends_with(single_backslash) {
+        return store_and_do_nothing(print_buffer, text);
+    }
+    if !print_buffer.is_empty() {
+        return join_and_clear(print_buffer, text);
+    }
+    text.to_string()
+}
+
+fn post_process(print_buffer: &mut Vec<String>, text: &str) -> String {
+    fix_newlines(print_buffer, text)
+}
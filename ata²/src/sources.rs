@@ -0,0 +1,225 @@
+//! Where each setting comes from: `ata2 config explain <key>` for one, with every layer that sets
+//! it, and `--dump-config` for all of them with the one that applies. The layers, each over the
+//! one before: the default, the configuration downloaded by `--config https://…`, the config file
+//! of the profile, the environment, options like `--model`, the credential store for `api_key`,
+//! and what `--a11y` or `ui.screen_reader` turn off.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use bevy_reflect::{ReflectRef, Struct};
+use toml::Value;
+
+use std::env;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::{Config, ConfigLocation};
+use crate::environment;
+use crate::remote;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+use crate::FLAGS;
+
+/// Settings whose default is read from a variable that other programs use too.
+const CONVENTIONAL: &[(&str, &str)] = &[
+    ("api_key", "OPENAI_API_KEY"),
+    ("api_base", "OPENAI_API_BASE"),
+    ("ui.pager", "PAGER"),
+    ("integrations.github.token", "GITHUB_TOKEN"),
+];
+
+/// Shown as `[redacted]` with `ui.redact_api_key`.
+const SECRETS: &[&str] = &["api_key", "rotation.keys", "integrations.github.token"];
+
+#[derive(Debug)]
+enum Source {
+    Default,
+    Url(String),
+    File(PathBuf),
+    Env(String),
+    CredentialStore,
+    /// `{profile}` in `ui.history_file`.
+    Profile(String),
+    Flag(&'static str),
+    /// Turned off for screen readers.
+    Implied(&'static str),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Source::Default => tr!("config-source-default"),
+            Source::Url(url) => tr!("config-source-url", url = url.as_str()),
+            Source::File(path) => tr!("config-source-file", path = path.display().to_string()),
+            Source::Env(var) => tr!("config-source-env", var = var.as_str()),
+            Source::CredentialStore => tr!("config-source-credentials"),
+            Source::Profile(profile) => tr!("config-source-profile", profile = profile.as_str()),
+            Source::Flag(flag) => tr!("config-source-flag", flag = *flag),
+            Source::Implied(key) => tr!("config-source-implied", key = *key),
+        };
+        f.write_str(&text)
+    }
+}
+
+/// The configuration as each layer leaves it.
+struct Layers {
+    file: PathBuf,
+    defaults: Value,
+    /// What `--config https://…` downloads, and from where.
+    remote: Option<(String, Value)>,
+    /// Only what the file has.
+    written: Value,
+    /// With the environment too.
+    loaded: Value,
+    resolved: Value,
+}
+
+impl Layers {
+    fn read() -> TokioResult<Self> {
+        let file = FLAGS.config.location();
+        let contents = fs::read_to_string(&file).unwrap_or_default();
+        let (remote, loaded) = match &FLAGS.config {
+            ConfigLocation::Url(url) => {
+                let remote = remote::cached_copy(url).unwrap_or_default();
+                let loaded = Config::from(remote::with_overrides(&remote, &contents)?.0);
+                (Some((url.clone(), toml::from_str(&remote)?)), loaded)
+            }
+            _ => (None, Config::from(&contents)),
+        };
+        Ok(Self {
+            defaults: Value::try_from(Config::default())?,
+            remote,
+            written: toml::from_str(&contents)?,
+            loaded: Value::try_from(loaded)?,
+            resolved: Value::try_from(&**CONFIGURATION)?,
+            file,
+        })
+    }
+
+    /// Each layer that sets the setting at `path`, with what it's set to there, the one that
+    /// applies last. `None` for one left unset.
+    fn of(&self, path: &str) -> Vec<(Source, Option<Value>)> {
+        let default = match CONVENTIONAL.iter().find(|(key, _)| *key == path) {
+            Some((_, var)) if env::var(var).is_ok() => Source::Env(var.to_string()),
+            _ => Source::Default,
+        };
+        let mut ret = vec![(default, get(&self.defaults, path))];
+        if let Some((url, remote)) = &self.remote {
+            if let Some(value) = get(remote, path) {
+                ret.push((Source::Url(url.clone()), Some(value)));
+            }
+        }
+        if let Some(value) = get(&self.written, path) {
+            ret.push((Source::File(self.file.clone()), Some(value)));
+        }
+        if let Some((var, _)) = environment::setting(path) {
+            ret.push((Source::Env(var), get(&self.loaded, path)));
+        }
+        let resolved = get(&self.resolved, path);
+        if let Some(flag) = option(path) {
+            ret.push((Source::Flag(flag), resolved.clone()));
+        }
+        if ret.last().map(|(_, value)| value) != Some(&resolved) {
+            let source = match path {
+                "api_key" => Source::CredentialStore,
+                "ui.history_file" => Source::Profile(FLAGS.config.profile()),
+                _ if FLAGS.a11y => Source::Flag("--a11y"),
+                _ => Source::Implied("ui.screen_reader"),
+            };
+            ret.push((source, resolved));
+        }
+        ret
+    }
+}
+
+/// The option that sets the setting at `path` over the rest, if it's given.
+fn option(path: &str) -> Option<&'static str> {
+    match path {
+        "model" if FLAGS.model.is_some() => Some("--model"),
+        "temperature" if FLAGS.temperature.is_some() => Some("--temperature"),
+        "max_tokens" if FLAGS.max_tokens.is_some() => Some("--max-tokens"),
+        _ => None,
+    }
+}
+
+/// `path`, dotted like `ui.wrap`, in `value`.
+fn get(value: &Value, path: &str) -> Option<Value> {
+    path.split('.')
+        .try_fold(value, |value, key| value.get(key))
+        .cloned()
+}
+
+/// The dotted paths of every setting, in the order of the config structs.
+pub fn paths() -> Vec<String> {
+    fn walk(shape: &dyn Struct, prefix: &str, ret: &mut Vec<String>) {
+        for (i, field) in shape.iter_fields().enumerate() {
+            let Some(name) = shape.name_at(i) else {
+                continue;
+            };
+            match field.reflect_ref() {
+                ReflectRef::Struct(shape) => walk(shape, &format!("{prefix}{name}."), ret),
+                _ => ret.push(format!("{prefix}{name}")),
+            }
+        }
+    }
+    let mut ret = vec![];
+    walk(&Config::default(), "", &mut ret);
+    ret
+}
+
+fn show(path: &str, value: &Option<Value>) -> String {
+    match value {
+        Some(_) if CONFIGURATION.ui.redact_api_key && SECRETS.contains(&path) => {
+            String::from("[redacted]")
+        }
+        Some(value) => value.to_string(),
+        None => tr!("config-unset"),
+    }
+}
+
+/// `ata2 config explain`: what `key` is set to, by each layer that sets it.
+pub fn explain(key: &str) -> TokioResult<()> {
+    if !paths().iter().any(|path| path == key) {
+        return Err(tr!("config-unknown-key", key = key).into());
+    }
+    let layers = Layers::read()?.of(key);
+    let (_, value) = layers.last().expect("there's always the default");
+    println!("{key} = {}", show(key, value));
+    for (i, (source, value)) in layers.iter().enumerate() {
+        let line = format!("  {source}: {}", show(key, value));
+        if i + 1 == layers.len() {
+            println!("{line}  {}", tr!("config-applies"));
+        } else {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+/// `--dump-config`: every setting, with where what it's set to comes from.
+pub fn dump() -> TokioResult<()> {
+    let layers = Layers::read()?;
+    for path in paths() {
+        let of = layers.of(&path);
+        let (source, value) = of.last().expect("there's always the default");
+        println!("{path} = {}  # {source}", show(&path, value));
+    }
+    Ok(())
+}
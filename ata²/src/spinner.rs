@@ -0,0 +1,89 @@
+//! Spinner shown between sending a request and receiving the first token of its response.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use terminal_size::{terminal_size, Width};
+use tokio::task::JoinHandle;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::progress::write_stderr;
+use crate::style;
+use crate::width;
+use crate::CONFIGURATION as config;
+
+const FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const FRAME_INTERVAL: Duration = Duration::from_millis(80);
+
+/// Erased on [`Spinner::stop`] or when dropped.
+pub struct Spinner {
+    inner: Option<Inner>,
+}
+
+struct Inner {
+    /// `false` once stopped. See `Progress` for why draws happen with this locked.
+    spinning: Arc<Mutex<bool>>,
+    ticker: JoinHandle<()>,
+}
+
+impl Spinner {
+    pub fn start() -> Self {
+        if !config.ui.spinner || !atty::is(atty::Stream::Stderr) {
+            return Self { inner: None };
+        }
+        let spinning = Arc::new(Mutex::new(true));
+        let ticker = tokio::spawn({
+            let spinning = spinning.clone();
+            async move {
+                for frame in FRAMES.iter().cycle() {
+                    if *spinning.lock().unwrap() {
+                        let text = format!("{frame} {}", config.ui.spinner_text);
+                        // Wider than the terminal, it would wrap, and `\r` only go back one row.
+                        let text = match terminal_size() {
+                            Some((Width(cols), _)) => width::truncate(&text, cols as usize),
+                            None => &text,
+                        };
+                        write_stderr(&format!("\r\x1b[2K{}", style::paint("dim", text)));
+                    }
+                    tokio::time::sleep(FRAME_INTERVAL).await;
+                }
+            }
+        });
+        Self {
+            inner: Some(Inner { spinning, ticker }),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        let Some(inner) = self.inner.take() else {
+            return;
+        };
+        inner.ticker.abort();
+        let mut spinning = inner.spinning.lock().unwrap();
+        *spinning = false;
+        write_stderr("\r\x1b[2K");
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
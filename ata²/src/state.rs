@@ -21,35 +21,49 @@
 use clap::Parser as _;
 
 use crate::args::Ata2;
-use crate::config::{self, Config};
+use crate::config::{self, Config, ConfigLocation};
+use crate::credentials;
 use crate::help;
+use crate::history;
+use crate::remote;
+use crate::workflow;
 
 use std::fs;
 use std::fs::File;
 use std::io::Read as _;
+use std::process::exit;
 use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 lazy_static! {
     pub static ref FLAGS: Ata2 = Ata2::parse();
     pub static ref EXIT: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
     pub static ref CONFIGURATION: Arc<Config> = {
-        if FLAGS.print_shortcuts {
-            help::commands();
-            EXIT.store(true, Ordering::Relaxed);
-        }
         let filename = FLAGS.config.location();
-        if !filename.exists() {
+        let remote = match &FLAGS.config {
+            ConfigLocation::Url(url) => Some(remote::fetch(url).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                exit(1)
+            })),
+            _ => None,
+        };
+        if FLAGS.print_shortcuts && !filename.exists() && remote.is_none() {
+            // With what the environment sets, as there is no file.
+            help::print_shortcuts(&Config::from(""));
+        }
+        if !filename.exists() && remote.is_none() {
             let v1_filename = FLAGS.config.location_v1();
             if v1_filename.exists() {
-                fs::create_dir_all(&config::default_path::<2>(None).parent().unwrap())
+                fs::create_dir_all(config::default_path::<2>(None).parent().unwrap())
                     .expect("Could not make configuration directory");
-                fs::copy(&v1_filename, &filename).expect(&format!(
-                    "Failed to copy {} to {}",
-                    v1_filename.to_string_lossy(),
-                    filename.to_string_lossy()
-                ));
+                fs::copy(&v1_filename, &filename).unwrap_or_else(|_| {
+                    panic!(
+                        "Failed to copy {} to {}",
+                        v1_filename.to_string_lossy(),
+                        filename.to_string_lossy()
+                    )
+                });
                 warn!(
                     "{}",
                     &format!(
@@ -62,15 +76,58 @@ lazy_static! {
             }
         }
         let mut contents = String::new();
-        File::open(filename)
-            .unwrap()
-            .read_to_string(&mut contents)
-            .expect("Could not read configuration file");
+        if filename.exists() {
+            File::open(filename)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .expect("Could not read configuration file");
+        }
+        if let Some(remote) = remote {
+            let left_out;
+            (contents, left_out) = remote::with_overrides(&remote, &contents)
+                .unwrap_or_else(|e| panic!("Config parsing failure!: {:?}", e));
+            if !left_out.is_empty() {
+                warn!(
+                    "{}",
+                    tr!("config-remote-untrusted", keys = left_out.join(", "))
+                );
+            }
+        }
 
-        let config_ = Arc::new(Config::from(&contents));
-        config_
+        let mut config = Config::from(&contents);
+        if FLAGS.print_shortcuts {
+            help::print_shortcuts(&config);
+        }
+        if config.auth == config::Auth::Key && config.api_key.as_deref().is_none_or(str::is_empty) {
+            config.api_key = credentials::api_key(&FLAGS.config.profile());
+        }
+        workflow::apply(&mut config);
+        if let Some(model) = &FLAGS.model {
+            config.model = model.clone();
+        }
+        if let Some(temperature) = FLAGS.temperature {
+            config.temperature = temperature;
+        }
+        if let Some(max_tokens) = FLAGS.max_tokens {
+            config.max_tokens = max_tokens;
+        }
+        config::migrate(&config);
+        config.ui.history_file = history::file(&config.ui.history_file, &FLAGS.config.profile());
+        // Not beside the configuration, see `ui.history_file`.
+        if let Some(dir) = config.ui.history_file.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if FLAGS.a11y || config.ui.screen_reader {
+            config.ui.for_screen_reader();
+        }
+        Arc::new(config)
     };
     pub static ref ABORT: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
     pub static ref IS_RUNNING: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
-    pub static ref HAD_FIRST_INTERRUPT: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    /// When the first of two ^C was pressed, with `ui.double_ctrlc`.
+    pub static ref FIRST_INTERRUPT: Mutex<Option<Instant>> = Mutex::new(None);
+    /// Print the rest of the current response at once, ignoring `ui.stream_pacing`.
+    pub static ref SKIP_PACING: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    /// Stop the current response, keeping what arrived so far.
+    pub static ref CANCEL: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
 }
@@ -0,0 +1,476 @@
+//! `storage = "sqlite"`: saved conversations and their tags, the prompt history and a ledger of
+//! every exchange's tokens and cost in one database, `ui.database_file`. The text of conversations
+//! and prompts is indexed with FTS5 for `ata2 search`.
+//!
+//! Conversations keep the path they would have as files, so the rest of ata² can go on naming them
+//! by it. Files are still read where a path isn't in the database, such as for `--load` of a file,
+//! and a profile's history comes from `ui.history_file` until it's first saved here.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::ChatCompletionRequestMessage;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension as _};
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::config::Storage;
+use crate::history;
+use crate::queue::Queued;
+use crate::readline::chat_completion_message_text;
+use crate::usage::Reported;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+use crate::FLAGS;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS conversations (
+    path TEXT PRIMARY KEY,
+    saved INTEGER NOT NULL,
+    messages TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tags (
+    path TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (path, tag)
+);
+CREATE TABLE IF NOT EXISTS threads (
+    path TEXT PRIMARY KEY,
+    response_id TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS pins (
+    path TEXT NOT NULL,
+    message INTEGER NOT NULL,
+    PRIMARY KEY (path, message)
+);
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    text TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS history_profile ON history (profile);
+CREATE TABLE IF NOT EXISTS queue (
+    id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    messages TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS usage (
+    id INTEGER PRIMARY KEY,
+    time INTEGER NOT NULL,
+    profile TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_tokens INTEGER NOT NULL,
+    completion_tokens INTEGER NOT NULL,
+    cost REAL,
+    cached_tokens INTEGER NOT NULL DEFAULT 0,
+    key TEXT
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS search USING fts5 (kind UNINDEXED, key UNINDEXED, text);
+";
+
+/// What the rows of the `search` table are of. `key` is the path of a conversation, or the profile
+/// of a prompt.
+const CONVERSATION: &str = "conversation";
+const PROMPT: &str = "prompt";
+
+/// How long to wait for another ata² to be done with the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref DB: Result<Mutex<Connection>, String> = open(&config.ui.database_file)
+        .map(Mutex::new)
+        .map_err(|e| tr!(
+            "store-open-failed",
+            path = config.ui.database_file.display().to_string(),
+            error = e.to_string()
+        ));
+}
+
+pub fn enabled() -> bool {
+    config.ui.storage == Storage::Sqlite
+}
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let db = Connection::open(path)?;
+    // Another ata² may be writing, as to the usage ledger, for as long as a transaction takes.
+    db.busy_timeout(BUSY_TIMEOUT)?;
+    db.execute_batch(SCHEMA)?;
+    // The ledgers of before there was caching, and key rotation.
+    for (column, definition) in [
+        ("cached_tokens", "INTEGER NOT NULL DEFAULT 0"),
+        ("key", "TEXT"),
+    ] {
+        let exists = db
+            .prepare("SELECT 1 FROM pragma_table_info('usage') WHERE name = ?1")?
+            .exists([column])?;
+        if !exists {
+            db.execute(
+                &format!("ALTER TABLE usage ADD COLUMN {column} {definition}"),
+                [],
+            )?;
+        }
+    }
+    Ok(db)
+}
+
+fn db() -> TokioResult<MutexGuard<'static, Connection>> {
+    match &*DB {
+        Ok(db) => Ok(db.lock().unwrap()),
+        Err(e) => Err(e.clone().into()),
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+pub fn save_conversation(
+    path: &Path,
+    conversation: &[ChatCompletionRequestMessage],
+) -> TokioResult<()> {
+    let key = path.to_string_lossy();
+    let json = serde_json::to_string(conversation)?;
+    let text = conversation
+        .iter()
+        .filter_map(chat_completion_message_text)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut db = db()?;
+    let tx = db.transaction()?;
+    tx.execute(
+        "INSERT INTO conversations (path, saved, messages) VALUES (?1, ?2, ?3)
+         ON CONFLICT (path) DO UPDATE SET saved = excluded.saved, messages = excluded.messages",
+        params![key, now(), json],
+    )?;
+    tx.execute(
+        "DELETE FROM search WHERE kind = ?1 AND key = ?2",
+        params![CONVERSATION, key],
+    )?;
+    tx.execute(
+        "INSERT INTO search (kind, key, text) VALUES (?1, ?2, ?3)",
+        params![CONVERSATION, key, text],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// The conversation saved as `path`, if it's in the database.
+pub fn conversation(path: &Path) -> TokioResult<Option<Vec<ChatCompletionRequestMessage>>> {
+    let json = db()?
+        .query_row(
+            "SELECT messages FROM conversations WHERE path = ?1",
+            [path.to_string_lossy()],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+}
+
+/// The saved conversations, newest first.
+pub fn conversations() -> TokioResult<Vec<PathBuf>> {
+    Ok(conversations_of(&*db()?)?)
+}
+
+/// Delete the conversation saved as `path`, and its tags.
+pub fn delete_conversation(path: &Path) -> TokioResult<()> {
+    let key = path.to_string_lossy();
+    let mut db = db()?;
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM conversations WHERE path = ?1", [&key])?;
+    tx.execute("DELETE FROM tags WHERE path = ?1", [&key])?;
+    tx.execute("DELETE FROM threads WHERE path = ?1", [&key])?;
+    tx.execute("DELETE FROM pins WHERE path = ?1", [&key])?;
+    tx.execute(
+        "DELETE FROM search WHERE kind = ?1 AND key = ?2",
+        params![CONVERSATION, key],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// The conversations saved in the database `path`, if there is one. Doesn't load the
+/// configuration, for completion.
+pub fn conversations_in(path: &Path) -> Vec<PathBuf> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|db| conversations_of(&db))
+        .unwrap_or_default()
+}
+
+fn conversations_of(db: &Connection) -> rusqlite::Result<Vec<PathBuf>> {
+    db.prepare("SELECT path FROM conversations ORDER BY saved DESC")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .map(|path| path.map(PathBuf::from))
+        .collect()
+}
+
+/// When the conversation `path` was last saved, if it's in the database.
+pub fn saved(path: &Path) -> TokioResult<Option<i64>> {
+    Ok(db()?
+        .query_row(
+            "SELECT saved FROM conversations WHERE path = ?1",
+            [path.to_string_lossy()],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// The tags of conversation `path`, sorted.
+pub fn tags(path: &Path) -> TokioResult<Vec<String>> {
+    let db = db()?;
+    let tags = db
+        .prepare("SELECT tag FROM tags WHERE path = ?1 ORDER BY tag")?
+        .query_map([path.to_string_lossy()], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(tags)
+}
+
+pub fn set_tags(path: &Path, tags: &[String]) -> TokioResult<()> {
+    let key = path.to_string_lossy();
+    let mut db = db()?;
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM tags WHERE path = ?1", [&key])?;
+    for tag in tags {
+        tx.execute(
+            "INSERT OR IGNORE INTO tags (path, tag) VALUES (?1, ?2)",
+            params![key, tag],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// The last response of the conversation `path` with `api = "responses"`.
+pub fn thread(path: &Path) -> TokioResult<Option<String>> {
+    let db = db()?;
+    let id = db
+        .query_row(
+            "SELECT response_id FROM threads WHERE path = ?1",
+            [path.to_string_lossy()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id)
+}
+
+pub fn set_thread(path: &Path, id: Option<&str>) -> TokioResult<()> {
+    let key = path.to_string_lossy();
+    let db = db()?;
+    match id {
+        Some(id) => db.execute(
+            "INSERT INTO threads (path, response_id) VALUES (?1, ?2)
+             ON CONFLICT (path) DO UPDATE SET response_id = excluded.response_id",
+            params![key, id],
+        )?,
+        None => db.execute("DELETE FROM threads WHERE path = ?1", [&key])?,
+    };
+    Ok(())
+}
+
+/// The pinned messages of the conversation saved as `path`, by their number.
+pub fn pins(path: &Path) -> TokioResult<Vec<usize>> {
+    let db = db()?;
+    let pins = db
+        .prepare("SELECT message FROM pins WHERE path = ?1 ORDER BY message")?
+        .query_map([path.to_string_lossy()], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(pins.into_iter().map(|pin| pin as usize).collect())
+}
+
+pub fn set_pins(path: &Path, pins: &[usize]) -> TokioResult<()> {
+    let key = path.to_string_lossy();
+    let mut db = db()?;
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM pins WHERE path = ?1", [&key])?;
+    for pin in pins {
+        tx.execute(
+            "INSERT INTO pins (path, message) VALUES (?1, ?2)",
+            params![key, *pin as i64],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// The newest `max` prompts of `profile`'s history, oldest first, or all of them if `max` is 0.
+pub fn history(profile: &str, max: usize) -> TokioResult<Vec<history::Entry>> {
+    let db = db()?;
+    // A negative limit is none to SQLite.
+    let limit = if max == 0 { -1 } else { max as i64 };
+    let entries = db
+        .prepare(
+            "SELECT time, text FROM (SELECT id, time, text FROM history WHERE profile = ?1 \
+             ORDER BY id DESC LIMIT ?2) ORDER BY id",
+        )?
+        .query_map(params![profile, limit], |row| {
+            Ok(history::Entry {
+                time: row.get(0)?,
+                text: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(entries)
+}
+
+/// Replace `profile`'s prompt history with `entries`.
+pub fn save_history(profile: &str, entries: &[history::Entry]) -> TokioResult<()> {
+    let mut db = db()?;
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM history WHERE profile = ?1", [profile])?;
+    tx.execute(
+        "DELETE FROM search WHERE kind = ?1 AND key = ?2",
+        params![PROMPT, profile],
+    )?;
+    for entry in entries {
+        tx.execute(
+            "INSERT INTO history (profile, time, text) VALUES (?1, ?2, ?3)",
+            params![profile, entry.time, entry.text],
+        )?;
+        tx.execute(
+            "INSERT INTO search (kind, key, text) VALUES (?1, ?2, ?3)",
+            params![PROMPT, profile, entry.text],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// `profile`'s queued prompts, oldest first.
+pub fn queue(profile: &str) -> TokioResult<Vec<Queued>> {
+    let db = db()?;
+    let rows = db
+        .prepare("SELECT time, messages FROM queue WHERE profile = ?1 ORDER BY id")?
+        .query_map([profile], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    rows.into_iter()
+        .map(|(time, messages)| {
+            Ok(Queued {
+                time,
+                messages: serde_json::from_str(&messages)?,
+            })
+        })
+        .collect()
+}
+
+/// Replace `profile`'s queued prompts with `queued`.
+pub fn save_queue(profile: &str, queued: &[Queued]) -> TokioResult<()> {
+    let mut db = db()?;
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM queue WHERE profile = ?1", [profile])?;
+    for entry in queued {
+        tx.execute(
+            "INSERT INTO queue (profile, time, messages) VALUES (?1, ?2, ?3)",
+            params![profile, entry.time, serde_json::to_string(&entry.messages)?],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Add an exchange to the usage ledger. `key` is the
+/// [fingerprint](crate::rotation::fingerprint) of the API key it was made with.
+pub fn record_usage(
+    model: &str,
+    usage: Reported,
+    cost: Option<f64>,
+    key: Option<&str>,
+) -> TokioResult<()> {
+    db()?.execute(
+        "INSERT INTO usage
+         (time, profile, model, prompt_tokens, completion_tokens, cost, cached_tokens, key)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            now(),
+            FLAGS.config.profile(),
+            model,
+            usage.prompt_tokens as i64,
+            usage.completion_tokens as i64,
+            cost,
+            usage.cached_tokens as i64,
+            key
+        ],
+    )?;
+    Ok(())
+}
+
+/// What the API key of [fingerprint](crate::rotation::fingerprint) `key` spent since `since`, in
+/// seconds since the Unix epoch, as far as the usage ledger knows.
+pub fn spent(key: &str, since: i64) -> TokioResult<f64> {
+    Ok(db()?.query_row(
+        "SELECT COALESCE(SUM(cost), 0) FROM usage WHERE key = ?1 AND time >= ?2",
+        params![key, since],
+        |row| row.get(0),
+    )?)
+}
+
+/// A conversation or prompt found by [`search`].
+pub struct Found {
+    /// The path of a conversation, `None` for a prompt.
+    pub path: Option<PathBuf>,
+    /// The profile of a prompt.
+    pub profile: String,
+    /// When it was saved or sent, in seconds since the Unix epoch.
+    pub time: i64,
+    /// Where it matches, each matched word between `\x01` and `\x02`.
+    pub snippet: String,
+}
+
+/// The conversations and prompts that have all of `words`, best matches first.
+pub fn search(words: &[&str]) -> TokioResult<Vec<Found>> {
+    // Each word quoted, so that FTS5 doesn't take any of it for its query syntax.
+    let query = words
+        .iter()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let db = db()?;
+    let mut statement = db.prepare(
+        "SELECT s.kind, s.key, s.snippet, coalesce(
+             c.saved,
+             (SELECT max(h.time) FROM history h WHERE h.profile = s.key AND h.text = s.text),
+             0
+         )
+         FROM (
+             SELECT kind, key, text, rank, snippet(search, 2, char(1), char(2), '…', 16) AS snippet
+             FROM search WHERE search MATCH ?1
+         ) s
+         LEFT JOIN conversations c ON s.kind = 'conversation' AND c.path = s.key
+         ORDER BY s.rank",
+    )?;
+    let found = statement
+        .query_map([query], |row| {
+            let kind = row.get::<_, String>(0)?;
+            let key = row.get::<_, String>(1)?;
+            Ok(Found {
+                path: (kind == CONVERSATION).then(|| PathBuf::from(&key)),
+                profile: if kind == PROMPT { key } else { String::new() },
+                snippet: row.get(2)?,
+                time: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(found)
+}
@@ -0,0 +1,267 @@
+//! Styles of everything ata² colors, as set in `[ui.theme]`.
+//!
+//! A style is written as words: `bold`, `dim`, `italic`, `underline` and `reverse`, a color, and
+//! `on` followed by a background color. A color is one of the eight ANSI names, optionally
+//! prefixed with `bright-`, a number from the 256-color palette, or `#rrggbb`. For example
+//! `bold cyan`, `208`, or `italic #ffaf00 on #303030`.
+//!
+//! Whether there's style at all is up to `--color` and, with `--color=auto`, `NO_COLOR`. See
+//! [`enabled`].
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use atty::Stream;
+
+use std::env;
+
+use crate::args::ColorChoice;
+use crate::CONFIGURATION as config;
+use crate::FLAGS;
+
+const NAMES: &[&str] = &[
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+const MODIFIERS: &[(&str, u8)] = &[
+    ("bold", 1),
+    ("dim", 2),
+    ("italic", 3),
+    ("underline", 4),
+    ("reverse", 7),
+];
+
+pub const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 ANSI colors, the bright ones from 8 on.
+    Ansi(u8),
+    /// From the 256-color palette.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn parse(word: &str) -> Option<Self> {
+        let (bright, name) = match word.strip_prefix("bright-") {
+            Some(name) => (8, name),
+            None => (0, word),
+        };
+        if let Some(i) = NAMES.iter().position(|n| *n == name) {
+            return Some(Color::Ansi(bright + i as u8));
+        }
+        if let Some(hex) = word.strip_prefix('#').filter(|hex| hex.len() == 6) {
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        word.parse().ok().map(Color::Indexed)
+    }
+
+    /// SGR parameters for this as the foreground color, or with `background` the background.
+    fn sgr(self, background: bool) -> String {
+        let (base, extended) = if background { (40, 48) } else { (30, 38) };
+        match self {
+            Color::Ansi(i) if i < 8 => (base + i).to_string(),
+            Color::Ansi(i) => (base + 60 + i - 8).to_string(),
+            Color::Indexed(i) => format!("{extended};5;{i}"),
+            Color::Rgb(r, g, b) => format!("{extended};2;{r};{g};{b}"),
+        }
+    }
+
+    fn ratatui(self) -> ratatui::style::Color {
+        use ratatui::style::Color as C;
+        const ANSI: [C; 16] = [
+            C::Black,
+            C::Red,
+            C::Green,
+            C::Yellow,
+            C::Blue,
+            C::Magenta,
+            C::Cyan,
+            C::Gray,
+            C::DarkGray,
+            C::LightRed,
+            C::LightGreen,
+            C::LightYellow,
+            C::LightBlue,
+            C::LightMagenta,
+            C::LightCyan,
+            C::White,
+        ];
+        match self {
+            Color::Ansi(i) => ANSI[i as usize],
+            Color::Indexed(i) => C::Indexed(i),
+            Color::Rgb(r, g, b) => C::Rgb(r, g, b),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    /// SGR parameters of `bold` and the like.
+    modifiers: Vec<u8>,
+}
+
+impl Style {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut style = Style::default();
+        let mut words = s.split_whitespace();
+        while let Some(word) = words.next() {
+            let word = word.to_lowercase();
+            if let Some((_, sgr)) = MODIFIERS.iter().find(|(name, _)| *name == word) {
+                style.modifiers.push(*sgr);
+            } else if word == "on" {
+                let color = words
+                    .next()
+                    .ok_or_else(|| tr!("config-style-no-color", style = s))?;
+                style.bg = Some(
+                    Color::parse(&color.to_lowercase())
+                        .ok_or_else(|| tr!("config-style-color", color = color, style = s))?,
+                );
+            } else if word != "none" {
+                style.fg = Some(
+                    Color::parse(&word)
+                        .ok_or_else(|| tr!("config-style-word", word = word, style = s))?,
+                );
+            }
+        }
+        Ok(style)
+    }
+
+    /// The escape that turns this style on, empty if it's no style at all.
+    pub fn start(&self) -> String {
+        let mut params = self.modifiers.iter().map(u8::to_string).collect::<Vec<_>>();
+        params.extend(self.fg.map(|c| c.sgr(false)));
+        params.extend(self.bg.map(|c| c.sgr(true)));
+        if params.is_empty() {
+            return String::new();
+        }
+        format!("\x1b[{}m", params.join(";"))
+    }
+
+    /// `text` in this style, if stderr is to be styled. Everything interactive goes there, or
+    /// to the same terminal.
+    pub fn paint(&self, text: &str) -> String {
+        self.paint_to(Stream::Stderr, text)
+    }
+
+    /// `text` in this style, if `stream` is to be styled. Styles already in `text` apply on top
+    /// of it, which is turned back on where they end.
+    pub fn paint_to(&self, stream: Stream, text: &str) -> String {
+        let start = self.start();
+        if start.is_empty() || text.is_empty() || !enabled(stream) {
+            return text.to_string();
+        }
+        let text = text.replace(RESET, &format!("{RESET}{start}"));
+        format!("{start}{text}{RESET}")
+    }
+
+    /// For the TUI, which only leaves out the colors if there's to be no style, as it needs the
+    /// likes of `reverse` to be usable.
+    pub fn ratatui(&self) -> ratatui::style::Style {
+        use ratatui::style::Modifier;
+        let mut style = ratatui::style::Style::default();
+        if let Some(fg) = self.fg {
+            style = style.fg(tui_color(fg.ratatui()));
+        }
+        if let Some(bg) = self.bg {
+            style = style.bg(tui_color(bg.ratatui()));
+        }
+        for sgr in &self.modifiers {
+            style = style.add_modifier(match sgr {
+                1 => Modifier::BOLD,
+                2 => Modifier::DIM,
+                3 => Modifier::ITALIC,
+                4 => Modifier::UNDERLINED,
+                _ => Modifier::REVERSED,
+            });
+        }
+        style
+    }
+}
+
+/// `[ui.theme]`, parsed.
+pub struct Theme {
+    pub prompt: Style,
+    pub response: Style,
+    pub error: Style,
+    pub code: Style,
+    pub user: Style,
+}
+
+lazy_static! {
+    static ref THEME: Theme = {
+        // Validated with the rest of the config, so nothing falls back to the default here.
+        let theme = &config.ui.theme;
+        let parse = |s: &str| Style::parse(s).unwrap_or_default();
+        Theme {
+            prompt: parse(&theme.prompt),
+            response: parse(&theme.response),
+            error: parse(&theme.error),
+            code: parse(&theme.code),
+            user: parse(&theme.user),
+        }
+    };
+}
+
+pub fn theme() -> &'static Theme {
+    &THEME
+}
+
+/// `text` in `style`, for what isn't themed, like the dim hints.
+pub fn paint(style: &str, text: &str) -> String {
+    Style::parse(style).unwrap_or_default().paint(text)
+}
+
+/// Whether to style what's written to `stream`: always or never if `--color` says so, otherwise
+/// if it's a terminal, `NO_COLOR` isn't set to something and it's not for a screen reader.
+pub fn enabled(stream: Stream) -> bool {
+    match FLAGS.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            !config.ui.screen_reader
+                && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                && atty::is(stream)
+                && ansi_supported()
+        }
+    }
+}
+
+/// Does the terminal take escape sequences? On Windows, asking turns them on in the console
+/// (conhost), which otherwise prints them as they are.
+#[cfg(windows)]
+pub fn ansi_supported() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(not(windows))]
+pub fn ansi_supported() -> bool {
+    true
+}
+
+/// `color` in the TUI, or the terminal's own if there's to be no style.
+pub fn tui_color(color: ratatui::style::Color) -> ratatui::style::Color {
+    if enabled(Stream::Stdout) {
+        color
+    } else {
+        ratatui::style::Color::Reset
+    }
+}
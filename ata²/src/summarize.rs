@@ -0,0 +1,134 @@
+//! `ata2 summarize <file|url|->`: a summary of a file, a web page or stdin, short or detailed. What
+//! doesn't fit in one request is summarized in chunks and the summaries merged, see
+//! [`crate::chunk`].
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, Role};
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+
+use std::io::{self, Read as _};
+use std::path::Path;
+
+use crate::args::SummaryLength;
+use crate::attach;
+use crate::chunk::MapReduce;
+use crate::extract;
+use crate::readline::{chat_completion_message_role, chat_completion_message_text};
+use crate::sanitize;
+use crate::TokioResult;
+
+/// How many tokens of the input a request has, leaving room for the instructions and the answer
+/// in the context of the smaller models.
+const CHUNK_TOKENS: usize = 3000;
+
+const SHORT: &str = "Summarize the text that the user sends in a few sentences, in the language \
+                     it's written in. Reply with the summary alone.";
+
+const DETAILED: &str = "Summarize the text that the user sends in detail: its points as lists \
+                        under headings for its parts, with the facts, figures and names that \
+                        matter, in the language it's written in. Reply with the summary alone.";
+
+const CHUNK: &str = "The user sends a part of a longer text. Summarize it as a list of its \
+                     points, with the facts, figures and names that matter, in the language it's \
+                     written in. Reply with the summary alone.";
+
+const MERGE_SHORT: &str = "The user sends summaries of consecutive parts of one text, separated \
+                           by `---`. Merge them into one summary of the whole text in a few \
+                           sentences, in the language they're written in. Reply with the \
+                           summary alone.";
+
+const MERGE_DETAILED: &str = "The user sends summaries of consecutive parts of one text, \
+                              separated by `---`. Merge them into one detailed summary of the \
+                              whole text: its points as lists under headings for its parts, with \
+                              the facts, figures and names that matter, in the language they're \
+                              written in. Reply with the summary alone.";
+
+pub async fn run(input: &str, length: SummaryLength) -> TokioResult<()> {
+    let text = read(input).await?;
+    if text.trim().is_empty() {
+        return Err(tr!("summarize-empty", input = input).into());
+    }
+    let (whole, reduce) = match length {
+        SummaryLength::Short => (SHORT, MERGE_SHORT),
+        SummaryLength::Detailed => (DETAILED, MERGE_DETAILED),
+    };
+    let summary = MapReduce {
+        max_tokens: CHUNK_TOKENS,
+        whole,
+        map: CHUNK,
+        reduce,
+    }
+    .run(&text)
+    .await?;
+    println!("{}", summary.trim());
+    Ok(())
+}
+
+/// A detailed summary of `conversation`, for it to be gone on from where it doesn't fit.
+pub async fn conversation(conversation: &[ChatCompletionRequestMessage]) -> TokioResult<String> {
+    let text = conversation
+        .iter()
+        .filter_map(|message| {
+            let text = chat_completion_message_text(message)?;
+            let role = match chat_completion_message_role(message) {
+                Role::User => "User",
+                Role::Assistant => "Assistant",
+                _ => return None,
+            };
+            Some(format!("{role}: {text}"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    MapReduce {
+        max_tokens: CHUNK_TOKENS,
+        whole: DETAILED,
+        map: CHUNK,
+        reduce: MERGE_DETAILED,
+    }
+    .run(&text)
+    .await
+}
+
+/// The text of `input`: stdin for `-`, the text of a web page for a URL, and else of a file, as
+/// `/attach` reads it.
+async fn read(input: &str) -> TokioResult<String> {
+    if input == "-" {
+        let mut bytes = vec![];
+        io::stdin().read_to_end(&mut bytes)?;
+        return Ok(sanitize::bytes(&bytes));
+    }
+    if input.starts_with("https://") || input.starts_with("http://") {
+        let response = reqwest::Client::new()
+            .get(input)
+            .header(USER_AGENT, concat!("ata2/", env!("CARGO_PKG_VERSION")))
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|e| tr!("summarize-fetch-failed", url = input, error = e.to_string()))?;
+        let html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|t| t.to_str().ok())
+            .is_some_and(|t| t.contains("html"));
+        let text = response.text().await?;
+        return Ok(if html { extract::html(&text) } else { text });
+    }
+    Ok(attach::read(Path::new(input))?)
+}
@@ -0,0 +1,319 @@
+//! `ata2 sweep --param temperature=0:1:0.2 --prompt-file p.txt`: send the same prompt with every
+//! combination of the values of some parameters, and write the responses as JSON Lines or Markdown
+//! to compare them.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::CreateChatCompletionRequestArgs;
+use futures_util::StreamExt as _;
+use serde_json::{Map, Value};
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::args::SweepFormat;
+use crate::cache;
+use crate::config::Config;
+use crate::openai;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::scheduler;
+use crate::style::Style;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// The parameters that can be swept.
+const PARAMS: [&str; 6] = [
+    "temperature",
+    "top_p",
+    "presence_penalty",
+    "frequency_penalty",
+    "max_tokens",
+    "model",
+];
+
+/// A parameter and the values to try it with.
+struct Param {
+    name: String,
+    values: Vec<Value>,
+}
+
+pub async fn run(
+    params: &[String],
+    prompt_file: &Path,
+    output: Option<&Path>,
+    format: SweepFormat,
+) -> TokioResult<()> {
+    let params = params
+        .iter()
+        .map(|param| parse(param))
+        .collect::<Result<Vec<_>, _>>()?;
+    let prompt = if prompt_file == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(prompt_file).map_err(|e| format!("{}: {e}", prompt_file.display()))?
+    };
+
+    let mut grid = vec![vec![]];
+    for param in &params {
+        grid = grid
+            .into_iter()
+            .flat_map(|combination: Vec<(&str, &Value)>| {
+                param.values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push((param.name.as_str(), value));
+                    combination
+                })
+            })
+            .collect();
+    }
+
+    let to_file = output.filter(|output| *output != Path::new("-"));
+    let mut out: Box<dyn Write> = match to_file {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+    if format == SweepFormat::Markdown {
+        for line in prompt.trim_end().lines() {
+            writeln!(out, "{}", format!("> {line}").trim_end())?;
+        }
+    }
+
+    let config: &Config = &CONFIGURATION;
+    let dim = Style::parse("dim").unwrap_or_default();
+    let jobs = grid
+        .iter()
+        .map(|combination| {
+            let model = combination
+                .iter()
+                .find(|(name, _)| *name == "model")
+                .and_then(|(_, value)| value.as_str())
+                .unwrap_or(&config.model)
+                .to_string();
+            let prompt = &prompt;
+            let job = async move {
+                let start = Instant::now();
+                let result = complete(prompt, combination).await;
+                (combination, result, start.elapsed())
+            };
+            (model, job)
+        })
+        .collect();
+    let mut results = scheduler::run(jobs)?;
+    while let Some((combination, result, elapsed)) = results.next().await {
+        let label = combination
+            .iter()
+            .map(|(name, value)| format!("{name} = {}", shown(value)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let seconds = format!("{:.2}", elapsed.as_secs_f64());
+        match &result {
+            Ok(_) => eprintln!(
+                "{}",
+                dim.paint_to(atty::Stream::Stderr, &format!("{label} · {seconds} s"))
+            ),
+            Err(e) => error!("{label}: {e}"),
+        }
+        match format {
+            SweepFormat::Jsonl => {
+                let mut record = Map::new();
+                record.insert(
+                    "params".into(),
+                    Value::Object(
+                        combination
+                            .iter()
+                            .map(|(name, value)| (name.to_string(), (*value).clone()))
+                            .collect(),
+                    ),
+                );
+                match &result {
+                    Ok((response, tokens)) => {
+                        record.insert("response".into(), response.as_str().into());
+                        record.insert("tokens".into(), (*tokens).into());
+                    }
+                    Err(e) => {
+                        record.insert("error".into(), e.to_string().into());
+                    }
+                }
+                record.insert(
+                    "seconds".into(),
+                    seconds.parse::<f64>().unwrap_or(0.0).into(),
+                );
+                writeln!(out, "{}", Value::Object(record))?;
+            }
+            SweepFormat::Markdown => {
+                writeln!(out, "\n## {label}\n")?;
+                match &result {
+                    Ok((response, tokens)) => {
+                        writeln!(out, "{}\n", response.trim())?;
+                        writeln!(
+                            out,
+                            "*{}*",
+                            tr!("sweep-stats", seconds = seconds.as_str(), tokens = *tokens)
+                        )?;
+                    }
+                    Err(e) => writeln!(out, "*{}*", tr!("sweep-error", error = e.to_string()))?,
+                }
+            }
+        }
+        out.flush()?;
+    }
+    if let Some(path) = to_file {
+        eprintln!(
+            "{}",
+            tr!(
+                "sweep-written",
+                count = grid.len(),
+                path = path.display().to_string()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// `name=start:end:step` or `name=a,b,c`.
+fn parse(param: &str) -> Result<Param, String> {
+    let (name, values) = param
+        .split_once('=')
+        .ok_or_else(|| tr!("sweep-bad-param", param = param))?;
+    let name = name.trim();
+    if !PARAMS.contains(&name) {
+        return Err(tr!(
+            "sweep-unknown-param",
+            name = name,
+            params = PARAMS.join(", ")
+        ));
+    }
+    if name == "model" {
+        let values = values
+            .split(',')
+            .map(str::trim)
+            .filter(|model| !model.is_empty())
+            .map(Value::from)
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return Err(tr!("sweep-bad-param", param = param));
+        }
+        return Ok(Param {
+            name: name.to_string(),
+            values,
+        });
+    }
+
+    let number = |value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| tr!("sweep-not-number", name = name, value = value.trim()))
+    };
+    let numbers: Vec<f64> = match values.split(':').collect::<Vec<_>>()[..] {
+        [start, end, step] => {
+            let (start, end, step) = (number(start)?, number(end)?, number(step)?);
+            if step <= 0.0 || end < start {
+                return Err(tr!("sweep-bad-range", param = param));
+            }
+            let steps = ((end - start) / step + 1e-9).floor() as usize;
+            // Rounded, for 0.6 rather than 0.6000000000000001.
+            (0..=steps)
+                .map(|i| ((start + i as f64 * step) * 1e9).round() / 1e9)
+                .collect()
+        }
+        [values] => values.split(',').map(number).collect::<Result<_, _>>()?,
+        _ => return Err(tr!("sweep-bad-range", param = param)),
+    };
+    let values = if name == "max_tokens" {
+        numbers
+            .into_iter()
+            .map(|value| {
+                (value >= 1.0 && value.fract() == 0.0)
+                    .then(|| Value::from(value as u64))
+                    .ok_or_else(|| tr!("sweep-not-number", name = name, value = value.to_string()))
+            })
+            .collect::<Result<_, _>>()?
+    } else {
+        numbers.into_iter().map(Value::from).collect()
+    };
+    Ok(Param {
+        name: name.to_string(),
+        values,
+    })
+}
+
+/// The response to `prompt` with the configuration's parameters but those in `combination`, and
+/// how many tokens it was. Not streamed, so the API says.
+async fn complete(prompt: &str, combination: &[(&str, &Value)]) -> TokioResult<(String, usize)> {
+    let config: &Config = &CONFIGURATION;
+    let messages = vec![string_to_chat_completion_request_user_message(
+        prompt.to_string(),
+    )];
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    request.n(1).stream(false).messages(messages.clone());
+    let mut model = config.model.clone();
+    for (name, value) in combination {
+        let number = value.as_f64().unwrap_or_default();
+        match *name {
+            "temperature" => request.temperature(number as f32),
+            "top_p" => request.top_p(number as f32),
+            "presence_penalty" => request.presence_penalty(number as f32),
+            "frequency_penalty" => request.frequency_penalty(number as f32),
+            "max_tokens" => request.max_tokens(number.min(u16::MAX as f64) as u16),
+            _ => {
+                model = value.as_str().unwrap_or_default().to_string();
+                request.model(&model)
+            }
+        };
+    }
+    let request = request.build()?;
+    if let Some(text) = cache::get("/chat/completions", &request) {
+        let tokens = usage::count_tokens(&model, &text);
+        return Ok((text, tokens));
+    }
+    let response = openai::chat(request.clone()).await?;
+    let text = response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+    let (prompt_tokens, completion_tokens) = match response.usage {
+        Some(usage) => (
+            usage.prompt_tokens as usize,
+            usage.completion_tokens as usize,
+        ),
+        None => (
+            usage::conversation_tokens(&model, &messages),
+            usage::count_tokens(&model, &text),
+        ),
+    };
+    usage::record_tokens(&model, prompt_tokens, completion_tokens);
+    cache::put("/chat/completions", &request, &text);
+    Ok((text, completion_tokens))
+}
+
+/// A value as it's written in `--param`.
+fn shown(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,187 @@
+//! CSV and TSV files for `/attach`, summed up rather than sent whole, which would fill the
+//! context window with all but the smallest: the header, the type of each column, how many rows
+//! there are, and `attachment_sample_rows` of them, taken evenly from the whole file. Only some
+//! lines, like `/attach data.csv lines 1-50`, are sent as they are.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::{ReaderBuilder, WriterBuilder};
+
+use std::path::Path;
+
+use crate::paste;
+use crate::CONFIGURATION as config;
+
+/// What the values of a column are.
+#[derive(Clone, Copy, PartialEq)]
+enum Type {
+    /// Only empty ones so far.
+    Empty,
+    Bool,
+    Int,
+    Float,
+    Date,
+    DateTime,
+    Text,
+}
+
+impl Type {
+    fn of(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            Type::Empty
+        } else if ["true", "false"].contains(&value.to_lowercase().as_str()) {
+            Type::Bool
+        } else if value.parse::<i64>().is_ok() {
+            Type::Int
+        } else if value.parse::<f64>().is_ok() {
+            Type::Float
+        } else if NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok() {
+            Type::Date
+        } else if ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+            .iter()
+            .any(|format| {
+                NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), format).is_ok()
+            })
+        {
+            Type::DateTime
+        } else {
+            Type::Text
+        }
+    }
+
+    /// What a column of `self` is with a value of `other`. Integers with floats are floats, and
+    /// the rest that differ text.
+    fn with(self, other: Self) -> Self {
+        match (self, other) {
+            (Type::Empty, other) | (other, Type::Empty) => other,
+            (a, b) if a == b => a,
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Type::Float,
+            (Type::Date, Type::DateTime) | (Type::DateTime, Type::Date) => Type::DateTime,
+            _ => Type::Text,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Empty => "empty",
+            Type::Bool => "bool",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Date => "date",
+            Type::DateTime => "datetime",
+            Type::Text => "text",
+        }
+    }
+}
+
+/// A column, as what its values have been so far.
+#[derive(Clone, Copy)]
+struct Column {
+    kind: Type,
+    empty: usize,
+}
+
+/// The delimiter of `path`, if it's a table.
+fn delimiter(path: &Path) -> Option<u8> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "csv" => Some(b','),
+        "tsv" | "tab" => Some(b'\t'),
+        _ => None,
+    }
+}
+
+/// `contents` of the table at `path` summed up, as it's attached. `None` if it isn't a table, or
+/// one that can be read, to attach as it is, or with `attachment_sample_rows` 0.
+pub fn summary(path: &Path, contents: &str) -> Option<String> {
+    let delimiter = delimiter(path)?;
+    let sample = config.attachment_sample_rows;
+    if sample == 0 {
+        return None;
+    }
+    let reader = || {
+        ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(contents.as_bytes())
+    };
+
+    let mut rows = reader();
+    let header = rows.headers().ok()?.clone();
+    let mut columns = vec![
+        Column {
+            kind: Type::Empty,
+            empty: 0,
+        };
+        header.len()
+    ];
+    let mut count = 0;
+    for row in rows.records() {
+        let row = row.ok()?;
+        for (column, value) in columns.iter_mut().zip(row.iter()) {
+            let kind = Type::of(value);
+            if kind == Type::Empty {
+                column.empty += 1;
+            }
+            column.kind = column.kind.with(kind);
+        }
+        count += 1;
+    }
+
+    // Evenly from the whole file, the first row included.
+    let picked = (0..sample.min(count))
+        .map(|i| i * count / sample.min(count))
+        .collect::<Vec<_>>();
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_writer(vec![]);
+    writer.write_record(&header).ok()?;
+    for row in reader()
+        .records()
+        .enumerate()
+        .filter(|(i, _)| picked.binary_search(i).is_ok())
+        .map(|(_, row)| row)
+    {
+        writer.write_record(&row.ok()?).ok()?;
+    }
+    let sampled = String::from_utf8(writer.into_inner().ok()?).ok()?;
+
+    let types = header
+        .iter()
+        .zip(&columns)
+        .map(|(name, column)| match column.empty {
+            0 => format!("  {name}: {}", column.kind.name()),
+            empty => format!(
+                "  {name}: {}, {}",
+                column.kind.name(),
+                tr!("tabular-empty", count = empty)
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(format!(
+        "{}\n{types}\n{}\n{}",
+        tr!("tabular-shape", rows = count, columns = header.len()),
+        tr!("tabular-sample", rows = picked.len(), count = count),
+        paste::fenced(sampled.trim_end())
+    ))
+}
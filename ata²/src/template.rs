@@ -0,0 +1,137 @@
+//! Templates of user-configurable strings, like `ui.prompt_format`: `{placeholders}` and style
+//! tags such as `<bold>…</bold>`, `<cyan>…</cyan>` or `<#ffaf00>…</#ffaf00>`.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use crate::style::{Style, RESET};
+
+/// `template` with each `{name}` replaced by its value in `values`, and style tags turned into
+/// ANSI escapes, or dropped if not `color`. A tag is any single-word style of [`Style::parse`].
+/// `{{` and `}}` are literal braces, as are `<` and `>` that aren't around a tag.
+pub fn render(template: &str, values: &[(&str, String)], color: bool) -> String {
+    let mut ret = String::with_capacity(template.len());
+    // Open tags, innermost last, to restore what's still open when one closes.
+    let mut open: Vec<(&str, String)> = vec![];
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("{{") {
+            ret.push('{');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("}}") {
+            ret.push('}');
+            rest = after;
+        } else if let Some((name, after)) = placeholder(rest) {
+            match values.iter().find(|(n, _)| *n == name) {
+                Some((_, value)) => ret.push_str(value),
+                None => ret.push_str(&rest[..rest.len() - after.len()]),
+            }
+            rest = after;
+        } else if let Some((closing, name, start, after)) = tag(rest) {
+            if closing {
+                if let Some(i) = open.iter().rposition(|(n, _)| *n == name) {
+                    open.remove(i);
+                }
+                if color {
+                    ret.push_str(RESET);
+                    for (_, start) in &open {
+                        ret.push_str(start);
+                    }
+                }
+            } else {
+                if color {
+                    ret.push_str(&start);
+                }
+                open.push((name, start));
+            }
+            rest = after;
+        } else {
+            ret.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if color && !open.is_empty() {
+        ret.push_str(RESET);
+    }
+    ret
+}
+
+/// Where the text goes in a prompt of `[templates]`.
+const PLACEHOLDER: &str = "{text}";
+
+/// The prompt of `[templates]` `template` with `text` in it, after it if it doesn't say where.
+pub fn prompt(template: &str, text: &str) -> String {
+    if template.contains(PLACEHOLDER) {
+        template.replace(PLACEHOLDER, text)
+    } else {
+        format!("{}\n\n{text}", template.trim_end())
+    }
+}
+
+/// Check that `template` only uses the placeholders in `names`.
+pub fn validate(template: &str, names: &[&str]) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+            rest = after;
+        } else if let Some((name, after)) = placeholder(rest) {
+            if !names.contains(&name) {
+                let known = names
+                    .iter()
+                    .map(|name| format!("{{{name}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(tr!(
+                    "config-placeholder",
+                    name = format!("{{{name}}}"),
+                    known = known
+                ));
+            }
+            rest = after;
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Ok(())
+}
+
+/// `{name}` at the start of `s`: the name, and what follows.
+fn placeholder(s: &str) -> Option<(&str, &str)> {
+    let inner = s.strip_prefix('{')?;
+    let end = inner.find('}')?;
+    let name = &inner[..end];
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .then(|| (name, &inner[end + 1..]))
+}
+
+/// A style tag at the start of `s`: whether it's closing, its name, the escape of its style, and
+/// what follows.
+fn tag(s: &str) -> Option<(bool, &str, String, &str)> {
+    let inner = s.strip_prefix('<')?;
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner),
+    };
+    let end = inner.find('>')?;
+    let name = &inner[..end];
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let start = Style::parse(name).ok()?.start();
+    Some((closing, name, start, &inner[end + 1..]))
+}
@@ -0,0 +1,169 @@
+//! tmux integration (`/tmux capture` and `/tmux send`).
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::Role;
+use tokio::io::AsyncWriteExt as _;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use std::env;
+use std::path::Path;
+use std::process::Stdio;
+
+use crate::commands;
+use crate::markdown;
+use crate::policy;
+use crate::prompt::CONVERSATION;
+use crate::readline::{
+    chat_completion_message_role, chat_completion_message_text,
+    string_to_chat_completion_request_user_message,
+};
+use crate::tools;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+pub async fn command(args: Vec<String>) -> TokioResult<()> {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        Some("capture") => {
+            let pane = args.next();
+            let lines = match args.next() {
+                Some(lines) => lines.parse().map_err(|_| commands::usage("tmux"))?,
+                None => config.integrations.tmux.capture_lines,
+            };
+            capture(pane, lines).await
+        }
+        Some("send") => send(args.next()).await,
+        _ => Err(commands::usage("tmux")),
+    }
+}
+
+/// Add the last `lines` lines of `pane`'s scrollback to the conversation as a user message.
+async fn capture(pane: Option<String>, lines: u64) -> TokioResult<()> {
+    let pane = pane
+        .or_else(|| config.integrations.tmux.capture_pane.clone())
+        .or_else(|| env::var("TMUX_PANE").ok())
+        .ok_or_else(|| tr!("tmux-no-pane"))?;
+    let start = format!("-{lines}");
+    let scrollback = tmux(
+        &["capture-pane", "-p", "-J", "-S", &start, "-t", &pane],
+        None,
+    )
+    .await?;
+    let scrollback = tools::truncated(&scrollback);
+    let message = format!("Here is the scrollback of my terminal:\n```\n{scrollback}\n```");
+    CONVERSATION
+        .lock()
+        .await
+        .push(string_to_chat_completion_request_user_message(message));
+    info!(
+        "{}",
+        tr!(
+            "tmux-captured",
+            lines = scrollback.lines().count(),
+            pane = pane.as_str()
+        )
+    );
+    Ok(())
+}
+
+/// Paste the last code block the model sent into `pane`.
+async fn send(pane: Option<String>) -> TokioResult<()> {
+    let pane = pane.unwrap_or_else(|| config.integrations.tmux.send_pane.clone());
+    if !config.tools.workdir.as_os_str().is_empty() {
+        let dir = tmux(
+            &["display-message", "-p", "-t", &pane, "#{pane_current_path}"],
+            None,
+        )
+        .await?;
+        tools::confine(Path::new(dir.trim_end()))?;
+    }
+    let code = CONVERSATION
+        .lock()
+        .await
+        .iter()
+        .rev()
+        .filter(|m| chat_completion_message_role(m) == Role::Assistant)
+        .filter_map(chat_completion_message_text)
+        .find_map(|text| markdown::code_blocks(&text).pop())
+        .ok_or_else(|| tr!("tmux-no-code-block"))?
+        .code;
+    let question = tr!(
+        "policy-ask-tmux",
+        pane = pane.as_str(),
+        enter = config.integrations.tmux.send_enter.to_string()
+    );
+    if !policy::permit(
+        "tmux_send",
+        code.trim_end(),
+        &format!("{}\n{question}", code.trim_end()),
+    )
+    .await
+    {
+        return Err(tr!("policy-not-allowed", tool = "tmux_send").into());
+    }
+    tmux(&["load-buffer", "-b", "ata2", "-"], Some(code.trim_end())).await?;
+    tmux(&["paste-buffer", "-d", "-b", "ata2", "-t", &pane], None).await?;
+    if config.integrations.tmux.send_enter {
+        tmux(&["send-keys", "-t", &pane, "Enter"], None).await?;
+    }
+    info!("{}", tr!("tmux-pasted", pane = pane));
+    Ok(())
+}
+
+async fn tmux(args: &[&str], stdin: Option<&str>) -> TokioResult<String> {
+    let mut child = Command::new("tmux")
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| tr!("run-failed", program = "tmux", error = e.to_string()))?;
+    let run = async {
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input.as_bytes()).await?;
+        }
+        child.wait_with_output().await
+    };
+    let output = match config.tools.timeouts.of("tmux") {
+        Some(limit) => timeout(limit, run).await.map_err(|_| {
+            tr!(
+                "tool-timed-out",
+                tool = format!("tmux {}", args[0]),
+                secs = limit.as_secs()
+            )
+        })??,
+        None => run.await?,
+    };
+    if !output.status.success() {
+        return Err(tr!(
+            "program-failed",
+            program = format!("tmux {}", args[0]),
+            error = String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
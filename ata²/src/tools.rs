@@ -0,0 +1,407 @@
+//! What the built-in tools of `[responses]` did in the middle of a response: which tool ran, with
+//! what code or queries, how long it took and what came of it. Each run is shown where it happened
+//! in the response, as a block of its own, and kept in the conversation as a tool message before
+//! the response, which the exports fold away. Chat completions, which never had them called, are
+//! sent the conversation without them, but for the runs of `[tools.custom]`, which are kept after
+//! the model's calls of them, for it to be sent what they output in the turns after too.
+//!
+//! `[tools]` limits them: how long each may run before the response is given up on, how much of
+//! what they output is kept, its start and its end, and the directory they're confined to.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionMessageToolCallChunk,
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestToolMessage, ChatCompletionToolType, FunctionCallStream, Role,
+};
+use serde_json::Value;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::paste;
+use crate::readline::chat_completion_message_role;
+use crate::style::Style;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// Each tool that's running, and when it started, by the ID of its call.
+static STARTED: Mutex<Option<HashMap<String, (String, Instant)>>> = Mutex::new(None);
+
+/// The tools that ran in the response that's coming, in order.
+static RUNS: Mutex<Vec<Run>> = Mutex::new(vec![]);
+
+/// Each round of calls of tools of `[tools.custom]` the model made in the response that's coming,
+/// in order.
+static CALLS: Mutex<Vec<Vec<ChatCompletionMessageToolCall>>> = Mutex::new(vec![]);
+
+pub struct Run {
+    id: String,
+    /// `code_interpreter`, `file_search`, …
+    name: String,
+    /// What it was given: the code it ran, or what it searched for.
+    arguments: String,
+    /// The language of `arguments`, for its code block.
+    language: &'static str,
+    output: String,
+    duration: Duration,
+}
+
+impl Run {
+    /// The run of a tool call that's done, an output item of the Responses API. `None` for an
+    /// item that isn't one.
+    fn of(item: &Value) -> Option<Self> {
+        let name = item["type"].as_str()?.strip_suffix("_call")?.to_string();
+        let id = item["id"].as_str().unwrap_or_default().to_string();
+        let strings = |value: &Value| {
+            value
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let (arguments, language, output) = match name.as_str() {
+            "code_interpreter" => {
+                let logs = item["outputs"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|output| match output["type"].as_str()? {
+                        "logs" => output["logs"].as_str().map(str::to_string),
+                        "image" => output["url"].as_str().map(|url| format!("[{url}]")),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let code = item["code"].as_str().unwrap_or_default().to_string();
+                (code, "python", logs)
+            }
+            "file_search" => {
+                let results = item["results"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|result| {
+                        let file = result["filename"]
+                            .as_str()
+                            .or(result["file_id"].as_str())
+                            .unwrap_or_default();
+                        match result["score"].as_f64() {
+                            Some(score) => format!("{file} ({score:.2})"),
+                            None => file.to_string(),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                (strings(&item["queries"]), "text", results)
+            }
+            _ => {
+                let action = match &item["action"] {
+                    Value::Null => String::new(),
+                    action => serde_json::to_string_pretty(action).unwrap_or_default(),
+                };
+                (action, "json", String::new())
+            }
+        };
+        let started = STARTED
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|started| started.remove(&id))
+            .map(|(_, started)| started);
+        Some(Self {
+            id,
+            name,
+            arguments,
+            language,
+            output: truncated(&output),
+            duration: started.map(|started| started.elapsed()).unwrap_or_default(),
+        })
+    }
+
+    /// “code_interpreter · 1.2 s”
+    fn summary(&self) -> String {
+        format!("{} · {:.1} s", self.name, self.duration.as_secs_f64())
+    }
+
+    /// As it's kept in the conversation: the summary, then what the tool was given and what it
+    /// output, in code blocks.
+    fn text(&self) -> String {
+        let mut ret = self.summary();
+        for (language, text) in [(self.language, &self.arguments), ("text", &self.output)] {
+            if !text.is_empty() {
+                ret += &format!("\n\n{}", paste::fenced_as(text.trim_end(), language));
+            }
+        }
+        ret
+    }
+
+    /// As it's shown in the response, set apart from it by a bar down the left.
+    fn block(&self) -> String {
+        let header = Style::parse("bold magenta").unwrap_or_default();
+        let dim = Style::parse("dim").unwrap_or_default();
+        let bar = Style::parse("magenta").unwrap_or_default().paint("│");
+        let mut ret = format!("{}\n", header.paint(&format!("⚙ {}", self.summary())));
+        for line in self.arguments.lines() {
+            ret += &format!("{bar} {line}\n");
+        }
+        if !self.output.is_empty() {
+            ret += &format!("{bar}\n");
+        }
+        for line in self.output.lines() {
+            ret += &format!("{bar} {}\n", dim.paint(line));
+        }
+        ret
+    }
+}
+
+/// Forget the runs of a response that didn't finish, as the next one starts.
+pub fn clear() {
+    RUNS.lock().unwrap().clear();
+    CALLS.lock().unwrap().clear();
+    *STARTED.lock().unwrap() = None;
+}
+
+/// Note that the tool call of the output `item` started, if it's one.
+pub fn started(item: &Value) {
+    let Some(name) = item["type"].as_str().and_then(|t| t.strip_suffix("_call")) else {
+        return;
+    };
+    if let Some(id) = item["id"].as_str() {
+        STARTED
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(id.to_string(), (name.to_string(), Instant::now()));
+    }
+}
+
+/// The tool that's running that has the least time left, and when it's up, if one has a limit.
+pub fn deadline() -> Option<(String, Instant)> {
+    let timeouts = &CONFIGURATION.tools.timeouts;
+    STARTED
+        .lock()
+        .unwrap()
+        .iter()
+        .flatten()
+        .filter_map(|(_, (name, started))| Some((name.clone(), *started + timeouts.of(name)?)))
+        .min_by_key(|(_, deadline)| *deadline)
+}
+
+/// Keep the run of the tool call of the output `item`, if it's one, and return the chunk of the
+/// call, for the response to show it where it happened.
+pub fn finished(item: &Value) -> Option<ChatCompletionMessageToolCallChunk> {
+    let run = Run::of(item)?;
+    let chunk = ChatCompletionMessageToolCallChunk {
+        index: 0,
+        id: Some(run.id.clone()),
+        r#type: Some(ChatCompletionToolType::Function),
+        function: Some(FunctionCallStream {
+            name: Some(run.name.clone()),
+            arguments: Some(run.arguments.clone()),
+        }),
+    };
+    RUNS.lock().unwrap().push(run);
+    Some(chunk)
+}
+
+/// Keep the run of the tool call `id` of ata²'s own, `name`, which ran what's in `code`, in its
+/// language, and return its output as it's kept. It's as much of it as [`truncated`] keeps
+/// already, as [`Capped`] read it.
+pub fn ran(
+    id: &str,
+    name: &str,
+    code: (&str, &'static str),
+    output: &str,
+    duration: Duration,
+) -> String {
+    let output = output.trim_end().to_string();
+    RUNS.lock().unwrap().push(Run {
+        id: id.to_string(),
+        name: name.to_string(),
+        arguments: code.0.to_string(),
+        language: code.1,
+        output: output.clone(),
+        duration,
+    });
+    output
+}
+
+/// The block of the run of the tool call `id`, to show in the response.
+pub fn block(id: &str) -> Option<String> {
+    RUNS.lock()
+        .unwrap()
+        .iter()
+        .find(|run| run.id == id)
+        .map(Run::block)
+}
+
+/// Keep a round of `calls` of tools of `[tools.custom]`, for the conversation to have them before
+/// their runs.
+pub fn called(calls: &[ChatCompletionMessageToolCall]) {
+    CALLS.lock().unwrap().push(calls.to_vec());
+}
+
+/// The tool messages of the runs of the response, which is done, for the conversation, those of
+/// calls of tools of `[tools.custom]` each after the assistant message of its round of calls.
+pub fn take() -> Vec<ChatCompletionRequestMessage> {
+    let mut runs = std::mem::take(&mut *RUNS.lock().unwrap());
+    let message = |run: Run| {
+        ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+            role: Role::Tool,
+            content: Some(run.text()),
+            tool_call_id: run.id,
+        })
+    };
+    let mut ret = vec![];
+    for calls in std::mem::take(&mut *CALLS.lock().unwrap()) {
+        let ids = calls.iter().map(|call| &call.id).collect::<HashSet<_>>();
+        let (of, rest): (Vec<_>, Vec<_>) = runs.into_iter().partition(|run| ids.contains(&run.id));
+        runs = rest;
+        // Without what the model said before them, which the response that's kept after them has.
+        #[allow(deprecated)]
+        ret.push(ChatCompletionRequestMessage::Assistant(
+            ChatCompletionRequestAssistantMessage {
+                content: None,
+                role: Role::Assistant,
+                tool_calls: Some(calls),
+                function_call: None,
+            },
+        ));
+        ret.extend(of.into_iter().map(message));
+    }
+    ret.extend(runs.into_iter().map(message));
+    ret
+}
+
+/// `messages` without the tool messages that don't follow a call of their own, for chat
+/// completions, which only take those that do: the runs of tools of `[tools.custom]` while the
+/// model answers.
+pub fn without(messages: &[ChatCompletionRequestMessage]) -> Vec<ChatCompletionRequestMessage> {
+    let mut called = HashSet::new();
+    messages
+        .iter()
+        .filter(|message| match message {
+            ChatCompletionRequestMessage::Assistant(message) => {
+                called.extend(message.tool_calls.iter().flatten().map(|call| &call.id));
+                true
+            }
+            ChatCompletionRequestMessage::Tool(message) => called.contains(&message.tool_call_id),
+            message => chat_completion_message_role(message) != Role::Tool,
+        })
+        .cloned()
+        .collect()
+}
+
+/// `output` as much of it is kept, `tools.max_output_bytes` of it: half from its start and half
+/// from its end, with how much was left out between them.
+pub fn truncated(output: &str) -> String {
+    cut(output, 0)
+}
+
+/// [`truncated`], for `output` that's had `skipped` bytes left out of it already, between its
+/// first and its last `tools.max_output_bytes`.
+fn cut(output: &str, skipped: usize) -> String {
+    let output = output.trim_end();
+    let max = CONFIGURATION.tools.max_output_bytes;
+    if max == 0 || (output.len() <= max && skipped == 0) {
+        return output.to_string();
+    }
+    let mut head = (max / 2).min(output.len());
+    while !output.is_char_boundary(head) {
+        head -= 1;
+    }
+    let mut tail = output.len().saturating_sub(max / 2).max(head);
+    while !output.is_char_boundary(tail) {
+        tail += 1;
+    }
+    // At the ends of lines, if there are any.
+    if let Some(end) = output[..head].rfind('\n') {
+        head = end;
+    }
+    if let Some(start) = output[tail..].find('\n') {
+        tail += start + 1;
+    }
+    format!(
+        "{}\n{}\n{}",
+        &output[..head],
+        tr!("tool-truncated", count = tail - head + skipped),
+        &output[tail..]
+    )
+}
+
+/// Output as it's read, of which no more is held than [`truncated`] needs: its first and its last
+/// `tools.max_output_bytes`, and how much was left out between them, for a tool that outputs
+/// without end not to take up memory without end.
+#[derive(Default)]
+pub struct Capped {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    skipped: usize,
+}
+
+impl Capped {
+    pub fn push(&mut self, bytes: &[u8]) {
+        let max = CONFIGURATION.tools.max_output_bytes;
+        if max == 0 {
+            self.head.extend_from_slice(bytes);
+            return;
+        }
+        let room = max.saturating_sub(self.head.len()).min(bytes.len());
+        self.head.extend_from_slice(&bytes[..room]);
+        self.tail.extend(&bytes[room..]);
+        let excess = self.tail.len().saturating_sub(max);
+        self.tail.drain(..excess);
+        self.skipped += excess;
+    }
+
+    /// What was read, as much of it as [`truncated`] keeps.
+    pub fn text(&self) -> String {
+        let mut read = self.head.clone();
+        read.extend(&self.tail);
+        cut(&String::from_utf8_lossy(&read), self.skipped)
+    }
+}
+
+/// Whether `path` is in `tools.workdir`, if tools are confined to it.
+pub fn confine(path: &Path) -> TokioResult<()> {
+    let workdir = &CONFIGURATION.tools.workdir;
+    if workdir.as_os_str().is_empty() {
+        return Ok(());
+    }
+    // Through whatever links there are, for none to lead out of it.
+    let inside = match (path.canonicalize(), workdir.canonicalize()) {
+        (Ok(path), Ok(workdir)) => path.starts_with(workdir),
+        _ => false,
+    };
+    if !inside {
+        return Err(tr!(
+            "tool-outside-workdir",
+            path = path.display().to_string(),
+            workdir = workdir.display().to_string()
+        )
+        .into());
+    }
+    Ok(())
+}
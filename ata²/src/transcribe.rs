@@ -0,0 +1,174 @@
+//! The transcript of a recording, for `/attach meeting.mp3`, from the transcription endpoint of
+//! the API with `transcription_model`. The API takes files of up to 25 MB, so a longer recording
+//! is split with `ffmpeg` into parts that fit, transcribed one after the other.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use reqwest::multipart::{Form, Part};
+use serde_json::Value;
+use tokio::process::Command;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::api_error::ApiError;
+use crate::openai::post;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+/// The extensions of what the transcription endpoint takes.
+const AUDIO: &[&str] = &[
+    "flac", "m4a", "mp3", "mp4", "mpeg", "mpga", "oga", "ogg", "wav", "webm",
+];
+
+/// What a part can be, under the 25 MB of the API for the rest of the form.
+const MAX_BYTES: u64 = 24 * 1024 * 1024;
+
+/// Whether `path` is a recording, by its extension.
+pub fn is_audio(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        AUDIO.contains(&extension.to_string_lossy().to_lowercase().as_str())
+    })
+}
+
+/// The transcript of the recording at `path`.
+pub async fn transcript(path: &Path) -> TokioResult<String> {
+    let size = fs::metadata(path)?.len();
+    info!(
+        "{}",
+        tr!("transcribe-started", path = path.display().to_string())
+    );
+    if size <= MAX_BYTES {
+        return transcribe(path).await;
+    }
+    let dir = env::temp_dir().join(format!("ata2-transcribe-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let ret = in_parts(path, size, &dir).await;
+    let _ = fs::remove_dir_all(&dir);
+    ret
+}
+
+/// The transcript of the recording at `path`, of `size` bytes, split into parts in `dir`.
+async fn in_parts(path: &Path, size: u64, dir: &Path) -> TokioResult<String> {
+    let duration = run(
+        "ffprobe",
+        &[
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            &path.to_string_lossy(),
+        ],
+    )
+    .await?;
+    let duration = duration
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| tr!("transcribe-no-duration", path = path.display().to_string()))?;
+    // A bit shorter than what fits on average, as the bitrate of some recordings varies.
+    let seconds = (duration * MAX_BYTES as f64 / size as f64 * 0.9)
+        .floor()
+        .max(1.0);
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let pattern = dir.join(format!("part%03d.{extension}"));
+    run(
+        "ffmpeg",
+        &[
+            "-v",
+            "error",
+            "-i",
+            &path.to_string_lossy(),
+            "-vn",
+            "-f",
+            "segment",
+            "-segment_time",
+            &seconds.to_string(),
+            "-c",
+            "copy",
+            &pattern.to_string_lossy(),
+        ],
+    )
+    .await?;
+
+    let mut parts = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    parts.sort_unstable();
+    let mut ret = vec![];
+    for (i, part) in parts.iter().enumerate() {
+        info!(
+            "{}",
+            tr!("transcribe-part", part = i + 1, count = parts.len())
+        );
+        ret.push(transcribe(part).await?);
+    }
+    Ok(ret.join("\n\n"))
+}
+
+/// What `program` prints given `args`.
+async fn run(program: &str, args: &[&str]) -> TokioResult<String> {
+    let output = match Command::new(program).args(args).output().await {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(tr!("transcribe-no-ffmpeg", program = program).into())
+        }
+        Err(e) => return Err(tr!("run-failed", program = program, error = e.to_string()).into()),
+    };
+    if !output.status.success() {
+        return Err(tr!(
+            "program-failed",
+            program = program,
+            error = String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The transcript of the file at `path`, which fits in a request.
+async fn transcribe(path: &Path) -> TokioResult<String> {
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let form = Form::new()
+        .text("model", config.transcription_model.clone())
+        .part("file", Part::bytes(fs::read(path)?).file_name(name));
+    let response = post("/audio/transcriptions")?
+        .multipart(form)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(ApiError::of(response, &config.transcription_model)
+            .await
+            .into());
+    }
+    let transcript = response.json::<Value>().await?;
+    Ok(transcript["text"]
+        .as_str()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
@@ -0,0 +1,187 @@
+//! `ata2 translate --to ja < README.md`: a Markdown document, translated part by part. It's cut
+//! into parts of whole paragraphs, each small enough to be translated in one response, and code
+//! blocks aren't sent at all but copied as they are, for the model not to translate what's in
+//! them. Each part is translated on its own, told to keep its Markdown as it is.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    CreateChatCompletionRequestArgs, Role,
+};
+
+use std::io::{self, Read as _, Write as _};
+
+use crate::config::Config;
+use crate::openai;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::sanitize;
+use crate::sink::{self, Sinks};
+use crate::usage::{self, count_tokens};
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// How many tokens of text a part can be, for its translation, which may well be longer, to fit
+/// in `max_tokens`.
+const PART_TOKENS: usize = 800;
+
+const INSTRUCTIONS: &str = "Translate the Markdown that the user sends into {language}. Keep its \
+                            Markdown exactly as it is: headings, lists, tables, emphasis, links, \
+                            HTML, line breaks and indentation. Don't translate inline code, URLs \
+                            or the targets of links. Reply with the translation alone, without \
+                            fencing it or saying anything about it.";
+
+/// What the document is cut into.
+enum Piece {
+    /// Whole paragraphs, with the blank lines after them.
+    Text(String),
+    /// A fenced code block, or blank lines.
+    Verbatim(String),
+}
+
+pub async fn run(to: &str) -> TokioResult<()> {
+    let mut bytes = vec![];
+    io::stdin().read_to_end(&mut bytes)?;
+    let document = sanitize::bytes(&bytes);
+    let model = &CONFIGURATION.model;
+    let parts = parts(blocks(&document), |text| count_tokens(model, text));
+    let count = parts
+        .iter()
+        .filter(|piece| matches!(piece, Piece::Text(_)))
+        .count();
+
+    let mut stdout = io::stdout();
+    let mut done = 0;
+    for piece in parts {
+        match piece {
+            Piece::Verbatim(text) => write!(stdout, "{text}")?,
+            Piece::Text(text) => {
+                done += 1;
+                info!("{}", tr!("translate-part", part = done, count = count));
+                let body = text.trim_end();
+                let translation = translate(body, to, done).await?;
+                write!(
+                    stdout,
+                    "{}{}",
+                    translation.trim_matches('\n'),
+                    &text[body.len()..]
+                )?;
+            }
+        }
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// `text`, part `part` of the document, translated `to` a language.
+async fn translate(text: &str, to: &str, part: usize) -> TokioResult<String> {
+    let config: &Config = &CONFIGURATION;
+    let messages = vec![
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            role: Role::System,
+            content: Some(INSTRUCTIONS.replace("{language}", to)),
+        }),
+        string_to_chat_completion_request_user_message(text.to_string()),
+    ];
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    let report = usage::Report::default();
+    let stream = openai::chat_stream(request.messages(messages.clone()).build()?, &report).await?;
+    let translation = sink::read(stream, &config.model, &mut Sinks::default()).await?;
+    usage::record(&config.model, &messages, &translation.text, &report);
+    if translation.cut_off {
+        warn!("{}", tr!("translate-cut-off", part = part));
+    }
+    Ok(translation.text)
+}
+
+/// `document` as paragraphs, code blocks and the blank lines before the first paragraph.
+fn blocks(document: &str) -> Vec<Piece> {
+    let mut ret = vec![];
+    let mut paragraph = String::new();
+    // The character and length of the fence of the code block that's open.
+    let mut fence: Option<(char, usize)> = None;
+    let mut code = String::new();
+    for line in document.split_inclusive('\n') {
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        let opening = ['`', '~'].into_iter().find_map(|c| {
+            let len = trimmed.len() - trimmed.trim_start_matches(c).len();
+            (indent < 4 && len >= 3).then_some((c, len))
+        });
+        match (fence, opening) {
+            (Some((c, len)), Some((close, close_len)))
+                if c == close
+                    && close_len >= len
+                    && trimmed.trim_start_matches(c).trim().is_empty() =>
+            {
+                code += line;
+                ret.push(Piece::Verbatim(std::mem::take(&mut code)));
+                fence = None;
+            }
+            (Some(_), _) => code += line,
+            (None, Some(opened)) => {
+                if !paragraph.is_empty() {
+                    ret.push(Piece::Text(std::mem::take(&mut paragraph)));
+                }
+                fence = Some(opened);
+                code += line;
+            }
+            (None, None) if line.trim().is_empty() && paragraph.is_empty() => {
+                ret.push(Piece::Verbatim(line.to_string()));
+            }
+            (None, None) => {
+                // A line after blank ones starts the next paragraph.
+                if !line.trim().is_empty()
+                    && paragraph
+                        .lines()
+                        .last()
+                        .is_some_and(|l| l.trim().is_empty())
+                {
+                    ret.push(Piece::Text(std::mem::take(&mut paragraph)));
+                }
+                paragraph += line;
+            }
+        }
+    }
+    if !paragraph.is_empty() {
+        ret.push(Piece::Text(paragraph));
+    }
+    // A block that's never closed goes on to the end.
+    if !code.is_empty() {
+        ret.push(Piece::Verbatim(code));
+    }
+    ret
+}
+
+/// `blocks` with paragraphs that follow each other together, as long as they're no more than
+/// [`PART_TOKENS`] as `tokens` counts them.
+fn parts(blocks: Vec<Piece>, tokens: impl Fn(&str) -> usize) -> Vec<Piece> {
+    let mut ret: Vec<Piece> = vec![];
+    for block in blocks {
+        match (ret.last_mut(), block) {
+            (Some(Piece::Text(part)), Piece::Text(text))
+                if tokens(part) + tokens(&text) <= PART_TOKENS =>
+            {
+                *part += &text;
+            }
+            (_, block) => ret.push(block),
+        }
+    }
+    ret
+}
@@ -0,0 +1,584 @@
+//! Full-screen interface (`--tui`), an alternative to the line-based REPL in [`crate::readline`].
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::{ChatCompletionRequestMessage, Role};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event,
+    EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+use crossterm::execute;
+use futures_util::future::{BoxFuture, FutureExt as _};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt as _;
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::clipboard;
+use crate::config::LargePaste;
+use crate::forecast::Forecast;
+use crate::paste;
+use crate::prompt::{self, CONVERSATION};
+use crate::readline::{
+    self, chat_completion_message_role, chat_completion_message_text, saved_conversations,
+};
+use crate::router;
+use crate::sink::{self, Sink, Sinks};
+use crate::style::{self, theme};
+use crate::usage;
+use crate::width;
+use crate::TokioResult;
+use crate::CONFIGURATION as config;
+
+const SIDEBAR_WIDTH: u16 = 28;
+const MAX_INPUT_HEIGHT: u16 = 8;
+const WHEEL_LINES: usize = 3;
+
+/// What the task streaming a response tells the UI.
+enum Streamed {
+    Chunk(String),
+    Done(Vec<ChatCompletionRequestMessage>, String, usage::Report),
+    Failed(String),
+}
+
+/// Where the task streaming a response sends what it has to tell, with which response it's of.
+type Updates = UnboundedSender<(u64, Streamed)>;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Input,
+    Sessions,
+}
+
+struct App {
+    input: String,
+    focus: Focus,
+    /// Response being streamed in, and the task streaming it.
+    streaming: Option<(String, JoinHandle<()>)>,
+    /// How long the conversation was before the prompt that's being answered.
+    answering: usize,
+    /// Counted up with each prompt, for what's still queued of a cancelled response to be dropped
+    /// rather than taken for the next one's.
+    response: u64,
+    /// How many lines the conversation is scrolled up from the bottom.
+    scroll: usize,
+    sessions: Vec<PathBuf>,
+    selected_session: ListState,
+    /// Rendered conversation lines as plain text, for copying selections.
+    rendered: Vec<String>,
+    /// Index into `rendered` of the first line shown, and where the conversation is on screen.
+    top: usize,
+    conversation_area: Rect,
+    /// Lines selected with the mouse, as indices into `rendered`: (anchor, cursor).
+    selection: Option<(usize, usize)>,
+    notice: String,
+    /// Large pastes into `input`.
+    pastes: Vec<String>,
+    /// Input above `ui.confirm_above_tokens` that Enter was pressed on once.
+    confirmed: Option<String>,
+    quit: bool,
+}
+
+/// Puts the terminal back the way we found it, also when we panic.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste);
+        ratatui::restore();
+    }
+}
+
+pub async fn run() -> TokioResult<()> {
+    let mut terminal = ratatui::init();
+    let _guard = TerminalGuard;
+    execute!(io::stdout(), EnableMouseCapture, EnableBracketedPaste)?;
+
+    let mut app = App {
+        input: String::new(),
+        focus: Focus::Input,
+        streaming: None,
+        answering: 0,
+        response: 0,
+        scroll: 0,
+        sessions: saved_conversations(),
+        selected_session: ListState::default(),
+        rendered: vec![],
+        top: 0,
+        conversation_area: Rect::default(),
+        selection: None,
+        notice: tr!("tui-hints"),
+        pastes: vec![],
+        confirmed: None,
+        quit: false,
+    };
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut events = EventStream::new();
+
+    while !app.quit {
+        let conversation = CONVERSATION.lock().await.clone();
+        draw(&mut terminal, &mut app, &conversation)?;
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(event)) => app.handle_event(event, &tx).await,
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            },
+            Some((of, streamed)) = rx.recv() => match streamed {
+                // Of a response that was cancelled, nothing is kept, not even that it was done.
+                _ if of != app.response || app.streaming.is_none() => {}
+                Streamed::Chunk(text) => {
+                    if let Some((response, _)) = &mut app.streaming {
+                        response.push_str(&text);
+                    }
+                }
+                Streamed::Done(messages, response, report) => {
+                    app.streaming = None;
+                    if let Some(route) = router::last() {
+                        app.notice = route;
+                    }
+                    prompt::finish_exchange(&messages, response, &report).await;
+                }
+                Streamed::Failed(e) => {
+                    app.streaming = None;
+                    app.take_back().await;
+                    app.notice = tr!("tui-error", error = e.to_string());
+                }
+            },
+        }
+    }
+    if let Some((_, task)) = app.streaming.take() {
+        task.abort();
+    }
+    readline::autosave().await;
+    Ok(())
+}
+
+fn draw(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    conversation: &[ChatCompletionRequestMessage],
+) -> io::Result<()> {
+    terminal.draw(|frame| app.render(frame, conversation))?;
+    Ok(())
+}
+
+impl App {
+    fn render(&mut self, frame: &mut Frame, conversation: &[ChatCompletionRequestMessage]) {
+        let [sidebar, main] =
+            Layout::horizontal([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(20)])
+                .areas(frame.area());
+        let input_height = (self.input.lines().count().max(1) as u16 + 2).min(MAX_INPUT_HEIGHT);
+        let [conversation_area, input_area, status_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(input_height),
+            Constraint::Length(1),
+        ])
+        .areas(main);
+
+        self.render_sessions(frame, sidebar);
+        self.render_conversation(frame, conversation_area, conversation);
+
+        let input = Paragraph::new(self.input.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(if self.streaming.is_some() {
+                    format!(" {} ", tr!("tui-prompt-waiting"))
+                } else if config.ui.input_tokens && !self.input.trim().is_empty() {
+                    let tokens = usage::tokens_text(usage::input_tokens(&self.input));
+                    format!(" {} ", tr!("tui-prompt-tokens", tokens = tokens))
+                } else {
+                    format!(" {} ", tr!("tui-prompt"))
+                })
+                .border_style(focused_style(self.focus == Focus::Input)),
+        );
+        let inner_height = input_height.saturating_sub(2) as usize;
+        let input_lines = self.input.split('\n').collect::<Vec<_>>();
+        let skip = input_lines.len().saturating_sub(inner_height);
+        frame.render_widget(input.scroll((skip as u16, 0)), input_area);
+        if self.focus == Focus::Input {
+            let last = input_lines.last().copied().unwrap_or_default();
+            let x = input_area.x + 1 + width::width(last) as u16;
+            let y = input_area.y + 1 + (input_lines.len() - 1 - skip) as u16;
+            frame.set_cursor_position(Position::new(
+                x.min(input_area.right().saturating_sub(2)),
+                y,
+            ));
+        }
+
+        let status = format!("{} │ {}", prompt::status_line(), self.notice);
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().add_modifier(Modifier::REVERSED)),
+            status_area,
+        );
+    }
+
+    fn render_sessions(&mut self, frame: &mut Frame, area: Rect) {
+        let items = self
+            .sessions
+            .iter()
+            .map(|path| {
+                ListItem::new(
+                    path.file_stem()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} ", tr!("tui-sessions")))
+                    .border_style(focused_style(self.focus == Focus::Sessions)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.selected_session);
+    }
+
+    fn render_conversation(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        conversation: &[ChatCompletionRequestMessage],
+    ) {
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let mut lines: Vec<(Style, String)> = vec![];
+        let mut push_message = |role: Role, text: &str| {
+            let (name, color) = match role {
+                Role::User => ("tui-you", Color::Cyan),
+                Role::Assistant => ("tui-assistant", Color::Green),
+                Role::System => ("tui-system", Color::Yellow),
+                _ => ("tui-tool", Color::Magenta),
+            };
+            let header = Style::default()
+                .fg(style::tui_color(color))
+                .add_modifier(Modifier::BOLD);
+            lines.push((header, tr!(name)));
+            let body = match role {
+                Role::User => theme().user.ratatui(),
+                _ => Style::default(),
+            };
+            for line in text.lines() {
+                for wrapped in wrap(line, width) {
+                    lines.push((body, wrapped));
+                }
+            }
+            lines.push((Style::default(), String::new()));
+        };
+        for message in conversation {
+            // The model's calls of tools, which say nothing.
+            if let Some(text) = chat_completion_message_text(message) {
+                push_message(chat_completion_message_role(message), &text);
+            }
+        }
+        if let Some((response, _)) = &self.streaming {
+            push_message(Role::Assistant, response);
+        }
+
+        let height = area.height.saturating_sub(2) as usize;
+        self.scroll = self.scroll.min(lines.len().saturating_sub(height));
+        self.top = lines.len().saturating_sub(height + self.scroll);
+        self.conversation_area = area;
+        let selected = self.selection.map(|(a, b)| (a.min(b), a.max(b)));
+        let visible = lines
+            .iter()
+            .enumerate()
+            .skip(self.top)
+            .take(height)
+            .map(|(i, (style, text))| {
+                let style = match selected {
+                    Some((start, end)) if (start..=end).contains(&i) => {
+                        style.add_modifier(Modifier::REVERSED)
+                    }
+                    _ => *style,
+                };
+                Line::from(Span::styled(text.clone(), style))
+            })
+            .collect::<Vec<_>>();
+        self.rendered = lines.into_iter().map(|(_, text)| text).collect();
+
+        let title = if self.scroll > 0 {
+            tr!("tui-conversation-scrolled", lines = self.scroll)
+        } else {
+            tr!("tui-conversation")
+        };
+        let title = format!(" {title} ");
+        frame.render_widget(
+            Paragraph::new(visible).block(Block::default().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+
+    async fn handle_event(&mut self, event: Event, tx: &Updates) {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key, tx).await,
+            Event::Mouse(mouse) => self.handle_mouse(mouse),
+            Event::Paste(text) => {
+                let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                if paste::is_large(&text) {
+                    self.pastes.push(text.clone());
+                }
+                self.input.push_str(&text);
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle_key(&mut self, key: KeyEvent, tx: &Updates) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => {
+                // The first Ctrl-C cancels a response that's streaming in, the next one quits.
+                match self.streaming.take() {
+                    Some((_, task)) => {
+                        task.abort();
+                        self.take_back().await;
+                        self.notice = tr!("tui-cancelled");
+                    }
+                    None => self.quit = true,
+                }
+            }
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Input => Focus::Sessions,
+                    Focus::Sessions => Focus::Input,
+                };
+                self.sessions = saved_conversations();
+            }
+            KeyCode::PageUp => self.scroll += self.page(),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(self.page()),
+            _ if self.focus == Focus::Sessions => self.handle_sessions_key(key).await,
+            KeyCode::Enter
+                if key
+                    .modifiers
+                    .intersects(KeyModifiers::ALT | KeyModifiers::SHIFT) =>
+            {
+                self.input.push('\n')
+            }
+            KeyCode::Enter => self.submit(tx).await,
+            KeyCode::Char('u') if ctrl => self.input.clear(),
+            KeyCode::Char(c) if !ctrl => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle_sessions_key(&mut self, key: KeyEvent) {
+        let n = self.sessions.len();
+        if n == 0 {
+            return;
+        }
+        let i = self.selected_session.selected();
+        match key.code {
+            KeyCode::Up => self
+                .selected_session
+                .select(Some(i.map(|i| i.saturating_sub(1)).unwrap_or(n - 1))),
+            KeyCode::Down => self
+                .selected_session
+                .select(Some(i.map(|i| (i + 1).min(n - 1)).unwrap_or(0))),
+            KeyCode::Enter => {
+                let Some(path) = i.and_then(|i| self.sessions.get(i)) else {
+                    return;
+                };
+                if self.streaming.is_some() {
+                    self.notice = tr!("tui-wait");
+                    return;
+                }
+                self.notice = match prompt::load_conversation(path).await {
+                    Ok(()) => tr!("conversation-loaded", path = path.display().to_string()),
+                    Err(e) => tr!(
+                        "conversation-load-failed",
+                        path = path.display().to_string(),
+                        error = e.to_string(),
+                    ),
+                };
+                self.scroll = 0;
+                self.focus = Focus::Input;
+            }
+            KeyCode::Esc => self.focus = Focus::Input,
+            _ => {}
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let area = self.conversation_area;
+        let inside = area.contains(Position::new(mouse.column, mouse.row));
+        // Which rendered line is under the mouse, clamped to the visible ones.
+        let line = || {
+            let row = mouse.row.clamp(area.y + 1, area.bottom().saturating_sub(2));
+            self.top + (row - area.y - 1) as usize
+        };
+        match mouse.kind {
+            MouseEventKind::ScrollUp if inside => self.scroll += WHEEL_LINES,
+            MouseEventKind::ScrollDown if inside => {
+                self.scroll = self.scroll.saturating_sub(WHEEL_LINES)
+            }
+            MouseEventKind::Down(MouseButton::Left) if inside => {
+                let line = line();
+                self.selection = Some((line, line));
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let line = line();
+                if let Some((_, cursor)) = &mut self.selection {
+                    *cursor = line;
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some((a, b)) = self.selection {
+                    let (start, end) = (a.min(b), a.max(b));
+                    let end = end.min(self.rendered.len().saturating_sub(1));
+                    if let Some(lines) = self.rendered.get(start..=end) {
+                        clipboard::copy(&lines.join("\n"));
+                        self.notice = tr!("tui-copied", lines = end - start + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn page(&self) -> usize {
+        self.conversation_area.height.saturating_sub(3).max(1) as usize
+    }
+
+    /// Take the prompt of a response that failed or was cancelled back out of the conversation,
+    /// for it not to be sent again, unanswered, with the next one.
+    async fn take_back(&self) {
+        CONVERSATION.lock().await.truncate(self.answering);
+    }
+
+    async fn submit(&mut self, tx: &Updates) {
+        if self.streaming.is_some() || self.input.trim().is_empty() {
+            return;
+        }
+        let limit = config.ui.confirm_above_tokens;
+        let tokens = usage::input_tokens(&self.input);
+        if limit > 0 && tokens > limit && self.confirmed.as_ref() != Some(&self.input) {
+            self.notice = tr!("tui-confirm-tokens", tokens = tokens);
+            self.confirmed = Some(self.input.clone());
+            return;
+        }
+        if config.ui.forecast || config.ui.confirm_above_cost > 0.0 {
+            let conversation = CONVERSATION.lock().await.clone();
+            if let Some(forecast) = Forecast::of(&self.input, &conversation) {
+                if forecast.too_costly() && self.confirmed.as_ref() != Some(&self.input) {
+                    self.notice = tr!("tui-confirm-forecast", forecast = forecast.text());
+                    self.confirmed = Some(self.input.clone());
+                    return;
+                }
+                if config.ui.forecast {
+                    self.notice = forecast.text();
+                }
+            }
+        }
+        self.confirmed = None;
+        let mut prompt = std::mem::take(&mut self.input);
+        let mut pastes = std::mem::take(&mut self.pastes);
+        pastes.retain(|paste| prompt.contains(paste.as_str()));
+        if !pastes.is_empty() {
+            // There's no asking here, so fence what would have been asked about.
+            let how = match config.ui.large_paste {
+                LargePaste::Ask => LargePaste::Fence,
+                how => how,
+            };
+            prompt = paste::apply(prompt, &pastes, how);
+        }
+        self.answering = CONVERSATION.lock().await.len();
+        self.response += 1;
+        let task = tokio::spawn(respond(prompt, self.response, tx.clone()));
+        self.streaming = Some((String::new(), task));
+        self.scroll = 0;
+        self.selection = None;
+    }
+}
+
+/// Stream the response to `prompt` to the UI, and run the tools of `[tools.custom]` it calls for
+/// the model to go on from what they output, as [`prompt::respond`] does.
+async fn respond(prompt: String, response: u64, tx: Updates) {
+    let (messages, stream, report) = match prompt::send(prompt).await {
+        Ok(sent) => sent,
+        Err(e) => {
+            let _ = tx.send((response, Streamed::Failed(e.to_string())));
+            return;
+        }
+    };
+    // The runs aren't shown here, but kept in the conversation as they are from the REPL.
+    let mut sinks = Sinks::new(vec![Box::new(Pane(response, tx.clone()))]);
+    let streamed = match sink::exchange(&messages, stream, report, &mut sinks).await {
+        Ok((text, _, report)) => Streamed::Done(messages, text, report),
+        Err(e) => Streamed::Failed(e.to_string()),
+    };
+    let _ = tx.send((response, streamed));
+}
+
+/// Response `.0` streamed to the conversation pane.
+struct Pane(u64, Updates);
+
+impl Sink for Pane {
+    fn push<'a>(&'a mut self, event: &'a sink::Event<'a>) -> BoxFuture<'a, ()> {
+        async move {
+            let text = match event {
+                sink::Event::Text(text) => text.to_string(),
+                sink::Event::Tools {
+                    paragraph: true, ..
+                } => "\n\n".to_string(),
+                _ => return,
+            };
+            let _ = self.1.send((self.0, Streamed::Chunk(text)));
+        }
+        .boxed()
+    }
+}
+
+fn focused_style(focused: bool) -> Style {
+    if focused {
+        Style::default().fg(style::tui_color(Color::Cyan))
+    } else {
+        Style::default()
+    }
+}
+
+/// Hard-wrap `line` to `width` columns.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut ret = vec![];
+    let mut rest = line;
+    loop {
+        let mut row = width::truncate(rest, width);
+        if row.is_empty() && !rest.is_empty() {
+            // Not even one character fits, so overflow with it.
+            row = &rest[..rest.chars().next().map_or(0, char::len_utf8)];
+        }
+        ret.push(row.to_string());
+        rest = &rest[row.len()..];
+        if rest.is_empty() {
+            return ret;
+        }
+    }
+}
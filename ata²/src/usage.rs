@@ -0,0 +1,229 @@
+//! Token counting, model prices and the usage of the current session.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::ChatCompletionRequestMessage;
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton};
+
+use std::sync::{Arc, Mutex};
+
+use crate::readline::chat_completion_message_text;
+use crate::rotation;
+use crate::store;
+use crate::CONFIGURATION;
+
+/// What we know about a model. Prices are in USD per million tokens.
+pub struct ModelInfo {
+    pub context: usize,
+    pub input_price: f64,
+    /// Of the input tokens that were cached. As much as the others for models without caching.
+    pub cached_price: f64,
+    pub output_price: f64,
+}
+
+/// Known models by ID prefix. The longest matching prefix wins, so `gpt-4o-mini` isn't priced like
+/// `gpt-4o`, nor `gpt-4o` like `gpt-4`.
+static MODELS: &[(&str, ModelInfo)] = &[
+    ("gpt-3.5-turbo", model(16_385, 0.50, 0.50, 1.50)),
+    ("gpt-4", model(8_192, 30.0, 30.0, 60.0)),
+    ("gpt-4-32k", model(32_768, 60.0, 60.0, 120.0)),
+    ("gpt-4-turbo", model(128_000, 10.0, 10.0, 30.0)),
+    ("gpt-4-1106", model(128_000, 10.0, 10.0, 30.0)),
+    ("gpt-4-0125", model(128_000, 10.0, 10.0, 30.0)),
+    ("gpt-4o", model(128_000, 2.50, 1.25, 10.0)),
+    ("gpt-4o-mini", model(128_000, 0.15, 0.075, 0.60)),
+    ("o1", model(200_000, 15.0, 7.50, 60.0)),
+    ("o1-mini", model(128_000, 1.10, 0.55, 4.40)),
+    ("o3-mini", model(200_000, 1.10, 0.55, 4.40)),
+];
+
+const fn model(
+    context: usize,
+    input_price: f64,
+    cached_price: f64,
+    output_price: f64,
+) -> ModelInfo {
+    ModelInfo {
+        context,
+        input_price,
+        cached_price,
+        output_price,
+    }
+}
+
+pub fn model_info(model: &str) -> Option<&'static ModelInfo> {
+    MODELS
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, info)| info)
+}
+
+/// Number of tokens in `text` with the tokenizer of `model`. Models we don't know get the
+/// tokenizer of `gpt-3.5-turbo` and `gpt-4`, which is close enough for an estimate.
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    const O200K_MODELS: &[&str] = &["gpt-4o", "o1", "o3", "o4"];
+    let bpe = if O200K_MODELS.iter().any(|prefix| model.starts_with(prefix)) {
+        o200k_base_singleton()
+    } else {
+        cl100k_base_singleton()
+    };
+    let bpe = bpe.lock();
+    bpe.encode_with_special_tokens(text).len()
+}
+
+/// [`count_tokens`] of the input being typed, with the configured model. It's counted again
+/// after every key, so the last count is kept for when nothing changed.
+pub fn input_tokens(text: &str) -> usize {
+    static LAST: Mutex<Option<(String, usize)>> = Mutex::new(None);
+    let mut last = LAST.lock().unwrap();
+    match &*last {
+        Some((last_text, tokens)) if last_text == text => *tokens,
+        _ => {
+            let tokens = count_tokens(&CONFIGURATION.model, text);
+            *last = Some((text.to_string(), tokens));
+            tokens
+        }
+    }
+}
+
+/// “1 token”, “2 tokens”.
+pub fn tokens_text(tokens: usize) -> String {
+    tr!("usage-tokens", tokens = tokens)
+}
+
+/// Number of prompt tokens `messages` take up, including the overhead of the chat format
+/// (see OpenAI's cookbook, “How to count tokens with tiktoken”).
+pub fn conversation_tokens(model: &str, messages: &[ChatCompletionRequestMessage]) -> usize {
+    const PER_MESSAGE: usize = 3;
+    const REPLY_PRIMING: usize = 3;
+    messages
+        .iter()
+        .map(|m| {
+            PER_MESSAGE
+                + chat_completion_message_text(m)
+                    .map(|text| count_tokens(model, &text))
+                    .unwrap_or_default()
+        })
+        .sum::<usize>()
+        + REPLY_PRIMING
+}
+
+/// Usage of this ata² session, as the API reported it, or else estimated with tiktoken.
+#[derive(Clone, Debug, Default)]
+pub struct SessionUsage {
+    pub prompt_tokens: usize,
+    /// Of the prompt tokens, those that were cached. Only ever reported.
+    pub cached_tokens: usize,
+    pub completion_tokens: usize,
+    /// Size of the conversation after the last exchange.
+    pub context_tokens: usize,
+    /// In USD. `None` as long as every exchange was with a model we have no price for.
+    pub cost: Option<f64>,
+    /// How many prompts were answered.
+    pub exchanges: usize,
+}
+
+/// The usage of a response, as the API reported it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Reported {
+    pub prompt_tokens: usize,
+    pub cached_tokens: usize,
+    pub completion_tokens: usize,
+}
+
+lazy_static! {
+    static ref SESSION: Mutex<SessionUsage> = Mutex::new(SessionUsage::default());
+}
+
+/// Where the API reports the usage of one response, as it streams in, for [`record`]. Each
+/// response has its own, so that those streaming in at once, as the daemon's clients' do, are
+/// accounted for with their own usage and not each other's.
+#[derive(Clone, Debug, Default)]
+pub struct Report(Arc<Mutex<Option<Reported>>>);
+
+impl Report {
+    /// The API reported the usage of the response.
+    pub fn set(&self, usage: Reported) {
+        *self.0.lock().unwrap() = Some(usage);
+    }
+
+    fn take(&self) -> Option<Reported> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+/// Account for one exchange: `messages` were sent to `model`, which replied `response`. With the
+/// usage the API reported to `report`, if it did, or else estimated, which is returned.
+pub fn record(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+    response: &str,
+    report: &Report,
+) -> Reported {
+    let usage = report.take().unwrap_or_else(|| Reported {
+        prompt_tokens: conversation_tokens(model, messages),
+        cached_tokens: 0,
+        completion_tokens: count_tokens(model, response),
+    });
+    record_usage(model, usage);
+    usage
+}
+
+/// Account for `prompt_tokens` sent to `model` and the `completion_tokens` of its reply. Returns
+/// what they cost, if the price of `model` is known.
+pub fn record_tokens(model: &str, prompt_tokens: usize, completion_tokens: usize) -> Option<f64> {
+    record_usage(
+        model,
+        Reported {
+            prompt_tokens,
+            cached_tokens: 0,
+            completion_tokens,
+        },
+    )
+}
+
+fn record_usage(model: &str, usage: Reported) -> Option<f64> {
+    let mut session = SESSION.lock().unwrap();
+    session.prompt_tokens += usage.prompt_tokens;
+    session.cached_tokens += usage.cached_tokens;
+    session.completion_tokens += usage.completion_tokens;
+    session.context_tokens = usage.prompt_tokens + usage.completion_tokens;
+    session.exchanges += 1;
+    let cost = model_info(model).map(|info| {
+        let uncached = usage.prompt_tokens.saturating_sub(usage.cached_tokens);
+        (uncached as f64 * info.input_price
+            + usage.cached_tokens as f64 * info.cached_price
+            + usage.completion_tokens as f64 * info.output_price)
+            / 1_000_000.0
+    });
+    if let Some(cost) = cost {
+        *session.cost.get_or_insert(0.0) += cost;
+    }
+    if store::enabled() {
+        if let Err(e) = store::record_usage(model, usage, cost, rotation::last().as_deref()) {
+            error!("{}", tr!("keys-ledger-write-failed", error = e.to_string()));
+        }
+    }
+    cost
+}
+
+pub fn session() -> SessionUsage {
+    SESSION.lock().unwrap().clone()
+}
@@ -0,0 +1,138 @@
+//! `ata2 watch`: whenever a file changes, send what was added to it with a standing instruction,
+//! and print the answers one after another. For keeping an eye on the log of a long build, say.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use async_openai::types::CreateChatCompletionRequestArgs;
+use notify::{RecursiveMode, Watcher as _};
+use tokio::time::timeout;
+
+use std::fs::{self, File};
+use std::io::{self, Read as _, Seek as _, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::openai;
+use crate::output;
+use crate::readline::string_to_chat_completion_request_user_message;
+use crate::sink::{self, Plain, Sinks};
+use crate::style::theme;
+use crate::usage;
+use crate::TokioResult;
+use crate::CONFIGURATION;
+
+/// How long the file has to stay as it is before what was added is sent, for a burst of writes to
+/// be sent at once.
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// At most this much of the end of what was added is sent, in bytes.
+const MAX_SENT: u64 = 32 * 1024;
+
+pub async fn run(file: &Path, prompt: &str) -> TokioResult<()> {
+    let path = file
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", file.display()))?;
+    // What's there already isn't new.
+    let mut offset = fs::metadata(&path)?.len();
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+    // The directory rather than the file, for a log that's rotated or written anew by each build
+    // to still be watched.
+    let dir = path.parent().unwrap_or(&path);
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    info!(
+        "{}",
+        tr!("watch-started", file = file.display().to_string())
+    );
+
+    while let Some(event) = rx.recv().await {
+        let event: notify::Event = event?;
+        if !event.paths.contains(&path) {
+            continue;
+        }
+        while let Ok(Some(_)) = timeout(SETTLE, rx.recv()).await {}
+        let added = read_added(&path, &mut offset)?;
+        if added.trim().is_empty() {
+            continue;
+        }
+        // A failed request is as good as skipped, and the next change is sent all the same.
+        if let Err(e) = ask(file, prompt, &added).await {
+            error!("{e}");
+        }
+    }
+    Ok(())
+}
+
+/// What was added to the file at `path` after `offset`, which is moved to its end. A file that got
+/// shorter was truncated or replaced, and is read from the start.
+fn read_added(path: &Path, offset: &mut u64) -> TokioResult<String> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        // Between being deleted and written anew.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(String::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let len = file.metadata()?.len();
+    if len < *offset {
+        *offset = 0;
+    }
+    let cut = len.saturating_sub(MAX_SENT) > *offset;
+    let start = (*offset).max(len.saturating_sub(MAX_SENT));
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = vec![];
+    file.take(len - start).read_to_end(&mut bytes)?;
+    *offset = len;
+    let added = String::from_utf8_lossy(&bytes).into_owned();
+    match added.split_once('\n') {
+        // Not from the middle of a line.
+        Some((_, rest)) if cut => Ok(rest.to_string()),
+        _ => Ok(added),
+    }
+}
+
+/// Send `added`, what was added to `file`, after `prompt` and print the answer as it streams in.
+async fn ask(file: &Path, prompt: &str, added: &str) -> TokioResult<()> {
+    let config: &Config = &CONFIGURATION;
+    let message = format!(
+        "{prompt}\n\nHere is what was just added to {}:\n```\n{}\n```",
+        file.display(),
+        added.trim_end()
+    );
+    let messages = vec![string_to_chat_completion_request_user_message(message)];
+    let mut request: CreateChatCompletionRequestArgs = config.into();
+    let report = usage::Report::default();
+    let stream = openai::chat_stream(request.messages(messages.clone()).build()?, &report).await?;
+
+    let header = tr!(
+        "watch-answer",
+        file = file.display().to_string(),
+        time = chrono::Local::now().format("%H:%M:%S").to_string()
+    );
+    eprintln!("\n{}", theme().response.paint(&header));
+    let mut sinks = Sinks::new(vec![Box::new(Plain::stdout())]);
+    let response = sink::read(stream, &config.model, &mut sinks).await?;
+    output::write("\n").await;
+    output::flush().await;
+    usage::record(&config.model, &messages, &response.text, &report);
+    Ok(())
+}
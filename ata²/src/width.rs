@@ -0,0 +1,154 @@
+//! How much room text takes up in the terminal. Measured by grapheme, so that combining
+//! characters, emoji with modifiers or joiners, and flags count as what they're drawn as, and
+//! CJK as two columns each. Tabs go to the next tab stop, and escape sequences take no room.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use unicode_segmentation::UnicodeSegmentation as _;
+use unicode_width::UnicodeWidthStr as _;
+
+const TAB_STOP: usize = 8;
+
+/// Columns `s` takes up on one line, starting at column 0.
+pub fn width(s: &str) -> usize {
+    advance(s, 0)
+}
+
+/// The column after `s` on one line, starting at `column`. Only differs from adding the width for
+/// tabs.
+pub fn advance(s: &str, column: usize) -> usize {
+    graphemes(s).fold(column, |column, (_, grapheme)| {
+        column + grapheme_width(grapheme, column)
+    })
+}
+
+/// Where the cursor ends up after printing `s` from `column` on a terminal `cols` wide: how many
+/// rows down, and in which column. Like rustyline, a character that doesn't fit in what's left of
+/// a row goes on the next, and a full row moves the cursor to the start of the next.
+pub fn position(s: &str, cols: usize, column: usize) -> (usize, usize) {
+    let cols = cols.max(1);
+    let (mut row, mut column) = (0, column);
+    for (_, grapheme) in graphemes(s) {
+        if grapheme == "\n" || grapheme == "\r\n" {
+            row += 1;
+            column = 0;
+            continue;
+        }
+        let width = grapheme_width(grapheme, column);
+        column += width;
+        if column > cols {
+            row += 1;
+            column = width;
+        }
+    }
+    if column >= cols {
+        row += 1;
+        column = 0;
+    }
+    (row, column)
+}
+
+/// The longest start of `s` that fits in `cols` columns, without splitting a grapheme.
+pub fn truncate(s: &str, cols: usize) -> &str {
+    let mut column = 0;
+    for (i, grapheme) in graphemes(s) {
+        column += grapheme_width(grapheme, column);
+        if column > cols {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+/// The graphemes of `s` and where they start, with escape sequences merged into one.
+fn graphemes(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut rest = s.grapheme_indices(true).peekable();
+    std::iter::from_fn(move || {
+        let (start, grapheme) = rest.next()?;
+        if grapheme != "\x1b" {
+            return Some((start, grapheme));
+        }
+        let len = escape_len(&s[start..]);
+        while rest.peek().is_some_and(|(i, _)| *i < start + len) {
+            rest.next();
+        }
+        Some((start, &s[start..start + len]))
+    })
+}
+
+/// Length of the CSI (`ESC [ … letter`) or OSC (`ESC ] … ST`) sequence at the start of `s`, or 1
+/// for a lone escape.
+fn escape_len(s: &str) -> usize {
+    match s.as_bytes().get(1) {
+        Some(b'[') => s[2..]
+            .find(|c: char| c.is_ascii_alphabetic() || c == '~')
+            .map_or(s.len(), |i| 2 + i + 1),
+        Some(b']') => match (s.find("\x1b\\"), s.find('\x07')) {
+            (Some(st), Some(bel)) if bel < st => bel + 1,
+            (Some(st), _) => st + 2,
+            (None, Some(bel)) => bel + 1,
+            (None, None) => s.len(),
+        },
+        _ => 1,
+    }
+}
+
+fn grapheme_width(grapheme: &str, column: usize) -> usize {
+    match grapheme {
+        "\t" => TAB_STOP - column % TAB_STOP,
+        _ if grapheme.starts_with(|c: char| c.is_control()) => 0,
+        _ => grapheme.width(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths_of_mixed_text() {
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(width("e\u{301}"), 1);
+        assert_eq!(width("👨‍👩‍👧"), 2);
+        assert_eq!(width("🇩🇪"), 2);
+        assert_eq!(width("a日\x1b[1mb\x1b[0m"), 4);
+        assert_eq!(advance("\t日", 3), 10);
+    }
+
+    #[test]
+    fn truncating_keeps_graphemes_whole() {
+        // A wide character that only half fits is left out.
+        assert_eq!(truncate("日本語", 5), "日本");
+        assert_eq!(truncate("a日本", 2), "a");
+        // As are emoji, whatever they're made of, and combining marks stay with their letter.
+        assert_eq!(truncate("👨‍👩‍👧👨‍👩‍👧", 3), "👨‍👩‍👧");
+        assert_eq!(truncate("🇩🇪🇫🇷", 3), "🇩🇪");
+        assert_eq!(truncate("e\u{301}e\u{301}e", 2), "e\u{301}e\u{301}");
+        assert_eq!(truncate("日本", 4), "日本");
+    }
+
+    #[test]
+    fn wide_characters_that_do_not_fit_go_on_the_next_row() {
+        // The third is one column short of the end of the row, so it starts the next.
+        assert_eq!(position("日本語", 5, 0), (1, 2));
+        assert_eq!(position("日本", 4, 0), (1, 0));
+        assert_eq!(position("e\u{301}👨‍👩‍👧", 4, 0), (0, 3));
+        assert_eq!(position("ab\n日本語", 80, 0), (1, 6));
+    }
+}
@@ -0,0 +1,129 @@
+//! `ata2 new --from workflow.toml`: a conversation started as a workflow that recurs starts, with
+//! its system message, its model, the tools the model may use, and what's attached to its first
+//! prompt: files, those a glob matches, and what commands output, like a “release notes” one that
+//! attaches the changelog and `git log` as it is when it starts.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use serde::Deserialize;
+
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+use std::sync::OnceLock;
+
+use crate::args::Command;
+use crate::attach;
+use crate::config::Config;
+use crate::TokioResult;
+use crate::FLAGS;
+
+/// The built-in tools of `[responses]`, which a workflow can have the model use too.
+const RESPONSES_TOOLS: &[&str] = &["code_interpreter", "file_search"];
+
+/// The workflow of `ata2 new --from` or `ata2 run`, read the first time it's needed.
+static WORKFLOW: OnceLock<Option<Workflow>> = OnceLock::new();
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    /// What it's for, said as it starts.
+    name: Option<String>,
+    /// Over `model` in the configuration, under `--model`.
+    model: Option<String>,
+    /// The system message the conversation starts with, under `--system`.
+    pub system: Option<String>,
+    /// Files attached to the first prompt, or globs of them, like `src/**/*.rs`.
+    #[serde(default)]
+    attach: Vec<String>,
+    /// Commands whose output is attached to the first prompt, like `git log --oneline`.
+    #[serde(default)]
+    commands: Vec<String>,
+    /// The tools of `[tools.custom]` and `[responses]` the model may use. All of those configured
+    /// if it's not given.
+    tools: Option<Vec<String>>,
+    /// What `ata2 run` asks, with what's attached.
+    pub prompt: Option<String>,
+}
+
+/// The workflow of `ata2 new --from` or `ata2 run`, if that's what ata² was run for. It exits if the workflow
+/// can't be read.
+pub fn get() -> Option<&'static Workflow> {
+    WORKFLOW
+        .get_or_init(|| {
+            let (Some(Command::New { from }) | Some(Command::Run { workflow: from, .. })) =
+                &FLAGS.command
+            else {
+                return None;
+            };
+            Some(read(from).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                exit(1)
+            }))
+        })
+        .as_ref()
+}
+
+fn read(path: &Path) -> Result<Workflow, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    toml::from_str(&contents).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Set the model and the tools of the workflow, if there's one, in `config`, for the flags to go
+/// over. It exits if the workflow names a tool that's not configured.
+pub fn apply(config: &mut Config) {
+    let Some(workflow) = get() else {
+        return;
+    };
+    if let Some(model) = &workflow.model {
+        config.model = model.clone();
+    }
+    let Some(tools) = &workflow.tools else {
+        return;
+    };
+    if let Some(unknown) = tools.iter().find(|tool| {
+        !RESPONSES_TOOLS.contains(&tool.as_str()) && !config.tools.custom.contains_key(*tool)
+    }) {
+        eprintln!("{}", tr!("workflow-unknown-tool", tool = unknown.as_str()));
+        exit(1);
+    }
+    config.tools.custom.retain(|name, _| tools.contains(name));
+    config.responses.tools = tools
+        .iter()
+        .filter(|tool| RESPONSES_TOOLS.contains(&tool.as_str()))
+        .cloned()
+        .collect();
+}
+
+/// Attach what the workflow attaches to the first prompt, if there's one, and say it's started.
+pub async fn start() -> TokioResult<()> {
+    let Some(workflow) = get() else {
+        return Ok(());
+    };
+    for pattern in &workflow.attach {
+        attach::glob(pattern).await?;
+    }
+    for command in &workflow.commands {
+        attach::output(command).await?;
+    }
+    if let Some(name) = &workflow.name {
+        info!("{}", tr!("workflow-started", name = name.as_str()));
+    }
+    Ok(())
+}
@@ -0,0 +1,255 @@
+//! Wrapping of responses (`ui.wrap`) as they stream in. A word is held back until the whitespace
+//! after it arrives, to tell whether it still fits on the line, and whether it's a link to make
+//! clickable (see [`crate::links`]). The lines a list item wraps onto are indented to its text,
+//! and code blocks are left alone.
+//!
+//! # ata²
+//!
+//!	 © 2023    Fredrick R. Brennan <copypaste@kittens.ph>
+//!	 © 2023    Rik Huijzer <t.h.huijzer@rug.nl>
+//!	 © 2023–   ATA Project Authors
+//!
+//!  Licensed under the Apache License, Version 2.0 (the "License");
+//!  you may _not_ use this file except in compliance with the License.
+//!  You may obtain a copy of the License at
+//!
+//!      http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!  Unless required by applicable law or agreed to in writing, software
+//!  distributed under the License is distributed on an "AS IS" BASIS,
+//!  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!  See the License for the specific language governing permissions and
+//!  limitations under the License.
+
+use terminal_size::{terminal_size, Width};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+
+use crate::config::Wrap;
+use crate::links;
+use crate::markdown;
+use crate::width;
+use crate::CONFIGURATION as config;
+
+/// The terminal's width, kept up to date once [`wrap_width`] is first asked for it.
+static COLUMNS: AtomicUsize = AtomicUsize::new(0);
+static WATCH_RESIZES: Once = Once::new();
+
+/// Wraps one response.
+pub struct Wrapper {
+    /// Column of the cursor.
+    column: usize,
+    /// Indent of the lines the current one wraps onto.
+    indent: usize,
+    /// Whitespace after the last word, not printed yet.
+    space: String,
+    /// The word coming in.
+    word: String,
+    /// Is the first word of the line yet to come?
+    line_start: bool,
+    /// Fence of the code block the response is in.
+    fence: Option<String>,
+    /// Where the width to wrap at comes from, [`wrap_width`], and how words are shown,
+    /// [`links::hyperlink`].
+    columns: fn() -> Option<usize>,
+    hyperlink: fn(&str) -> String,
+}
+
+impl Wrapper {
+    pub fn new() -> Self {
+        Self {
+            column: 0,
+            indent: 0,
+            space: String::new(),
+            word: String::new(),
+            line_start: true,
+            fence: None,
+            columns: wrap_width,
+            hyperlink: links::hyperlink,
+        }
+    }
+
+    /// What to print of `text`, with line breaks where it needs wrapping.
+    pub fn push(&mut self, text: &str) -> String {
+        if (self.columns)().is_none()
+            && !links::enabled()
+            && self.word.is_empty()
+            && self.space.is_empty()
+        {
+            return text.to_string();
+        }
+        let mut ret = String::with_capacity(text.len());
+        for c in text.chars() {
+            if c == '\n' {
+                ret.push_str(&self.flush_word());
+                if self.fence.is_some() {
+                    ret.push_str(&self.space);
+                }
+                ret.push('\n');
+                self.space.clear();
+                self.column = 0;
+                self.indent = 0;
+                self.line_start = true;
+            } else if self.fence.is_some() && !self.line_start {
+                ret.push(c);
+            } else if c.is_whitespace() {
+                ret.push_str(&self.flush_word());
+                // Flushing the first word may have found the line to be code.
+                if self.fence.is_some() && !self.line_start {
+                    ret.push(c);
+                } else {
+                    self.space.push(c);
+                }
+            } else {
+                self.word.push(c);
+            }
+        }
+        ret
+    }
+
+    /// The word held back at the end of the response.
+    pub fn finish(&mut self) -> String {
+        let ret = self.flush_word();
+        *self = Self {
+            columns: self.columns,
+            hyperlink: self.hyperlink,
+            ..Self::new()
+        };
+        ret
+    }
+
+    /// The word that just ended, and the whitespace before it, on a new line if it doesn't fit.
+    fn flush_word(&mut self) -> String {
+        if self.word.is_empty() {
+            return String::new();
+        }
+        let space = std::mem::take(&mut self.space);
+        let word = std::mem::take(&mut self.word);
+        if self.line_start {
+            self.line_start = false;
+            match &self.fence {
+                Some(fence) if word.starts_with(fence.as_str()) => self.fence = None,
+                Some(_) => {}
+                None => self.fence = markdown::opening_fence(&word),
+            }
+            self.indent = width::width(&space);
+            self.column = width::advance(&word, self.indent);
+            if is_list_marker(&word) {
+                self.indent = self.column + 1;
+            }
+            return format!("{space}{}", (self.hyperlink)(&word));
+        }
+        if self.fence.is_some() {
+            return format!("{space}{word}");
+        }
+        let end = width::advance(&word, width::advance(&space, self.column));
+        let fits = (self.columns)().is_none_or(|columns| end <= columns);
+        // A word that doesn't fit on a line of its own is left to the terminal.
+        let shown = (self.hyperlink)(&word);
+        if fits || self.column <= self.indent {
+            self.column = end;
+            format!("{space}{shown}")
+        } else {
+            self.column = width::advance(&word, self.indent);
+            format!("\n{}{shown}", " ".repeat(self.indent))
+        }
+    }
+}
+
+/// `-`, `*`, `+`, or a number like `1.` or `1)`, that starts a Markdown list item.
+fn is_list_marker(word: &str) -> bool {
+    if matches!(word, "-" | "*" | "+" | "•") {
+        return true;
+    }
+    let digits = word.trim_end_matches(['.', ')']);
+    word.len() == digits.len() + 1
+        && (1..=9).contains(&digits.len())
+        && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// The width to wrap at, if any.
+fn wrap_width() -> Option<usize> {
+    match config.ui.wrap {
+        Wrap::Off => None,
+        Wrap::Columns(columns) => Some(columns),
+        Wrap::Auto => {
+            if !atty::is(atty::Stream::Stdout) {
+                return None;
+            }
+            WATCH_RESIZES.call_once(|| {
+                COLUMNS.store(terminal_width(), Ordering::Relaxed);
+                watch_resizes();
+            });
+            Some(COLUMNS.load(Ordering::Relaxed)).filter(|&columns| columns > 0)
+        }
+    }
+}
+
+fn terminal_width() -> usize {
+    terminal_size().map_or(0, |(Width(columns), _)| columns.into())
+}
+
+/// Query the width again whenever the terminal is resized.
+#[cfg(unix)]
+fn watch_resizes() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let Ok(mut resized) = signal(SignalKind::window_change()) else {
+        return;
+    };
+    tokio::spawn(async move {
+        while resized.recv().await.is_some() {
+            COLUMNS.store(terminal_width(), Ordering::Relaxed);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn watch_resizes() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `text` wrapped at 10 columns.
+    fn wrapped(text: &str) -> String {
+        let mut wrapper = Wrapper {
+            columns: || Some(10),
+            hyperlink: str::to_string,
+            ..Wrapper::new()
+        };
+        let mut ret = wrapper.push(text);
+        ret += &wrapper.finish();
+        ret
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        // Four CJK characters are eight columns, so a fifth and a space don't fit beside them.
+        assert_eq!(wrapped("日本語 よ"), "日本語 よ");
+        assert_eq!(wrapped("日本語だ よ"), "日本語だ\nよ");
+        assert_eq!(wrapped("日本語だ 日本"), "日本語だ\n日本");
+        assert_eq!(wrapped("ab 日本語 日本語"), "ab 日本語\n日本語");
+    }
+
+    #[test]
+    fn emoji_take_what_they_are_drawn_as() {
+        // A family joined with zero-width joiners, and a flag, are two columns each.
+        assert_eq!(wrapped("👨‍👩‍👧 🇩🇪 abcd"), "👨‍👩‍👧 🇩🇪 abcd");
+        assert_eq!(wrapped("👨‍👩‍👧 🇩🇪 abcde"), "👨‍👩‍👧 🇩🇪\nabcde");
+    }
+
+    #[test]
+    fn combining_marks_take_no_column() {
+        // "e" and a combining acute accent, five times, are five columns.
+        let accented = "e\u{301}".repeat(5);
+        assert_eq!(
+            wrapped(&format!("{accented} {accented}")),
+            format!("{accented}\n{accented}")
+        );
+        assert_eq!(
+            wrapped(&format!("{accented} abcd")),
+            format!("{accented} abcd")
+        );
+    }
+}